@@ -26,7 +26,7 @@ impl AsyncArgon {
                 settings.inner.salt.as_slice(),
                 &settings.as_argon_config(),
             ) {
-                Ok(hashed) => ArgonStatus::HashSuccess(SecBuffer::from(hashed)),
+                Ok(hashed) => ArgonStatus::HashSuccess(SecBuffer::new_guarded(hashed)),
                 Err(err) => ArgonStatus::HashFailed(err.to_string()),
             }
         });
@@ -0,0 +1,300 @@
+//! Directories as a single object
+//!
+//! A directory is sent as one object: the contents of each file, concatenated in the order given by the directory's
+//! [`DirectoryManifest`]. The manifest travels inside the object's header, allowing the receiver to inspect the entire
+//! directory before accepting it, and to unpack and verify each file as its bytes arrive. If the session uses a signature
+//! algorithm, the sender signs the manifest with its signature key, and the receiver verifies the signature before the
+//! directory is offered to the local user. Otherwise, the manifest is sent unsigned, protected in transit only by the
+//! session's authenticated encryption
+use crate::misc::CryptError;
+use crate::streaming_crypt_scrambler::ObjectDigest;
+#[cfg(feature = "filesystem")]
+use crate::streaming_crypt_scrambler::{FixedSizedStream, ObjectSource, ObjectStream};
+use citadel_pqcrypto::algorithm_dictionary::SigAlgorithm;
+use citadel_pqcrypto::PostQuantumContainer;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, PathBuf};
+
+/// Lists the contents of a directory, in the order the files are sent
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DirectoryManifest {
+    pub entries: Vec<ManifestEntry>,
+    /// The sender's signature over the entries. Empty until signed, and empty if the session does not use a signature
+    /// algorithm
+    pub signature: Vec<u8>,
+}
+
+// prefixed to the signed entries, such that a manifest's signature cannot be mistaken for any other signature
+const MANIFEST_SIGNATURE_CONTEXT: &[u8] = b"citadel-directory-manifest";
+
+/// A file or directory within a [`DirectoryManifest`]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ManifestEntry {
+    /// The path relative to the directory's root, with components separated by '/'
+    pub path: String,
+    pub kind: ManifestEntryKind,
+    /// The number of bytes of the file. Zero for directories
+    pub size: u64,
+    /// The unix permission bits, if known
+    pub mode: Option<u32>,
+    /// The SHA3-256 digest of the file's contents. Zeroed for directories
+    pub digest: ObjectDigest,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ManifestEntryKind {
+    File,
+    Directory,
+}
+
+impl DirectoryManifest {
+    /// Returns the total number of bytes of the files in the manifest
+    pub fn total_len(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Returns the number of files in the manifest
+    pub fn file_count(&self) -> usize {
+        self.files().count()
+    }
+
+    /// Returns the files in the manifest, in the order they are sent
+    pub fn files(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == ManifestEntryKind::File)
+    }
+
+    /// Builds the manifest of the directory at `root`, hashing each file. Symbolic links are not followed, and are skipped
+    #[cfg(feature = "filesystem")]
+    pub fn build<T: AsRef<std::path::Path>>(root: T) -> Result<Self, CryptError> {
+        let mut entries = Vec::new();
+        walk(root.as_ref(), "", &mut entries)?;
+        Ok(Self {
+            entries,
+            signature: Vec::new(),
+        })
+    }
+
+    /// Signs the entries with the local signature key of `pqc`. If `pqc` was created without a signature algorithm, the
+    /// manifest is left unsigned
+    pub fn sign(&mut self, pqc: &PostQuantumContainer) -> Result<(), CryptError> {
+        if pqc.params.sig_algorithm == SigAlgorithm::None {
+            self.signature.clear();
+            return Ok(());
+        }
+
+        self.signature = pqc
+            .sign(self.signed_bytes()?)
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Verifies that the entries were signed by the adjacent node of `pqc`. Since both nodes of a session use the same
+    /// algorithms, an unsigned manifest is accepted only if `pqc` was created without a signature algorithm
+    pub fn verify(&self, pqc: &PostQuantumContainer) -> Result<(), CryptError> {
+        if pqc.params.sig_algorithm == SigAlgorithm::None {
+            return if self.signature.is_empty() {
+                Ok(())
+            } else {
+                Err(CryptError::Decrypt(
+                    "The manifest was signed, but the session does not use a signature algorithm"
+                        .to_string(),
+                ))
+            };
+        }
+
+        pqc.verify_remote_signature(self.signed_bytes()?, &self.signature)
+            .map_err(|err| CryptError::Decrypt(err.to_string()))
+    }
+
+    fn signed_bytes(&self) -> Result<Vec<u8>, CryptError> {
+        let mut bytes = MANIFEST_SIGNATURE_CONTEXT.to_vec();
+        bincode2::serialize_into(&mut bytes, &self.entries)
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+        Ok(bytes)
+    }
+}
+
+impl ManifestEntry {
+    /// Returns the path of the entry relative to the directory's root, or None if the path could escape the root
+    /// (e.g., an absolute path, or one containing '..')
+    pub fn relative_path(&self) -> Option<PathBuf> {
+        let path = PathBuf::from(&self.path);
+        let is_contained = !self.path.is_empty()
+            && path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        is_contained.then_some(path)
+    }
+}
+
+#[cfg(feature = "filesystem")]
+fn walk(
+    directory: &std::path::Path,
+    prefix: &str,
+    entries: &mut Vec<ManifestEntry>,
+) -> Result<(), CryptError> {
+    let mut children = std::fs::read_dir(directory)
+        .map_err(|err| CryptError::Encrypt(err.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+    // sorted, such that the manifest of an unchanged directory is identical between attempts
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let name = child
+            .file_name()
+            .into_string()
+            .map_err(|_| CryptError::Encrypt("Unable to get filename".to_string()))?;
+        let path = format!("{}{}", prefix, name);
+        let metadata = child
+            .metadata()
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+        let mode = get_mode(&metadata);
+
+        if metadata.is_dir() {
+            entries.push(ManifestEntry {
+                path: path.clone(),
+                kind: ManifestEntryKind::Directory,
+                size: 0,
+                mode,
+                digest: ObjectDigest::default(),
+            });
+            walk(&child.path(), &format!("{}/", path), entries)?;
+        } else if metadata.is_file() {
+            entries.push(ManifestEntry {
+                path,
+                kind: ManifestEntryKind::File,
+                size: metadata.len(),
+                mode,
+                digest: hash_file(&child.path())?,
+            });
+        } else {
+            log::warn!(target: "citadel", "Skipping {:?}, since it is neither a file nor a directory", child.path());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "filesystem")]
+fn hash_file(path: &std::path::Path) -> Result<ObjectDigest, CryptError> {
+    use sha3::Digest;
+
+    let mut file = std::fs::File::open(path).map_err(|err| CryptError::Encrypt(err.to_string()))?;
+    let mut hasher = sha3::Sha3_256::default();
+    let _ = std::io::copy(&mut file, &mut hasher)
+        .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(all(feature = "filesystem", unix))]
+fn get_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(all(feature = "filesystem", not(unix)))]
+fn get_mode(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Sends a directory, and everything beneath it, as a single object
+#[cfg(feature = "filesystem")]
+pub struct DirectorySource {
+    root: PathBuf,
+    manifest: DirectoryManifest,
+}
+
+#[cfg(feature = "filesystem")]
+impl DirectorySource {
+    /// Builds the manifest of the directory, hashing each file. Since this reads every file, this should not be called
+    /// from within an async context
+    pub fn new<T: Into<PathBuf>>(root: T) -> Result<Self, CryptError> {
+        let root = root.into();
+        let manifest = DirectoryManifest::build(&root)?;
+        Ok(Self { root, manifest })
+    }
+
+    pub fn manifest(&self) -> &DirectoryManifest {
+        &self.manifest
+    }
+}
+
+#[cfg(feature = "filesystem")]
+impl ObjectSource for DirectorySource {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        let files = self
+            .manifest
+            .files()
+            .map(|entry| (self.root.join(&entry.path), entry.size))
+            .collect();
+
+        Ok(ObjectStream::Fixed(Box::new(DirectoryStream {
+            files,
+            current: None,
+            len: self.manifest.total_len(),
+        })))
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        self.root.get_source_name()
+    }
+
+    fn get_manifest(&self) -> Option<&DirectoryManifest> {
+        Some(&self.manifest)
+    }
+}
+
+/// Reads each file of a directory in turn. Each file must still have the length recorded in the manifest, otherwise
+/// the files following it would be misaligned
+#[cfg(feature = "filesystem")]
+struct DirectoryStream {
+    files: std::collections::VecDeque<(PathBuf, u64)>,
+    current: Option<(PathBuf, std::io::Take<std::fs::File>)>,
+    len: u64,
+}
+
+#[cfg(feature = "filesystem")]
+impl std::io::Read for DirectoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::Read;
+
+        loop {
+            if self.current.is_none() {
+                match self.files.pop_front() {
+                    Some((path, size)) => {
+                        let file = std::fs::File::open(&path)?;
+                        self.current = Some((path, file.take(size)));
+                    }
+
+                    None => return Ok(0),
+                }
+            }
+
+            let (path, file) = self.current.as_mut().unwrap();
+            match file.read(buf)? {
+                0 if buf.is_empty() => return Ok(0),
+
+                0 if file.limit() != 0 => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!("{:?} was truncated while being sent", path),
+                    ))
+                }
+
+                0 => self.current = None,
+
+                n => return Ok(n),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "filesystem")]
+impl FixedSizedStream for DirectoryStream {
+    fn length(&self) -> std::io::Result<u64> {
+        Ok(self.len)
+    }
+}
@@ -0,0 +1,593 @@
+use crate::endpoint_crypto_container::EndpointRatchetConstructor;
+use crate::entropy_bank::{EntropyBank, SecurityLevel};
+use crate::fcm::fcm_ratchet::{
+    FcmAliceToBobTransfer, FcmBobToAliceTransfer, ThinRatchet, ThinRatchetConstructor,
+};
+use crate::misc::CryptError;
+use crate::stacked_ratchet::constructor::{AliceToBobTransferType, BobToAliceTransferType};
+use crate::stacked_ratchet::Ratchet;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use citadel_pqcrypto::bytes_in_place::EzBuffer;
+use citadel_pqcrypto::constructor_opts::ConstructorOpts;
+use citadel_pqcrypto::kem::kem_for;
+use citadel_pqcrypto::PostQuantumContainer;
+use dpb_kem::{Ciphertext, KeyEncapsulationMechanism, PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha3::Digest;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of message keys that may be skipped within a single chain
+pub const MAX_SKIP: u32 = 1024;
+/// The maximum number of skipped message keys held at once. Once reached, the oldest keys are discarded
+pub const MAX_SKIPPED_MESSAGE_KEYS: usize = 2048;
+
+const ROOT_LABEL: &[u8] = b"citadel-double-ratchet-root";
+const INITIATOR_LABEL: &[u8] = b"citadel-double-ratchet-initiator";
+const RESPONDER_LABEL: &[u8] = b"citadel-double-ratchet-responder";
+const MESSAGE_KEY_LABEL: &[u8] = &[0x01];
+const CHAIN_KEY_LABEL: &[u8] = &[0x02];
+/// Each message key is only ever used once, so a fixed nonce is safe
+const NONCE: [u8; 12] = [0u8; 12];
+
+/// The transfer types are shared with the [`ThinRatchet`], since the initial handshake is identical
+pub type DoubleRatchetAliceToBobTransfer = FcmAliceToBobTransfer;
+/// The transfer types are shared with the [`ThinRatchet`], since the initial handshake is identical
+pub type DoubleRatchetBobToAliceTransfer = FcmBobToAliceTransfer;
+
+/// A Signal-style Double Ratchet. The root key is established by the post-quantum handshake of a [`ThinRatchet`].
+/// Thereafter, each message is protected by a unique message key derived from a symmetric-key chain, and, each time
+/// the conversation changes direction, the root key is advanced by a KEM ratchet step: the sender encapsulates a new
+/// shared secret against the latest public key of its peer, and advertises a fresh public key of its own.
+///
+/// Message keys for messages that have not yet arrived are retained (up to [`MAX_SKIP`] per chain), allowing messages
+/// to be received out-of-order. Every message key is discarded after its first successful use, preventing replays.
+///
+/// Only message packets use the Double Ratchet. All other operations (e.g., scrambling) use the underlying [`ThinRatchet`]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DoubleRatchet {
+    inner: Arc<DoubleRatchetInner>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DoubleRatchetInner {
+    base: ThinRatchet,
+    state: Mutex<DoubleRatchetState>,
+}
+
+/// The per-message header, appended in the clear (but authenticated) to each message packet
+#[derive(Serialize, Deserialize)]
+struct MessageHeader {
+    // the root step whose sending chain produced the message key
+    step: u32,
+    // the index of the message key within the sending chain
+    n: u32,
+    // the length of the sender's previous sending chain
+    previous_chain_len: u32,
+    ratchet_step: Option<RatchetStep>,
+}
+
+/// Sent with each message until the peer responds with a ratchet step of its own
+#[derive(Serialize, Deserialize, Clone)]
+struct RatchetStep {
+    // encapsulated against the peer's latest public key. Only absent for the initiator's first step
+    ciphertext: Option<Ciphertext>,
+    // the public key the peer must encapsulate against during its next step
+    public_key: PublicKey,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ChainKey {
+    key: [u8; 32],
+    n: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DoubleRatchetState {
+    is_initiator: bool,
+    root_key: [u8; 32],
+    // the index of the current root key. The initiator performs even steps, the responder performs odd steps
+    root_step: u32,
+    send_chain: ChainKey,
+    send_step: u32,
+    previous_send_len: u32,
+    local_step: Option<RatchetStep>,
+    local_secret_key: Option<SecretKey>,
+    recv_chain: ChainKey,
+    recv_step: u32,
+    // the peer's public key that has not yet been encapsulated against
+    remote_public_key: Option<PublicKey>,
+    // keyed by (step, n)
+    skipped_message_keys: BTreeMap<(u32, u32), [u8; 32]>,
+    has_received: bool,
+}
+
+impl DoubleRatchet {
+    fn new(base: ThinRatchet, is_initiator: bool) -> Result<Self, CryptError> {
+        let (pqc, _) = base.message_pqc_drill(None);
+        let kem = kem_for(pqc.params.kem_algorithm, pqc.params.kyber_parameter_set);
+        let shared_secret = pqc
+            .get_shared_secret()
+            .map_err(|err| CryptError::DrillUpdateError(err.to_string()))?;
+        let chain = pqc
+            .get_chain()
+            .map_err(|err| CryptError::DrillUpdateError(err.to_string()))?;
+        let root_key = kdf(&[ROOT_LABEL, &shared_secret[..], &chain.chain[..]]);
+
+        let (local_label, remote_label) = labels(is_initiator);
+        let (local_step, local_secret_key) = if is_initiator {
+            let (public_key, secret_key) = kem
+                .keypair()
+                .map_err(|err| CryptError::DrillUpdateError(err.to_string()))?;
+            (
+                Some(RatchetStep {
+                    ciphertext: None,
+                    public_key,
+                }),
+                Some(secret_key),
+            )
+        } else {
+            (None, None)
+        };
+
+        let state = DoubleRatchetState {
+            is_initiator,
+            root_key,
+            root_step: 0,
+            send_chain: ChainKey::new(&root_key, local_label),
+            send_step: 0,
+            previous_send_len: 0,
+            local_step,
+            local_secret_key,
+            recv_chain: ChainKey::new(&root_key, remote_label),
+            recv_step: 0,
+            remote_public_key: None,
+            skipped_message_keys: BTreeMap::new(),
+            has_received: false,
+        };
+
+        Ok(Self {
+            inner: Arc::new(DoubleRatchetInner {
+                base,
+                state: Mutex::new(state),
+            }),
+        })
+    }
+
+    /// Returns the number of skipped message keys currently retained for out-of-order messages
+    pub fn skipped_message_keys(&self) -> usize {
+        self.state().skipped_message_keys.len()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, DoubleRatchetState> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn kem(&self) -> Box<dyn KeyEncapsulationMechanism> {
+        let (pqc, _) = self.message_pqc_drill(None);
+        kem_for(pqc.params.kem_algorithm, pqc.params.kyber_parameter_set)
+    }
+}
+
+impl Ratchet for DoubleRatchet {
+    type Constructor = DoubleRatchetConstructor;
+
+    fn get_cid(&self) -> u64 {
+        self.inner.base.get_cid()
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.base.version()
+    }
+
+    fn has_verified_packets(&self) -> bool {
+        self.inner.base.has_verified_packets() || self.state().has_received
+    }
+
+    fn reset_ara(&self) {
+        self.inner.base.reset_ara()
+    }
+
+    fn get_default_security_level(&self) -> SecurityLevel {
+        self.inner.base.get_default_security_level()
+    }
+
+    fn message_pqc_drill(&self, idx: Option<usize>) -> (&PostQuantumContainer, &EntropyBank) {
+        self.inner.base.message_pqc_drill(idx)
+    }
+
+    fn get_scramble_drill(&self) -> &EntropyBank {
+        self.inner.base.get_scramble_drill()
+    }
+
+    fn get_next_constructor_opts(&self) -> Vec<ConstructorOpts> {
+        self.inner.base.get_next_constructor_opts()
+    }
+
+    /// The message header is appended to the end of the packet as follows: [ciphertext][message header][message header len: u32]
+    fn protect_message_packet<T: EzBuffer>(
+        &self,
+        _security_level: Option<SecurityLevel>,
+        header_len_bytes: usize,
+        packet: &mut T,
+    ) -> Result<(), CryptError<String>> {
+        let kem = self.kem();
+        let mut state = self.state();
+        let (message_key, message_header) = state.next_sending_key(&*kem)?;
+        let message_header = bincode2::serialize(&message_header)
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+
+        let payload = packet.split_off(header_len_bytes);
+        let aad = [packet.as_ref(), &message_header[..]].concat();
+        let ciphertext = aead(&message_key)
+            .encrypt(
+                Nonce::from_slice(&NONCE),
+                Payload {
+                    msg: payload.as_ref(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| CryptError::Encrypt("Unable to encrypt message".to_string()))?;
+
+        packet
+            .extend_from_slice(&ciphertext)
+            .and_then(|_| packet.extend_from_slice(&message_header))
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+        packet.put_u32(message_header.len() as u32);
+        Ok(())
+    }
+
+    fn validate_message_packet<H: AsRef<[u8]>, T: EzBuffer>(
+        &self,
+        _security_level: Option<SecurityLevel>,
+        header: H,
+        packet: &mut T,
+    ) -> Result<(), CryptError<String>> {
+        let len = packet.len();
+        if len < 4 {
+            return Err(CryptError::Decrypt("Packet too short".to_string()));
+        }
+
+        let mut message_header_len = [0u8; 4];
+        message_header_len.copy_from_slice(&packet.as_ref()[len - 4..]);
+        let message_header_len = u32::from_be_bytes(message_header_len) as usize;
+        let message_header_start = (len - 4)
+            .checked_sub(message_header_len)
+            .ok_or_else(|| CryptError::Decrypt("Bad message header length".to_string()))?;
+
+        let message_header_bytes = packet.subset(message_header_start..len - 4).to_vec();
+        let message_header: MessageHeader = bincode2::deserialize(&message_header_bytes)
+            .map_err(|err| CryptError::Decrypt(err.to_string()))?;
+        let aad = [header.as_ref(), &message_header_bytes[..]].concat();
+        packet.truncate(message_header_start);
+
+        let kem = self.kem();
+        let plaintext = self
+            .state()
+            .decrypt(&*kem, message_header, &aad, packet.as_ref())?;
+        packet.truncate(0);
+        packet
+            .extend_from_slice(&plaintext)
+            .map_err(|err| CryptError::Decrypt(err.to_string()))
+    }
+}
+
+impl DoubleRatchetState {
+    /// Performs a KEM ratchet step if the peer advertised a new public key, then returns the next message key
+    fn next_sending_key(
+        &mut self,
+        kem: &dyn KeyEncapsulationMechanism,
+    ) -> Result<([u8; 32], MessageHeader), CryptError> {
+        if let Some(remote_public_key) = self.remote_public_key.take() {
+            let (ciphertext, shared_secret) = kem
+                .encapsulate(remote_public_key.as_slice())
+                .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+            let (public_key, secret_key) = kem
+                .keypair()
+                .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+            let root_step = self
+                .root_step
+                .checked_add(1)
+                .ok_or_else(|| CryptError::Encrypt("Root step exhausted".to_string()))?;
+
+            self.root_key = next_root_key(&self.root_key, root_step, shared_secret.as_slice());
+            self.root_step = root_step;
+            self.previous_send_len = self.send_chain.n;
+            self.send_chain = ChainKey::new(&self.root_key, labels(self.is_initiator).0);
+            self.send_step = root_step;
+            self.local_step = Some(RatchetStep {
+                ciphertext: Some(ciphertext),
+                public_key,
+            });
+            self.local_secret_key = Some(secret_key);
+        }
+
+        let message_header = MessageHeader {
+            step: self.send_step,
+            n: self.send_chain.n,
+            previous_chain_len: self.previous_send_len,
+            ratchet_step: self.local_step.clone(),
+        };
+
+        Ok((self.send_chain.next_message_key()?, message_header))
+    }
+
+    /// State is only committed once the message successfully decrypts, ensuring forged messages cannot
+    /// advance the ratchet or consume message keys
+    fn decrypt(
+        &mut self,
+        kem: &dyn KeyEncapsulationMechanism,
+        message_header: MessageHeader,
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, CryptError> {
+        let key = (message_header.step, message_header.n);
+        if let Some(message_key) = self.skipped_message_keys.get(&key) {
+            let plaintext = decrypt_with(message_key, aad, ciphertext)?;
+            let _ = self.skipped_message_keys.remove(&key);
+            self.has_received = true;
+            return Ok(plaintext);
+        }
+
+        let mut skipped = Vec::new();
+
+        if message_header.step == self.recv_step {
+            let mut recv_chain = self.recv_chain.clone();
+            let message_key =
+                recv_chain.skip_to(message_header.step, message_header.n, &mut skipped)?;
+            let plaintext = decrypt_with(&message_key, aad, ciphertext)?;
+            self.recv_chain = recv_chain;
+
+            // the responder learns the initiator's first public key from any of its initial messages
+            if self.root_step == 0 && !self.is_initiator && self.remote_public_key.is_none() {
+                self.remote_public_key = message_header.ratchet_step.map(|step| step.public_key);
+            }
+
+            self.store_skipped(skipped);
+            self.has_received = true;
+            return Ok(plaintext);
+        }
+
+        // otherwise, this must be the peer's next ratchet step, which is always performed with respect to our latest root key
+        let is_peer_step = (message_header.step % 2 == 1) == self.is_initiator;
+        if !is_peer_step || Some(message_header.step) != self.root_step.checked_add(1) {
+            return Err(CryptError::Decrypt(format!(
+                "Message key for step {} (n={}) is unavailable",
+                message_header.step, message_header.n
+            )));
+        }
+
+        let ratchet_step = message_header
+            .ratchet_step
+            .ok_or_else(|| CryptError::Decrypt("Missing ratchet step".to_string()))?;
+        let ciphertext_kem = ratchet_step
+            .ciphertext
+            .as_ref()
+            .ok_or_else(|| CryptError::Decrypt("Missing ratchet ciphertext".to_string()))?;
+        let local_secret_key = self
+            .local_secret_key
+            .as_ref()
+            .ok_or_else(|| CryptError::Decrypt("No local ratchet key available".to_string()))?;
+
+        // retain the keys for any messages of the peer's previous step that have yet to arrive
+        self.recv_chain.clone().skip_until(
+            self.recv_step,
+            message_header.previous_chain_len,
+            &mut skipped,
+        )?;
+
+        let shared_secret = kem
+            .decapsulate(ciphertext_kem.as_slice(), local_secret_key.as_slice())
+            .map_err(|err| CryptError::Decrypt(err.to_string()))?;
+        let root_key = next_root_key(
+            &self.root_key,
+            message_header.step,
+            shared_secret.as_slice(),
+        );
+        let mut recv_chain = ChainKey::new(&root_key, labels(self.is_initiator).1);
+        let message_key =
+            recv_chain.skip_to(message_header.step, message_header.n, &mut skipped)?;
+        let plaintext = decrypt_with(&message_key, aad, ciphertext)?;
+
+        self.root_key = root_key;
+        self.root_step = message_header.step;
+        self.recv_chain = recv_chain;
+        self.recv_step = message_header.step;
+        self.remote_public_key = Some(ratchet_step.public_key);
+        // the peer has consumed our public key, so there is no need to keep advertising it
+        self.local_step = None;
+        self.store_skipped(skipped);
+        self.has_received = true;
+        Ok(plaintext)
+    }
+
+    fn store_skipped(&mut self, skipped: Vec<((u32, u32), [u8; 32])>) {
+        self.skipped_message_keys.extend(skipped);
+        while self.skipped_message_keys.len() > MAX_SKIPPED_MESSAGE_KEYS {
+            let _ = self.skipped_message_keys.pop_first();
+        }
+    }
+}
+
+impl ChainKey {
+    fn new(root_key: &[u8; 32], label: &[u8]) -> Self {
+        Self {
+            key: kdf(&[&root_key[..], label]),
+            n: 0,
+        }
+    }
+
+    fn next_message_key(&mut self) -> Result<[u8; 32], CryptError> {
+        let message_key = kdf(&[&self.key[..], MESSAGE_KEY_LABEL]);
+        self.key = kdf(&[&self.key[..], CHAIN_KEY_LABEL]);
+        self.n = self
+            .n
+            .checked_add(1)
+            .ok_or_else(|| CryptError::Encrypt("Chain exhausted".to_string()))?;
+        Ok(message_key)
+    }
+
+    /// Advances the chain until reaching `n`, storing each skipped message key. Returns the message key for `n`
+    fn skip_to(
+        &mut self,
+        step: u32,
+        n: u32,
+        skipped: &mut Vec<((u32, u32), [u8; 32])>,
+    ) -> Result<[u8; 32], CryptError> {
+        self.skip_until(step, n, skipped)?;
+        self.next_message_key()
+    }
+
+    /// Advances the chain until reaching `n`, storing each skipped message key
+    fn skip_until(
+        &mut self,
+        step: u32,
+        n: u32,
+        skipped: &mut Vec<((u32, u32), [u8; 32])>,
+    ) -> Result<(), CryptError> {
+        if n < self.n {
+            return Err(CryptError::Decrypt(format!(
+                "Message key for step {step} (n={n}) was already used"
+            )));
+        }
+
+        if n - self.n > MAX_SKIP {
+            return Err(CryptError::Decrypt(format!(
+                "Too many skipped messages in step {step}"
+            )));
+        }
+
+        while self.n < n {
+            let idx = self.n;
+            skipped.push(((step, idx), self.next_message_key()?));
+        }
+
+        Ok(())
+    }
+}
+
+fn labels(is_initiator: bool) -> (&'static [u8], &'static [u8]) {
+    if is_initiator {
+        (INITIATOR_LABEL, RESPONDER_LABEL)
+    } else {
+        (RESPONDER_LABEL, INITIATOR_LABEL)
+    }
+}
+
+fn next_root_key(root_key: &[u8; 32], step: u32, shared_secret: &[u8]) -> [u8; 32] {
+    kdf(&[
+        ROOT_LABEL,
+        &root_key[..],
+        &step.to_be_bytes(),
+        shared_secret,
+    ])
+}
+
+/// Each input is length-prefixed to prevent ambiguity between adjacent inputs
+fn kdf(inputs: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = sha3::Sha3_256::default();
+    for input in inputs {
+        hasher.update((input.len() as u64).to_be_bytes());
+        hasher.update(input);
+    }
+
+    hasher.finalize().into()
+}
+
+fn aead(message_key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(Key::from_slice(message_key))
+}
+
+fn decrypt_with(
+    message_key: &[u8; 32],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+    aead(message_key)
+        .decrypt(
+            Nonce::from_slice(&NONCE),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| CryptError::Decrypt("Unable to decrypt message".to_string()))
+}
+
+/// Used for constructing the ratchet. The handshake is performed by a [`ThinRatchetConstructor`]
+#[derive(Serialize, Deserialize)]
+pub struct DoubleRatchetConstructor {
+    inner: ThinRatchetConstructor,
+    is_initiator: bool,
+}
+
+impl EndpointRatchetConstructor<DoubleRatchet> for DoubleRatchetConstructor {
+    fn new_alice(
+        mut opts: Vec<ConstructorOpts>,
+        cid: u64,
+        new_version: u32,
+        _security_level: Option<SecurityLevel>,
+    ) -> Option<Self> {
+        Some(Self {
+            inner: ThinRatchetConstructor::new_alice(cid, new_version, opts.remove(0))?,
+            is_initiator: true,
+        })
+    }
+
+    fn new_bob(
+        _cid: u64,
+        _new_drill_vers: u32,
+        mut opts: Vec<ConstructorOpts>,
+        transfer: AliceToBobTransferType,
+    ) -> Option<Self> {
+        match transfer {
+            AliceToBobTransferType::DoubleRatchet(transfer) => Some(Self {
+                inner: ThinRatchetConstructor::new_bob(opts.remove(0), transfer)?,
+                is_initiator: false,
+            }),
+
+            _ => {
+                log::error!(target: "citadel", "Incompatible Ratchet Type passed! [X-45]");
+                None
+            }
+        }
+    }
+
+    fn stage0_alice(&self) -> Option<AliceToBobTransferType> {
+        Some(AliceToBobTransferType::DoubleRatchet(
+            self.inner.stage0_alice()?,
+        ))
+    }
+
+    fn stage0_bob(&self) -> Option<BobToAliceTransferType> {
+        Some(BobToAliceTransferType::DoubleRatchet(
+            self.inner.stage0_bob()?,
+        ))
+    }
+
+    fn stage1_alice(&mut self, transfer: BobToAliceTransferType) -> Result<(), CryptError> {
+        match transfer {
+            BobToAliceTransferType::DoubleRatchet(transfer) => self.inner.stage1_alice(transfer),
+
+            _ => Err(CryptError::DrillUpdateError(
+                "Incompatible Ratchet Type passed! [X-46]".to_string(),
+            )),
+        }
+    }
+
+    fn update_version(&mut self, version: u32) -> Option<()> {
+        self.inner.update_version(version)
+    }
+
+    fn finish_with_custom_cid(self, cid: u64) -> Option<DoubleRatchet> {
+        DoubleRatchet::new(self.inner.finish_with_custom_cid(cid)?, self.is_initiator).ok()
+    }
+
+    fn finish(self) -> Option<DoubleRatchet> {
+        DoubleRatchet::new(self.inner.finish()?, self.is_initiator).ok()
+    }
+}
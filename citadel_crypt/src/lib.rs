@@ -27,6 +27,10 @@ pub mod prelude {
 
 /// For argon-related functionality
 pub mod argon;
+/// For sending a directory as a single object
+pub mod directory_source;
+/// A Signal-style Double Ratchet, providing a unique key for each message
+pub mod double_ratchet;
 /// An abstraction binding the drill and the PQC
 pub mod endpoint_crypto_container;
 /// Organizes the different types of drills that can be used. Currently, there is only one: The Standard Drill
@@ -39,6 +43,8 @@ pub mod misc;
 pub mod packet_vector;
 /// Contains the subroutines for network-related functionality
 pub mod scramble;
+/// For sealing objects that are stored and forwarded by a server on behalf of an offline recipient
+pub mod sealed_object;
 /// For secure byte handling
 pub mod secure_buffer;
 /// This is a container for holding the drill and PQC, and is intended to replace the seperate use of the drill/PQC
@@ -0,0 +1,360 @@
+//! Objects sealed for a recipient who is offline
+//!
+//! Before an object is handed to the server for store-and-forward delivery, the sender seals it under a key shared only
+//! with the recipient. The server stores and forwards the sealed object, but is unable to decrypt it.
+//!
+//! A sealed object consists of a header holding the id of the key it was sealed under, a random salt, and the length of
+//! the plaintext, followed by frames of
+//! at most [`SEALED_FRAME_LEN`] bytes of plaintext, each sealed with ChaCha20-Poly1305 under a key derived from the shared
+//! key and the salt. Each nonce holds the index of its frame and marks the final frame, and every frame authenticates the
+//! header, such that frames cannot be reordered, dropped, or moved between objects. Callers may additionally bind an
+//! object to its context (such as its sender) through associated data, which every frame authenticates alongside the header
+use crate::misc::CryptError;
+use crate::streaming_crypt_scrambler::{FixedSizedStream, ObjectSource, ObjectStream};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use num_integer::Integer;
+use rand::RngCore;
+use sha3::Digest;
+use std::io::Read;
+
+/// The number of plaintext bytes sealed within each frame
+pub const SEALED_FRAME_LEN: usize = 64 * 1024;
+const KEY_ID_LEN: usize = 8;
+const SALT_LEN: usize = 32;
+const HEADER_LEN: usize = KEY_ID_LEN + SALT_LEN + 8;
+const TAG_LEN: usize = 16;
+/// Domain separator for the per-object key
+const SEALING_LABEL: &[u8] = b"citadel-sealed-object";
+/// Domain separator for the id of a key
+const KEY_ID_LABEL: &[u8] = b"citadel-sealing-key-id";
+
+/// A key shared between the sender and recipient of sealed objects
+pub type SealingKey = [u8; 32];
+/// Identifies a [`SealingKey`] without revealing it, allowing the recipient to select the key an object was sealed
+/// under from among the keys it shares with the sender
+pub type SealingKeyId = u64;
+
+/// Returns the id of the key, as written into the header of each object sealed under the key
+pub fn sealing_key_id(key: &SealingKey) -> SealingKeyId {
+    let mut hasher = sha3::Sha3_256::default();
+    hasher.update(KEY_ID_LABEL);
+    hasher.update(key);
+    let digest: [u8; 32] = hasher.finalize().into();
+    let mut key_id = [0u8; KEY_ID_LEN];
+    key_id.copy_from_slice(&digest[..KEY_ID_LEN]);
+    SealingKeyId::from_be_bytes(key_id)
+}
+
+/// Returns the id of the key the object was sealed under, or None if the object is too short to hold one
+pub fn sealed_key_id(sealed: &[u8]) -> Option<SealingKeyId> {
+    let key_id = sealed.get(..KEY_ID_LEN)?;
+    Some(SealingKeyId::from_be_bytes(key_id.try_into().ok()?))
+}
+
+/// Returns the number of bytes of a sealed object, given the number of bytes of its plaintext
+pub fn sealed_len(plaintext_length: u64) -> u64 {
+    HEADER_LEN as u64 + plaintext_length + frame_count(plaintext_length) * TAG_LEN as u64
+}
+
+// even an empty object has one frame, such that its length is always authenticated
+fn frame_count(plaintext_length: u64) -> u64 {
+    std::cmp::max(
+        1,
+        Integer::div_ceil(&plaintext_length, &(SEALED_FRAME_LEN as u64)),
+    )
+}
+
+// the id of the key, a random salt, and the length of the plaintext
+fn new_header(key: &SealingKey, plaintext_length: u64) -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[..KEY_ID_LEN].copy_from_slice(&sealing_key_id(key).to_be_bytes());
+    rand::thread_rng().fill_bytes(&mut header[KEY_ID_LEN..KEY_ID_LEN + SALT_LEN]);
+    header[KEY_ID_LEN + SALT_LEN..].copy_from_slice(&plaintext_length.to_be_bytes());
+    header
+}
+
+/// Seals an object held entirely in memory, such as a message
+pub fn seal(key: &SealingKey, plaintext: &[u8]) -> Result<Vec<u8>, CryptError> {
+    seal_with_associated_data(key, &[], plaintext)
+}
+
+/// Seals an object held entirely in memory, additionally authenticating `associated_data`. The associated data is not
+/// stored in the sealed object; the same associated data must be given to [`open_with_associated_data`]
+pub fn seal_with_associated_data(
+    key: &SealingKey,
+    associated_data: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+    let header = new_header(key, plaintext.len() as u64);
+    let cipher = FrameCipher::new(key, header, associated_data);
+    let mut sealed = Vec::with_capacity(sealed_len(plaintext.len() as u64) as usize);
+    sealed.extend_from_slice(&header);
+
+    for index in 0..cipher.frame_count {
+        let start = index as usize * SEALED_FRAME_LEN;
+        let frame = &plaintext[start..start + cipher.frame_len(index)];
+        sealed.extend(cipher.seal(index, frame)?);
+    }
+
+    Ok(sealed)
+}
+
+/// Opens an object sealed by [`seal`], or by a [`SealedSource`]
+pub fn open(key: &SealingKey, sealed: &[u8]) -> Result<Vec<u8>, CryptError> {
+    open_with_associated_data(key, &[], sealed)
+}
+
+/// Opens an object sealed by [`seal_with_associated_data`]. Fails unless `associated_data` matches the associated data
+/// given when sealing
+pub fn open_with_associated_data(
+    key: &SealingKey,
+    associated_data: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+    let mut unsealer = ObjectUnsealer::new(*key);
+    unsealer.associated_data = associated_data.to_vec();
+    let plaintext = unsealer.push(sealed)?;
+    unsealer.finish()?;
+    Ok(plaintext)
+}
+
+struct FrameCipher {
+    cipher: ChaCha20Poly1305,
+    // the header, followed by any associated data
+    aad: Vec<u8>,
+    plaintext_length: u64,
+    frame_count: u64,
+}
+
+impl FrameCipher {
+    fn new(key: &SealingKey, header: [u8; HEADER_LEN], associated_data: &[u8]) -> Self {
+        let mut hasher = sha3::Sha3_256::default();
+        hasher.update(SEALING_LABEL);
+        hasher.update(key);
+        hasher.update(&header[KEY_ID_LEN..KEY_ID_LEN + SALT_LEN]);
+        let object_key: [u8; 32] = hasher.finalize().into();
+        let mut plaintext_length = [0u8; 8];
+        plaintext_length.copy_from_slice(&header[KEY_ID_LEN + SALT_LEN..]);
+        let plaintext_length = u64::from_be_bytes(plaintext_length);
+
+        let mut aad = Vec::with_capacity(HEADER_LEN + associated_data.len());
+        aad.extend_from_slice(&header);
+        aad.extend_from_slice(associated_data);
+
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&object_key)),
+            aad,
+            plaintext_length,
+            frame_count: frame_count(plaintext_length),
+        }
+    }
+
+    /// Returns the number of plaintext bytes in the given frame
+    fn frame_len(&self, index: u64) -> usize {
+        let remaining = self.plaintext_length - index * SEALED_FRAME_LEN as u64;
+        std::cmp::min(remaining, SEALED_FRAME_LEN as u64) as usize
+    }
+
+    fn nonce(&self, index: u64) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..8].copy_from_slice(&index.to_be_bytes());
+        nonce[11] = (index + 1 == self.frame_count) as u8;
+        nonce
+    }
+
+    fn seal(&self, index: u64, plaintext: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.cipher
+            .encrypt(
+                Nonce::from_slice(&self.nonce(index)),
+                Payload {
+                    msg: plaintext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| CryptError::Encrypt("Unable to seal frame".to_string()))
+    }
+
+    fn open(&self, index: u64, ciphertext: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(&self.nonce(index)),
+                Payload {
+                    msg: ciphertext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| CryptError::Decrypt("Unable to open sealed frame".to_string()))
+    }
+}
+
+/// Seals an object for a recipient, such that only holders of the [`SealingKey`] may read it. The source must have a
+/// known length
+pub struct SealedSource<S> {
+    source: S,
+    key: SealingKey,
+}
+
+impl<S: ObjectSource> SealedSource<S> {
+    pub fn new(source: S, key: SealingKey) -> Self {
+        Self { source, key }
+    }
+}
+
+impl<S: ObjectSource> ObjectSource for SealedSource<S> {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        let stream = match self.source.try_get_stream()? {
+            ObjectStream::Fixed(stream) => stream,
+            ObjectStream::Async(_) => {
+                return Err(CryptError::Encrypt(
+                    "Sealed objects require a source of known length".to_string(),
+                ))
+            }
+        };
+
+        let plaintext_length = stream
+            .length()
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+        let header = new_header(&self.key, plaintext_length);
+
+        Ok(ObjectStream::Fixed(Box::new(SealingReader {
+            inner: stream,
+            cipher: FrameCipher::new(&self.key, header, &[]),
+            len: sealed_len(plaintext_length),
+            next_frame: 0,
+            pending: header.to_vec(),
+            pending_offset: 0,
+        })))
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        self.source.get_source_name()
+    }
+}
+
+/// Seals each frame of the inner stream as it is read
+struct SealingReader {
+    inner: Box<dyn FixedSizedStream>,
+    cipher: FrameCipher,
+    len: u64,
+    next_frame: u64,
+    // the sealed bytes not yet read
+    pending: Vec<u8>,
+    pending_offset: usize,
+}
+
+impl Read for SealingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending_offset == self.pending.len() {
+            if self.next_frame == self.cipher.frame_count {
+                return Ok(0);
+            }
+
+            let frame_len = self.cipher.frame_len(self.next_frame);
+            let mut frame = Vec::with_capacity(frame_len);
+            let _ = self
+                .inner
+                .by_ref()
+                .take(frame_len as u64)
+                .read_to_end(&mut frame)?;
+            if frame.len() != frame_len {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "The source ended before its stated length",
+                ));
+            }
+
+            self.pending = self
+                .cipher
+                .seal(self.next_frame, &frame)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.into_string()))?;
+            self.pending_offset = 0;
+            self.next_frame += 1;
+        }
+
+        let len = std::cmp::min(buf.len(), self.pending.len() - self.pending_offset);
+        buf[..len].copy_from_slice(&self.pending[self.pending_offset..self.pending_offset + len]);
+        self.pending_offset += len;
+        Ok(len)
+    }
+}
+
+impl FixedSizedStream for SealingReader {
+    fn length(&self) -> std::io::Result<u64> {
+        Ok(self.len)
+    }
+}
+
+/// Opens a sealed object incrementally, as its bytes arrive in arbitrarily-sized chunks
+pub struct ObjectUnsealer {
+    key: SealingKey,
+    associated_data: Vec<u8>,
+    cipher: Option<FrameCipher>,
+    buffer: Vec<u8>,
+    next_frame: u64,
+}
+
+impl ObjectUnsealer {
+    pub fn new(key: SealingKey) -> Self {
+        Self {
+            key,
+            associated_data: Vec::new(),
+            cipher: None,
+            buffer: Vec::new(),
+            next_frame: 0,
+        }
+    }
+
+    /// Consumes the next chunk of the sealed object, returning the plaintext of every frame completed by the chunk
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.buffer.extend_from_slice(chunk);
+        let mut plaintext = Vec::new();
+
+        if self.cipher.is_none() {
+            if self.buffer.len() < HEADER_LEN {
+                return Ok(plaintext);
+            }
+
+            if sealed_key_id(&self.buffer) != Some(sealing_key_id(&self.key)) {
+                return Err(CryptError::Decrypt(
+                    "The object was sealed under a different key".to_string(),
+                ));
+            }
+
+            let mut header = [0u8; HEADER_LEN];
+            header.copy_from_slice(&self.buffer[..HEADER_LEN]);
+            let _ = self.buffer.drain(..HEADER_LEN);
+            self.cipher = Some(FrameCipher::new(&self.key, header, &self.associated_data));
+        }
+
+        let cipher = self.cipher.as_ref().unwrap();
+        while self.next_frame < cipher.frame_count {
+            let frame_len = cipher.frame_len(self.next_frame) + TAG_LEN;
+            if self.buffer.len() < frame_len {
+                break;
+            }
+
+            plaintext.extend(cipher.open(self.next_frame, &self.buffer[..frame_len])?);
+            let _ = self.buffer.drain(..frame_len);
+            self.next_frame += 1;
+        }
+
+        if self.next_frame == cipher.frame_count && !self.buffer.is_empty() {
+            return Err(CryptError::Decrypt(
+                "Unexpected bytes after the final sealed frame".to_string(),
+            ));
+        }
+
+        Ok(plaintext)
+    }
+
+    /// Ensures the entire object was opened. Must be called once the sealed object ends
+    pub fn finish(self) -> Result<(), CryptError> {
+        match self.cipher {
+            Some(cipher) if self.next_frame == cipher.frame_count => Ok(()),
+            _ => Err(CryptError::Decrypt(
+                "The sealed object ended before its final frame".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Guarded memory for long-lived secrets
+//!
+//! Each allocation is placed in its own set of pages, surrounded by inaccessible guard pages. The pages holding the
+//! data are locked into RAM (preventing them from being swapped to disk), and are excluded from core dumps. The data is
+//! placed at the end of its pages, such that reading or writing past the end immediately faults on the trailing guard page.
+//!
+//! Guarded memory requires the `guarded-memory` feature, and is currently only supported on Linux. On other platforms,
+//! allocation returns an [`ErrorKind::Unsupported`] error, and callers fall back to locked heap memory
+use std::io::ErrorKind;
+
+/// A fixed-length region of guarded memory. The contents are zeroed when dropped
+pub struct GuardedAllocation {
+    region: sys::Region,
+}
+
+impl GuardedAllocation {
+    /// Allocates guarded memory holding a copy of `input`
+    pub fn from_slice(input: &[u8]) -> std::io::Result<Self> {
+        let mut region = sys::Region::new(input.len())?;
+        region.as_mut_slice().copy_from_slice(input);
+        Ok(Self { region })
+    }
+
+    /// Returns the number of bytes stored
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.region.as_slice()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.region.as_mut_slice()
+    }
+
+    /// Copies the contents into a new guarded allocation. Fails if the new pages cannot be mapped or locked, e.g., when
+    /// `RLIMIT_MEMLOCK` is exhausted
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Self::from_slice(self.as_slice())
+    }
+}
+
+/// Returns true if guarded memory is available on this build and platform
+pub const fn is_supported() -> bool {
+    sys::SUPPORTED
+}
+
+pub(crate) fn is_unsupported(err: &std::io::Error) -> bool {
+    err.kind() == ErrorKind::Unsupported
+}
+
+#[cfg(all(feature = "guarded-memory", target_os = "linux"))]
+mod sys {
+    use std::ptr::NonNull;
+
+    pub const SUPPORTED: bool = true;
+
+    pub struct Region {
+        base: NonNull<u8>,
+        page_size: usize,
+        // the number of accessible bytes between the two guard pages
+        capacity: usize,
+        // the number of bytes in use, placed at the end of the accessible pages
+        len: usize,
+    }
+
+    // The region is uniquely owned, and is only ever accessed through &self/&mut self
+    unsafe impl Send for Region {}
+    unsafe impl Sync for Region {}
+
+    impl Region {
+        pub fn new(len: usize) -> std::io::Result<Self> {
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            let capacity = len.max(1).div_ceil(page_size) * page_size;
+            let total_len = capacity + 2 * page_size;
+
+            unsafe {
+                let base = libc::mmap(
+                    std::ptr::null_mut(),
+                    total_len,
+                    libc::PROT_NONE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                );
+
+                if base == libc::MAP_FAILED {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                let this = Self {
+                    base: NonNull::new_unchecked(base as *mut u8),
+                    page_size,
+                    capacity,
+                    len,
+                };
+
+                let data = this.data_ptr() as *mut libc::c_void;
+                if libc::mprotect(data, capacity, libc::PROT_READ | libc::PROT_WRITE) != 0
+                    || libc::mlock(data, capacity) != 0
+                    || libc::madvise(data, capacity, libc::MADV_DONTDUMP) != 0
+                {
+                    // dropping unmaps the region
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                Ok(this)
+            }
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            unsafe {
+                std::slice::from_raw_parts(self.data_ptr().add(self.capacity - self.len), self.len)
+            }
+        }
+
+        pub fn as_mut_slice(&mut self) -> &mut [u8] {
+            unsafe {
+                std::slice::from_raw_parts_mut(
+                    self.data_ptr().add(self.capacity - self.len),
+                    self.len,
+                )
+            }
+        }
+
+        fn data_ptr(&self) -> *mut u8 {
+            unsafe { self.base.as_ptr().add(self.page_size) }
+        }
+
+        fn total_len(&self) -> usize {
+            self.capacity + 2 * self.page_size
+        }
+    }
+
+    impl Drop for Region {
+        #[allow(unused_results)]
+        fn drop(&mut self) {
+            unsafe {
+                // if the data pages were never made accessible, the mprotect call fails and the pages are not zeroed
+                let data = self.data_ptr();
+                if libc::mprotect(
+                    data as *mut libc::c_void,
+                    self.capacity,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                ) == 0
+                {
+                    crate::misc::zeroize(data, self.capacity);
+                }
+
+                libc::munlock(data as *const libc::c_void, self.capacity);
+                libc::munmap(self.base.as_ptr() as *mut libc::c_void, self.total_len());
+            }
+        }
+    }
+}
+
+#[cfg(not(all(feature = "guarded-memory", target_os = "linux")))]
+mod sys {
+    use std::io::{Error, ErrorKind};
+
+    pub const SUPPORTED: bool = false;
+
+    pub enum Region {}
+
+    impl Region {
+        pub fn new(_len: usize) -> std::io::Result<Self> {
+            Err(Error::new(
+                ErrorKind::Unsupported,
+                "Guarded memory is not supported on this build",
+            ))
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            match *self {}
+        }
+
+        pub fn as_mut_slice(&mut self) -> &mut [u8] {
+            match *self {}
+        }
+    }
+}
@@ -1,3 +1,5 @@
+/// Locked memory surrounded by guard pages, for long-lived secrets
+pub mod guarded_alloc;
 /// For efficient writing to data
 pub mod partitioned_sec_buffer;
 ///
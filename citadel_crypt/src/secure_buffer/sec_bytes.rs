@@ -1,3 +1,4 @@
+use crate::secure_buffer::guarded_alloc::GuardedAllocation;
 use crate::secure_buffer::sec_string::SecString;
 use bytes::BytesMut;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -6,11 +7,39 @@ use std::fmt::Formatter;
 use std::ops::{Deref, DerefMut};
 
 /// A memory-secure wrapper for shipping around Bytes
+///
+/// By default, the bytes are stored in locked heap memory. Buffers created via [`SecBuffer::new_guarded`] are instead
+/// stored in guarded memory (see [`guarded_alloc`](crate::secure_buffer::guarded_alloc)), which is better suited for
+/// long-lived secrets such as passwords
 pub struct SecBuffer {
     inner: BytesMut,
+    guarded: Option<GuardedAllocation>,
 }
 
 impl SecBuffer {
+    /// Creates a buffer backed by guarded memory. If guarded memory is unavailable, falls back to locked heap memory
+    pub fn new_guarded<T: AsRef<[u8]>>(input: T) -> Self {
+        match GuardedAllocation::from_slice(input.as_ref()) {
+            Ok(guarded) => Self {
+                inner: BytesMut::new(),
+                guarded: Some(guarded),
+            },
+
+            Err(err) => {
+                if !super::guarded_alloc::is_unsupported(&err) {
+                    log::warn!(target: "citadel", "Unable to allocate guarded memory, falling back to the heap: {:?}", err);
+                }
+
+                Self::from(input.as_ref())
+            }
+        }
+    }
+
+    /// Returns true if this buffer is backed by guarded memory
+    pub fn is_guarded(&self) -> bool {
+        self.guarded.is_some()
+    }
+
     /// Creates an unlocked, empty buffer
     pub fn empty() -> Self {
         Self::with_capacity(0)
@@ -20,8 +49,12 @@ impl SecBuffer {
         Self::from(BytesMut::with_capacity(cap))
     }
 
-    /// Returns the inner element without dropping the memory
+    /// Returns the inner element without dropping the memory. If guarded, the bytes are copied out
     pub fn into_buffer(mut self) -> BytesMut {
+        if let Some(guarded) = self.guarded.as_ref() {
+            return BytesMut::from(guarded.as_slice());
+        }
+
         self.unlock();
         std::mem::take(&mut self.inner)
     }
@@ -33,23 +66,26 @@ impl SecBuffer {
 
     /// returns the length of the buffer
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.slice().len()
     }
 
     fn lock(&self) {
-        unsafe { crate::misc::mlock(self.slice().as_ptr(), self.inner.len()) }
+        unsafe { crate::misc::mlock(self.inner.as_ptr(), self.inner.len()) }
     }
 
     fn unlock(&self) {
-        unsafe { crate::misc::munlock(self.slice().as_ptr(), self.inner.len()) }
+        unsafe { crate::misc::munlock(self.inner.as_ptr(), self.inner.len()) }
     }
 
     fn zeroize(&mut self) {
-        unsafe { crate::misc::zeroize(self.slice().as_ptr(), self.inner.len()) }
+        unsafe { crate::misc::zeroize(self.inner.as_ptr(), self.inner.len()) }
     }
 
     fn slice(&self) -> &[u8] {
-        &self.inner[..]
+        match self.guarded.as_ref() {
+            Some(guarded) => guarded.as_slice(),
+            None => &self.inner[..],
+        }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -57,14 +93,24 @@ impl SecBuffer {
     }
 }
 
+/// Guarded memory has a fixed length. Thus, while a handle to a guarded buffer is alive, the bytes are moved into heap memory,
+/// and are moved back into a new guarded allocation once the handle is dropped
 pub struct SecureBufMutHandle<'a> {
     inner: &'a mut SecBuffer,
+    was_guarded: bool,
 }
 
 impl<'a> SecureBufMutHandle<'a> {
     fn new(inner: &'a mut SecBuffer) -> SecureBufMutHandle<'a> {
+        let was_guarded = if let Some(guarded) = inner.guarded.take() {
+            inner.inner = BytesMut::from(guarded.as_slice());
+            true
+        } else {
+            false
+        };
+
         inner.unlock();
-        Self { inner }
+        Self { inner, was_guarded }
     }
 }
 
@@ -84,19 +130,31 @@ impl DerefMut for SecureBufMutHandle<'_> {
 
 impl Drop for SecureBufMutHandle<'_> {
     fn drop(&mut self) {
+        if self.was_guarded {
+            if let Ok(guarded) = GuardedAllocation::from_slice(&self.inner.inner) {
+                self.inner.zeroize();
+                self.inner.inner = BytesMut::new();
+                self.inner.guarded = Some(guarded);
+                return;
+            }
+        }
+
         self.inner.lock()
     }
 }
 
 impl AsRef<[u8]> for SecBuffer {
     fn as_ref(&self) -> &[u8] {
-        &self.inner[..]
+        self.slice()
     }
 }
 
 impl AsMut<[u8]> for SecBuffer {
     fn as_mut(&mut self) -> &mut [u8] {
-        self.inner.as_mut()
+        match self.guarded.as_mut() {
+            Some(guarded) => guarded.as_mut_slice(),
+            None => self.inner.as_mut(),
+        }
     }
 }
 
@@ -114,7 +172,10 @@ impl From<SecString> for SecBuffer {
 
 impl From<BytesMut> for SecBuffer {
     fn from(inner: BytesMut) -> Self {
-        let this = Self { inner };
+        let this = Self {
+            inner,
+            guarded: None,
+        };
         this.lock();
         this
     }
@@ -159,6 +220,10 @@ impl<T: AsRef<[u8]>> PartialEq<T> for SecBuffer {
 
 impl Clone for SecBuffer {
     fn clone(&self) -> Self {
+        if self.is_guarded() {
+            return SecBuffer::new_guarded(self.slice());
+        }
+
         self.unlock();
         let ret = SecBuffer::from(self.inner.clone());
         self.lock();
@@ -171,6 +236,10 @@ impl Serialize for SecBuffer {
     where
         S: Serializer,
     {
+        if let Some(guarded) = self.guarded.as_ref() {
+            return serializer.serialize_bytes(guarded.as_slice());
+        }
+
         self.unlock();
         let ret = self.inner.serialize(serializer);
         self.lock();
@@ -1,11 +1,34 @@
+use crate::secure_buffer::guarded_alloc::GuardedAllocation;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::Formatter;
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
 /// Allows mutable access
+///
+/// Strings created via [`SecString::new_guarded`] are stored in guarded memory. Since guarded memory has a fixed length,
+/// each mutation of a guarded string copies it into a new guarded allocation
 pub struct SecString {
     inner: String,
+    guarded: Option<GuardedString>,
+}
+
+/// A string stored in guarded memory. The guarded bytes are only ever exposed as a `&str`; a `String` is never built
+/// over the allocation, since the guarded pages are not owned by the global allocator
+struct GuardedString {
+    allocation: GuardedAllocation,
+}
+
+impl GuardedString {
+    fn new(input: &str) -> std::io::Result<Self> {
+        let allocation = GuardedAllocation::from_slice(input.as_bytes())?;
+        Ok(Self { allocation })
+    }
+
+    fn as_str(&self) -> &str {
+        // the bytes are a copy of a valid str, and are never mutated afterwards
+        std::str::from_utf8(self.allocation.as_slice()).expect("Guarded string is valid UTF-8")
+    }
 }
 
 impl SecString {
@@ -13,18 +36,45 @@ impl SecString {
     pub const fn new() -> Self {
         Self {
             inner: String::new(),
+            guarded: None,
+        }
+    }
+
+    /// Creates a string backed by guarded memory. If guarded memory is unavailable, falls back to locked heap memory
+    pub fn new_guarded<T: AsRef<str>>(input: T) -> Self {
+        match GuardedString::new(input.as_ref()) {
+            Ok(guarded) => Self {
+                inner: String::new(),
+                guarded: Some(guarded),
+            },
+
+            Err(err) => {
+                if !super::guarded_alloc::is_unsupported(&err) {
+                    log::warn!(target: "citadel", "Unable to allocate guarded memory, falling back to the heap: {:?}", err);
+                }
+
+                Self::from(input.as_ref())
+            }
         }
     }
 
+    /// Returns true if this string is backed by guarded memory
+    pub fn is_guarded(&self) -> bool {
+        self.guarded.is_some()
+    }
+
     /// Safely pushes a new character
     pub fn push(&mut self, val: char) {
-        self.unlock();
-        self.inner.push(val);
-        self.lock();
+        self.modify(|inner| inner.push(val))
     }
 
     /// Clears and zeroizes the vector. Keeps the allocation in-tact
     pub fn clear(&mut self) {
+        if self.guarded.is_some() {
+            self.guarded = GuardedString::new("").ok();
+            return;
+        }
+
         self.unlock();
         self.zeroize();
         self.inner.clear();
@@ -33,25 +83,53 @@ impl SecString {
 
     /// Inserts a char at `pos`
     pub fn insert(&mut self, pos: usize, val: char) {
-        self.unlock();
-        self.inner.insert(pos, val);
-        self.lock();
+        self.modify(|inner| inner.insert(pos, val))
     }
 
     /// removes a char at `pos`
     pub fn remove(&mut self, pos: usize) -> char {
-        self.unlock();
-        let val = self.inner.remove(pos);
-        self.lock();
-        val
+        self.modify(|inner| inner.remove(pos))
     }
 
-    /// Gets the inner string
+    /// Returns the string as a slice
+    pub fn as_str(&self) -> &str {
+        self.deref()
+    }
+
+    /// Gets the inner string. If guarded, the string is copied out
     pub fn into_buffer(mut self) -> String {
+        if self.guarded.is_some() {
+            return self.as_str().to_string();
+        }
+
         self.unlock();
         std::mem::take(&mut self.inner)
     }
 
+    /// Applies `modify` to the string. Guarded strings are copied into locked heap memory with enough spare capacity to
+    /// avoid reallocations, then moved back into a new guarded allocation
+    fn modify<R>(&mut self, modify: impl FnOnce(&mut String) -> R) -> R {
+        let Some(guarded) = self.guarded.take() else {
+            self.unlock();
+            let ret = modify(&mut self.inner);
+            self.lock();
+            return ret;
+        };
+
+        let mut inner = String::with_capacity(guarded.allocation.len() + 4);
+        inner.push_str(guarded.as_str());
+        let mut tmp = Self::from(inner);
+        let ret = modify(&mut tmp.inner);
+
+        match GuardedString::new(tmp.inner.as_str()) {
+            Ok(guarded) => self.guarded = Some(guarded),
+            // keep the locked heap copy
+            Err(_) => std::mem::swap(self, &mut tmp),
+        }
+
+        ret
+    }
+
     fn lock(&self) {
         let (ptr, len) = decompose(&self.inner);
         unsafe { crate::misc::mlock(ptr, len) }
@@ -71,6 +149,7 @@ impl<T: Into<String>> From<T> for SecString {
     fn from(inner: T) -> Self {
         let this = Self {
             inner: inner.into(),
+            guarded: None,
         };
         this.lock();
         this
@@ -86,9 +165,13 @@ impl Drop for SecString {
 
 impl Clone for SecString {
     fn clone(&self) -> Self {
+        if self.is_guarded() {
+            return Self::new_guarded(self.as_str());
+        }
+
         self.unlock();
         let ret = Self::from(self.inner.clone());
-        self.unlock();
+        self.lock();
         ret
     }
 }
@@ -100,10 +183,13 @@ impl Default for SecString {
 }
 
 impl Deref for SecString {
-    type Target = String;
+    type Target = str;
 
     fn deref(&self) -> &Self::Target {
-        &self.inner
+        match self.guarded.as_ref() {
+            Some(guarded) => guarded.as_str(),
+            None => self.inner.as_str(),
+        }
     }
 }
 
@@ -131,7 +217,7 @@ impl Serialize for SecString {
         S: Serializer,
     {
         self.unlock();
-        let res = serializer.serialize_bytes(self.as_ref());
+        let res = serializer.serialize_bytes(self.as_bytes());
         self.lock();
         res
     }
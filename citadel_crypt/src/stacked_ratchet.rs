@@ -369,8 +369,58 @@ impl StackedRatchet {
     pub fn get_default_security_level(&self) -> SecurityLevel {
         self.inner.default_security_level
     }
+
+    /// Derives `len` bytes of keying material bound to this ratchet version, in the style of RFC 5705. Both endpoints
+    /// derive identical output for the same `label` and `context`, whereas distinct labels or contexts yield independent keys.
+    /// The output is derived from the shared secrets of every PQC in the ratchet, and is never used by the protocol itself
+    pub fn export_keying_material(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, CryptError<String>> {
+        use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+        if len == 0 {
+            return Err(CryptError::OutOfBoundsError);
+        }
+
+        let mut hasher = sha3::Shake256::default();
+        // each input is length-prefixed to prevent ambiguity between adjacent inputs
+        let mut absorb = |input: &[u8]| {
+            Update::update(&mut hasher, &(input.len() as u64).to_be_bytes());
+            Update::update(&mut hasher, input);
+        };
+
+        absorb(KEYING_MATERIAL_EXPORTER_LABEL);
+        absorb(label);
+        absorb(context);
+        absorb(&(len as u64).to_be_bytes());
+        absorb(&self.version().to_be_bytes());
+
+        let pqcs = self
+            .inner
+            .message
+            .inner
+            .iter()
+            .map(|r| &r.pqc)
+            .chain(std::iter::once(self.get_scramble_pqc()));
+        for pqc in pqcs {
+            let shared_secret = pqc
+                .get_shared_secret()
+                .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+            absorb(&shared_secret[..]);
+        }
+
+        let mut output = vec![0u8; len];
+        hasher.finalize_xof().read(&mut output);
+        Ok(output)
+    }
 }
 
+/// Domain separator for [`StackedRatchet::export_keying_material`]
+const KEYING_MATERIAL_EXPORTER_LABEL: &[u8] = b"citadel-keying-material-exporter";
+
 #[derive(Serialize, Deserialize, Debug)]
 ///
 pub struct StackedRatchetInner {
@@ -408,6 +458,7 @@ impl From<StackedRatchetInner> for StackedRatchet {
 
 /// For constructing the StackedRatchet during KEM stage
 pub mod constructor {
+    use crate::double_ratchet::{DoubleRatchetAliceToBobTransfer, DoubleRatchetBobToAliceTransfer};
     use crate::endpoint_crypto_container::EndpointRatchetConstructor;
     use crate::entropy_bank::{EntropyBank, SecurityLevel};
     use crate::fcm::fcm_ratchet::{FcmAliceToBobTransfer, FcmBobToAliceTransfer, ThinRatchet};
@@ -495,6 +546,7 @@ pub mod constructor {
     pub enum AliceToBobTransferType {
         Default(AliceToBobTransfer),
         Fcm(FcmAliceToBobTransfer),
+        DoubleRatchet(DoubleRatchetAliceToBobTransfer),
     }
 
     impl AliceToBobTransferType {
@@ -502,6 +554,7 @@ pub mod constructor {
             match self {
                 AliceToBobTransferType::Default(tx) => tx.new_version,
                 AliceToBobTransferType::Fcm(tx) => tx.version,
+                AliceToBobTransferType::DoubleRatchet(tx) => tx.version,
             }
         }
     }
@@ -590,6 +643,7 @@ pub mod constructor {
     pub enum BobToAliceTransferType {
         Default(BobToAliceTransfer),
         Fcm(FcmBobToAliceTransfer),
+        DoubleRatchet(DoubleRatchetBobToAliceTransfer),
     }
 
     impl BobToAliceTransfer {
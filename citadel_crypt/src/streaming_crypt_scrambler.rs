@@ -1,28 +1,63 @@
-use bytes::BytesMut;
-use futures::task::Context;
+use bytes::{Bytes, BytesMut};
 use std::io::{BufReader, Read};
-use tokio::macros::support::Pin;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::sync::mpsc::Sender as GroupChanneler;
-use tokio::sync::oneshot::Receiver;
+use tokio::sync::oneshot::{Receiver, Sender};
 
+use crate::directory_source::DirectoryManifest;
 use crate::entropy_bank::{EntropyBank, SecurityLevel};
 use crate::packet_vector::PacketVector;
 use crate::scramble::crypt_splitter::{par_scramble_encrypt_group, GroupSenderDevice};
 
-use crate::misc::blocking_spawn::{BlockingSpawn, BlockingSpawnError};
 use crate::misc::CryptError;
 use crate::stacked_ratchet::StackedRatchet;
-use citadel_runtime::Mutex;
-use futures::Future;
 use num_integer::Integer;
+use serde::{Deserialize, Serialize};
+use sha3::Digest;
 use std::sync::Arc;
-use std::task::Poll;
-use tokio_stream::{Stream, StreamExt};
 
 /// 3Mb per group
 pub const MAX_BYTES_PER_GROUP: usize = crate::scramble::crypt_splitter::MAX_BYTES_PER_GROUP;
 const DEFAULT_BYTES_PER_GROUP: usize = 1024 * 1024 * 3;
 
+/// A SHA3-256 digest of an object's plaintext. The object digest is computed over the digests of each group in order,
+/// allowing the receiver of a resumed transfer to verify the entire object using the group digests it recorded before the
+/// interruption
+pub type ObjectDigest = [u8; 32];
+
+/// Returns the digest of a single group's plaintext
+pub fn group_digest(plaintext: &[u8]) -> ObjectDigest {
+    sha3::Sha3_256::digest(plaintext).into()
+}
+
+/// Returns the digest of an object, given the digests of each of its groups in order
+pub fn object_digest(group_digests: &[ObjectDigest]) -> ObjectDigest {
+    let mut hasher = sha3::Sha3_256::default();
+    for digest in group_digests {
+        hasher.update(digest);
+    }
+    hasher.finalize().into()
+}
+
+/// Describes an object once its source has been read in its entirety
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ObjectSummary {
+    pub digest: ObjectDigest,
+    pub plaintext_length: usize,
+    pub group_count: usize,
+}
+
+/// The group from which the async cryptscrambler begins rendering
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct StartGroup {
+    /// The number of groups skipped
+    pub group: usize,
+    /// When resuming, the [`object_digest`] the receiver recorded over the skipped groups. The skipped groups of the source
+    /// must match this digest, ensuring that a source modified since the transfer was interrupted is never spliced onto
+    /// the groups received beforehand
+    pub prefix_digest: Option<ObjectDigest>,
+}
+
 /// Used for streaming sources of a fixed size
 pub trait FixedSizedStream: Read + Send + 'static {
     fn length(&self) -> std::io::Result<u64>;
@@ -49,18 +84,62 @@ impl<
 {
 }
 
+impl FixedSizedStream for std::io::Cursor<Vec<u8>> {
+    fn length(&self) -> std::io::Result<u64> {
+        Ok(self.get_ref().len() as u64)
+    }
+}
+
+impl FixedSizedStream for std::io::Cursor<Bytes> {
+    fn length(&self) -> std::io::Result<u64> {
+        Ok(self.get_ref().len() as u64)
+    }
+}
+
+/// The stream an [`ObjectSource`] is read from
+pub enum ObjectStream {
+    /// A blocking reader whose length is known before reading
+    Fixed(Box<dyn FixedSizedStream>),
+    /// An asynchronous reader whose length is only discovered once it reaches EOF
+    Async(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+impl ObjectStream {
+    /// Returns the length of the stream, or None if unknown until EOF
+    pub fn length(&self) -> std::io::Result<Option<u64>> {
+        match self {
+            Self::Fixed(stream) => stream.length().map(Some),
+            Self::Async(_) => Ok(None),
+        }
+    }
+}
+
+impl From<Box<dyn FixedSizedStream>> for ObjectStream {
+    fn from(stream: Box<dyn FixedSizedStream>) -> Self {
+        Self::Fixed(stream)
+    }
+}
+
 #[auto_impl::auto_impl(Box)]
 pub trait ObjectSource: Send + Sync + 'static {
-    fn try_get_stream(&mut self) -> Result<Box<dyn FixedSizedStream>, CryptError>;
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError>;
     fn get_source_name(&self) -> Result<String, CryptError>;
+    /// Returns the manifest if the source is a directory, in which case the stream holds the contents of each file in the
+    /// manifest's order
+    fn get_manifest(&self) -> Option<&DirectoryManifest> {
+        None
+    }
 }
 
+/// The name given to in-memory objects, unless wrapped in a [`NamedSource`]
+pub const UNNAMED_OBJECT: &str = "untitled";
+
 #[cfg(feature = "filesystem")]
 impl ObjectSource for std::path::PathBuf {
-    fn try_get_stream(&mut self) -> Result<Box<dyn FixedSizedStream>, CryptError> {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
         std::fs::File::open(self)
             .map_err(|err| CryptError::Encrypt(err.to_string()))
-            .map(|r| Box::new(r) as Box<dyn FixedSizedStream>)
+            .map(|r| ObjectStream::Fixed(Box::new(r)))
     }
 
     fn get_source_name(&self) -> Result<String, CryptError> {
@@ -72,6 +151,87 @@ impl ObjectSource for std::path::PathBuf {
     }
 }
 
+impl ObjectSource for Vec<u8> {
+    /// The bytes are moved into the stream, and thus the object may only be read once
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        Ok(ObjectStream::Fixed(Box::new(std::io::Cursor::new(
+            std::mem::take(self),
+        ))))
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        Ok(UNNAMED_OBJECT.to_string())
+    }
+}
+
+impl ObjectSource for Bytes {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        Ok(ObjectStream::Fixed(Box::new(std::io::Cursor::new(
+            self.clone(),
+        ))))
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        Ok(UNNAMED_OBJECT.to_string())
+    }
+}
+
+/// Gives a name to a source, such as an in-memory object. The receiver uses the name when saving the object
+pub struct NamedSource<S> {
+    name: String,
+    source: S,
+}
+
+impl<S: ObjectSource> NamedSource<S> {
+    pub fn new<T: Into<String>>(name: T, source: S) -> Self {
+        Self {
+            name: name.into(),
+            source,
+        }
+    }
+}
+
+impl<S: ObjectSource> ObjectSource for NamedSource<S> {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        self.source.try_get_stream()
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        Ok(self.name.clone())
+    }
+}
+
+/// An asynchronous reader of unknown length, such as a database dump generated on the fly. Since the reader can only be
+/// read once, transfers from this source cannot be resumed if interrupted
+pub struct AsyncReadSource<R> {
+    name: String,
+    reader: std::sync::Mutex<Option<R>>,
+}
+
+impl<R: AsyncRead + Send + Unpin + 'static> AsyncReadSource<R> {
+    pub fn new<T: Into<String>>(name: T, reader: R) -> Self {
+        Self {
+            name: name.into(),
+            reader: std::sync::Mutex::new(Some(reader)),
+        }
+    }
+}
+
+impl<R: AsyncRead + Send + Unpin + 'static> ObjectSource for AsyncReadSource<R> {
+    fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
+        self.reader
+            .get_mut()
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?
+            .take()
+            .map(|reader| ObjectStream::Async(Box::new(reader)))
+            .ok_or_else(|| CryptError::Encrypt("The reader was already consumed".to_string()))
+    }
+
+    fn get_source_name(&self) -> Result<String, CryptError> {
+        Ok(self.name.clone())
+    }
+}
+
 /// As the networking protocol receives ACKs from the packets it gets from the sender, it should call the waker that this function sends through `waker_sender` once
 /// it is close to finishing the group (depending on speed).
 ///
@@ -79,14 +239,59 @@ impl ObjectSource for std::path::PathBuf {
 ///
 /// `header_inscriber`: the feed order for u64's is first the target_cid, and then the object-ID
 ///
-/// This is ran on a separate thread on the threadpool. Returns the number of bytes and number of groups
+/// This is ran on a separate thread on the threadpool. Returns the number of bytes and number of groups, both of which are
+/// zero if the length of the source is unknown until EOF
 #[allow(clippy::too_many_arguments)]
 pub fn scramble_encrypt_source<S: ObjectSource, F: HeaderInscriberFn, const N: usize>(
+    source: S,
+    max_group_size: Option<usize>,
+    object_id: u32,
+    group_sender: GroupChanneler<Result<GroupSenderDevice<N>, CryptError>>,
+    stop: Receiver<()>,
+    security_level: SecurityLevel,
+    hyper_ratchet: StackedRatchet,
+    header_size_bytes: usize,
+    target_cid: u64,
+    group_id: u64,
+    header_inscriber: F,
+) -> Result<(usize, usize), CryptError> {
+    let (start_group_tx, start_group_rx) = tokio::sync::oneshot::channel();
+    let _ = start_group_tx.send(StartGroup::default());
+    let (summary_tx, _summary_rx) = tokio::sync::oneshot::channel();
+    scramble_encrypt_source_resumable(
+        source,
+        max_group_size,
+        object_id,
+        group_sender,
+        stop,
+        start_group_rx,
+        summary_tx,
+        security_level,
+        hyper_ratchet,
+        header_size_bytes,
+        target_cid,
+        group_id,
+        header_inscriber,
+    )
+}
+
+/// Like [`scramble_encrypt_source`], but does not begin reading the source until the first group to render is sent through
+/// `start_group`. All groups before the first group are skipped, allowing an interrupted transfer to be resumed. Skipped groups
+/// still consume their group IDs, such that each group keeps the same ID it would have had if no groups were skipped. If
+/// the skipped groups do not match the given prefix digest, rendering fails before any group is sent.
+///
+/// Once the entire source has been read, its [`ObjectSummary`] (including the digest of any skipped groups) is sent through
+/// `summary`. The summary is always sent before the final group is sent through `group_sender`, allowing the final group
+/// to be identified even if the length of the source was unknown
+#[allow(clippy::too_many_arguments)]
+pub fn scramble_encrypt_source_resumable<S: ObjectSource, F: HeaderInscriberFn, const N: usize>(
     mut source: S,
     max_group_size: Option<usize>,
     object_id: u32,
     group_sender: GroupChanneler<Result<GroupSenderDevice<N>, CryptError>>,
     stop: Receiver<()>,
+    start_group: Receiver<StartGroup>,
+    summary: Sender<ObjectSummary>,
     security_level: SecurityLevel,
     hyper_ratchet: StackedRatchet,
     header_size_bytes: usize,
@@ -97,7 +302,8 @@ pub fn scramble_encrypt_source<S: ObjectSource, F: HeaderInscriberFn, const N: u
     let source = source.try_get_stream()?;
     let object_len = source
         .length()
-        .map_err(|err| CryptError::Encrypt(err.to_string()))? as usize;
+        .map_err(|err| CryptError::Encrypt(err.to_string()))?
+        .map(|len| len as usize);
     let max_bytes_per_group = max_group_size.unwrap_or(DEFAULT_BYTES_PER_GROUP);
 
     if max_bytes_per_group > MAX_BYTES_PER_GROUP {
@@ -107,22 +313,26 @@ pub fn scramble_encrypt_source<S: ObjectSource, F: HeaderInscriberFn, const N: u
         )));
     }
 
-    let total_groups = Integer::div_ceil(&object_len, &max_bytes_per_group);
+    let total_groups = object_len.map(|len| Integer::div_ceil(&len, &max_bytes_per_group));
+
+    if let (Some(object_len), Some(total_groups)) = (object_len, total_groups) {
+        log::trace!(target: "citadel", "Will parallel_scramble_encrypt file object {}, which is {} bytes or {} MB. {} groups total", object_id, object_len, (object_len as f32)/(1024f32*1024f32), total_groups);
+    } else {
+        log::trace!(target: "citadel", "Will parallel_scramble_encrypt file object {} of unknown length", object_id);
+    }
 
-    log::trace!(target: "citadel", "Will parallel_scramble_encrypt file object {}, which is {} bytes or {} MB. {} groups total", object_id, object_len, (object_len as f32)/(1024f32*1024f32), total_groups);
-    let reader = BufReader::with_capacity(std::cmp::min(object_len, max_bytes_per_group), source);
+    let reader = match source {
+        ObjectStream::Fixed(source) => GroupReader::Fixed(BufReader::with_capacity(
+            std::cmp::min(object_len.unwrap_or_default(), max_bytes_per_group),
+            source,
+        )),
+        ObjectStream::Async(source) => GroupReader::Async(source),
+    };
 
-    let buffer = Arc::new(Mutex::new(vec![
-        0u8;
-        std::cmp::min(
-            object_len,
-            max_bytes_per_group
-        )
-    ]));
     let file_scrambler = AsyncCryptScrambler {
         total_groups,
-        buffer,
         groups_rendered: 0,
+        group_digests: Vec::with_capacity(total_groups.unwrap_or_default()),
         object_id,
         header_size_bytes,
         target_cid,
@@ -132,16 +342,16 @@ pub fn scramble_encrypt_source<S: ObjectSource, F: HeaderInscriberFn, const N: u
         reader,
         file_len: object_len,
         max_bytes_per_group,
-        read_cursor: 0,
+        bytes_read: 0,
+        next_group: None,
+        summary: Some(summary),
         header_inscriber: Arc::new(header_inscriber),
-        poll_amt: 0,
-        cur_task: None,
     };
 
     let _ = tokio::task::spawn(async move {
         let res = tokio::select! {
             res0 = stopper(stop) => res0,
-            res1 = file_streamer(group_sender.clone(), file_scrambler) => res1
+            res1 = resumable_file_streamer(group_sender.clone(), file_scrambler, start_group) => res1
         };
 
         if let Err(err) = res {
@@ -149,7 +359,10 @@ pub fn scramble_encrypt_source<S: ObjectSource, F: HeaderInscriberFn, const N: u
         }
     });
 
-    Ok((object_len, total_groups))
+    Ok((
+        object_len.unwrap_or_default(),
+        total_groups.unwrap_or_default(),
+    ))
 }
 
 async fn stopper(stop: Receiver<()>) -> Result<(), CryptError> {
@@ -157,142 +370,219 @@ async fn stopper(stop: Receiver<()>) -> Result<(), CryptError> {
         .map_err(|err| CryptError::Encrypt(err.to_string()))
 }
 
-async fn file_streamer<F: HeaderInscriberFn, R: Read, const N: usize>(
+async fn resumable_file_streamer<F: HeaderInscriberFn, const N: usize>(
+    group_sender: GroupChanneler<Result<GroupSenderDevice<N>, CryptError>>,
+    mut file_scrambler: AsyncCryptScrambler<F, N>,
+    start_group: Receiver<StartGroup>,
+) -> Result<(), CryptError> {
+    let start_group = start_group
+        .await
+        .map_err(|err| CryptError::Encrypt(err.to_string()))?;
+    file_scrambler
+        .skip_groups(start_group.group, start_group.prefix_digest)
+        .await?;
+    file_streamer(group_sender, &mut file_scrambler).await
+}
+
+async fn file_streamer<F: HeaderInscriberFn, const N: usize>(
     group_sender: GroupChanneler<Result<GroupSenderDevice<N>, CryptError>>,
-    mut file_scrambler: AsyncCryptScrambler<F, R, N>,
+    file_scrambler: &mut AsyncCryptScrambler<F, N>,
 ) -> Result<(), CryptError> {
-    while let Some(val) = file_scrambler.next().await {
+    while let Some(val) = file_scrambler.next_group().await? {
         group_sender
             .send(Ok(val))
             .await
             .map_err(|err| CryptError::Encrypt(err.to_string()))?;
     }
 
+    log::trace!(target: "citadel", "Done rendering all groups!");
     Ok(())
 }
 
-#[allow(dead_code)]
-struct AsyncCryptScrambler<F: HeaderInscriberFn, R: Read, const N: usize> {
-    reader: BufReader<R>,
+enum GroupReader {
+    Fixed(BufReader<Box<dyn FixedSizedStream>>),
+    Async(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+impl GroupReader {
+    /// Fills `buf`, returning fewer bytes than requested only once the end of the source is reached
+    async fn read_group(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let res = match self {
+                Self::Fixed(reader) => reader.read(&mut buf[filled..]),
+                Self::Async(reader) => reader.read(&mut buf[filled..]).await,
+            };
+
+            match res {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+struct AsyncCryptScrambler<F: HeaderInscriberFn, const N: usize> {
+    reader: GroupReader,
     hyper_ratchet: StackedRatchet,
     security_level: SecurityLevel,
-    file_len: usize,
-    read_cursor: usize,
+    // None if the length is unknown until EOF
+    file_len: Option<usize>,
+    bytes_read: usize,
     object_id: u32,
     header_size_bytes: usize,
     target_cid: u64,
     group_id: u64,
-    total_groups: usize,
+    // None if the length is unknown until EOF
+    total_groups: Option<usize>,
     groups_rendered: usize,
+    group_digests: Vec<ObjectDigest>,
     max_bytes_per_group: usize,
-    poll_amt: usize,
-    buffer: Arc<Mutex<Vec<u8>>>,
+    // when the length is unknown, the next group is read ahead of time to determine whether the current group is the last
+    next_group: Option<Vec<u8>>,
+    summary: Option<Sender<ObjectSummary>>,
     header_inscriber: Arc<F>,
-    cur_task: Option<BlockingSpawn<Result<GroupSenderDevice<N>, CryptError<String>>>>,
-}
-
-impl<F: HeaderInscriberFn, R: Read, const N: usize> AsyncCryptScrambler<F, R, N> {
-    fn poll_task(
-        groups_rendered: &mut usize,
-        read_cursor: &mut usize,
-        poll_amt: usize,
-        cur_task: &mut Option<BlockingSpawn<Result<GroupSenderDevice<N>, CryptError<String>>>>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<GroupSenderDevice<N>>> {
-        let res: Result<Result<GroupSenderDevice<N>, CryptError<String>>, BlockingSpawnError> =
-            futures::ready!(Pin::new(cur_task.as_mut().unwrap()).poll(cx));
-        return if let Ok(Ok(sender)) = res {
-            *groups_rendered += 1;
-            *read_cursor += poll_amt;
-            *cur_task = None;
-            Poll::Ready(Some(sender))
-        } else {
-            log::error!(target: "citadel", "Unable to par_scramble_encrypt group");
-            Poll::Ready(None)
-        };
-    }
 }
 
-impl<F: HeaderInscriberFn, R: Read, const N: usize> Unpin for AsyncCryptScrambler<F, R, N> {}
-
-impl<F: HeaderInscriberFn, R: Read, const N: usize> AsyncCryptScrambler<F, R, N> {
-    fn poll_scramble_next_group(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<GroupSenderDevice<N>>> {
-        let Self {
-            hyper_ratchet,
-            file_len,
-            read_cursor,
-            buffer,
-            group_id,
-            groups_rendered,
-            header_size_bytes,
-            target_cid,
-            object_id,
-            header_inscriber,
-            reader,
-            security_level,
-            max_bytes_per_group,
-            cur_task,
-            poll_amt,
-            ..
-        } = &mut *self;
-
-        if cur_task.is_some() {
-            return Self::poll_task(groups_rendered, read_cursor, *poll_amt, cur_task, cx);
+impl<F: HeaderInscriberFn, const N: usize> AsyncCryptScrambler<F, N> {
+    /// Discards the first `groups` groups of the source without encrypting them. Each skipped group is still read to
+    /// compute its digest, which, if `prefix_digest` is given, must match the digest the receiver recorded
+    async fn skip_groups(
+        &mut self,
+        groups: usize,
+        prefix_digest: Option<ObjectDigest>,
+    ) -> Result<(), CryptError> {
+        if groups == 0 {
+            return Ok(());
+        }
+
+        if groups >= self.total_groups.unwrap_or_default() {
+            return Err(CryptError::Encrypt(format!(
+                "Cannot skip {} groups of an object with {:?} groups",
+                groups, self.total_groups
+            )));
         }
 
-        if *read_cursor != *file_len {
-            let remaining = *file_len - *read_cursor;
-            let poll_len = std::cmp::min(remaining, *max_bytes_per_group);
-            let mut lock = buffer.lock();
-            let bytes = &mut lock[..poll_len];
-            if reader.read_exact(bytes).is_ok() {
-                let group_id_input = *group_id + (*groups_rendered as u64);
-                std::mem::drop(lock);
-                // let mut compressed = Vec::new();
-                // flate3::Compressor::new().deflate(bytes as &[u8])
-                // let len = flate2::bufread::DeflateEncoder::new(bytes as &[u8], flate2::Compression::fast()).read_to_end(&mut compressed).unwrap();
-                let header_inscriber = header_inscriber.clone();
-                let buffer = buffer.clone();
-                let security_level = *security_level;
-                let hyper_ratchet = hyper_ratchet.clone();
-                let header_size_bytes = *header_size_bytes;
-                let target_cid = *target_cid;
-                let object_id = *object_id;
-
-                let task = crate::misc::blocking_spawn::spawn_blocking(move || {
-                    par_scramble_encrypt_group(
-                        &buffer.lock()[..poll_len],
-                        security_level,
-                        &hyper_ratchet,
-                        header_size_bytes,
-                        target_cid,
-                        object_id,
-                        group_id_input,
-                        |a, b, c, d, e| (header_inscriber)(a, b, c, d, e),
-                    )
-                });
-
-                *cur_task = Some(task);
-                *poll_amt = poll_len;
-                Self::poll_task(groups_rendered, read_cursor, *poll_amt, cur_task, cx)
-            } else {
-                log::error!(target: "citadel", "Error polling exact amt {}", poll_len);
-                Poll::Ready(None)
+        for _ in 0..groups {
+            let plaintext = self.read_group().await?.ok_or_else(|| {
+                CryptError::Encrypt(format!(
+                    "The source ended before {} groups were skipped",
+                    groups
+                ))
+            })?;
+            self.group_digests.push(group_digest(&plaintext));
+        }
+
+        if let Some(prefix_digest) = prefix_digest {
+            if object_digest(&self.group_digests) != prefix_digest {
+                return Err(CryptError::Encrypt(format!(
+                    "The first {} groups of the source changed since the transfer was interrupted",
+                    groups
+                )));
             }
-        } else {
-            log::trace!(target: "citadel", "Done rendering all groups!");
-            Poll::Ready(None)
         }
+
+        log::trace!(target: "citadel", "Skipped {} groups ({} bytes) of object {}", groups, self.bytes_read, self.object_id);
+        self.groups_rendered = groups;
+        Ok(())
     }
-}
 
-impl<F: HeaderInscriberFn, R: Read, const N: usize> Stream for AsyncCryptScrambler<F, R, N> {
-    type Item = GroupSenderDevice<N>;
+    /// Reads the plaintext of the next group from the source, or None once the source is exhausted
+    async fn read_group(&mut self) -> Result<Option<Vec<u8>>, CryptError> {
+        let len = match self.file_len {
+            Some(file_len) => std::cmp::min(file_len - self.bytes_read, self.max_bytes_per_group),
+            None => self.max_bytes_per_group,
+        };
+
+        let mut plaintext = vec![0u8; len];
+        let read = self
+            .reader
+            .read_group(&mut plaintext)
+            .await
+            .map_err(|err| CryptError::Encrypt(err.to_string()))?;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.poll_scramble_next_group(cx)
+        if self.file_len.is_some() && read != len {
+            log::error!(target: "citadel", "Error polling exact amt {}", len);
+            return Err(CryptError::Encrypt(format!(
+                "Expected to read {} bytes, but the source ended after {} bytes",
+                len, read
+            )));
+        }
+
+        plaintext.truncate(read);
+        self.bytes_read += read;
+        Ok((read != 0).then_some(plaintext))
+    }
+
+    /// Renders the next group, or returns None once every group has been rendered
+    async fn next_group(&mut self) -> Result<Option<GroupSenderDevice<N>>, CryptError> {
+        let plaintext = match self.next_group.take() {
+            Some(plaintext) => plaintext,
+            None => match self.read_group().await? {
+                Some(plaintext) => plaintext,
+                None => {
+                    // an empty source has no final group
+                    self.send_summary();
+                    return Ok(None);
+                }
+            },
+        };
+
+        self.group_digests.push(group_digest(&plaintext));
+        let is_last_group = match self.total_groups {
+            Some(total_groups) => self.groups_rendered + 1 == total_groups,
+            None => {
+                self.next_group = self.read_group().await?;
+                self.next_group.is_none()
+            }
+        };
+
+        if is_last_group {
+            self.send_summary();
+        }
+
+        let group_id_input = self.group_id + (self.groups_rendered as u64);
+        let header_inscriber = self.header_inscriber.clone();
+        let security_level = self.security_level;
+        let hyper_ratchet = self.hyper_ratchet.clone();
+        let header_size_bytes = self.header_size_bytes;
+        let target_cid = self.target_cid;
+        let object_id = self.object_id;
+
+        let sender = crate::misc::blocking_spawn::spawn_blocking(move || {
+            par_scramble_encrypt_group(
+                &plaintext,
+                security_level,
+                &hyper_ratchet,
+                header_size_bytes,
+                target_cid,
+                object_id,
+                group_id_input,
+                |a, b, c, d, e| (header_inscriber)(a, b, c, d, e),
+            )
+        })
+        .await
+        .map_err(|err| CryptError::Encrypt(err.message))?
+        .map_err(|err| {
+            log::error!(target: "citadel", "Unable to par_scramble_encrypt group");
+            err
+        })?;
+
+        self.groups_rendered += 1;
+        Ok(Some(sender))
+    }
+
+    fn send_summary(&mut self) {
+        if let Some(summary) = self.summary.take() {
+            let _ = summary.send(ObjectSummary {
+                digest: object_digest(&self.group_digests),
+                plaintext_length: self.bytes_read,
+                group_count: self.group_digests.len(),
+            });
+        }
     }
 }
@@ -6,20 +6,23 @@ mod tests {
     };
     #[cfg(not(coverage))]
     use citadel_crypt::argon::autotuner::calculate_optimal_argon_params;
-    use citadel_crypt::endpoint_crypto_container::EndpointRatchetConstructor;
+    use citadel_crypt::double_ratchet::{DoubleRatchet, DoubleRatchetConstructor};
+    use citadel_crypt::endpoint_crypto_container::{EndpointRatchetConstructor, PeerSessionCrypto};
     use citadel_crypt::entropy_bank::SecurityLevel;
     use citadel_crypt::misc::CryptError;
     use citadel_crypt::scramble::crypt_splitter::{par_scramble_encrypt_group, GroupReceiver};
+    use citadel_crypt::secure_buffer::guarded_alloc;
     use citadel_crypt::secure_buffer::sec_bytes::SecBuffer;
     use citadel_crypt::secure_buffer::sec_string::SecString;
     use citadel_crypt::stacked_ratchet::{Ratchet, StackedRatchet};
-    use citadel_crypt::streaming_crypt_scrambler::{FixedSizedStream, ObjectSource};
+    use citadel_crypt::streaming_crypt_scrambler::{FixedSizedStream, ObjectSource, ObjectStream};
     use citadel_crypt::toolset::{Toolset, UpdateStatus, MAX_HYPER_RATCHETS_IN_MEMORY};
     use citadel_pqcrypto::algorithm_dictionary::{
         AlgorithmsExt, CryptoParameters, EncryptionAlgorithm, KemAlgorithm, SigAlgorithm,
         KEM_ALGORITHM_COUNT,
     };
     use citadel_pqcrypto::constructor_opts::ConstructorOpts;
+    use rand::seq::SliceRandom;
     use rstest::rstest;
     use std::path::PathBuf;
     use std::time::Instant;
@@ -258,6 +261,71 @@ mod tests {
         assert_eq!(&*retrieved, b"Hello, world!");
     }
 
+    #[test]
+    fn secbytes_guarded() {
+        citadel_logging::setup_log();
+        let mut buf = SecBuffer::new_guarded("Hello, world!");
+        assert_eq!(buf.is_guarded(), guarded_alloc::is_supported());
+        assert_eq!(buf.as_ref(), b"Hello, world!");
+
+        let cloned = buf.clone();
+        assert_eq!(cloned.is_guarded(), buf.is_guarded());
+        assert_eq!(cloned, b"Hello, world!");
+
+        buf.handle().extend_from_slice(b" Goodbye!");
+        assert_eq!(buf.is_guarded(), guarded_alloc::is_supported());
+        assert_eq!(buf.as_ref(), b"Hello, world! Goodbye!");
+        assert_eq!(cloned, b"Hello, world!");
+
+        let serde = bincode2::serialize(&buf).unwrap();
+        let deserialized = bincode2::deserialize::<SecBuffer>(&serde).unwrap();
+        assert_eq!(deserialized, buf.as_ref());
+        assert_eq!(&*buf.into_buffer(), b"Hello, world! Goodbye!");
+    }
+
+    #[test]
+    fn guarded_allocation_try_clone() {
+        citadel_logging::setup_log();
+        match guarded_alloc::GuardedAllocation::from_slice(b"secret") {
+            Ok(allocation) => {
+                let cloned = allocation.try_clone().unwrap();
+                assert_eq!(cloned.as_slice(), b"secret");
+                assert_ne!(cloned.as_slice().as_ptr(), allocation.as_slice().as_ptr());
+            }
+
+            Err(err) => {
+                assert!(!guarded_alloc::is_supported());
+                assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+            }
+        }
+    }
+
+    #[test]
+    fn secstring_guarded() {
+        citadel_logging::setup_log();
+        let mut val = SecString::new_guarded("hé");
+        assert_eq!(val.is_guarded(), guarded_alloc::is_supported());
+        val.push('y');
+        val.insert(0, '!');
+        assert_eq!(val.as_str(), "!héy");
+        let view: &str = &val;
+        assert_eq!(view, "!héy");
+        assert_eq!(val.remove(0), '!');
+        assert_eq!(val.is_guarded(), guarded_alloc::is_supported());
+
+        let cloned = val.clone();
+        val.clear();
+        assert!(val.is_empty());
+        assert_eq!(cloned.as_str(), "héy");
+
+        let serde = bincode2::serialize(&cloned).unwrap();
+        let retrieved = bincode2::deserialize::<SecString>(&serde)
+            .unwrap()
+            .into_buffer();
+        assert_eq!(retrieved, "héy");
+        assert_eq!(cloned.into_buffer(), "héy");
+    }
+
     #[test]
     fn hyper_ratchets() {
         citadel_logging::setup_log();
@@ -296,6 +364,228 @@ mod tests {
         }
     }
 
+    #[test]
+    fn hyper_ratchets_double() {
+        citadel_logging::setup_log();
+        for x in 0u8..KEM_ALGORITHM_COUNT {
+            let _ = hyper_ratchet::<DoubleRatchet, _>(
+                KemAlgorithm::from_u8(x).unwrap() + EncryptionAlgorithm::AES_GCM_256_SIV,
+                None,
+                false,
+            );
+        }
+    }
+
+    const DOUBLE_RATCHET_HEADER_LEN: usize = 50;
+
+    fn double_ratchet_protect(ratchet: &DoubleRatchet, message: &[u8]) -> BytesMut {
+        let mut packet = BytesMut::new();
+        for x in 0..DOUBLE_RATCHET_HEADER_LEN {
+            packet.put_u8(x as u8);
+        }
+
+        packet.put(message);
+        ratchet
+            .protect_message_packet(None, DOUBLE_RATCHET_HEADER_LEN, &mut packet)
+            .unwrap();
+        packet
+    }
+
+    fn double_ratchet_validate(
+        ratchet: &DoubleRatchet,
+        mut packet: BytesMut,
+    ) -> Result<Vec<u8>, CryptError> {
+        let header = packet.split_to(DOUBLE_RATCHET_HEADER_LEN);
+        ratchet.validate_message_packet(None, &header[..], &mut packet)?;
+        Ok(packet.to_vec())
+    }
+
+    #[test]
+    fn double_ratchet_out_of_order() {
+        citadel_logging::setup_log();
+        const COUNT: usize = 100;
+        let (alice, bob) = gen::<DoubleRatchet>(
+            0,
+            0,
+            SecurityLevel::Standard,
+            KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV,
+        );
+
+        let mut packets = (0..COUNT)
+            .map(|idx| {
+                let message = format!("message {idx}").into_bytes();
+                let packet = double_ratchet_protect(&alice, &message);
+                (message, packet)
+            })
+            .collect::<Vec<_>>();
+        packets.shuffle(&mut rand::thread_rng());
+
+        for (message, packet) in &packets {
+            // a forged packet must not consume the message key
+            let mut tampered = packet.clone();
+            tampered[DOUBLE_RATCHET_HEADER_LEN] ^= 1;
+            assert!(double_ratchet_validate(&bob, tampered).is_err());
+            assert_eq!(
+                &double_ratchet_validate(&bob, packet.clone()).unwrap(),
+                message
+            );
+        }
+
+        assert_eq!(bob.skipped_message_keys(), 0);
+
+        // each message key is discarded after use, so replays must fail
+        for (_, packet) in packets {
+            assert!(double_ratchet_validate(&bob, packet).is_err());
+        }
+    }
+
+    #[rstest]
+    #[case(KemAlgorithm::Kyber)]
+    #[case(KemAlgorithm::HybridX25519Kyber)]
+    fn double_ratchet_kem_steps_out_of_order(#[case] kem: KemAlgorithm) {
+        citadel_logging::setup_log();
+        let (alice, bob) = gen::<DoubleRatchet>(
+            0,
+            0,
+            SecurityLevel::Standard,
+            kem + EncryptionAlgorithm::AES_GCM_256_SIV,
+        );
+
+        let a0 = double_ratchet_protect(&alice, b"a0");
+        let a1 = double_ratchet_protect(&alice, b"a1");
+        // sent before bob hears from alice, so this uses bob's initial chain
+        let b0 = double_ratchet_protect(&bob, b"b0");
+        assert_eq!(double_ratchet_validate(&bob, a1).unwrap(), b"a1");
+
+        // bob now performs the first KEM ratchet step
+        let b1 = double_ratchet_protect(&bob, b"b1");
+        let b2 = double_ratchet_protect(&bob, b"b2");
+        assert_eq!(double_ratchet_validate(&alice, b2).unwrap(), b"b2");
+
+        // alice responds with a KEM ratchet step of her own
+        let a2 = double_ratchet_protect(&alice, b"a2");
+        assert_eq!(double_ratchet_validate(&bob, a2.clone()).unwrap(), b"a2");
+        assert!(double_ratchet_validate(&bob, a2).is_err());
+
+        // the delayed messages from the previous steps must still be readable
+        assert_eq!(double_ratchet_validate(&alice, b0).unwrap(), b"b0");
+        assert_eq!(double_ratchet_validate(&alice, b1).unwrap(), b"b1");
+        assert_eq!(double_ratchet_validate(&bob, a0).unwrap(), b"a0");
+        assert_eq!(alice.skipped_message_keys(), 0);
+        assert_eq!(bob.skipped_message_keys(), 0);
+
+        for round in 0..10 {
+            let delayed = double_ratchet_protect(&bob, format!("delayed {round}").as_bytes());
+            let packet = double_ratchet_protect(&bob, format!("bob {round}").as_bytes());
+            assert_eq!(
+                double_ratchet_validate(&alice, packet).unwrap(),
+                format!("bob {round}").into_bytes()
+            );
+            let packet = double_ratchet_protect(&alice, format!("alice {round}").as_bytes());
+            assert_eq!(
+                double_ratchet_validate(&bob, packet).unwrap(),
+                format!("alice {round}").into_bytes()
+            );
+            assert_eq!(
+                double_ratchet_validate(&alice, delayed).unwrap(),
+                format!("delayed {round}").into_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn double_ratchet_peer_session_crypto() {
+        citadel_logging::setup_log();
+        let algorithm = KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV;
+        let (alice, bob) = gen::<DoubleRatchet>(0, 0, SecurityLevel::Standard, algorithm);
+        let alice = PeerSessionCrypto::new(Toolset::new(0, alice), true);
+        let bob = PeerSessionCrypto::new(Toolset::new(0, bob), false);
+        let alice_ratchet = alice.get_hyper_ratchet(None).unwrap();
+        let bob_ratchet = bob.get_hyper_ratchet(None).unwrap();
+
+        let packet = double_ratchet_protect(alice_ratchet, b"Hello, world!");
+        assert_eq!(
+            double_ratchet_validate(bob_ratchet, packet).unwrap(),
+            b"Hello, world!"
+        );
+
+        // re-keying produces a fresh double ratchet
+        let mut alice_constructor = alice_ratchet.next_alice_constructor().unwrap();
+        let bob_constructor = DoubleRatchetConstructor::new_bob(
+            0,
+            1,
+            bob_ratchet.get_next_constructor_opts(),
+            alice_constructor.stage0_alice().unwrap(),
+        )
+        .unwrap();
+        alice_constructor
+            .stage1_alice(bob_constructor.stage0_bob().unwrap())
+            .unwrap();
+        let alice_next = alice_constructor.finish().unwrap();
+        let bob_next = bob_constructor.finish().unwrap();
+        assert_eq!(alice_next.version(), 1);
+        assert_eq!(bob_next.version(), 1);
+
+        let packet = double_ratchet_protect(&bob_next, b"Hello, world!");
+        assert_eq!(
+            double_ratchet_validate(&alice_next, packet).unwrap(),
+            b"Hello, world!"
+        );
+    }
+
+    #[test]
+    fn export_keying_material() {
+        citadel_logging::setup_log();
+        let (alice, bob) = gen::<StackedRatchet>(
+            0,
+            0,
+            SecurityLevel::High,
+            KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV,
+        );
+
+        let alice_key = alice
+            .export_keying_material(b"database", b"context", 32)
+            .unwrap();
+        assert_eq!(alice_key.len(), 32);
+        assert_eq!(
+            alice_key,
+            bob.export_keying_material(b"database", b"context", 32)
+                .unwrap()
+        );
+
+        assert_ne!(
+            alice_key,
+            bob.export_keying_material(b"media", b"context", 32)
+                .unwrap()
+        );
+        assert_ne!(
+            alice_key,
+            bob.export_keying_material(b"database", b"other", 32)
+                .unwrap()
+        );
+        // the output length is bound to the derivation, so a shorter key is not a prefix of a longer key
+        assert_ne!(
+            alice_key[..16],
+            bob.export_keying_material(b"database", b"context", 16)
+                .unwrap()[..]
+        );
+        assert!(alice.export_keying_material(b"database", b"", 0).is_err());
+
+        // distinct ratchet versions yield distinct keys
+        let (next_alice, _) = gen::<StackedRatchet>(
+            0,
+            1,
+            SecurityLevel::High,
+            KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV,
+        );
+        assert_ne!(
+            alice_key,
+            next_alice
+                .export_keying_material(b"database", b"context", 32)
+                .unwrap()
+        );
+    }
+
     #[test]
     fn security_levels() {
         citadel_logging::setup_log();
@@ -395,6 +685,7 @@ mod tests {
         toolset::<StackedRatchet>(enx, kem, sig);
         #[cfg(feature = "fcm")]
         toolset::<citadel_crypt::fcm::fcm_ratchet::ThinRatchet>(enx, kem, sig);
+        toolset::<DoubleRatchet>(enx, kem, sig);
     }
 
     fn toolset<R: Ratchet>(enx: EncryptionAlgorithm, kem: KemAlgorithm, sig: SigAlgorithm) {
@@ -456,6 +747,54 @@ mod tests {
         assert_eq!(toolset.len(), MAX_HYPER_RATCHETS_IN_MEMORY);
     }
 
+    #[test]
+    fn directory_manifest_signature() {
+        use citadel_crypt::directory_source::{
+            DirectoryManifest, ManifestEntry, ManifestEntryKind,
+        };
+
+        citadel_logging::setup_log();
+        let mut manifest = DirectoryManifest {
+            entries: vec![ManifestEntry {
+                path: "README.md".to_string(),
+                kind: ManifestEntryKind::File,
+                size: 5,
+                mode: None,
+                digest: [7u8; 32],
+            }],
+            signature: Vec::new(),
+        };
+
+        let (alice, bob) = gen::<StackedRatchet>(
+            10,
+            0,
+            SecurityLevel::Standard,
+            KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV + SigAlgorithm::Falcon1024,
+        );
+        // unsigned manifests are rejected
+        assert!(manifest.verify(bob.get_scramble_pqc()).is_err());
+        manifest.sign(alice.get_scramble_pqc()).unwrap();
+        manifest.verify(bob.get_scramble_pqc()).unwrap();
+
+        let mut tampered = manifest.clone();
+        tampered.entries[0].path = "../README.md".to_string();
+        assert!(tampered.verify(bob.get_scramble_pqc()).is_err());
+
+        // sessions without a signature algorithm send the manifest unsigned
+        let (alice, bob) = gen::<StackedRatchet>(
+            10,
+            0,
+            SecurityLevel::Standard,
+            KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV + SigAlgorithm::None,
+        );
+        let mut unsigned = manifest.clone();
+        unsigned.sign(alice.get_scramble_pqc()).unwrap();
+        assert!(unsigned.signature.is_empty());
+        unsigned.verify(bob.get_scramble_pqc()).unwrap();
+        // a signature cannot be verified without a signature algorithm, and thus is not accepted
+        assert!(manifest.verify(bob.get_scramble_pqc()).is_err());
+    }
+
     fn gen<R: Ratchet>(
         cid: u64,
         version: u32,
@@ -754,6 +1093,368 @@ mod tests {
         }
     }
 
+    const RESUMABLE_HEADER_LEN: usize = 52;
+    const RESUMABLE_GROUP_SIZE: usize = 4000;
+
+    /// The channels of a transfer started by [`scramble_resumable`], along with the receiving ratchet
+    struct ResumableTransfer {
+        bytes: usize,
+        num_groups: usize,
+        groups: tokio::sync::mpsc::Receiver<
+            Result<
+                citadel_crypt::scramble::crypt_splitter::GroupSenderDevice<RESUMABLE_HEADER_LEN>,
+                CryptError,
+            >,
+        >,
+        start_group:
+            tokio::sync::oneshot::Sender<citadel_crypt::streaming_crypt_scrambler::StartGroup>,
+        summary:
+            tokio::sync::oneshot::Receiver<citadel_crypt::streaming_crypt_scrambler::ObjectSummary>,
+        _stop: tokio::sync::oneshot::Sender<()>,
+        bob: StackedRatchet,
+    }
+
+    fn scramble_resumable<S: ObjectSource>(source: S, group_id: u64) -> ResumableTransfer {
+        use citadel_crypt::prelude::{EntropyBank, PacketVector};
+        use citadel_crypt::streaming_crypt_scrambler::scramble_encrypt_source_resumable;
+
+        fn header_inscribe(
+            _: &PacketVector,
+            _: &EntropyBank,
+            _: u32,
+            _: u64,
+            packet: &mut BytesMut,
+        ) {
+            packet.extend_from_slice(&[0u8; RESUMABLE_HEADER_LEN])
+        }
+
+        let (alice, bob) = gen::<StackedRatchet>(
+            0,
+            0,
+            SecurityLevel::Standard,
+            EncryptionAlgorithm::AES_GCM_256_SIV + KemAlgorithm::Kyber,
+        );
+        let (group_sender_tx, groups) = tokio::sync::mpsc::channel(1);
+        let (_stop, stop_rx) = tokio::sync::oneshot::channel();
+        let (start_group, start_group_rx) = tokio::sync::oneshot::channel();
+        let (summary_tx, summary) = tokio::sync::oneshot::channel();
+        let (bytes, num_groups) = scramble_encrypt_source_resumable::<_, _, RESUMABLE_HEADER_LEN>(
+            source,
+            Some(RESUMABLE_GROUP_SIZE),
+            99,
+            group_sender_tx,
+            stop_rx,
+            start_group_rx,
+            summary_tx,
+            SecurityLevel::Standard,
+            alice,
+            RESUMABLE_HEADER_LEN,
+            bob.get_cid(),
+            group_id,
+            header_inscribe,
+        )
+        .unwrap();
+
+        ResumableTransfer {
+            bytes,
+            num_groups,
+            groups,
+            start_group,
+            summary,
+            _stop,
+            bob,
+        }
+    }
+
+    /// Receives and decrypts every group rendered by the transfer. `on_group` is called with each group's config
+    /// before its packets are received
+    async fn receive_resumable(
+        groups: &mut tokio::sync::mpsc::Receiver<
+            Result<
+                citadel_crypt::scramble::crypt_splitter::GroupSenderDevice<RESUMABLE_HEADER_LEN>,
+                CryptError,
+            >,
+        >,
+        bob: &StackedRatchet,
+        mut on_group: impl FnMut(&citadel_crypt::scramble::crypt_splitter::GroupReceiverConfig),
+    ) -> Vec<u8> {
+        use citadel_crypt::scramble::crypt_splitter::GroupReceiverStatus;
+
+        let mut bytes_ret = Vec::new();
+        while let Some(gs) = groups.recv().await {
+            let mut gs = gs.unwrap();
+            let config = gs.get_receiver_config();
+            on_group(&config);
+            let mut receiver = GroupReceiver::new(config.clone(), 0, 0);
+            while let Some(mut packet) = gs.get_next_packet() {
+                let packet_payload = packet.packet.split_off(RESUMABLE_HEADER_LEN);
+                let result = receiver.on_packet_received(
+                    config.group_id as u64,
+                    packet.vector.true_sequence,
+                    packet.vector.wave_id,
+                    bob,
+                    packet_payload,
+                );
+
+                if let GroupReceiverStatus::GROUP_COMPLETE(_) = result {
+                    bytes_ret.extend_from_slice(receiver.finalize().as_slice());
+                    break;
+                }
+            }
+        }
+
+        bytes_ret
+    }
+
+    #[tokio::test]
+    async fn encrypt_decrypt_resumed_source() {
+        use citadel_crypt::streaming_crypt_scrambler::{group_digest, object_digest, StartGroup};
+        citadel_logging::setup_log();
+        const START_GROUP: usize = 2;
+        const START_GROUP_ID: usize = 10;
+
+        let cmp = (0..(RESUMABLE_GROUP_SIZE * 4 + 100))
+            .map(|r| (r % 256) as u8)
+            .collect::<Vec<u8>>();
+        let mut transfer =
+            scramble_resumable(VecWrapper { inner: cmp.clone() }, START_GROUP_ID as u64);
+
+        assert_eq!(transfer.bytes, cmp.len());
+        assert_eq!(transfer.num_groups, 5);
+        // the receiver's digest of the groups it already holds
+        let group_digests = cmp
+            .chunks(RESUMABLE_GROUP_SIZE)
+            .map(group_digest)
+            .collect::<Vec<_>>();
+        transfer
+            .start_group
+            .send(StartGroup {
+                group: START_GROUP,
+                prefix_digest: Some(object_digest(&group_digests[..START_GROUP])),
+            })
+            .unwrap();
+
+        let mut expected_group_id = START_GROUP_ID + START_GROUP;
+        let bytes_ret = receive_resumable(&mut transfer.groups, &transfer.bob, |config| {
+            assert_eq!(config.group_id, expected_group_id);
+            expected_group_id += 1;
+        })
+        .await;
+
+        assert_eq!(expected_group_id, START_GROUP_ID + transfer.num_groups);
+        assert_eq!(
+            bytes_ret.as_slice(),
+            &cmp[START_GROUP * RESUMABLE_GROUP_SIZE..]
+        );
+
+        // the digest covers the skipped groups too
+        let summary = transfer.summary.await.unwrap();
+        assert_eq!(summary.digest, object_digest(&group_digests));
+        assert_ne!(summary.digest, object_digest(&group_digests[START_GROUP..]));
+        assert_eq!(summary.plaintext_length, cmp.len());
+        assert_eq!(summary.group_count, transfer.num_groups);
+    }
+
+    #[tokio::test]
+    async fn resumed_source_with_modified_prefix_fails() {
+        use citadel_crypt::streaming_crypt_scrambler::{group_digest, object_digest, StartGroup};
+        citadel_logging::setup_log();
+        const START_GROUP: usize = 2;
+
+        let received = (0..(RESUMABLE_GROUP_SIZE * 4))
+            .map(|r| (r % 256) as u8)
+            .collect::<Vec<u8>>();
+        // the same name and length as the object partially received, but a different first group
+        let mut modified = received.clone();
+        modified[0] ^= 1;
+
+        let mut transfer = scramble_resumable(VecWrapper { inner: modified }, 0);
+
+        let group_digests = received
+            .chunks(RESUMABLE_GROUP_SIZE)
+            .take(START_GROUP)
+            .map(group_digest)
+            .collect::<Vec<_>>();
+        transfer
+            .start_group
+            .send(StartGroup {
+                group: START_GROUP,
+                prefix_digest: Some(object_digest(&group_digests)),
+            })
+            .unwrap();
+
+        // no group is rendered once the skipped groups fail to match
+        assert!(transfer.groups.recv().await.unwrap().is_err());
+        assert!(transfer.groups.recv().await.is_none());
+    }
+
+    #[test]
+    fn seal_and_open_object() {
+        use citadel_crypt::sealed_object::{
+            sealed_len, ObjectUnsealer, SealedSource, SEALED_FRAME_LEN,
+        };
+        use std::io::Read;
+
+        let key = [7u8; 32];
+        let seal = |plaintext: Vec<u8>| {
+            let len = plaintext.len() as u64;
+            let mut source = SealedSource::new(plaintext, key);
+            let mut sealed = Vec::new();
+            match source.try_get_stream().unwrap() {
+                ObjectStream::Fixed(mut stream) => {
+                    assert_eq!(stream.length().unwrap(), sealed_len(len));
+                    let _ = stream.read_to_end(&mut sealed).unwrap();
+                }
+                ObjectStream::Async(_) => panic!("Sealed objects have a known length"),
+            }
+            assert_eq!(sealed.len() as u64, sealed_len(len));
+            sealed
+        };
+
+        let open = |sealed: &[u8], key: [u8; 32]| {
+            let mut unsealer = ObjectUnsealer::new(key);
+            let mut plaintext = Vec::new();
+            for chunk in sealed.chunks(1000) {
+                plaintext.extend(unsealer.push(chunk)?);
+            }
+            unsealer.finish().map(|_| plaintext)
+        };
+
+        for len in [0, 1, SEALED_FRAME_LEN, 2 * SEALED_FRAME_LEN + 10] {
+            let plaintext = (0..len).map(|idx| idx as u8).collect::<Vec<u8>>();
+            let sealed = seal(plaintext.clone());
+            // the salt is random, and thus the same object is sealed differently each time
+            assert_ne!(sealed, seal(plaintext.clone()));
+            assert_eq!(open(&sealed, key).unwrap(), plaintext);
+            // a different key cannot open the object
+            assert!(open(&sealed, [8u8; 32]).is_err());
+            // nor can a truncated, or modified, object be opened
+            assert!(open(&sealed[..sealed.len() - 1], key).is_err());
+            let mut modified = sealed.clone();
+            let last = modified.len() - 1;
+            modified[last] ^= 1;
+            assert!(open(&modified, key).is_err());
+        }
+
+        // dropping the final frame of an object is detected
+        let sealed = seal(vec![1u8; 2 * SEALED_FRAME_LEN]);
+        assert!(open(&sealed[..sealed.len() - SEALED_FRAME_LEN - 16], key).is_err());
+    }
+
+    #[test]
+    fn sealed_object_key_id() {
+        use citadel_crypt::sealed_object::{open, seal, sealed_key_id, sealing_key_id};
+
+        let key = [7u8; 32];
+        let other_key = [8u8; 32];
+        assert_ne!(sealing_key_id(&key), sealing_key_id(&other_key));
+
+        // the recipient selects the key by the id in the header
+        let sealed = seal(&key, b"hello, world").unwrap();
+        assert_eq!(sealed_key_id(&sealed), Some(sealing_key_id(&key)));
+        assert_eq!(sealed_key_id(&sealed[..4]), None);
+
+        // the id is authenticated, such that it cannot be swapped for that of another key
+        let mut modified = sealed.clone();
+        modified[..8].copy_from_slice(&sealing_key_id(&other_key).to_be_bytes());
+        assert!(open(&key, &modified).is_err());
+        assert!(open(&other_key, &modified).is_err());
+    }
+
+    #[test]
+    fn sealed_object_associated_data() {
+        use citadel_crypt::sealed_object::{
+            open, open_with_associated_data, seal, seal_with_associated_data,
+        };
+
+        let key = [7u8; 32];
+        let sealed = seal_with_associated_data(&key, b"sender 1", b"hello, world").unwrap();
+        assert_eq!(
+            open_with_associated_data(&key, b"sender 1", &sealed).unwrap(),
+            b"hello, world"
+        );
+        assert!(open_with_associated_data(&key, b"sender 2", &sealed).is_err());
+        assert!(open(&key, &sealed).is_err());
+
+        // objects sealed without associated data are unaffected
+        let sealed = seal(&key, b"hello, world").unwrap();
+        assert_eq!(
+            open_with_associated_data(&key, &[], &sealed).unwrap(),
+            b"hello, world"
+        );
+    }
+
+    #[test]
+    fn seal_and_open_message() {
+        use citadel_crypt::sealed_object::{open, seal, sealed_len, ObjectUnsealer};
+
+        let key = [7u8; 32];
+        for message in [&b""[..], b"hello, world", &[9u8; 100_000]] {
+            let sealed = seal(&key, message).unwrap();
+            assert_eq!(sealed.len() as u64, sealed_len(message.len() as u64));
+            assert_eq!(open(&key, &sealed).unwrap(), message);
+            assert!(open(&[8u8; 32], &sealed).is_err());
+            assert!(open(&key, &sealed[..sealed.len() - 1]).is_err());
+
+            // messages are sealed in the same format as objects
+            let mut unsealer = ObjectUnsealer::new(key);
+            assert_eq!(unsealer.push(&sealed).unwrap(), message);
+            unsealer.finish().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn encrypt_decrypt_source_of_unknown_length() {
+        use citadel_crypt::streaming_crypt_scrambler::{
+            group_digest, object_digest, AsyncReadSource, StartGroup,
+        };
+        citadel_logging::setup_log();
+
+        // an exact multiple of the group size, such that EOF is only discovered after the final group is read
+        let cmp = (0..(RESUMABLE_GROUP_SIZE * 3))
+            .map(|r| (r % 256) as u8)
+            .collect::<Vec<u8>>();
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        let cmp_writer = cmp.clone();
+        let writer_task = tokio::task::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            // written in small chunks, like a dump generated on the fly
+            for chunk in cmp_writer.chunks(700) {
+                writer.write_all(chunk).await.unwrap();
+            }
+        });
+
+        let mut transfer = scramble_resumable(AsyncReadSource::new("dump.sql", reader), 0);
+
+        // the length is unknown until EOF
+        assert_eq!((transfer.bytes, transfer.num_groups), (0, 0));
+        transfer.start_group.send(StartGroup::default()).unwrap();
+
+        let mut groups = 0;
+        let mut summary = None;
+        let summary_rx = &mut transfer.summary;
+        let bytes_ret = receive_resumable(&mut transfer.groups, &transfer.bob, |_| {
+            groups += 1;
+            // the summary is sent before the final group
+            if let Ok(received) = summary_rx.try_recv() {
+                summary = Some(received);
+            }
+            assert_eq!(summary.is_some(), groups == 3);
+        })
+        .await;
+
+        writer_task.await.unwrap();
+        assert_eq!(bytes_ret, cmp);
+        let summary = summary.unwrap();
+        let group_digests = cmp
+            .chunks(RESUMABLE_GROUP_SIZE)
+            .map(group_digest)
+            .collect::<Vec<_>>();
+        assert_eq!(summary.digest, object_digest(&group_digests));
+        assert_eq!(summary.plaintext_length, cmp.len());
+        assert_eq!(summary.group_count, 3);
+    }
+
     const DATA: &[u8] = b"Hello, world!";
 
     #[rstest]
@@ -814,7 +1515,7 @@ mod tests {
     }
 
     impl ObjectSource for VecWrapper {
-        fn try_get_stream(&mut self) -> Result<Box<dyn FixedSizedStream>, CryptError> {
+        fn try_get_stream(&mut self) -> Result<ObjectStream, CryptError> {
             struct VecReader {
                 len: usize,
                 cursor: std::io::Cursor<Vec<u8>>,
@@ -834,7 +1535,7 @@ mod tests {
 
             let len = self.inner.len();
             let cursor = std::io::Cursor::new(self.inner.clone());
-            Ok(Box::new(VecReader { len, cursor }))
+            Ok(ObjectStream::Fixed(Box::new(VecReader { len, cursor })))
         }
 
         fn get_source_name(&self) -> Result<String, CryptError> {
@@ -108,8 +108,6 @@ pub(crate) mod chacha_impl {
 }
 
 pub(crate) mod kyber_module {
-    #[cfg(target_family = "wasm")]
-    use crate::functions::AsSlice;
     use crate::wire::ScramCryptDictionary;
     use crate::{
         AeadModule, Error, KemAlgorithm, PostQuantumMetaKex, PostQuantumMetaSig, SigAlgorithm,
@@ -139,6 +137,7 @@ pub(crate) mod kyber_module {
 
             let aes_nonce = &nonce[..AES_GCM_NONCE_LENGTH_BYTES];
             let signature = crate::functions::signature_sign(
+                self.sig_alg,
                 sha3_256_with_ad(ad, input.as_ref()),
                 self.sig.sig_private_key.as_slice(),
             )?;
@@ -228,6 +227,7 @@ pub(crate) mod kyber_module {
             let (_, signature_bytes) = input.as_ref().split_at(split_pt);
             let sig_verify_input = sha3_256_with_ad(ad, &input.as_ref()[..split_pt]);
             crate::functions::signature_verify(
+                self.sig_alg,
                 sig_verify_input,
                 signature_bytes,
                 sig_remote_pk.as_slice(),
@@ -0,0 +1,292 @@
+use crate::algorithm_dictionary::SigAlgorithm;
+use crate::Error;
+
+pub type SecretKeyType = Vec<u8>;
+pub type PublicKeyType = Vec<u8>;
+
+pub const ED25519_PUBLIC_KEY_LENGTH_BYTES: usize = 32;
+pub const ED25519_SECRET_KEY_LENGTH_BYTES: usize = 32;
+pub const ED25519_SIGNATURE_LENGTH_BYTES: usize = 64;
+
+/// Signs the message. For the hybrid scheme, the output is the Ed25519 signature followed by the Dilithium3 signature
+pub fn signature_sign(
+    sig_alg: SigAlgorithm,
+    message: impl AsRef<[u8]>,
+    secret_key: impl AsRef<[u8]>,
+) -> Result<Vec<u8>, Error> {
+    let message = message.as_ref();
+    let secret_key = secret_key.as_ref();
+
+    match sig_alg {
+        SigAlgorithm::None => Err(Error::Generic("No signature algorithm selected")),
+        SigAlgorithm::Falcon1024 | SigAlgorithm::Dilithium3 => {
+            pq::signature_sign(sig_alg, message, secret_key)
+        }
+        SigAlgorithm::HybridEd25519Dilithium3 => {
+            let (ed25519_sk, dilithium_sk) =
+                split_at_checked(secret_key, ED25519_SECRET_KEY_LENGTH_BYTES)?;
+            let mut signature = ed25519::signature_sign(message, ed25519_sk)?;
+            signature.extend(pq::signature_sign(
+                SigAlgorithm::Dilithium3,
+                message,
+                dilithium_sk,
+            )?);
+            Ok(signature)
+        }
+    }
+}
+
+/// Verifies the signature. For the hybrid scheme, both component signatures must verify
+pub fn signature_verify(
+    sig_alg: SigAlgorithm,
+    message: impl AsRef<[u8]>,
+    signature: impl AsRef<[u8]>,
+    public_key: impl AsRef<[u8]>,
+) -> Result<(), Error> {
+    let message = message.as_ref();
+    let signature = signature.as_ref();
+    let public_key = public_key.as_ref();
+
+    match sig_alg {
+        SigAlgorithm::None => Err(Error::Generic("No signature algorithm selected")),
+        SigAlgorithm::Falcon1024 | SigAlgorithm::Dilithium3 => {
+            pq::signature_verify(sig_alg, message, signature, public_key)
+        }
+        SigAlgorithm::HybridEd25519Dilithium3 => {
+            let (ed25519_sig, dilithium_sig) =
+                split_at_checked(signature, ED25519_SIGNATURE_LENGTH_BYTES)?;
+            let (ed25519_pk, dilithium_pk) =
+                split_at_checked(public_key, ED25519_PUBLIC_KEY_LENGTH_BYTES)?;
+            ed25519::signature_verify(message, ed25519_sig, ed25519_pk)?;
+            pq::signature_verify(
+                SigAlgorithm::Dilithium3,
+                message,
+                dilithium_sig,
+                dilithium_pk,
+            )
+        }
+    }
+}
+
+/// For the hybrid scheme, both the public and secret keys are the Ed25519 key followed by the Dilithium3 key
+pub fn signature_keypair(sig_alg: SigAlgorithm) -> Result<(PublicKeyType, SecretKeyType), Error> {
+    match sig_alg {
+        SigAlgorithm::None => Err(Error::Generic("No signature algorithm selected")),
+        SigAlgorithm::Falcon1024 | SigAlgorithm::Dilithium3 => pq::signature_keypair(sig_alg),
+        SigAlgorithm::HybridEd25519Dilithium3 => {
+            let (mut public_key, mut secret_key) = ed25519::signature_keypair();
+            let (dilithium_pk, dilithium_sk) = pq::signature_keypair(SigAlgorithm::Dilithium3)?;
+            public_key.extend(dilithium_pk);
+            secret_key.extend(dilithium_sk);
+            Ok((public_key, secret_key))
+        }
+    }
+}
+
+/// Returns the maximum length of a signature produced by the given algorithm, or zero if the algorithm is unsupported
+/// on the current target
+pub fn signature_bytes(sig_alg: SigAlgorithm) -> usize {
+    match sig_alg {
+        SigAlgorithm::None => 0,
+        SigAlgorithm::Falcon1024 | SigAlgorithm::Dilithium3 => pq::signature_bytes(sig_alg),
+        SigAlgorithm::HybridEd25519Dilithium3 => {
+            match pq::signature_bytes(SigAlgorithm::Dilithium3) {
+                0 => 0,
+                len => ED25519_SIGNATURE_LENGTH_BYTES + len,
+            }
+        }
+    }
+}
+
+fn split_at_checked(input: &[u8], mid: usize) -> Result<(&[u8], &[u8]), Error> {
+    if input.len() <= mid {
+        Err(Error::InvalidLength)
+    } else {
+        Ok(input.split_at(mid))
+    }
+}
+
+mod ed25519 {
+    use crate::functions::{PublicKeyType, SecretKeyType};
+    use crate::Error;
+    use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+    use rand::rngs::ThreadRng;
+
+    pub fn signature_sign(message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>, Error> {
+        let secret_key = secret_key.try_into().map_err(|_| Error::InvalidLength)?;
+        Ok(SigningKey::from_bytes(secret_key)
+            .sign(message)
+            .to_bytes()
+            .to_vec())
+    }
+
+    pub fn signature_verify(
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        let signature =
+            Signature::from_slice(signature).map_err(|err| Error::Other(err.to_string()))?;
+        let public_key = public_key.try_into().map_err(|_| Error::InvalidLength)?;
+        VerifyingKey::from_bytes(public_key)
+            .and_then(|public_key| public_key.verify_strict(message, &signature))
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub fn signature_keypair() -> (PublicKeyType, SecretKeyType) {
+        let signing_key = SigningKey::generate(&mut ThreadRng::default());
+        (
+            signing_key.verifying_key().to_bytes().to_vec(),
+            signing_key.to_bytes().to_vec(),
+        )
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod pq {
+    use crate::algorithm_dictionary::SigAlgorithm;
+    use crate::functions::{PublicKeyType, SecretKeyType};
+    use crate::Error;
+    use oqs::sig::Sig;
+
+    pub fn signature_sign(
+        sig_alg: SigAlgorithm,
+        message: &[u8],
+        secret_key: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let sig = get_sig(sig_alg)?;
+        let secret_key = sig
+            .secret_key_from_bytes(secret_key)
+            .ok_or(Error::Generic("Bad secret key length"))?;
+        sig.sign(message, secret_key)
+            .map(|r| r.into_vec())
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub fn signature_verify(
+        sig_alg: SigAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        let sig = get_sig(sig_alg)?;
+        let signature = sig
+            .signature_from_bytes(signature)
+            .ok_or(Error::Generic("Bad signature length"))?;
+        let public_key = sig
+            .public_key_from_bytes(public_key)
+            .ok_or(Error::Generic("Bad public key length"))?;
+
+        sig.verify(message, signature, public_key)
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub fn signature_keypair(
+        sig_alg: SigAlgorithm,
+    ) -> Result<(PublicKeyType, SecretKeyType), Error> {
+        get_sig(sig_alg)?
+            .keypair()
+            .map_err(|err| Error::Other(err.to_string()))
+            .map(|(l, r)| (l.into_vec(), r.into_vec()))
+    }
+
+    /// Returns zero for algorithms that are not post-quantum signature algorithms
+    pub fn signature_bytes(sig_alg: SigAlgorithm) -> usize {
+        get_sig(sig_alg)
+            .map(|sig| sig.length_signature())
+            .unwrap_or(0)
+    }
+
+    fn get_sig(sig_alg: SigAlgorithm) -> Result<Sig, Error> {
+        let alg = match sig_alg {
+            SigAlgorithm::Falcon1024 => oqs::sig::Algorithm::Falcon1024,
+            // round-3 Dilithium3. This is not byte-compatible with FIPS 204 ML-DSA-65
+            SigAlgorithm::Dilithium3 => oqs::sig::Algorithm::Dilithium3,
+            _ => return Err(Error::Generic("Not a post-quantum signature algorithm")),
+        };
+
+        oqs::sig::Sig::new(alg).map_err(|err| Error::Other(err.to_string()))
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod pq {
+    use crate::algorithm_dictionary::SigAlgorithm;
+    use crate::functions::{PublicKeyType, SecretKeyType};
+    use crate::Error;
+    use pqcrypto_falcon_wasi::falcon1024;
+    use pqcrypto_traits_wasi::sign::{DetachedSignature, PublicKey, SecretKey};
+
+    pub fn signature_sign(
+        sig_alg: SigAlgorithm,
+        message: &[u8],
+        secret_key: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        check_supported(sig_alg)?;
+        let secret_key = falcon1024::SecretKey::from_bytes(secret_key)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        Ok(falcon1024::detached_sign(message, &secret_key)
+            .as_bytes()
+            .to_vec())
+    }
+
+    pub fn signature_verify(
+        sig_alg: SigAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        check_supported(sig_alg)?;
+        let signature = falcon1024::DetachedSignature::from_bytes(signature)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        let public_key = falcon1024::PublicKey::from_bytes(public_key)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        falcon1024::verify_detached_signature(&signature, message, &public_key)
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    pub fn signature_keypair(
+        sig_alg: SigAlgorithm,
+    ) -> Result<(PublicKeyType, SecretKeyType), Error> {
+        check_supported(sig_alg)?;
+        let (public_key, secret_key) = falcon1024::keypair();
+        Ok((
+            public_key.as_bytes().to_vec(),
+            secret_key.as_bytes().to_vec(),
+        ))
+    }
+
+    /// Returns zero for algorithms unsupported on wasm, since no signature of that algorithm can be produced
+    pub fn signature_bytes(sig_alg: SigAlgorithm) -> usize {
+        match sig_alg {
+            SigAlgorithm::Falcon1024 => falcon1024::signature_bytes(),
+            _ => 0,
+        }
+    }
+
+    fn check_supported(sig_alg: SigAlgorithm) -> Result<(), Error> {
+        if sig_alg == SigAlgorithm::Falcon1024 {
+            Ok(())
+        } else {
+            Err(Error::Generic(
+                "Only Falcon1024 signatures are currently supported on wasm targets",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm_dictionary::SigAlgorithm;
+    use crate::functions::{pq, signature_bytes};
+
+    #[test]
+    fn test_signature_bytes_without_signature_algorithm() {
+        assert_eq!(signature_bytes(SigAlgorithm::None), 0);
+        assert_eq!(pq::signature_bytes(SigAlgorithm::None), 0);
+        assert_eq!(
+            pq::signature_bytes(SigAlgorithm::HybridEd25519Dilithium3),
+            0
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use crate::algorithm_dictionary::{KemAlgorithm, KyberParameterSet};
+use crate::Error;
+use dpb_kem::prelude::*;
+
+/// Domain separator for the hybrid shared secret combiner
+const HYBRID_KEM_LABEL: &[u8] = b"citadel-hybrid-x25519-kyber";
+
+/// Returns the KEM for the given algorithm. Every KEM begins its keys with a Kyber component,
+/// allowing the Kyber portion to additionally be used for Kyber encryption
+pub fn kem_for(
+    kem_alg: KemAlgorithm,
+    parameter_set: KyberParameterSet,
+) -> Box<dyn KeyEncapsulationMechanism> {
+    let kyber = kyber_for(parameter_set);
+    match kem_alg {
+        KemAlgorithm::Kyber => Box::new(kyber),
+        KemAlgorithm::HybridX25519Kyber => {
+            Box::new(KemCombiner::new(kyber, X25519, HYBRID_KEM_LABEL))
+        }
+    }
+}
+
+/// Returns the plain Kyber KEM for the given parameter set
+pub fn kyber_for(parameter_set: KyberParameterSet) -> Kyber {
+    Kyber::new(match parameter_set {
+        KyberParameterSet::Kyber512 => KyberVariant::Kyber512,
+        KyberParameterSet::Kyber768 => KyberVariant::Kyber768,
+        KyberParameterSet::Kyber1024 => KyberVariant::Kyber1024,
+    })
+}
+
+impl From<KemError> for Error {
+    fn from(err: KemError) -> Self {
+        match err {
+            KemError::InvalidLength => Error::InvalidLength,
+            KemError::Unsupported(err) | KemError::Generic(err) => Error::Generic(err),
+            KemError::Other(err) => Error::Other(err),
+        }
+    }
+}
@@ -2,7 +2,7 @@
 #![forbid(unsafe_code)]
 
 use crate::algorithm_dictionary::{
-    CryptoParameters, EncryptionAlgorithm, KemAlgorithm, SigAlgorithm,
+    CryptoParameters, EncryptionAlgorithm, KemAlgorithm, KyberParameterSet, SigAlgorithm,
 };
 use crate::bytes_in_place::{EzBuffer, InPlaceBuffer};
 use crate::constructor_opts::{ConstructorOpts, RecursiveChain};
@@ -11,7 +11,6 @@ use crate::export::keys_to_aead_store;
 use crate::ez_error::Error;
 use crate::wire::{AliceToBobTransferParameters, BobToAliceTransferParameters};
 use generic_array::GenericArray;
-use rand::rngs::ThreadRng;
 use serde::{Deserialize, Serialize};
 use sha3::Digest;
 use std::fmt::Debug;
@@ -19,9 +18,6 @@ use std::fmt::Formatter;
 use std::ops::Deref;
 use std::sync::Arc;
 
-#[cfg(target_family = "wasm")]
-use crate::functions::AsSlice;
-
 pub use crate::replay_attack_container::AntiReplayAttackContainer;
 
 pub mod prelude {
@@ -50,6 +46,9 @@ pub mod encryption;
 
 pub mod constructor_opts;
 
+/// For dispatching KEM operations through dpb_kem
+pub mod kem;
+
 pub mod wire;
 
 /// For debug purposes
@@ -69,130 +68,8 @@ pub const fn get_approx_bytes_per_container() -> usize {
     2000
 }
 
-#[cfg(not(target_family = "wasm"))]
-pub(crate) mod functions {
-    use crate::Error;
-    use oqs::sig::Sig;
-
-    pub type SecretKeyType = Vec<u8>;
-    pub type PublicKeyType = Vec<u8>;
-    const ALG: oqs::sig::Algorithm = oqs::sig::Algorithm::Falcon1024;
-
-    pub fn signature_sign(
-        message: impl AsRef<[u8]>,
-        secret_key: impl AsRef<[u8]>,
-    ) -> Result<Vec<u8>, Error> {
-        let sig = get_sig()?;
-        let secret_key = sig
-            .secret_key_from_bytes(secret_key.as_ref())
-            .ok_or(Error::Generic("Bad secret key length"))?;
-        sig.sign(message.as_ref(), secret_key)
-            .map(|r| r.into_vec())
-            .map_err(|err| Error::Other(err.to_string()))
-    }
-
-    pub fn signature_verify(
-        message: impl AsRef<[u8]>,
-        signature: impl AsRef<[u8]>,
-        public_key: impl AsRef<[u8]>,
-    ) -> Result<(), Error> {
-        let sig = get_sig()?;
-        let signature = sig
-            .signature_from_bytes(signature.as_ref())
-            .ok_or(Error::Generic("Bad signature length"))?;
-        let public_key = sig
-            .public_key_from_bytes(public_key.as_ref())
-            .ok_or(Error::Generic("Bad public key length"))?;
-
-        sig.verify(message.as_ref(), signature, public_key)
-            .map_err(|err| Error::Other(err.to_string()))
-    }
-
-    pub fn signature_keypair() -> Result<(PublicKeyType, SecretKeyType), Error> {
-        get_sig()?
-            .keypair()
-            .map_err(|err| Error::Other(err.to_string()))
-            .map(|(l, r)| (l.into_vec(), r.into_vec()))
-    }
-
-    pub fn signature_bytes() -> usize {
-        get_sig().unwrap().length_signature()
-    }
-
-    fn get_sig() -> Result<Sig, Error> {
-        oqs::sig::Sig::new(ALG).map_err(|err| Error::Other(err.to_string()))
-    }
-}
-
-#[cfg(target_family = "wasm")]
-pub(crate) mod functions {
-    use crate::Error;
-    use pqcrypto_falcon_wasi::falcon1024;
-    use pqcrypto_falcon_wasi::falcon1024::DetachedSignature;
-    use pqcrypto_traits_wasi::sign::PublicKey as PublicKeyTrait;
-    use pqcrypto_traits_wasi::sign::{DetachedSignature as DetachedSignatureTrait, SecretKey};
-
-    pub trait AsSlice {
-        fn as_slice(&self) -> &[u8];
-    }
-
-    pub type SecretKeyType = falcon1024::SecretKey;
-    pub type PublicKeyType = falcon1024::PublicKey;
-
-    impl AsSlice for SecretKeyType {
-        fn as_slice(&self) -> &[u8] {
-            self.as_bytes()
-        }
-    }
-
-    impl AsSlice for DetachedSignature {
-        fn as_slice(&self) -> &[u8] {
-            self.as_bytes()
-        }
-    }
-
-    impl AsSlice for PublicKeyType {
-        fn as_slice(&self) -> &[u8] {
-            use pqcrypto_traits_wasi::sign::PublicKey;
-            self.as_bytes()
-        }
-    }
-
-    pub fn signature_sign(
-        message: impl AsRef<[u8]>,
-        secret_key: impl AsRef<[u8]>,
-    ) -> Result<Vec<u8>, Error> {
-        let secret_key = falcon1024::SecretKey::from_bytes(secret_key.as_ref())
-            .map_err(|err| Error::Other(err.to_string()))?;
-        Ok(falcon1024::detached_sign(message.as_ref(), &secret_key)
-            .as_bytes()
-            .to_vec())
-    }
-
-    pub fn signature_verify(
-        message: impl AsRef<[u8]>,
-        signature: impl AsRef<[u8]>,
-        public_key: impl AsRef<[u8]>,
-    ) -> Result<(), Error> {
-        let signature = deserialize::<falcon1024::DetachedSignature>(signature.as_ref())?;
-        let public_key = falcon1024::PublicKey::from_bytes(public_key.as_ref())
-            .map_err(|err| Error::Other(err.to_string()))?;
-        falcon1024::verify_detached_signature(&signature, message.as_ref(), &public_key)
-            .map_err(|err| Error::Other(err.to_string()))
-    }
-
-    pub fn signature_keypair() -> Result<(PublicKeyType, SecretKeyType), Error> {
-        Ok(falcon1024::keypair())
-    }
-
-    pub fn signature_bytes() -> usize {
-        pqcrypto_falcon_wasi::falcon1024::signature_bytes()
-    }
-
-    fn deserialize<T: DetachedSignatureTrait>(bytes: &[u8]) -> Result<T, Error> {
-        T::from_bytes(bytes).map_err(|err| Error::Other(err.to_string()))
-    }
-}
+/// For dispatching signature operations per-algorithm
+pub(crate) mod functions;
 
 /// Contains the public keys for Alice and Bob
 #[derive(Serialize, Deserialize)]
@@ -239,8 +116,12 @@ impl PostQuantumContainer {
         let params = opts.cryptography.unwrap_or_default();
         validate_crypto_params(&params)?;
         let previous_symmetric_key = opts.chain;
-        let data = Self::create_new_alice(params.kem_algorithm, params.sig_algorithm)
-            .map_err(|err| Error::Other(err.to_string()))?;
+        let data = Self::create_new_alice(
+            params.kem_algorithm,
+            params.kyber_parameter_set,
+            params.sig_algorithm,
+        )
+        .map_err(|err| Error::Other(err.to_string()))?;
         let aes_gcm_key = None;
         log::trace!(target: "citadel", "Success creating new ALICE container");
 
@@ -274,7 +155,8 @@ impl PostQuantumContainer {
 
         let chain = opts.chain;
 
-        let data = Self::create_new_bob(tx_params).map_err(|err| Error::Other(err.to_string()))?;
+        let data =
+            Self::create_new_bob(tx_params, params).map_err(|err| Error::Other(err.to_string()))?;
         // We must call the below to refresh the internal state to allow get_shared_secret to function
         let ss = data.get_shared_secret().unwrap().clone();
         let kex = data.kex().clone();
@@ -521,6 +403,38 @@ impl PostQuantumContainer {
         self.data.get_shared_secret()
     }
 
+    /// Signs the message with the local signature key. Fails if no signature algorithm was selected
+    pub fn sign<T: AsRef<[u8]>>(&self, message: T) -> Result<Vec<u8>, Error> {
+        let sig = self
+            .data
+            .sig()
+            .ok_or(Error::Generic("No signature algorithm selected"))?;
+        crate::functions::signature_sign(sig.sig_alg, message, sig.sig_private_key.as_slice())
+    }
+
+    /// Verifies a signature created by the adjacent node, whose signature key was authenticated during the key
+    /// exchange. Fails if no signature algorithm was selected
+    pub fn verify_remote_signature<T: AsRef<[u8]>, R: AsRef<[u8]>>(
+        &self,
+        message: T,
+        signature: R,
+    ) -> Result<(), Error> {
+        let sig = self
+            .data
+            .sig()
+            .ok_or(Error::Generic("No signature algorithm selected"))?;
+        let remote_sig_public_key = sig
+            .remote_sig_public_key
+            .as_ref()
+            .ok_or(Error::Generic("The remote signature key is not yet known"))?;
+        crate::functions::signature_verify(
+            sig.sig_alg,
+            message,
+            signature,
+            remote_sig_public_key.as_slice(),
+        )
+    }
+
     /// Serializes the entire package to a vector
     pub fn serialize_to_vector(&self) -> Result<Vec<u8>, Error> {
         bincode2::serialize(self).map_err(|_err| Error::Generic("Deserialization failure"))
@@ -649,15 +563,17 @@ impl PostQuantumContainer {
 
     fn create_new_alice(
         kem_algorithm: KemAlgorithm,
+        kyber_parameter_set: KyberParameterSet,
         sig_algorithm: SigAlgorithm,
     ) -> Result<PostQuantumMeta, Error> {
-        PostQuantumMeta::new_alice(kem_algorithm, sig_algorithm)
+        PostQuantumMeta::new_alice(kem_algorithm, kyber_parameter_set, sig_algorithm)
     }
 
     fn create_new_bob(
         alice_to_bob_transfer_params: AliceToBobTransferParameters,
+        params: CryptoParameters,
     ) -> Result<PostQuantumMeta, Error> {
-        PostQuantumMeta::new_bob(alice_to_bob_transfer_params)
+        PostQuantumMeta::new_bob(alice_to_bob_transfer_params, params)
     }
 }
 
@@ -686,21 +602,54 @@ pub mod algorithm_dictionary {
 
     pub const KEM_ALGORITHM_COUNT: u8 = KemAlgorithm::COUNT as u8;
 
-    #[derive(PackedStruct, Default, Serialize, Deserialize, Copy, Clone, Debug)]
-    #[packed_struct(bit_numbering = "msb0")]
+    /// Packed into a single byte (msb0): the encryption algorithm occupies bits 0..=2, the KEM bits 3..=5 and the
+    /// signature algorithm bits 6..=7. The KEM bits hold both the KEM algorithm and the Kyber parameter set, as listed
+    /// in [`KEM_FIELD_VALUES`]
+    #[derive(Default, Serialize, Deserialize, Copy, Clone, Debug)]
     pub struct CryptoParameters {
-        #[packed_field(bits = "0..=2", ty = "enum")]
         pub encryption_algorithm: EncryptionAlgorithm,
-        #[packed_field(bits = "3..=5", ty = "enum")]
         pub kem_algorithm: KemAlgorithm,
-        #[packed_field(bits = "6..=7", ty = "enum")]
+        pub kyber_parameter_set: KyberParameterSet,
         pub sig_algorithm: SigAlgorithm,
     }
 
+    const ENCRYPTION_ALGORITHM_SHIFT: u8 = 5;
+    const KEM_SHIFT: u8 = 2;
+    const KEM_MASK: u8 = 0b111;
+    const SIG_ALGORITHM_MASK: u8 = 0b11;
+
+    /// The value of the packed KEM bits for each combination of KEM algorithm and Kyber parameter set, indexed by value.
+    /// The values are part of the wire format: new combinations may only be appended, and existing entries must never
+    /// be reordered. Parameters using the default parameter set (Kyber1024) keep the values they held before the
+    /// parameter set was selectable
+    pub const KEM_FIELD_VALUES: [(KemAlgorithm, KyberParameterSet); 6] = [
+        (KemAlgorithm::Kyber, KyberParameterSet::Kyber1024),
+        (
+            KemAlgorithm::HybridX25519Kyber,
+            KyberParameterSet::Kyber1024,
+        ),
+        (KemAlgorithm::Kyber, KyberParameterSet::Kyber512),
+        (KemAlgorithm::HybridX25519Kyber, KyberParameterSet::Kyber512),
+        (KemAlgorithm::Kyber, KyberParameterSet::Kyber768),
+        (KemAlgorithm::HybridX25519Kyber, KyberParameterSet::Kyber768),
+    ];
+
+    // every combination must have a value, and every value must fit within its bits
+    const _: () = assert!(KEM_FIELD_VALUES.len() <= KEM_MASK as usize + 1);
+    const _: () = assert!(KEM_FIELD_VALUES.len() == KemAlgorithm::COUNT * KyberParameterSet::COUNT);
+    const _: () =
+        assert!(EncryptionAlgorithm::COUNT <= (u8::MAX >> ENCRYPTION_ALGORITHM_SHIFT) as usize + 1);
+    const _: () = assert!(SigAlgorithm::COUNT <= SIG_ALGORITHM_MASK as usize + 1);
+
     impl From<CryptoParameters> for u8 {
         fn from(val: CryptoParameters) -> Self {
-            let bytes: [u8; 1] = val.pack().unwrap();
-            bytes[0]
+            let kem = KEM_FIELD_VALUES
+                .iter()
+                .position(|entry| *entry == (val.kem_algorithm, val.kyber_parameter_set))
+                .expect("KEM_FIELD_VALUES lists every combination") as u8;
+            (val.encryption_algorithm.to_primitive() << ENCRYPTION_ALGORITHM_SHIFT)
+                | (kem << KEM_SHIFT)
+                | val.sig_algorithm.to_primitive()
         }
     }
 
@@ -708,9 +657,21 @@ pub mod algorithm_dictionary {
         type Error = crate::ez_error::Error;
 
         fn try_from(value: u8) -> Result<Self, Self::Error> {
-            let value: [u8; 1] = [value];
-            let this: CryptoParameters =
-                CryptoParameters::unpack(&value).map_err(|err| Error::Other(err.to_string()))?;
+            let (kem_algorithm, kyber_parameter_set) = *KEM_FIELD_VALUES
+                .get(((value >> KEM_SHIFT) & KEM_MASK) as usize)
+                .ok_or(Error::Generic(
+                    "Invalid KEM algorithm or Kyber parameter set",
+                ))?;
+            let this = CryptoParameters {
+                encryption_algorithm: EncryptionAlgorithm::from_primitive(
+                    value >> ENCRYPTION_ALGORITHM_SHIFT,
+                )
+                .ok_or(Error::Generic("Invalid encryption algorithm"))?,
+                kem_algorithm,
+                kyber_parameter_set,
+                sig_algorithm: SigAlgorithm::from_primitive(value & SIG_ALGORITHM_MASK)
+                    .ok_or(Error::Generic("Invalid signature algorithm"))?,
+            };
             validate_crypto_params(&this)?;
             Ok(this)
         }
@@ -728,6 +689,7 @@ pub mod algorithm_dictionary {
         Deserialize,
         strum::EnumString,
         strum::EnumIter,
+        strum::EnumCount,
     )]
     pub enum EncryptionAlgorithm {
         #[default]
@@ -746,7 +708,7 @@ pub mod algorithm_dictionary {
         }
 
         // calculates the max ciphertext len given an input plaintext length
-        pub fn max_ciphertext_len(&self, plaintext_length: usize, _sig_alg: SigAlgorithm) -> usize {
+        pub fn max_ciphertext_len(&self, plaintext_length: usize, sig_alg: SigAlgorithm) -> usize {
             const SYMMETRIC_CIPHER_OVERHEAD: usize = 16;
             match self {
                 Self::AES_GCM_256_SIV => plaintext_length + SYMMETRIC_CIPHER_OVERHEAD,
@@ -755,7 +717,7 @@ pub mod algorithm_dictionary {
                 // Add 32 for internal apendees
                 Self::Kyber => {
                     const LENGTH_FIELD: usize = 8;
-                    let signature_len = crate::functions::signature_bytes();
+                    let signature_len = crate::functions::signature_bytes(sig_alg);
 
                     let aes_input_len = signature_len + LENGTH_FIELD;
                     let aes_output_len = aes_input_len + SYMMETRIC_CIPHER_OVERHEAD;
@@ -795,12 +757,54 @@ pub mod algorithm_dictionary {
         #[strum(ascii_case_insensitive)]
         #[default]
         Kyber = 0,
+        /// Combines X25519 ECDH with Kyber. The derived shared secret remains secure so long as
+        /// either of the two primitives remains unbroken
+        #[strum(ascii_case_insensitive)]
+        HybridX25519Kyber = 1,
+    }
+
+    impl KemAlgorithm {
+        /// Returns true if this KEM additionally performs a classical key agreement
+        pub fn is_hybrid(&self) -> bool {
+            matches!(self, Self::HybridX25519Kyber)
+        }
     }
 
+    /// The Kyber parameter set used by the KEM (including the Kyber half of the hybrid KEM). Kyber1024 is the "90s"
+    /// variant of round-3 Kyber, whereas Kyber512 and Kyber768 are the standard variant, so endpoints must agree upon
+    /// the parameter set. None of the parameter sets are FIPS 203 ML-KEM
     #[derive(
         PrimitiveEnum_u8,
+        Default,
+        Copy,
+        Clone,
+        Debug,
+        Eq,
+        PartialEq,
+        Serialize,
+        Deserialize,
         strum::EnumString,
         strum::EnumIter,
+        strum::EnumCount,
+    )]
+    pub enum KyberParameterSet {
+        /// NIST security level 5. Required when using [`EncryptionAlgorithm::Kyber`]
+        #[strum(ascii_case_insensitive)]
+        #[default]
+        Kyber1024 = 0,
+        /// NIST security level 1. Smallest keys and ciphertexts, suitable for constrained devices
+        #[strum(ascii_case_insensitive)]
+        Kyber512 = 1,
+        /// NIST security level 3
+        #[strum(ascii_case_insensitive)]
+        Kyber768 = 2,
+    }
+
+    #[derive(
+        PrimitiveEnum_u8,
+        strum::EnumString,
+        strum::EnumIter,
+        strum::EnumCount,
         Default,
         Serialize,
         Deserialize,
@@ -814,6 +818,10 @@ pub mod algorithm_dictionary {
         #[default]
         None = 0,
         Falcon1024 = 1,
+        /// Round-3 CRYSTALS-Dilithium3 (NIST security level 3). Not interoperable with FIPS 204 ML-DSA-65
+        Dilithium3 = 2,
+        /// Ed25519 combined with Dilithium3. Both signatures must verify
+        HybridEd25519Dilithium3 = 3,
     }
 
     pub trait AlgorithmsExt:
@@ -862,6 +870,12 @@ pub mod algorithm_dictionary {
         }
     }
 
+    impl AlgorithmsExt for KyberParameterSet {
+        fn set_crypto_param(&self, params: &mut CryptoParameters) {
+            params.kyber_parameter_set = *self;
+        }
+    }
+
     impl<R: AlgorithmsExt> Add<R> for KemAlgorithm {
         type Output = CryptoParameters;
 
@@ -886,6 +900,14 @@ pub mod algorithm_dictionary {
         }
     }
 
+    impl<R: AlgorithmsExt> Add<R> for KyberParameterSet {
+        type Output = CryptoParameters;
+
+        fn add(self, rhs: R) -> Self::Output {
+            add_inner(self, rhs)
+        }
+    }
+
     impl<R: AlgorithmsExt> Add<R> for CryptoParameters {
         type Output = CryptoParameters;
 
@@ -913,11 +935,11 @@ pub mod algorithm_dictionary {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PostQuantumMetaKex {
-    /// The public key of remote
+    /// The Kyber public key of remote
     remote_public_key: Option<Arc<Vec<u8>>>,
-    /// The public key. Both Alice and Bob get this
+    /// The public key. Both Alice and Bob get this. For Alice, this is the public key of the selected KEM
     public_key: Arc<Vec<u8>>,
-    /// Only Alice gets this one
+    /// For Alice, this is the secret key of the selected KEM until Bob's ciphertext arrives, after which only its Kyber component is kept
     secret_key: Option<Arc<Vec<u8>>>,
     /// Both Bob and Alice get this one
     ciphertext: Option<Arc<Vec<u8>>>,
@@ -925,6 +947,8 @@ pub struct PostQuantumMetaKex {
     shared_secret: Option<Arc<Vec<u8>>>,
     /// the kem algorithm
     kem_alg: KemAlgorithm,
+    /// the kyber parameter set
+    kyber_parameter_set: KyberParameterSet,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -953,26 +977,35 @@ pub enum PostQuantumMeta {
 }
 
 impl PostQuantumMeta {
-    fn new_alice(kem_alg: KemAlgorithm, sig_alg: SigAlgorithm) -> Result<Self, Error> {
-        log::trace!(target: "citadel", "About to generate keypair for {:?}", kem_alg);
-        let (public_key, secret_key) = kyber_pke::kem_keypair();
+    fn new_alice(
+        kem_alg: KemAlgorithm,
+        kyber_parameter_set: KyberParameterSet,
+        sig_alg: SigAlgorithm,
+    ) -> Result<Self, Error> {
+        log::trace!(target: "citadel", "About to generate keypair for {:?} ({:?})", kem_alg, kyber_parameter_set);
+        let (public_key, secret_key) =
+            crate::kem::kem_for(kem_alg, kyber_parameter_set).keypair()?;
         let ciphertext = None;
         let shared_secret = None;
         let remote_sig_public_key = None;
-        let secret_key = Some(Arc::new(secret_key.to_vec()));
+        let secret_key = Some(Arc::new(secret_key.into_vec()));
 
         let kex = PostQuantumMetaKex {
-            public_key: Arc::new(public_key.to_vec()),
+            public_key: Arc::new(public_key.into_vec()),
             secret_key,
             ciphertext,
             shared_secret,
             kem_alg,
+            kyber_parameter_set,
             remote_public_key: None,
         };
 
         match sig_alg {
-            SigAlgorithm::Falcon1024 => {
-                let (sig_public_key, sig_private_key) = crate::functions::signature_keypair()?;
+            SigAlgorithm::None => Ok(Self::PureSymmetricEncryption { kex }),
+
+            sig_alg => {
+                let (sig_public_key, sig_private_key) =
+                    crate::functions::signature_keypair(sig_alg)?;
                 let sig = PostQuantumMetaSig {
                     sig_public_key: Arc::new(sig_public_key),
                     sig_private_key: Arc::new(sig_private_key),
@@ -982,41 +1015,58 @@ impl PostQuantumMeta {
 
                 Ok(Self::MixedAsymmetric { kex, sig })
             }
-
-            SigAlgorithm::None => Ok(Self::PureSymmetricEncryption { kex }),
         }
     }
 
-    fn new_bob(params: AliceToBobTransferParameters) -> Result<Self, Error> {
-        let (kem_scheme, pk_alice) = match &params {
+    fn new_bob(
+        params: AliceToBobTransferParameters,
+        local_params: CryptoParameters,
+    ) -> Result<Self, Error> {
+        let (kem_scheme, kyber_parameter_set, pk_alice) = match &params {
             AliceToBobTransferParameters::MixedAsymmetric {
                 kem_scheme,
+                kyber_parameter_set,
                 alice_pk,
                 ..
             }
             | AliceToBobTransferParameters::PureSymmetric {
                 kem_scheme,
+                kyber_parameter_set,
                 alice_pk,
                 ..
-            } => (*kem_scheme, alice_pk),
+            } => (*kem_scheme, *kyber_parameter_set, alice_pk),
         };
 
-        let (kem_pk_bob, kem_sk_bob) = kyber_pke::kem_keypair();
-        let (kem_pk_bob, kem_sk_bob) = (kem_pk_bob.to_vec(), kem_sk_bob.to_vec());
-
-        let (ciphertext, shared_secret) = match kem_scheme {
-            KemAlgorithm::Kyber => {
-                let (ciphertext, shared_secret) =
-                    kyber_pke::encapsulate(pk_alice, &mut ThreadRng::default())
-                        .map_err(|_err| get_generic_error("Failed encapsulate step"))?;
-                (ciphertext.to_vec(), shared_secret.to_vec())
-            }
-        };
+        // Both endpoints must agree on the KEM before any keys are derived
+        if kem_scheme != local_params.kem_algorithm
+            || kyber_parameter_set != local_params.kyber_parameter_set
+        {
+            return Err(Error::Other(format!(
+                "KEM parameter mismatch. Remote declared {:?} ({:?}), but local node expects {:?} ({:?})",
+                kem_scheme,
+                kyber_parameter_set,
+                local_params.kem_algorithm,
+                local_params.kyber_parameter_set
+            )));
+        }
 
-        let public_key = Arc::new(kem_pk_bob);
-        let secret_key = Some(Arc::new(kem_sk_bob));
-        let shared_secret = Some(Arc::new(shared_secret));
-        let ciphertext = Some(Arc::new(ciphertext));
+        let (ciphertext, shared_secret) =
+            crate::kem::kem_for(kem_scheme, kyber_parameter_set).encapsulate(pk_alice)?;
+        // Bob's own keypair is only used for Kyber encryption, so a plain Kyber keypair suffices
+        let kyber = crate::kem::kyber_for(kyber_parameter_set);
+        let (kem_pk_bob, kem_sk_bob) = kyber.keypair()?;
+        // every KEM's public key begins with its Kyber component
+        let remote_public_key = Arc::new(
+            pk_alice
+                .get(..kyber.public_key_len())
+                .ok_or(Error::InvalidLength)?
+                .to_vec(),
+        );
+
+        let public_key = Arc::new(kem_pk_bob.into_vec());
+        let secret_key = Some(Arc::new(kem_sk_bob.into_vec()));
+        let shared_secret = Some(Arc::new(shared_secret.into_vec()));
+        let ciphertext = Some(Arc::new(ciphertext.into_vec()));
 
         match params {
             AliceToBobTransferParameters::MixedAsymmetric {
@@ -1025,12 +1075,13 @@ impl PostQuantumMeta {
                 alice_public_key_signature,
                 sig_scheme,
                 kem_scheme,
+                ..
             } => {
-                let (sig_pk_bob, sig_sk_bob) = crate::functions::signature_keypair()?;
-                let public_key_alice = alice_pk;
+                let (sig_pk_bob, sig_sk_bob) = crate::functions::signature_keypair(sig_scheme)?;
 
                 crate::functions::signature_verify(
-                    public_key_alice.as_slice(),
+                    sig_scheme,
+                    alice_pk.as_slice(),
                     alice_public_key_signature.as_slice(),
                     alice_pk_sig.as_slice(),
                 )?;
@@ -1038,12 +1089,13 @@ impl PostQuantumMeta {
                 let remote_sig_public_key = Some(alice_pk_sig);
 
                 let kex = PostQuantumMetaKex {
-                    remote_public_key: Some(public_key_alice),
+                    remote_public_key: Some(remote_public_key),
                     public_key,
                     secret_key,
                     ciphertext,
                     shared_secret,
                     kem_alg: kem_scheme,
+                    kyber_parameter_set,
                 };
 
                 let sig = PostQuantumMetaSig {
@@ -1055,18 +1107,15 @@ impl PostQuantumMeta {
 
                 Ok(Self::MixedAsymmetric { kex, sig })
             }
-            AliceToBobTransferParameters::PureSymmetric {
-                alice_pk,
-                kem_scheme,
-            } => {
-                let public_key_alice = alice_pk;
+            AliceToBobTransferParameters::PureSymmetric { kem_scheme, .. } => {
                 let kex = PostQuantumMetaKex {
-                    remote_public_key: Some(public_key_alice),
+                    remote_public_key: Some(remote_public_key),
                     public_key,
                     secret_key,
                     ciphertext,
                     shared_secret,
                     kem_alg: kem_scheme,
+                    kyber_parameter_set,
                 };
 
                 Ok(Self::PureSymmetricEncryption { kex })
@@ -1089,7 +1138,11 @@ impl PostQuantumMeta {
                 bob_ciphertext,
                 ..
             } => {
+                let sig_alg = self
+                    .get_sig_algorithm()
+                    .ok_or(Error::Generic("Signature parameters missing"))?;
                 crate::functions::signature_verify(
+                    sig_alg,
                     bob_ciphertext.as_slice(),
                     bob_ciphertext_signature.as_slice(),
                     bob_pk_sig.as_slice(),
@@ -1099,10 +1152,20 @@ impl PostQuantumMeta {
         };
 
         let secret_key = self.get_secret_key()?;
-        let shared_secret = kyber_pke::decapsulate(&bob_ciphertext, secret_key)
-            .map_err(|err| Error::Other(err.to_string()))?;
-        self.get_kex_mut().shared_secret = Some(Arc::new(shared_secret.to_vec()));
-        self.get_kex_mut().ciphertext = Some(bob_ciphertext);
+        let kex = self.kex();
+        let kyber_parameter_set = kex.kyber_parameter_set;
+        let shared_secret = crate::kem::kem_for(kex.kem_alg, kyber_parameter_set)
+            .decapsulate(&bob_ciphertext, secret_key)?;
+        // Once the shared secret is derived, only the Kyber component of the secret key is needed
+        let kyber_secret_key = secret_key
+            .get(..crate::kem::kyber_for(kyber_parameter_set).secret_key_len())
+            .ok_or(Error::InvalidLength)?
+            .to_vec();
+
+        let kex = self.get_kex_mut();
+        kex.shared_secret = Some(Arc::new(shared_secret.into_vec()));
+        kex.ciphertext = Some(bob_ciphertext);
+        kex.secret_key = Some(Arc::new(kyber_secret_key));
 
         match params {
             BobToAliceTransferParameters::MixedAsymmetric {
@@ -1125,11 +1188,13 @@ impl PostQuantumMeta {
                 let alice_pk = kex.public_key.clone();
                 let alice_pk_sig = sig.sig_public_key.clone();
                 let alice_public_key_signature = crate::functions::signature_sign(
+                    sig.sig_alg,
                     alice_pk.as_slice(),
                     sig.sig_private_key.as_slice(),
                 )?;
                 let sig_scheme = sig.sig_alg;
                 let kem_scheme = kex.kem_alg;
+                let kyber_parameter_set = kex.kyber_parameter_set;
 
                 Ok(AliceToBobTransferParameters::MixedAsymmetric {
                     alice_pk,
@@ -1137,15 +1202,18 @@ impl PostQuantumMeta {
                     alice_public_key_signature,
                     sig_scheme,
                     kem_scheme,
+                    kyber_parameter_set,
                 })
             }
             PostQuantumMeta::PureSymmetricEncryption { kex } => {
                 let alice_pk = kex.public_key.clone();
                 let kem_scheme = kex.kem_alg;
+                let kyber_parameter_set = kex.kyber_parameter_set;
 
                 Ok(AliceToBobTransferParameters::PureSymmetric {
                     alice_pk,
                     kem_scheme,
+                    kyber_parameter_set,
                 })
             }
         }
@@ -1163,6 +1231,7 @@ impl PostQuantumMeta {
             }
             PostQuantumMeta::MixedAsymmetric { sig, .. } => {
                 let bob_signed_ciphertext = crate::functions::signature_sign(
+                    sig.sig_alg,
                     bob_ciphertext.as_slice(),
                     sig.sig_private_key.as_slice(),
                 )?;
@@ -1178,7 +1247,6 @@ impl PostQuantumMeta {
         }
     }
 
-    #[allow(dead_code)]
     fn get_sig_algorithm(&self) -> Option<SigAlgorithm> {
         match self {
             PostQuantumMeta::PureSymmetricEncryption { .. } => None,
@@ -1279,16 +1347,23 @@ impl Debug for PostQuantumContainer {
 }
 
 pub fn validate_crypto_params(params: &CryptoParameters) -> Result<(), Error> {
+    // NOTE: every KEM, including the hybrid KEM, yields a Kyber keypair, so Kyber encryption may be paired with any of them
     if params.encryption_algorithm == EncryptionAlgorithm::Kyber
-        && params.kem_algorithm != KemAlgorithm::Kyber
+        && params.kyber_parameter_set != KyberParameterSet::Kyber1024
     {
         return Err(Error::Generic(
-            "Invalid crypto parameter combination. Kyber encryption must be paired with Kyber KEM",
+            "Invalid crypto parameter combination. Kyber encryption requires the Kyber1024 parameter set",
+        ));
+    }
+
+    #[cfg(target_family = "wasm")]
+    if params.kyber_parameter_set != KyberParameterSet::Kyber1024 {
+        return Err(Error::Generic(
+            "Only the Kyber1024 parameter set is currently supported on wasm targets",
         ));
     }
 
     if params.encryption_algorithm == EncryptionAlgorithm::Kyber
-        && params.kem_algorithm == KemAlgorithm::Kyber
         && params.sig_algorithm == SigAlgorithm::None
     {
         return Err(Error::Generic(
@@ -1,25 +1,32 @@
-use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::hash::{BuildHasher, Hasher};
-use std::marker::PhantomData;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-/// The past HISTORY_LEN packets arrived will be saved to allow out-of-order delivery of packets
+/// The default number of past PIDs that may arrive out-of-order before being considered too old
 pub const HISTORY_LEN: u64 = 1024;
+/// The number of PIDs tracked per block of the bitmap. Each block is packed into a single [`AtomicU64`],
+/// where the upper 32 bits tag which block of PIDs the slot currently tracks, and the lower 32 bits
+/// are the bitmap itself
+const BITS_PER_BLOCK: u64 = 32;
+const BITMAP_MASK: u64 = 0xFFFF_FFFF;
+
 /// Helps ensure that each packet protected is only used once
 ///
 /// packets that get "protected" get a unique packet ID (PID) that gets encrypted with the plaintext to ensure each packet that gets crafted
-/// can only be used once. In the validation stage, if the the decrypted PID already exists, then the decryption fails.
-/// NOTE: we must use a circular queue over a simple atomic incrementer because a packet with PID k + n may arrive before
-/// packet with PID k. By using a circular queue, we ensure that packets may arrive out of order, and, that they can still
-/// be kept tracked of within a small range (maybe 100)
+/// can only be used once. In the validation stage, if the the decrypted PID already arrived, then the decryption fails.
+///
+/// Received PIDs are tracked using an RFC 6479-style sliding window bitmap. Any PID within `window_size` of the highest PID received
+/// may arrive out-of-order, whereas any PID older than that is rejected, preventing delayed replay attacks. The bitmap is
+/// made of one more block than necessary to cover the window, allowing the window to advance by reusing the oldest block without
+/// ever clearing bits that are still inside the window. Each block carries a tag, allowing the container to be entirely lock-free
 ///
 /// This should be session-unique. There's no point to saving this, especially since re-keying occurs in the networking stack
 #[derive(Serialize, Deserialize)]
 pub struct AntiReplayAttackContainer {
-    // the first value is the number of packets received
-    history: Mutex<(u64, HashSet<u64, NoHashHasher<u64>>)>,
+    // each slot is (block tag << 32 | bitmap)
+    blocks: Box<[AtomicU64]>,
+    // one more than the highest PID received, or zero if no PIDs have been received
+    highest: AtomicU64,
+    window_size: u64,
     // used for getting the next unique outbound PID. Each node has a unique counter
     counter_out: AtomicU64,
 }
@@ -27,106 +34,100 @@ pub struct AntiReplayAttackContainer {
 const ORDERING: Ordering = Ordering::Relaxed;
 
 impl AntiReplayAttackContainer {
+    /// Creates a new container that accepts out-of-order PIDs within `window_size` of the highest PID received.
+    /// The window size is rounded up to the nearest multiple of 32
+    pub fn with_window_size(window_size: u64) -> Self {
+        let window_size = window_size.max(1).div_ceil(BITS_PER_BLOCK) * BITS_PER_BLOCK;
+        let block_count = (window_size / BITS_PER_BLOCK + 1) as usize;
+
+        Self {
+            blocks: (0..block_count).map(|_| AtomicU64::new(0)).collect(),
+            highest: AtomicU64::new(0),
+            window_size,
+            counter_out: AtomicU64::new(0),
+        }
+    }
+
     #[inline]
     pub fn get_next_pid(&self) -> u64 {
         self.counter_out.fetch_add(1, ORDERING)
     }
 
-    /// If the value already exists, this will return an error. If not, this will save
-    /// the PID in the internal circular buffer
-    #[allow(unused_results)]
+    /// Returns the number of PIDs behind the highest PID received that may still arrive
+    pub fn window_size(&self) -> u64 {
+        self.window_size
+    }
+
+    /// If the PID already arrived, or, is too old to be tracked by the window, this will return false.
+    /// If not, this will save the PID in the window and return true
     pub fn on_pid_received(&self, pid_received: u64) -> bool {
-        let mut queue = self.history.lock();
-        //log::trace!(target: "citadel", "Circular queue: {:?}", &queue.1);
-        if queue.1.contains(&pid_received) {
-            log::error!(target: "citadel", "[ARA] packet {} already arrived!", pid_received);
-            false
-        } else {
-            // this means the PID is not in the history. HOWEVER, it may still be possible that the packet
-            // was withheld long enough for the history to be cleared, thus enabling a delayed replay attack.
-            // To ensure we protect against a delayed replay attack, check to see that the received PID is
-            // within HISTORY_LEN of counter_in
-            //let min = queue.0.saturating_sub(HISTORY_LEN);
-            let min = queue.0.saturating_sub(HISTORY_LEN);
-            //let max = queue.0 + HISTORY_LEN;
-            //log::trace!(target: "citadel", "RECV {}. Must be >= {} (st: {})", pid_received, min, queue.0);
-            // TODO: Consider logic of this section of code. This may not do what I want it to do
-            if pid_received >= min {
-                if queue.1.len() >= HISTORY_LEN as _ {
-                    let lowest = queue.0;
-
-                    // remove the lowest value. Only increment if the lowest value exists
-                    if queue.1.remove(&lowest) {
-                        queue.0 += 1;
-                    }
+        let highest = self.highest.load(Ordering::Acquire);
+        // Reject any PID that is behind the window. This protects against delayed replay attacks, where
+        // the packet is withheld long enough for its entry in the window to be recycled
+        if pid_received.saturating_add(self.window_size) < highest {
+            log::error!(target: "citadel", "[ARA] out of range! Recv: {}. Expected > {}", pid_received, highest - 1 - self.window_size);
+            return false;
+        }
+
+        // The window must advance before the block is claimed below. Otherwise, a concurrent receiver with an older
+        // PID mapped to the same slot could mistake the newer block for a stale one and overwrite it
+        let _ = self
+            .highest
+            .fetch_max(pid_received.saturating_add(1), Ordering::AcqRel);
+
+        let block_idx = pid_received / BITS_PER_BLOCK;
+        let tag = block_idx & BITMAP_MASK;
+        let bit = 1 << (pid_received % BITS_PER_BLOCK);
+        let slot = &self.blocks[(block_idx % self.blocks.len() as u64) as usize];
+        let mut current = slot.load(Ordering::Acquire);
+
+        loop {
+            let current_tag = current >> BITS_PER_BLOCK;
+            let next = if current_tag == tag {
+                if current & bit != 0 {
+                    log::error!(target: "citadel", "[ARA] packet {} already arrived!", pid_received);
+                    return false;
                 }
 
-                //queue.0 += 1;
+                current | bit
+            } else {
+                // the slot either tracks an older block, in which case it is recycled, or, a newer block, in which case
+                // this PID was pushed out of the window while being processed. The newer block may have been claimed
+                // after `highest` was loaded above, so it is reloaded: the receiver that claimed the block advanced the
+                // window beforehand, and that advance is visible now that its claim has been observed
+                let highest = self.highest.load(Ordering::Acquire);
+                let highest_block_idx = (highest - 1) / BITS_PER_BLOCK;
+                let distance = current_tag.wrapping_sub(tag) & BITMAP_MASK;
+                if distance <= highest_block_idx - block_idx {
+                    log::error!(target: "citadel", "[ARA] packet {} fell out of the window", pid_received);
+                    return false;
+                }
 
-                queue.1.insert(pid_received);
+                (tag << BITS_PER_BLOCK) | bit
+            };
 
-                true
-            } else {
-                log::error!(target: "citadel", "[ARA] out of range! Recv: {}. Expected >= {}", pid_received, min);
-                false
+            match slot.compare_exchange_weak(current, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return true,
+                Err(updated) => current = updated,
             }
         }
     }
 
     pub fn has_tracked_packets(&self) -> bool {
-        (self.counter_out.load(ORDERING) != 0) || (self.history.lock().0 != 0)
+        (self.counter_out.load(ORDERING) != 0) || (self.highest.load(ORDERING) != 0)
     }
 
     pub fn reset(&self) {
         self.counter_out.store(0, ORDERING);
-        let mut lock = self.history.lock();
-        lock.0 = 0;
-        lock.1 = HashSet::with_capacity_and_hasher(HISTORY_LEN as usize, Default::default());
-    }
-}
-
-impl Default for AntiReplayAttackContainer {
-    fn default() -> Self {
-        Self {
-            history: Mutex::new((
-                0,
-                HashSet::with_capacity_and_hasher(HISTORY_LEN as usize, Default::default()),
-            )),
-            counter_out: AtomicU64::new(0),
+        self.highest.store(0, ORDERING);
+        for block in self.blocks.iter() {
+            block.store(0, ORDERING);
         }
     }
 }
 
-struct NoHashHasher<T>(u64, PhantomData<T>);
-
-impl<T> Default for NoHashHasher<T> {
+impl Default for AntiReplayAttackContainer {
     fn default() -> Self {
-        NoHashHasher(0, PhantomData)
-    }
-}
-
-trait IsEnabled {}
-
-impl IsEnabled for u64 {}
-
-impl<T: IsEnabled> Hasher for NoHashHasher<T> {
-    fn finish(&self) -> u64 {
-        self.0
-    }
-
-    fn write(&mut self, _: &[u8]) {
-        panic!("Invalid use of NoHashHasher")
-    }
-
-    fn write_u64(&mut self, n: u64) {
-        self.0 = n
-    }
-}
-
-impl<T: IsEnabled> BuildHasher for NoHashHasher<T> {
-    type Hasher = Self;
-
-    fn build_hasher(&self) -> Self::Hasher {
-        Self::default()
+        Self::with_window_size(HISTORY_LEN)
     }
 }
@@ -1,4 +1,4 @@
-use crate::{Error, KemAlgorithm, SigAlgorithm};
+use crate::{Error, KemAlgorithm, KyberParameterSet, SigAlgorithm};
 use aes_gcm_siv::aead::Buffer;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
@@ -12,10 +12,12 @@ pub enum AliceToBobTransferParameters {
         alice_public_key_signature: Vec<u8>,
         sig_scheme: SigAlgorithm,
         kem_scheme: KemAlgorithm,
+        kyber_parameter_set: KyberParameterSet,
     },
     PureSymmetric {
         alice_pk: Arc<Vec<u8>>,
         kem_scheme: KemAlgorithm,
+        kyber_parameter_set: KyberParameterSet,
     },
 }
 
@@ -2,15 +2,18 @@
 mod tests {
     use bytes::{BufMut, BytesMut};
     use rand::prelude::ThreadRng;
-    use rand::RngCore;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, RngCore};
 
     use citadel_logging::setup_log;
     use citadel_pqcrypto::algorithm_dictionary::{
-        AlgorithmsExt, CryptoParameters, EncryptionAlgorithm, KemAlgorithm, SigAlgorithm,
+        AlgorithmsExt, CryptoParameters, EncryptionAlgorithm, KemAlgorithm, KyberParameterSet,
+        SigAlgorithm, KEM_FIELD_VALUES,
     };
     use citadel_pqcrypto::bytes_in_place::EzBuffer;
     use citadel_pqcrypto::constructor_opts::ConstructorOpts;
-    use citadel_pqcrypto::replay_attack_container::HISTORY_LEN;
+    use citadel_pqcrypto::replay_attack_container::{AntiReplayAttackContainer, HISTORY_LEN};
+    use citadel_pqcrypto::wire::{AliceToBobTransferParameters, BobToAliceTransferParameters};
     use citadel_pqcrypto::{validate_crypto_params, PostQuantumContainer};
     use std::convert::TryFrom;
     use std::fmt::Debug;
@@ -345,6 +348,188 @@ mod tests {
         .unwrap()
     }
 
+    #[test]
+    fn test_all_signature_algorithms() {
+        citadel_logging::setup_log();
+        let nonce = Vec::from_iter(0..EncryptionAlgorithm::Kyber.nonce_len() as u8);
+        let message = b"Hello, world!";
+
+        for sig in SigAlgorithm::list() {
+            if sig == SigAlgorithm::None {
+                continue;
+            }
+
+            log::trace!(target: "citadel", "About to test {:?}", sig);
+            run(
+                KemAlgorithm::Kyber.as_u8(),
+                EncryptionAlgorithm::AES_GCM_256_SIV,
+                sig,
+            )
+            .unwrap();
+            run(KemAlgorithm::Kyber.as_u8(), EncryptionAlgorithm::Kyber, sig).unwrap();
+
+            let (alice_container, bob_container) =
+                gen(KemAlgorithm::Kyber, EncryptionAlgorithm::Kyber, sig);
+            let ciphertext = alice_container.encrypt(message, &nonce).unwrap();
+            assert!(
+                ciphertext.len()
+                    <= EncryptionAlgorithm::Kyber.max_ciphertext_len(message.len(), sig)
+            );
+
+            // the signature keys must survive a serialization round-trip
+            let alice_container = PostQuantumContainer::deserialize_from_bytes(
+                alice_container.serialize_to_vector().unwrap(),
+            )
+            .unwrap();
+            let bob_container = PostQuantumContainer::deserialize_from_bytes(
+                bob_container.serialize_to_vector().unwrap(),
+            )
+            .unwrap();
+
+            assert_eq!(bob_container.decrypt(&ciphertext, &nonce).unwrap(), message);
+            let ciphertext = bob_container.encrypt(message, &nonce).unwrap();
+            assert_eq!(
+                alice_container.decrypt(&ciphertext, &nonce).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_remote_signature() {
+        citadel_logging::setup_log();
+        let message = b"Hello, world!";
+
+        for sig in SigAlgorithm::list() {
+            let (alice_container, bob_container) = gen(
+                KemAlgorithm::Kyber,
+                EncryptionAlgorithm::AES_GCM_256_SIV,
+                sig,
+            );
+            if sig == SigAlgorithm::None {
+                assert!(alice_container.sign(message).is_err());
+                continue;
+            }
+
+            let signature = alice_container.sign(message).unwrap();
+            bob_container
+                .verify_remote_signature(message, &signature)
+                .unwrap();
+            // a node's own signatures are not accepted as the adjacent node's
+            assert!(alice_container
+                .verify_remote_signature(message, &signature)
+                .is_err());
+            assert!(bob_container
+                .verify_remote_signature(b"Goodbye, world!", &signature)
+                .is_err());
+
+            let signature = bob_container.sign(message).unwrap();
+            alice_container
+                .verify_remote_signature(message, &signature)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_hybrid_signature_requires_both_components() {
+        citadel_logging::setup_log();
+        let params = KemAlgorithm::Kyber
+            + EncryptionAlgorithm::Kyber
+            + SigAlgorithm::HybridEd25519Dilithium3;
+        let alice_container =
+            PostQuantumContainer::new_alice(ConstructorOpts::new_init(Some(params))).unwrap();
+        let tx_params = alice_container.generate_alice_to_bob_transfer().unwrap();
+
+        // corrupt the Ed25519 component (the first 64 bytes), and then, the Dilithium3 component
+        for idx in [0, 64] {
+            let mut tx_params = tx_params.clone();
+            match &mut tx_params {
+                AliceToBobTransferParameters::MixedAsymmetric {
+                    alice_public_key_signature,
+                    ..
+                } => alice_public_key_signature[idx] ^= 0xFF,
+                _ => panic!("Expected mixed asymmetric transfer parameters"),
+            }
+
+            assert!(PostQuantumContainer::new_bob(
+                ConstructorOpts::new_init(Some(params)),
+                tx_params
+            )
+            .is_err());
+        }
+
+        assert!(
+            PostQuantumContainer::new_bob(ConstructorOpts::new_init(Some(params)), tx_params)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_hybrid_x25519_kyber() {
+        citadel_logging::setup_log();
+        let kem_algorithm = KemAlgorithm::HybridX25519Kyber;
+        run(
+            kem_algorithm.as_u8(),
+            EncryptionAlgorithm::AES_GCM_256_SIV,
+            SigAlgorithm::None,
+        )
+        .unwrap();
+        run(
+            kem_algorithm.as_u8(),
+            EncryptionAlgorithm::Kyber,
+            SigAlgorithm::Falcon1024,
+        )
+        .unwrap();
+
+        // tampering with the classical component of Bob's ciphertext must yield a different secret for Alice
+        let mut alice_container = PostQuantumContainer::new_alice(ConstructorOpts::new_init(Some(
+            kem_algorithm + EncryptionAlgorithm::AES_GCM_256_SIV,
+        )))
+        .unwrap();
+        let tx_params = alice_container.generate_alice_to_bob_transfer().unwrap();
+        let bob_container = PostQuantumContainer::new_bob(
+            ConstructorOpts::new_init(Some(kem_algorithm + EncryptionAlgorithm::AES_GCM_256_SIV)),
+            tx_params,
+        )
+        .unwrap();
+
+        let (bob_pk, mut bob_ciphertext) =
+            match bob_container.generate_bob_to_alice_transfer().unwrap() {
+                BobToAliceTransferParameters::PureSymmetric {
+                    bob_pk,
+                    bob_ciphertext,
+                } => (bob_pk, (*bob_ciphertext).clone()),
+                _ => panic!("Expected pure symmetric transfer parameters"),
+            };
+
+        let (_, x25519_pk_bob) = bob_ciphertext.split_at(bob_ciphertext.len() - 32);
+        // swap in an unrelated X25519 public key
+        let other = PostQuantumContainer::new_alice(ConstructorOpts::new_init(Some(
+            kem_algorithm + EncryptionAlgorithm::AES_GCM_256_SIV,
+        )))
+        .unwrap();
+        let replacement_pk = match other.generate_alice_to_bob_transfer().unwrap() {
+            AliceToBobTransferParameters::PureSymmetric { alice_pk, .. } => {
+                alice_pk[alice_pk.len() - 32..].to_vec()
+            }
+            _ => panic!("Expected pure symmetric transfer parameters"),
+        };
+        assert_ne!(x25519_pk_bob, replacement_pk.as_slice());
+        let len = bob_ciphertext.len();
+        bob_ciphertext[len - 32..].copy_from_slice(&replacement_pk);
+
+        alice_container
+            .alice_on_receive_ciphertext(BobToAliceTransferParameters::PureSymmetric {
+                bob_pk,
+                bob_ciphertext: std::sync::Arc::new(bob_ciphertext),
+            })
+            .unwrap();
+        assert_ne!(
+            alice_container.get_shared_secret().unwrap(),
+            bob_container.get_shared_secret().unwrap()
+        );
+    }
+
     #[test]
     fn parse() {
         fn test<T: AlgorithmsExt + Copy + PartialEq>() {
@@ -356,6 +541,7 @@ mod tests {
         }
 
         test::<KemAlgorithm>();
+        test::<KyberParameterSet>();
         test::<SigAlgorithm>();
         test::<EncryptionAlgorithm>();
     }
@@ -458,9 +644,310 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_params_packed_layout() {
+        // the encryption algorithm occupies bits 0..=2, the KEM bits 3..=5 and the signature algorithm bits 6..=7
+        let params = CryptoParameters::try_from(0b0010_0101).unwrap();
+        assert_eq!(
+            params.encryption_algorithm,
+            EncryptionAlgorithm::Xchacha20Poly_1305
+        );
+        assert_eq!(params.kem_algorithm, KemAlgorithm::HybridX25519Kyber);
+        assert_eq!(params.kyber_parameter_set, KyberParameterSet::Kyber1024);
+        assert_eq!(params.sig_algorithm, SigAlgorithm::Falcon1024);
+
+        // the default parameter set packs identically to parameters without a parameter set
+        for enx in EncryptionAlgorithm::list() {
+            for kex in KemAlgorithm::list() {
+                for sig in SigAlgorithm::list() {
+                    let params = enx + kex + sig;
+                    if validate_crypto_params(&params).is_ok() {
+                        let packed: u8 = params.into();
+                        assert_eq!(packed, (enx as u8) << 5 | (kex as u8) << 2 | sig as u8);
+                    }
+                }
+            }
+        }
+
+        // KEM values beyond those of the KEM algorithms and parameter sets are rejected
+        assert!(CryptoParameters::try_from(0b0001_1000).is_err());
+    }
+
+    #[test]
+    fn test_params_roundtrip_every_combination() {
+        for enx in EncryptionAlgorithm::list() {
+            for kex in KemAlgorithm::list() {
+                for param_set in KyberParameterSet::list() {
+                    for sig in SigAlgorithm::list() {
+                        let params = enx + kex + sig + param_set;
+                        if validate_crypto_params(&params).is_err() {
+                            continue;
+                        }
+
+                        let packed: u8 = params.into();
+                        let unpacked = CryptoParameters::try_from(packed).unwrap();
+                        assert_eq!(unpacked.encryption_algorithm, enx);
+                        assert_eq!(unpacked.kem_algorithm, kex);
+                        assert_eq!(unpacked.kyber_parameter_set, param_set);
+                        assert_eq!(unpacked.sig_algorithm, sig);
+                    }
+                }
+            }
+        }
+
+        // each combination of KEM algorithm and parameter set packs to a distinct value
+        for (idx, entry) in KEM_FIELD_VALUES.iter().enumerate() {
+            assert_eq!(
+                KEM_FIELD_VALUES.iter().position(|other| other == entry),
+                Some(idx)
+            );
+        }
+    }
+
     #[test]
     fn test_bad_crypto_params() {
         let bad_params = EncryptionAlgorithm::Kyber + KemAlgorithm::Kyber;
         assert!(validate_crypto_params(&bad_params).is_err());
+        let bad_params = EncryptionAlgorithm::Kyber
+            + KemAlgorithm::Kyber
+            + SigAlgorithm::Falcon1024
+            + KyberParameterSet::Kyber512;
+        assert!(validate_crypto_params(&bad_params).is_err());
+    }
+
+    #[test]
+    fn test_kyber_parameter_sets() {
+        citadel_logging::setup_log();
+        for kem in KemAlgorithm::list() {
+            for parameter_set in KyberParameterSet::list() {
+                log::trace!(target: "citadel", "About to test {:?} w/ {:?}", kem, parameter_set);
+                let params = kem + parameter_set + EncryptionAlgorithm::AES_GCM_256_SIV;
+
+                // the parameter set must survive the packed wire encoding
+                let packed: u8 = params.into();
+                let unpacked = CryptoParameters::try_from(packed).unwrap();
+                assert_eq!(unpacked.kyber_parameter_set, parameter_set);
+                assert_eq!(unpacked.kem_algorithm, kem);
+
+                let mut alice_container =
+                    PostQuantumContainer::new_alice(ConstructorOpts::new_init(Some(params)))
+                        .unwrap();
+                let tx_params = alice_container.generate_alice_to_bob_transfer().unwrap();
+                let bob_container = PostQuantumContainer::new_bob(
+                    ConstructorOpts::new_init(Some(params)),
+                    tx_params,
+                )
+                .unwrap();
+                alice_container
+                    .alice_on_receive_ciphertext(
+                        bob_container.generate_bob_to_alice_transfer().unwrap(),
+                    )
+                    .unwrap();
+
+                assert_eq!(
+                    alice_container.get_shared_secret().unwrap(),
+                    bob_container.get_shared_secret().unwrap()
+                );
+
+                let nonce = [0u8; 12];
+                let ciphertext = alice_container.encrypt(b"Hello, world!", nonce).unwrap();
+                assert_eq!(
+                    bob_container.decrypt(ciphertext, nonce).unwrap(),
+                    b"Hello, world!"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_kyber_parameter_set_mismatch() {
+        let alice_params = KyberParameterSet::Kyber512 + EncryptionAlgorithm::AES_GCM_256_SIV;
+        let bob_params = KyberParameterSet::Kyber1024 + EncryptionAlgorithm::AES_GCM_256_SIV;
+        let alice_container =
+            PostQuantumContainer::new_alice(ConstructorOpts::new_init(Some(alice_params))).unwrap();
+        let tx_params = alice_container.generate_alice_to_bob_transfer().unwrap();
+        assert!(PostQuantumContainer::new_bob(
+            ConstructorOpts::new_init(Some(bob_params)),
+            tx_params
+        )
+        .is_err());
+    }
+
+    /// A reference model of the anti-replay window: a PID is accepted iff it has not yet arrived
+    /// and is no more than `window_size` behind one past the highest PID received
+    struct ReplayModel {
+        received: std::collections::HashSet<u64>,
+        highest: u64,
+        window_size: u64,
+    }
+
+    impl ReplayModel {
+        fn on_pid_received(&mut self, pid: u64) -> bool {
+            if pid + self.window_size < self.highest || !self.received.insert(pid) {
+                return false;
+            }
+
+            self.highest = self.highest.max(pid + 1);
+            true
+        }
+    }
+
+    #[test]
+    fn test_replay_window_out_of_order_delivery() {
+        let mut rng = ThreadRng::default();
+        for window_size in [32, 64, 100, HISTORY_LEN] {
+            let container = AntiReplayAttackContainer::with_window_size(window_size);
+            let window_size = container.window_size();
+            assert_eq!(window_size % 32, 0);
+
+            // deliver many windows' worth of PIDs, where each window is shuffled
+            let mut next = 0;
+            for _ in 0..16 {
+                let mut pids = (next..next + window_size).collect::<Vec<u64>>();
+                pids.shuffle(&mut rng);
+                for pid in pids.iter().copied() {
+                    assert!(container.on_pid_received(pid), "{pid} should be accepted");
+                }
+
+                // every PID in the window has arrived, so any duplicate must be rejected
+                for pid in pids {
+                    assert!(!container.on_pid_received(pid), "{pid} should be rejected");
+                }
+
+                next += window_size;
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_window_matches_model() {
+        let mut rng = ThreadRng::default();
+        for window_size in [32, 96, HISTORY_LEN] {
+            for _ in 0..32 {
+                let container = AntiReplayAttackContainer::with_window_size(window_size);
+                let mut model = ReplayModel {
+                    received: Default::default(),
+                    highest: 0,
+                    window_size: container.window_size(),
+                };
+
+                // a slowly advancing stream with jitter, occasional jumps ahead, and duplicates
+                let mut base = 0u64;
+                for _ in 0..5000 {
+                    let pid = match rng.gen_range(0..10) {
+                        0 => base + rng.gen_range(0..4 * window_size),
+                        1 => base.saturating_sub(rng.gen_range(0..2 * window_size)),
+                        _ => base.saturating_sub(rng.gen_range(0..8)) + rng.gen_range(0..8),
+                    };
+                    base += rng.gen_range(0..3);
+
+                    assert_eq!(
+                        container.on_pid_received(pid),
+                        model.on_pid_received(pid),
+                        "mismatch for pid {pid} (window: {window_size})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_window_delayed_replay() {
+        let container = AntiReplayAttackContainer::default();
+        let window_size = container.window_size();
+        assert!(container.on_pid_received(0));
+        assert!(container.on_pid_received(window_size - 1));
+        // PID 0 is still at the edge of the window, so it is rejected only for having already arrived
+        assert!(!container.on_pid_received(0));
+        assert!(container.on_pid_received(1));
+
+        // advance the window such that both PIDs 0 and 1 fall behind it, recycling their block
+        assert!(container.on_pid_received(window_size + 2));
+        assert!(!container.on_pid_received(0));
+        assert!(!container.on_pid_received(1));
+        // a PID that never arrived, but is now behind the window, must also be rejected
+        assert!(!container.on_pid_received(2));
+        assert!(container.on_pid_received(3));
+
+        // jump far ahead. Every block gets recycled, yet nothing behind the window is accepted
+        assert!(container.on_pid_received(100 * window_size));
+        for pid in 0..=window_size + 2 {
+            assert!(!container.on_pid_received(pid));
+        }
+
+        container.reset();
+        assert!(!container.has_tracked_packets());
+        assert!(container.on_pid_received(0));
+    }
+
+    #[test]
+    fn test_replay_window_concurrent() {
+        const THREADS: usize = 8;
+        const PIDS: u64 = 4096;
+        let container = &AntiReplayAttackContainer::with_window_size(PIDS);
+        let accepted = &std::sync::atomic::AtomicU64::new(0);
+
+        // each PID gets delivered by two different threads in a random order, yet must only be accepted once
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(move || {
+                    let mut pids = (0..PIDS).collect::<Vec<u64>>();
+                    pids.shuffle(&mut ThreadRng::default());
+                    for pid in pids {
+                        if container.on_pid_received(pid) {
+                            accepted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::Relaxed), PIDS);
+        for pid in 0..PIDS {
+            assert!(!container.on_pid_received(pid));
+        }
+    }
+
+    #[test]
+    fn test_replay_window_concurrent_sliding() {
+        use std::sync::atomic::{AtomicU8, Ordering};
+        const THREADS: usize = 8;
+        const WINDOW_SIZE: u64 = 32;
+        const PIDS: u64 = 1 << 16;
+        let container = &AntiReplayAttackContainer::with_window_size(WINDOW_SIZE);
+        let acceptances = &(0..PIDS).map(|_| AtomicU8::new(0)).collect::<Vec<_>>();
+
+        // each thread delivers every PID, shuffled within spans several windows wide, such that the window slides
+        // far beyond its size while older and newer PIDs mapping to the same slot race each other
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(move || {
+                    let mut rng = ThreadRng::default();
+                    let mut pids = (0..PIDS).collect::<Vec<u64>>();
+                    for span in pids.chunks_mut(4 * WINDOW_SIZE as usize) {
+                        span.shuffle(&mut rng);
+                    }
+
+                    for pid in pids {
+                        if container.on_pid_received(pid) {
+                            let _ = acceptances[pid as usize].fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        // some PIDs fall behind the window before any thread delivers them, but none may be accepted twice
+        for (pid, acceptances) in acceptances.iter().enumerate() {
+            assert!(
+                acceptances.load(Ordering::Relaxed) <= 1,
+                "{pid} was accepted more than once"
+            );
+        }
+
+        // the most recent PIDs remain tracked, and must be rejected as replays
+        for pid in PIDS - WINDOW_SIZE..PIDS {
+            assert!(!container.on_pid_received(pid));
+        }
     }
 }
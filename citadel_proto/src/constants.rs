@@ -60,6 +60,9 @@ pub const CODEC_BUFFER_CAPACITY: usize = u16::MAX as usize;
 pub const CODEC_MIN_BUFFER: usize = 8192;
 /// After the time defined below, any incomplete packet groups will be discarded
 pub const GROUP_EXPIRE_TIME_MS: std::time::Duration = std::time::Duration::from_millis(60000);
+/// The number of group IDs reserved for an object whose length is unknown until the sender reaches EOF. Since relative
+/// group IDs are 32 bits, this covers every group such an object may have
+pub const UNKNOWN_LENGTH_GROUP_RESERVATION: u64 = u32::MAX as u64;
 /// After this time, the registration state is invalidated
 pub const DO_REGISTER_EXPIRE_TIME_MS: std::time::Duration = std::time::Duration::from_millis(10000);
 /// After this time, the connect state is invalidated
@@ -97,3 +100,7 @@ pub const TCP_CONN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs
 
 pub const MAX_OUTGOING_UNPROCESSED_REQUESTS: usize = 512;
 pub const MAX_INCOMING_UNPROCESSED_REQUESTS: usize = 512;
+/// The number of delivery receipts buffered per channel before lagging subscribers begin to miss receipts
+pub const MAX_BUFFERED_DELIVERY_RECEIPTS: usize = 512;
+/// The default duration that an acknowledged send waits for the remote endpoint to confirm delivery
+pub const DEFAULT_MESSAGE_ACK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
@@ -355,6 +355,7 @@ pub mod re_imports {
     pub use futures::future::try_join3;
 
     pub use citadel_pqcrypto::build_tag;
+    pub use citadel_user::serialization::bincode_config;
     pub use citadel_wire::exports::openssl;
     pub use citadel_wire::exports::rustls_pemfile;
     pub use citadel_wire::exports::ClientConfig as RustlsClientConfig;
@@ -369,17 +370,27 @@ pub mod prelude {
     pub use citadel_crypt::argon::argon_container::ArgonDefaultServerSettings;
     #[cfg(not(coverage))]
     pub use citadel_crypt::argon::autotuner::calculate_optimal_argon_params;
+    #[cfg(feature = "filesystem")]
+    pub use citadel_crypt::directory_source::DirectorySource;
+    pub use citadel_crypt::directory_source::{
+        DirectoryManifest, ManifestEntry, ManifestEntryKind,
+    };
     pub use citadel_crypt::fcm::keys::FcmKeys;
     pub use citadel_crypt::secure_buffer::{sec_bytes::SecBuffer, sec_string::SecString};
+    pub use citadel_crypt::streaming_crypt_scrambler::{
+        AsyncReadSource, NamedSource, ObjectSource,
+    };
     pub use citadel_pqcrypto::algorithm_dictionary::{
-        AlgorithmsExt, EncryptionAlgorithm, KemAlgorithm, SigAlgorithm,
+        AlgorithmsExt, EncryptionAlgorithm, KemAlgorithm, KyberParameterSet, SigAlgorithm,
     };
     pub use citadel_user::account_manager::AccountManager;
     pub use citadel_user::auth::proposed_credentials::ProposedCredentials;
     pub use citadel_user::backend::BackendType;
     pub use citadel_user::external_services::{RtdbConfig, ServicesConfig, ServicesObject};
     pub use citadel_user::prelude::{ConnectProtocol, UserIdentifier};
-    pub use citadel_user::server_misc_settings::ServerMiscSettings;
+    pub use citadel_user::server_misc_settings::{
+        CryptoPolicy, ForwardingPolicy, MessageQueuePolicy, ServerMiscSettings,
+    };
 
     pub use crate::error::NetworkError;
     pub use crate::functional::*;
@@ -403,9 +414,10 @@ pub mod prelude {
         GroupBroadcastPayload, GroupChannel, GroupChannelRecvHalf, GroupChannelSendHalf,
     };
     pub use crate::proto::peer::message_group::MessageGroupKey;
-    pub use crate::proto::peer::message_group::{GroupType, MessageGroupOptions};
+    pub use crate::proto::peer::message_group::{GroupRole, GroupType, MessageGroupOptions};
     pub use crate::proto::peer::peer_layer::HypernodeConnectionType;
     pub use crate::proto::peer::peer_layer::PeerResponse;
+    pub use crate::proto::peer::peer_layer::{MailboxTransfer, QueuedMessage};
     pub use crate::proto::peer::peer_layer::{PeerConnectionType, PeerSignal, UdpMode};
     pub use crate::proto::remote::Ticket;
     pub use crate::proto::state_container::VirtualTargetType;
@@ -0,0 +1,567 @@
+//! Store-and-forward delivery of objects to offline peers
+//!
+//! When a peer is offline, the sender seals the object under a key shared only with the recipient, and uploads the
+//! sealed object to the server. Subject to the [`ForwardingPolicy`], the server reserves the recipient's quota for the
+//! object, streams the object into its backend's object store, and forwards it once the recipient next connects. Since
+//! the server never holds the sealing key, it is unable to read the objects it stores.
+//!
+//! A sealing key is exported from the peer channel each time two mutual peers connect, and is stored locally by both
+//! endpoints under the key's id. Objects are sealed under the most recent key, and the recipient opens each object with
+//! the key named in its sealed header, such that objects sealed before the peers reconnected remain readable. Thus, a
+//! peer must have connected with the recipient at least once before sending objects while the recipient is offline
+use crate::error::NetworkError;
+use crate::proto::misc::recipient_locks::RecipientLocks;
+use crate::proto::node_result::ObjectTransferHandle;
+use crate::proto::peer::channel::KeyingMaterialExporter;
+use crate::proto::remote::Ticket;
+use crate::proto::session::{HdpSession, SessionState};
+use crate::proto::state_container::VirtualTargetType;
+use citadel_crypt::entropy_bank::SecurityLevel;
+use citadel_crypt::misc::CryptError;
+use citadel_crypt::sealed_object::{self, ObjectUnsealer, SealingKey, SealingKeyId};
+use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_user::backend::utils::{
+    ObjectForwarding, ObjectTransferSink, ObjectTransferStatus, StreamableTargetInformation,
+    VirtualObjectMetadata,
+};
+use citadel_user::backend::PersistenceHandler;
+use citadel_user::misc::AccountError;
+use citadel_user::serialization::SyncIO;
+use citadel_user::server_misc_settings::ForwardingPolicy;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The byte map key under which each endpoint stores the keys shared with a peer, keyed by the id of each key
+const OFFLINE_DELIVERY_KEYS: &str = "offline_delivery_keys";
+/// The number of keys retained for each peer. Objects sealed under older keys can no longer be opened
+const MAX_OFFLINE_DELIVERY_KEYS: usize = 32;
+/// The byte map key under which the server stores objects awaiting delivery, keyed by the recipient's cid
+const FORWARDED_OBJECTS: &str = "forwarded_objects";
+/// The label used to export the sealing key from the peer channel
+const OFFLINE_DELIVERY_LABEL: &[u8] = b"citadel-offline-delivery";
+
+/// A sealed object stored by the server on behalf of an offline recipient. The sealed bytes are held in the backend's
+/// object store under the same key as this record
+#[derive(Serialize, Deserialize)]
+struct ForwardedObject {
+    metadata: VirtualObjectMetadata,
+    /// Unix time, in seconds, after which the object is discarded
+    expires_at: u64,
+    /// The number of sealed bytes reserved against the recipient's quota
+    len: u64,
+    /// False while the object is uploaded. Only complete objects are delivered
+    complete: bool,
+}
+
+/// A key shared with a peer
+#[derive(Serialize, Deserialize)]
+struct StoredDeliveryKey {
+    key: SealingKey,
+    /// Unix time, in nanoseconds, at which the key was stored
+    stored_at: u128,
+}
+
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Exports a key for sealing objects sent between `implicated_cid` and `peer_cid`, then stores it in the background
+/// alongside the keys exported upon previous connections. Both endpoints export the same key, regardless of which
+/// endpoint initiated the channel
+pub(crate) fn spawn_store_offline_delivery_key<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: PersistenceHandler<R, Fcm>,
+    exporter: &KeyingMaterialExporter,
+    implicated_cid: u64,
+    peer_cid: u64,
+) {
+    let mut context = Vec::with_capacity(16);
+    context.extend_from_slice(&std::cmp::min(implicated_cid, peer_cid).to_be_bytes());
+    context.extend_from_slice(&std::cmp::max(implicated_cid, peer_cid).to_be_bytes());
+
+    let key = match exporter
+        .export_keying_material(OFFLINE_DELIVERY_LABEL, &context, 32)
+        .map_err(|err| format!("{:?}", err))
+        .and_then(|key| {
+            SealingKey::try_from(key.as_slice()).map_err(|_| "Invalid key length".to_string())
+        }) {
+        Ok(key) => key,
+        Err(err) => {
+            log::warn!(target: "citadel", "Unable to export offline delivery key for {}: {}", peer_cid, err);
+            return;
+        }
+    };
+
+    let task = async move {
+        if let Err(err) =
+            store_offline_delivery_key(&persistence_handler, implicated_cid, peer_cid, key).await
+        {
+            log::warn!(target: "citadel", "Unable to store offline delivery key for {}: {:?}", peer_cid, err);
+        }
+    };
+
+    spawn!(task);
+}
+
+/// Stores the key under its id, then removes the oldest keys beyond [`MAX_OFFLINE_DELIVERY_KEYS`]
+async fn store_offline_delivery_key<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    key: SealingKey,
+) -> Result<(), AccountError> {
+    let stored_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+    let value = StoredDeliveryKey { key, stored_at }.serialize_to_vector()?;
+    let _ = persistence_handler
+        .store_byte_map_value(
+            implicated_cid,
+            peer_cid,
+            OFFLINE_DELIVERY_KEYS,
+            &key_id_to_sub_key(sealed_object::sealing_key_id(&key)),
+            value,
+        )
+        .await?;
+
+    let mut stored =
+        load_offline_delivery_keys(persistence_handler, implicated_cid, peer_cid).await?;
+    if stored.len() > MAX_OFFLINE_DELIVERY_KEYS {
+        stored.sort_by_key(|(_, stored)| std::cmp::Reverse(stored.stored_at));
+        for (sub_key, _) in stored.drain(MAX_OFFLINE_DELIVERY_KEYS..) {
+            let _ = persistence_handler
+                .remove_byte_map_value(implicated_cid, peer_cid, OFFLINE_DELIVERY_KEYS, &sub_key)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn key_id_to_sub_key(key_id: SealingKeyId) -> String {
+    format!("{:016x}", key_id)
+}
+
+async fn load_offline_delivery_keys<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+) -> Result<Vec<(String, StoredDeliveryKey)>, AccountError> {
+    Ok(persistence_handler
+        .get_byte_map_values_by_key(implicated_cid, peer_cid, OFFLINE_DELIVERY_KEYS)
+        .await?
+        .into_iter()
+        .filter_map(|(sub_key, value)| {
+            StoredDeliveryKey::deserialize_from_vector(&value)
+                .ok()
+                .map(|stored| (sub_key, stored))
+        })
+        .collect())
+}
+
+/// Loads the most recent key for sealing objects sent between `implicated_cid` and `peer_cid`
+pub(crate) async fn load_offline_delivery_key<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+) -> Result<SealingKey, NetworkError> {
+    load_offline_delivery_keys(persistence_handler, implicated_cid, peer_cid)
+        .await
+        .map_err(|err| NetworkError::Generic(err.into_string()))?
+        .into_iter()
+        .max_by_key(|(_, stored)| stored.stored_at)
+        .map(|(_, stored)| stored.key)
+        .ok_or_else(|| {
+            NetworkError::Generic(format!(
+                "No offline delivery key exists for peer {}. The peers must connect at least once beforehand",
+                peer_cid
+            ))
+        })
+}
+
+/// Loads the key shared with `peer_cid` that has the given id
+pub(crate) async fn load_offline_delivery_key_by_id<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    key_id: SealingKeyId,
+) -> Result<SealingKey, NetworkError> {
+    let value = persistence_handler
+        .get_byte_map_value(
+            implicated_cid,
+            peer_cid,
+            OFFLINE_DELIVERY_KEYS,
+            &key_id_to_sub_key(key_id),
+        )
+        .await
+        .map_err(|err| NetworkError::Generic(err.into_string()))?
+        .ok_or_else(|| {
+            NetworkError::Generic(format!(
+                "The object from peer {} was sealed under an unknown or expired key",
+                peer_cid
+            ))
+        })?;
+
+    StoredDeliveryKey::deserialize_from_vector(&value)
+        .map(|stored| stored.key)
+        .map_err(|_| NetworkError::InternalError("Invalid offline delivery key"))
+}
+
+/// Opens an object sealed by `peer_cid`, using the key named in the object's header
+pub(crate) async fn open_sealed<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    sealed: &[u8],
+) -> Result<Vec<u8>, NetworkError> {
+    let key_id = sealed_object::sealed_key_id(sealed).ok_or(NetworkError::InternalError(
+        "The sealed object is too short",
+    ))?;
+    let key =
+        load_offline_delivery_key_by_id(persistence_handler, implicated_cid, peer_cid, key_id)
+            .await?;
+    sealed_object::open(&key, sealed).map_err(|err| NetworkError::Generic(err.into_string()))
+}
+
+/// Returns the number of bytes currently stored, or reserved, for `recipient_cid`, removing any expired objects
+async fn get_stored_len<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    recipient_cid: u64,
+    now: u64,
+) -> Result<u64, AccountError> {
+    let stored = persistence_handler
+        .get_byte_map_values_by_key(recipient_cid, 0, FORWARDED_OBJECTS)
+        .await?;
+    let mut total = 0;
+
+    for (sub_key, value) in stored {
+        match ForwardedObject::deserialize_from_vector(&value) {
+            Ok(object) if object.expires_at > now => total += object.len,
+            _ => remove_forwarded_object(persistence_handler, recipient_cid, &sub_key).await?,
+        }
+    }
+
+    Ok(total)
+}
+
+/// Reserves the quota of the recipient for the object, returning false if storing the object would exceed the quota. The
+/// recipient's lock is held while the quota is checked and reserved, such that concurrent uploads cannot together exceed
+/// the quota
+async fn reserve_forwarded_object<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    policy: &ForwardingPolicy,
+    recipient_locks: &RecipientLocks,
+    recipient_cid: u64,
+    store_key: &str,
+    object: &ForwardedObject,
+    now: u64,
+) -> Result<bool, AccountError> {
+    let _reservation = recipient_locks.lock(recipient_cid).await;
+    let stored_len = get_stored_len(persistence_handler, recipient_cid, now).await?;
+    if stored_len.saturating_add(object.len) > policy.max_bytes_per_recipient as u64 {
+        return Ok(false);
+    }
+
+    store_forwarded_object(persistence_handler, recipient_cid, store_key, object).await?;
+    Ok(true)
+}
+
+/// Run by the server after a client uploads a sealed object. The object is accepted only if the sender and recipient are
+/// mutual peers, and if the recipient's quota can be reserved for the object. The object is streamed into the backend's
+/// object store as it arrives
+pub(crate) async fn receive_forwarded_object<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: PersistenceHandler<R, Fcm>,
+    policy: ForwardingPolicy,
+    recipient_locks: RecipientLocks,
+    handle: ObjectTransferHandle,
+    forwarding: ObjectForwarding,
+    metadata: VirtualObjectMetadata,
+) {
+    let mut handle = handle.handle;
+    let now = unix_time();
+    let recipient_cid = forwarding.recipient_cid;
+    let store_key = uuid::Uuid::new_v4().to_string();
+    let mut metadata = metadata;
+    metadata.forwarding = Some(forwarding);
+    let mut object = ForwardedObject {
+        len: metadata.plaintext_length as u64,
+        metadata,
+        expires_at: now.saturating_add(policy.time_to_live.as_secs()),
+        complete: false,
+    };
+
+    let is_permitted = async {
+        let are_mutuals = persistence_handler
+            .hyperlan_peers_are_mutuals(forwarding.sender_cid, &[recipient_cid])
+            .await?
+            .first()
+            .copied()
+            .unwrap_or(false);
+        if !are_mutuals {
+            return Ok::<_, AccountError>(false);
+        }
+
+        reserve_forwarded_object(
+            &persistence_handler,
+            &policy,
+            &recipient_locks,
+            recipient_cid,
+            &store_key,
+            &object,
+            now,
+        )
+        .await
+    }
+    .await;
+
+    match is_permitted {
+        Ok(true) => {}
+        Ok(false) => {
+            log::warn!(target: "citadel", "Declining object from {} forwarded to {}", forwarding.sender_cid, recipient_cid);
+            let _ = handle.decline();
+            return;
+        }
+        Err(err) => {
+            log::error!(target: "citadel", "Unable to check forwarding permissions: {:?}", err);
+            let _ = handle.decline();
+            return;
+        }
+    }
+
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    let stored = if handle
+        .accept_into(ObjectTransferSink::Writer(Box::new(writer)))
+        .is_ok()
+    {
+        let (persistence_handler, store_key, len) =
+            (&persistence_handler, store_key.as_str(), object.len);
+        // the reader is dropped once storing ends, such that the transfer fails, rather than stalls, if storing fails
+        let stored = async move {
+            let mut reader = reader;
+            persistence_handler
+                .stream_object_to_store(store_key, &mut reader, len)
+                .await
+        };
+        let completed = async {
+            while let Some(status) = handle.next().await {
+                match status {
+                    ObjectTransferStatus::ReceptionComplete => return true,
+                    status if status.is_finished_type() => return false,
+                    _ => {}
+                }
+            }
+
+            false
+        };
+
+        match tokio::join!(stored, completed) {
+            (Ok(len), true) => len == object.len,
+            (Err(err), _) => {
+                log::warn!(target: "citadel", "Unable to store object forwarded to {}: {:?}", recipient_cid, err);
+                false
+            }
+            _ => false,
+        }
+    } else {
+        false
+    };
+
+    let res = if stored {
+        object.complete = true;
+        store_forwarded_object(&persistence_handler, recipient_cid, &store_key, &object).await
+    } else {
+        log::warn!(target: "citadel", "Did not store object forwarded to {}, since it was not received in full", recipient_cid);
+        remove_forwarded_object(&persistence_handler, recipient_cid, &store_key).await
+    };
+
+    if let Err(err) = res {
+        log::error!(target: "citadel", "Unable to update object forwarded to {}: {:?}", recipient_cid, err);
+    }
+}
+
+async fn store_forwarded_object<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    recipient_cid: u64,
+    store_key: &str,
+    object: &ForwardedObject,
+) -> Result<(), AccountError> {
+    let _ = persistence_handler
+        .store_byte_map_value(
+            recipient_cid,
+            0,
+            FORWARDED_OBJECTS,
+            store_key,
+            object.serialize_to_vector()?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Removes both the record of the object and its sealed bytes
+async fn remove_forwarded_object<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    recipient_cid: u64,
+    store_key: &str,
+) -> Result<(), AccountError> {
+    let _ = persistence_handler
+        .remove_byte_map_value(recipient_cid, 0, FORWARDED_OBJECTS, store_key)
+        .await?;
+    persistence_handler
+        .remove_object_from_store(store_key)
+        .await
+}
+
+/// Run by the server once `recipient_cid` connects. Each stored object is sent to the recipient in turn. Objects that
+/// the recipient does not receive in full are kept, to be delivered upon a later connection
+pub(crate) async fn deliver_forwarded_objects(session: HdpSession, recipient_cid: u64) {
+    let persistence_handler = session.account_manager.get_persistence_handler().clone();
+    let stored = match persistence_handler
+        .get_byte_map_values_by_key(recipient_cid, 0, FORWARDED_OBJECTS)
+        .await
+    {
+        Ok(stored) => stored,
+        Err(err) => {
+            log::error!(target: "citadel", "Unable to load objects forwarded to {}: {:?}", recipient_cid, err);
+            return;
+        }
+    };
+
+    let now = unix_time();
+    for (store_key, value) in stored {
+        let object = match ForwardedObject::deserialize_from_vector(&value) {
+            Ok(object) if object.expires_at > now => object,
+            _ => {
+                let _ =
+                    remove_forwarded_object(&persistence_handler, recipient_cid, &store_key).await;
+                continue;
+            }
+        };
+
+        // objects still being uploaded are delivered upon a later connection
+        if !object.complete {
+            continue;
+        }
+
+        let source = match persistence_handler
+            .get_object_from_store(&store_key, object.metadata.name.clone())
+            .await
+        {
+            Ok(source) => source,
+            Err(err) => {
+                log::error!(target: "citadel", "Unable to load object forwarded to {}: {:?}", recipient_cid, err);
+                let _ =
+                    remove_forwarded_object(&persistence_handler, recipient_cid, &store_key).await;
+                continue;
+            }
+        };
+
+        let (handle_tx, handle_rx) = tokio::sync::oneshot::channel();
+        let ticket = Ticket::from(uuid::Uuid::new_v4().as_u128());
+
+        let delivered = match session.process_outbound_object(
+            ticket,
+            None,
+            source,
+            VirtualTargetType::LocalGroupServer(recipient_cid),
+            SecurityLevel::Standard,
+            object.metadata.forwarding,
+            Some(handle_tx),
+        ) {
+            Ok(()) => match handle_rx.await {
+                Ok(mut handle) => {
+                    let mut delivered = false;
+                    while let Some(status) = handle.handle.next().await {
+                        if status.is_finished_type() {
+                            delivered = matches!(status, ObjectTransferStatus::TransferComplete);
+                            break;
+                        }
+                    }
+                    delivered
+                }
+                // the transfer ended before it began. Unless the session ended, the recipient declined the object
+                Err(_) => session.state.load(Ordering::Relaxed) == SessionState::Connected,
+            },
+
+            Err(err) => {
+                log::warn!(target: "citadel", "Unable to deliver object forwarded to {}: {:?}", recipient_cid, err);
+                false
+            }
+        };
+
+        // objects not delivered are kept for a later connection
+        if delivered {
+            if let Err(err) =
+                remove_forwarded_object(&persistence_handler, recipient_cid, &store_key).await
+            {
+                log::error!(target: "citadel", "Unable to remove object delivered to {}: {:?}", recipient_cid, err);
+            }
+        }
+    }
+}
+
+/// Run by the recipient of a forwarded object. Opens the sealed object as it arrives, streaming the plaintext into the sink
+pub(crate) async fn stream_forwarded_object_to_sink<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    forwarding: ObjectForwarding,
+    mut source: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+    metadata: Arc<dyn StreamableTargetInformation>,
+    sink: ObjectTransferSink,
+    status_tx: tokio::sync::mpsc::UnboundedSender<ObjectTransferStatus>,
+) -> Result<(), AccountError> {
+    let (plaintext_tx, plaintext_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let unseal = async move {
+        // the key is selected once the header, which names the key, arrives
+        let mut unsealer: Option<ObjectUnsealer> = None;
+        let mut header = Vec::new();
+        while let Some(chunk) = source.recv().await {
+            let plaintext = match unsealer.as_mut() {
+                Some(unsealer) => unsealer.push(&chunk)?,
+                None => {
+                    header.extend_from_slice(&chunk);
+                    let key_id = match sealed_object::sealed_key_id(&header) {
+                        Some(key_id) => key_id,
+                        None => continue,
+                    };
+
+                    let key = load_offline_delivery_key_by_id(
+                        persistence_handler,
+                        implicated_cid,
+                        forwarding.sender_cid,
+                        key_id,
+                    )
+                    .await
+                    .map_err(|err| CryptError::Decrypt(err.into_string()))?;
+                    unsealer
+                        .insert(ObjectUnsealer::new(key))
+                        .push(&std::mem::take(&mut header))?
+                }
+            };
+
+            if !plaintext.is_empty() && plaintext_tx.send(plaintext).is_err() {
+                return Ok(());
+            }
+        }
+
+        unsealer
+            .ok_or_else(|| {
+                CryptError::Decrypt("The sealed object ended before its header".to_string())
+            })?
+            .finish()
+    };
+
+    let (unsealed, streamed) = tokio::join!(
+        unseal,
+        persistence_handler.stream_object_to_sink(plaintext_rx, metadata, sink, status_tx)
+    );
+
+    unsealed.map_err(|err| AccountError::Generic(err.into_string()))?;
+    streamed
+}
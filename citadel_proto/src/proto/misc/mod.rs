@@ -15,6 +15,7 @@ pub mod lock_holder;
 pub mod net;
 pub mod ordered_channel;
 pub mod panic_future;
+pub(crate) mod recipient_locks;
 pub mod session_security_settings;
 pub mod udp_internal_interface;
 pub mod underlying_proto;
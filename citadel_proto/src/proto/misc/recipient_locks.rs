@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Serializes updates to the state a server keeps on behalf of each recipient, such as the quota reserved for forwarded
+/// objects and the queue of offline messages. Each server holds its own locks, which are shared by all of its sessions
+#[derive(Clone, Default)]
+pub(crate) struct RecipientLocks {
+    locks: Arc<parking_lot::Mutex<HashMap<u64, Weak<Mutex<()>>>>>,
+}
+
+impl RecipientLocks {
+    /// Waits until no other task holds the lock of `recipient_cid`. The lock is held until the guard is dropped
+    pub async fn lock(&self, recipient_cid: u64) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock();
+            // the lock of a recipient is dropped once no task holds, or waits for, the lock
+            locks.retain(|_, lock| lock.strong_count() != 0);
+            match locks.get(&recipient_cid).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(Mutex::new(()));
+                    let _ = locks.insert(recipient_cid, Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+
+        lock.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::misc::recipient_locks::RecipientLocks;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn locks_are_held_per_recipient() {
+        let locks = RecipientLocks::default();
+        let guard = locks.lock(1).await;
+        // another recipient is not blocked
+        assert!(locks.lock(2).now_or_never().is_some());
+        assert!(locks.lock(1).now_or_never().is_none());
+        drop(guard);
+        assert!(locks.lock(1).now_or_never().is_some());
+
+        // the locks of a separate server are independent
+        let _guard = locks.lock(1).await;
+        assert!(RecipientLocks::default().lock(1).now_or_never().is_some());
+    }
+}
@@ -54,6 +54,17 @@ impl SessionSecuritySettingsBuilder {
     /// .with_crypto_params(EncryptionAlgorithm::AES_GCM_256_SIV + KemAlgorithm::Kyber)
     /// .build();
     /// ```
+    ///
+    /// For hybrid key establishment, where the session secret combines X25519 with Kyber, use [`KemAlgorithm::HybridX25519Kyber`](citadel_pqcrypto::algorithm_dictionary::KemAlgorithm::HybridX25519Kyber)
+    ///
+    /// The Kyber parameter set may be selected in the same way (default: Kyber1024)
+    /// ```
+    /// use citadel_proto::prelude::SessionSecuritySettingsBuilder;
+    /// use citadel_pqcrypto::algorithm_dictionary::{EncryptionAlgorithm, KemAlgorithm, KyberParameterSet};
+    /// SessionSecuritySettingsBuilder::default()
+    /// .with_crypto_params(EncryptionAlgorithm::AES_GCM_256_SIV + KemAlgorithm::Kyber + KyberParameterSet::Kyber512)
+    /// .build();
+    /// ```
     pub fn with_crypto_params(mut self, params: impl Into<CryptoParameters>) -> Self {
         self.crypto_params = Some(params.into());
         self
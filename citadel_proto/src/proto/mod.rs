@@ -2,11 +2,15 @@
 pub(crate) mod codec;
 ///
 pub(crate) mod endpoint_crypto_accessor;
+/// Stores sealed objects on the server on behalf of offline peers, and delivers them once the peer connects
+pub(crate) mod forwarded_objects;
 pub(crate) mod misc;
 /// Used at each HyperNode
 pub mod node;
 pub mod node_request;
 pub mod node_result;
+/// Persists the progress of object transfers, allowing interrupted transfers to resume
+pub(crate) mod object_transfer_progress;
 /// A cloneable handle for sending data through UDP ports
 pub(crate) mod outbound_sender;
 /// The fundamental packet types
@@ -784,6 +784,43 @@ impl HdpServer {
                     }
                 }
 
+                NodeRequest::ConnectToFederatedServer(ConnectToHypernode {
+                    auth_request: authentication_request,
+                    connect_mode,
+                    udp_mode,
+                    keep_alive_timeout,
+                    session_security_settings: security_settings,
+                }) => {
+                    // the session becomes a federated link once it upgrades into a protected connection
+                    session_manager.expect_federated_link(ticket_id);
+                    match session_manager
+                        .initiate_connection(
+                            local_node_type,
+                            local_nat_type.clone(),
+                            HdpSessionInitMode::Connect(authentication_request),
+                            ticket_id,
+                            Some(connect_mode),
+                            listener_underlying_proto.clone(),
+                            Some(udp_mode),
+                            keep_alive_timeout.map(|val| (val as i64) * 1_000_000_000),
+                            security_settings,
+                            &default_client_config,
+                        )
+                        .await
+                    {
+                        Ok(session) => {
+                            session_spawner
+                                .unbounded_send(session)
+                                .map_err(|err| NetworkError::Generic(err.to_string()))?;
+                        }
+
+                        Err(err) => {
+                            session_manager.cancel_federated_link(ticket_id);
+                            send_error(ticket_id, err)?;
+                        }
+                    }
+                }
+
                 NodeRequest::DisconnectFromHypernode(DisconnectFromHypernode {
                     implicated_cid,
                     v_conn_type: target,
@@ -841,15 +878,31 @@ impl HdpServer {
                     chunk_size,
                     implicated_cid,
                     v_conn_type: virtual_target,
+                    store_and_forward,
                 }) => {
-                    if let Err(err) = session_manager.process_outbound_file(
-                        ticket_id,
-                        chunk_size,
-                        path,
-                        implicated_cid,
-                        virtual_target,
-                        SecurityLevel::Standard,
-                    ) {
+                    let res = if store_and_forward {
+                        session_manager
+                            .process_outbound_forwarded_object(
+                                ticket_id,
+                                chunk_size,
+                                path,
+                                implicated_cid,
+                                virtual_target.get_target_cid(),
+                                SecurityLevel::Standard,
+                            )
+                            .await
+                    } else {
+                        session_manager.process_outbound_file(
+                            ticket_id,
+                            chunk_size,
+                            path,
+                            implicated_cid,
+                            virtual_target,
+                            SecurityLevel::Standard,
+                        )
+                    };
+
+                    if let Err(err) = res {
                         send_error(ticket_id, err)?;
                     }
                 }
@@ -40,6 +40,8 @@ pub struct SendObject {
     pub chunk_size: Option<usize>,
     pub implicated_cid: u64,
     pub v_conn_type: VirtualTargetType,
+    /// If true, the object is sealed for the peer and uploaded to the server, which delivers it once the peer connects
+    pub store_and_forward: bool,
 }
 
 pub struct GroupBroadcastCommand {
@@ -64,6 +66,9 @@ pub enum NodeRequest {
     DeregisterFromHypernode(DeregisterFromHypernode),
     /// Implicated CID, creds, connect mode, fcm keys, TCP/TLS only, keep alive timeout, security settings
     ConnectToHypernode(ConnectToHypernode),
+    /// Connects to an adjacent server as a federated (HyperWAN) link. The credentials must belong to an account on
+    /// the adjacent server whose username is listed as a federated server therein
+    ConnectToFederatedServer(ConnectToHypernode),
     /// Updates the drill for the given CID
     ReKey(ReKey),
     /// Send a file
@@ -0,0 +1,157 @@
+use crate::proto::outbound_sender::{unbounded, UnboundedSender};
+use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_crypt::streaming_crypt_scrambler::ObjectDigest;
+use citadel_user::backend::utils::{
+    ObjectTransferOrientation, ObjectTransferProgress, ResumePoint, VirtualObjectMetadata,
+};
+use citadel_user::backend::PersistenceHandler;
+
+/// Persists the progress of an object transfer in the background as each group completes. If the session ends before
+/// the transfer completes, the persisted progress allows the transfer to resume from the first missing group
+pub(crate) struct ObjectTransferProgressTracker {
+    progress: ObjectTransferProgress,
+    persist_tx: UnboundedSender<Option<ObjectTransferProgress>>,
+}
+
+impl ObjectTransferProgressTracker {
+    /// Spawns the background task, and persists the initial progress. The progress is stored under
+    /// `implicated_cid`'s account, keyed by `peer_cid` (zero for the server)
+    pub fn spawn<R: Ratchet, Fcm: Ratchet>(
+        persistence_handler: PersistenceHandler<R, Fcm>,
+        implicated_cid: u64,
+        peer_cid: u64,
+        progress: ObjectTransferProgress,
+    ) -> Self {
+        let (persist_tx, mut persist_rx) = unbounded();
+        let orientation = progress.orientation;
+        let transfer_id = progress.metadata.transfer_id;
+
+        let task = async move {
+            // updates are applied in-order, ensuring a completed transfer is never overwritten by an older update
+            while let Some(update) = persist_rx.recv().await {
+                let res = match update {
+                    Some(progress) => {
+                        persistence_handler
+                            .store_object_transfer_progress(implicated_cid, peer_cid, &progress)
+                            .await
+                    }
+
+                    None => {
+                        persistence_handler
+                            .remove_object_transfer_progress(
+                                implicated_cid,
+                                peer_cid,
+                                orientation,
+                                transfer_id,
+                            )
+                            .await
+                    }
+                };
+
+                if let Err(err) = res {
+                    log::warn!(target: "citadel", "Unable to persist progress of transfer {}: {:?}", transfer_id, err);
+                }
+            }
+        };
+
+        spawn!(task);
+
+        let this = Self {
+            progress,
+            persist_tx,
+        };
+
+        this.persist(Some(this.progress.clone()));
+        this
+    }
+
+    /// Records that the next group, containing `group_len` plaintext bytes, completed. The receiver records the digest
+    /// of each group, allowing the entire object to be verified if the transfer is later resumed
+    pub fn on_group_completed(&mut self, group_len: usize, digest: Option<ObjectDigest>) {
+        self.progress.on_group_completed(group_len, digest);
+        self.persist(Some(self.progress.clone()))
+    }
+
+    /// Removes the persisted progress, since there is nothing left to resume
+    pub fn on_transfer_complete(&self) {
+        self.persist(None)
+    }
+
+    fn persist(&self, update: Option<ObjectTransferProgress>) {
+        if let Err(err) = self.persist_tx.unbounded_send(update) {
+            log::warn!(target: "citadel", "Unable to persist progress of transfer {}: {:?}", self.progress.metadata.transfer_id, err);
+        }
+    }
+}
+
+/// Loads the progress of a previously interrupted transfer, if the transfer may resume
+async fn load_progress<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    orientation: ObjectTransferOrientation,
+    transfer_id: u64,
+) -> Option<ObjectTransferProgress> {
+    match persistence_handler
+        .get_object_transfer_progress(implicated_cid, peer_cid, orientation, transfer_id)
+        .await
+    {
+        Ok(progress) => progress.filter(|progress| progress.resume_point().is_some()),
+        Err(err) => {
+            log::warn!(target: "citadel", "Unable to load progress of transfer {}: {:?}", transfer_id, err);
+            None
+        }
+    }
+}
+
+/// Loads the point from which a previously interrupted transfer may resume, if any
+pub(crate) async fn load_resume_point<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    orientation: ObjectTransferOrientation,
+    transfer_id: u64,
+) -> Option<ResumePoint> {
+    load_progress(
+        persistence_handler,
+        implicated_cid,
+        peer_cid,
+        orientation,
+        transfer_id,
+    )
+    .await?
+    .resume_point()
+}
+
+/// Determines the point from which the receiver resumes an interrupted transfer, returning the receiver's progress
+/// up to that point. Resuming requires that both ends recorded progress for the transfer, that the receiver recorded
+/// the digest of each group it received, and that the partially-received object is still stored in the backend.
+/// Otherwise, the transfer begins from the first group
+pub(crate) async fn negotiate_receiver_resume_point<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    metadata: &VirtualObjectMetadata,
+) -> Option<ObjectTransferProgress> {
+    let proposed = metadata.resume_point?;
+    let mut stored = load_progress(
+        persistence_handler,
+        implicated_cid,
+        peer_cid,
+        ObjectTransferOrientation::Receiver,
+        metadata.transfer_id,
+    )
+    .await?;
+    let agreed = stored.completed.agree(proposed)?;
+    // without the digests of the groups already received, the object could not be verified once complete
+    if stored.group_digests.len() < agreed.groups {
+        return None;
+    }
+
+    let partial_len = persistence_handler
+        .get_partial_object_len(metadata)
+        .await
+        .ok()??;
+    stored.truncate(agreed);
+    (partial_len >= agreed.bytes).then_some(stored)
+}
@@ -109,6 +109,8 @@ pub(crate) mod packet_flags {
             pub(crate) mod file {
                 pub(crate) const FILE_HEADER: u8 = 0;
                 pub(crate) const FILE_HEADER_ACK: u8 = 1;
+                pub(crate) const FILE_CONTROL: u8 = 2;
+                pub(crate) const FILE_DIGEST: u8 = 3;
             }
 
             pub(crate) mod udp {
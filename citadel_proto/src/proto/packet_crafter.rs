@@ -200,6 +200,11 @@ impl GroupTransmitter {
         self.bytes_encrypted
     }
 
+    /// Returns the object this group belongs to
+    pub fn get_object_id(&self) -> u32 {
+        self.object_id
+    }
+
     #[allow(unused_results)]
     pub fn transmit_tcp_file_transfer(&mut self) -> bool {
         let to_primary_stream = &self.to_primary_stream;
@@ -433,7 +438,7 @@ pub(crate) mod do_connect {
 
     use crate::constants::HDP_HEADER_BYTE_LEN;
     use crate::proto::packet::{packet_flags, HdpHeader};
-    use crate::proto::peer::peer_layer::MailboxTransfer;
+    use crate::proto::peer::peer_layer::{MailboxTransfer, QueuedMessage};
     use citadel_crypt::prelude::SecurityLevel;
     use citadel_crypt::stacked_ratchet::StackedRatchet;
     use citadel_user::auth::proposed_credentials::ProposedCredentials;
@@ -487,6 +492,8 @@ pub(crate) mod do_connect {
     #[derive(Serialize, Deserialize)]
     pub struct DoConnectFinalStatusPacket<'a> {
         pub mailbox: Option<MailboxTransfer>,
+        /// Messages queued while the client was offline, in the order they were sent
+        pub offline_messages: Vec<QueuedMessage>,
         pub peers: Vec<MutualPeer>,
         // in order to allow interoperability between protocols that have fields in the services object
         // and those that don't, default on error
@@ -510,6 +517,7 @@ pub(crate) mod do_connect {
         hyper_ratchet: &StackedRatchet,
         success: bool,
         mailbox: Option<MailboxTransfer>,
+        offline_messages: Vec<QueuedMessage>,
         post_login_object: citadel_user::external_services::ServicesObject,
         message: T,
         peers: Vec<MutualPeer>,
@@ -518,6 +526,7 @@ pub(crate) mod do_connect {
     ) -> BytesMut {
         let payload = DoConnectFinalStatusPacket {
             mailbox,
+            offline_messages,
             peers,
             message: message.as_ref(),
             post_login_object,
@@ -1210,12 +1219,15 @@ pub(crate) mod pre_connect {
     pub struct SynAckPacket {
         pub transfer: BobToAliceTransfer,
         pub nat_type: NatType,
+        /// The settings that the server negotiated based on its crypto policy
+        pub session_security_settings: SessionSecuritySettings,
     }
 
     pub(crate) fn craft_syn_ack(
         static_aux_hr: &StaticAuxRatchet,
         transfer: BobToAliceTransfer,
         nat_type: NatType,
+        session_security_settings: SessionSecuritySettings,
         timestamp: i64,
         security_level: SecurityLevel,
     ) -> BytesMut {
@@ -1236,9 +1248,13 @@ pub(crate) mod pre_connect {
         let mut packet = BytesMut::with_capacity(HDP_HEADER_BYTE_LEN);
         header.inscribe_into(&mut packet);
 
-        SynAckPacket { transfer, nat_type }
-            .serialize_into_buf(&mut packet)
-            .unwrap();
+        SynAckPacket {
+            transfer,
+            nat_type,
+            session_security_settings,
+        }
+        .serialize_into_buf(&mut packet)
+        .unwrap();
 
         static_aux_hr
             .protect_message_packet(Some(security_level), HDP_HEADER_BYTE_LEN, &mut packet)
@@ -1554,7 +1570,11 @@ pub(crate) mod file {
     use bytes::{BufMut, BytesMut};
     use citadel_crypt::scramble::crypt_splitter::AES_GCM_GHASH_OVERHEAD;
     use citadel_crypt::stacked_ratchet::StackedRatchet;
-    use citadel_user::backend::utils::VirtualObjectMetadata;
+    use citadel_crypt::streaming_crypt_scrambler::{ObjectDigest, ObjectSummary};
+    use citadel_user::backend::utils::{
+        ObjectTransferControl, ObjectTransferOrientation, VirtualObjectMetadata,
+    };
+    use citadel_user::serialization::SyncIO;
     use zerocopy::{I64, U128, U32, U64};
 
     pub(crate) fn craft_file_header_packet(
@@ -1600,10 +1620,13 @@ pub(crate) mod file {
         packet
     }
 
+    /// `start_group`: None if the transfer was declined, otherwise, the relative group from which the transfer begins.
+    /// `prefix_digest`: when resuming, the digest of the groups already received, which the sender verifies its source against
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn craft_file_header_ack_packet(
         hyper_ratchet: &StackedRatchet,
-        success: bool,
+        start_group: Option<usize>,
+        prefix_digest: Option<ObjectDigest>,
         object_id: u32,
         target_cid: u64,
         ticket: Ticket,
@@ -1611,7 +1634,10 @@ pub(crate) mod file {
         virtual_target: VirtualTargetType,
         timestamp: i64,
     ) -> BytesMut {
-        let success: u64 = u64::from(success);
+        // zero signals a declined transfer
+        let start_group = start_group
+            .map(|start_group| start_group as u64 + 1)
+            .unwrap_or(0);
         let serialized_vt = virtual_target.serialize();
         let header = HdpHeader {
             cmd_primary: packet_flags::cmd::primary::FILE,
@@ -1619,7 +1645,7 @@ pub(crate) mod file {
             algorithm: 0,
             security_level: security_level.value(),
             context_info: U128::new(ticket.0),
-            group: U64::new(success),
+            group: U64::new(start_group),
             wave_id: U32::new(object_id),
             session_cid: U64::new(hyper_ratchet.get_cid()),
             drill_version: U32::new(hyper_ratchet.version()),
@@ -1628,10 +1654,96 @@ pub(crate) mod file {
         };
 
         let mut packet = BytesMut::with_capacity(
-            HDP_HEADER_BYTE_LEN + serialized_vt.len() + AES_GCM_GHASH_OVERHEAD,
+            HDP_HEADER_BYTE_LEN
+                + serialized_vt.len()
+                + std::mem::size_of::<ObjectDigest>()
+                + AES_GCM_GHASH_OVERHEAD,
         );
         header.inscribe_into(&mut packet);
         packet.put(serialized_vt.as_slice());
+        if let Some(prefix_digest) = prefix_digest {
+            packet.put(prefix_digest.as_slice());
+        }
+
+        hyper_ratchet
+            .protect_message_packet(Some(security_level), HDP_HEADER_BYTE_LEN, &mut packet)
+            .unwrap();
+
+        packet
+    }
+
+    /// `orientation`: the orientation of the adjacent node's end of the transfer being controlled
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn craft_file_control_packet(
+        hyper_ratchet: &StackedRatchet,
+        orientation: ObjectTransferOrientation,
+        control: ObjectTransferControl,
+        object_id: u32,
+        target_cid: u64,
+        ticket: Ticket,
+        security_level: SecurityLevel,
+        virtual_target: VirtualTargetType,
+        timestamp: i64,
+    ) -> BytesMut {
+        let payload = (orientation, control, virtual_target)
+            .serialize_to_vector()
+            .unwrap();
+        let header = HdpHeader {
+            cmd_primary: packet_flags::cmd::primary::FILE,
+            cmd_aux: packet_flags::cmd::aux::file::FILE_CONTROL,
+            algorithm: 0,
+            security_level: security_level.value(),
+            context_info: U128::new(ticket.0),
+            group: U64::new(0),
+            wave_id: U32::new(object_id),
+            session_cid: U64::new(hyper_ratchet.get_cid()),
+            drill_version: U32::new(hyper_ratchet.version()),
+            timestamp: I64::new(timestamp),
+            target_cid: U64::new(target_cid),
+        };
+
+        let mut packet =
+            BytesMut::with_capacity(HDP_HEADER_BYTE_LEN + payload.len() + AES_GCM_GHASH_OVERHEAD);
+        header.inscribe_into(&mut packet);
+        packet.put(payload.as_slice());
+
+        hyper_ratchet
+            .protect_message_packet(Some(security_level), HDP_HEADER_BYTE_LEN, &mut packet)
+            .unwrap();
+
+        packet
+    }
+
+    /// Sent by the sender once the entire object has been read, allowing the receiver to verify the reassembled object.
+    /// The summary also carries the length of the object, which is unknown to the receiver for streams of unknown length
+    pub(crate) fn craft_file_digest_packet(
+        hyper_ratchet: &StackedRatchet,
+        summary: ObjectSummary,
+        object_id: u32,
+        target_cid: u64,
+        ticket: Ticket,
+        security_level: SecurityLevel,
+        timestamp: i64,
+    ) -> BytesMut {
+        let payload = summary.serialize_to_vector().unwrap();
+        let header = HdpHeader {
+            cmd_primary: packet_flags::cmd::primary::FILE,
+            cmd_aux: packet_flags::cmd::aux::file::FILE_DIGEST,
+            algorithm: 0,
+            security_level: security_level.value(),
+            context_info: U128::new(ticket.0),
+            group: U64::new(0),
+            wave_id: U32::new(object_id),
+            session_cid: U64::new(hyper_ratchet.get_cid()),
+            drill_version: U32::new(hyper_ratchet.version()),
+            timestamp: I64::new(timestamp),
+            target_cid: U64::new(target_cid),
+        };
+
+        let mut packet =
+            BytesMut::with_capacity(HDP_HEADER_BYTE_LEN + payload.len() + AES_GCM_GHASH_OVERHEAD);
+        header.inscribe_into(&mut packet);
+        packet.put(payload.as_slice());
 
         hyper_ratchet
             .protect_message_packet(Some(security_level), HDP_HEADER_BYTE_LEN, &mut packet)
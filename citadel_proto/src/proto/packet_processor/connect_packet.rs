@@ -1,8 +1,11 @@
 use super::includes::*;
 use crate::error::NetworkError;
+use crate::proto::forwarded_objects;
 use crate::proto::node::ConnectMode;
 use crate::proto::node_result::{ConnectFail, ConnectSuccess, MailboxDelivery};
 use crate::proto::packet_processor::primary_group_packet::get_proper_hyper_ratchet;
+use crate::proto::peer::offline_messages;
+use crate::proto::peer::peer_layer::MailboxTransfer;
 use crate::proto::state_container::VirtualConnectionType;
 use citadel_user::external_services::ServicesObject;
 use std::sync::atomic::Ordering;
@@ -107,6 +110,16 @@ pub async fn process_connect(
                                     .get_hyperlan_peer_list_as_server(cid)
                                     .await?
                                     .unwrap_or_default();
+                                let (unacknowledged_messages, offline_messages): (Vec<_>, Vec<_>) =
+                                    offline_messages::load_message_queue(
+                                        account_manager.get_persistence_handler(),
+                                        &account_manager.get_misc_settings().message_queue_policy,
+                                        &session.session_manager.recipient_locks(),
+                                        cid,
+                                    )
+                                    .await?
+                                    .into_iter()
+                                    .unzip();
 
                                 #[cfg(feature = "google-services")]
                                 let post_login_object = account_manager
@@ -122,6 +135,7 @@ pub async fn process_connect(
                                         &hyper_ratchet,
                                         true,
                                         mailbox_items,
+                                        offline_messages,
                                         post_login_object.clone(),
                                         session.create_welcome_message(cid),
                                         peers,
@@ -147,12 +161,23 @@ pub async fn process_connect(
                                     udp_rx_opt: udp_channel_rx
                                 });
                                 // safe unwrap. Store the signal
-                                inner_mut_state!(session.state_container)
-                                    .c2s_channel_container
-                                    .as_mut()
-                                    .unwrap()
-                                    .channel_signal = Some(channel_signal);
-                                Ok(PrimaryProcessorResult::ReplyToSender(success_packet))
+                                {
+                                    let mut state_container =
+                                        inner_mut_state!(session.state_container);
+                                    let c2s_channel_container =
+                                        state_container.c2s_channel_container.as_mut().unwrap();
+                                    c2s_channel_container.channel_signal = Some(channel_signal);
+                                    // the queued messages are removed once the client acknowledges the success packet
+                                    c2s_channel_container.unacknowledged_messages =
+                                        unacknowledged_messages;
+                                }
+                                // the success packet must precede any objects forwarded while the client was offline
+                                session.send_to_primary_stream(None, success_packet)?;
+                                spawn!(forwarded_objects::deliver_forwarded_objects(
+                                    session.clone(),
+                                    cid
+                                ));
+                                Ok(PrimaryProcessorResult::Void)
                             }
                         }
 
@@ -165,6 +190,7 @@ pub async fn process_connect(
                                 &hyper_ratchet,
                                 false,
                                 None,
+                                Vec::new(),
                                 ServicesObject::default(),
                                 err.to_string(),
                                 Vec::new(),
@@ -314,8 +340,26 @@ pub async fn process_connect(
                                     },
                                 ))?;
                             }
+                            let offline_messages = payload.offline_messages;
                             // TODO: Clean this up to prevent multiple saves
                             async move {
+                                // messages sent while this node was offline are delivered after any signals
+                                let messages = offline_messages::open_messages(
+                                    &persistence_handler,
+                                    cid,
+                                    offline_messages,
+                                )
+                                .await;
+                                if !messages.is_empty() {
+                                    session.send_to_kernel(NodeResult::MailboxDelivery(
+                                        MailboxDelivery {
+                                            implicated_cid: cid,
+                                            ticket_opt: None,
+                                            items: MailboxTransfer::Messages(messages),
+                                        },
+                                    ))?;
+                                }
+
                                 persistence_handler
                                     .synchronize_hyperlan_peer_list_as_client(&cnac, peers)
                                     .await?;
@@ -391,14 +435,38 @@ pub async fn process_connect(
             packet_flags::cmd::aux::do_connect::SUCCESS_ACK => {
                 log::trace!(target: "citadel", "RECV SUCCESS_ACK");
                 if session.is_server {
-                    let signal = inner_mut_state!(session.state_container)
-                        .c2s_channel_container
-                        .as_mut()
-                        .ok_or_else(|| NetworkError::InternalError("C2S channel not loaded"))?
-                        .channel_signal
-                        .take()
-                        .ok_or(NetworkError::InternalError("Channel signal missing"))?;
+                    let (signal, unacknowledged_messages) = {
+                        let mut state_container = inner_mut_state!(session.state_container);
+                        let c2s_channel_container = state_container
+                            .c2s_channel_container
+                            .as_mut()
+                            .ok_or_else(|| NetworkError::InternalError("C2S channel not loaded"))?;
+                        let signal = c2s_channel_container
+                            .channel_signal
+                            .take()
+                            .ok_or(NetworkError::InternalError("Channel signal missing"))?;
+                        (
+                            signal,
+                            std::mem::take(&mut c2s_channel_container.unacknowledged_messages),
+                        )
+                    };
                     session.send_to_kernel(signal)?;
+
+                    // the client has received the queued messages carried by the success packet
+                    if !unacknowledged_messages.is_empty() {
+                        let cid = hyper_ratchet.get_cid();
+                        if let Err(err) = offline_messages::acknowledge_messages(
+                            session.account_manager.get_persistence_handler(),
+                            &session.session_manager.recipient_locks(),
+                            cid,
+                            unacknowledged_messages,
+                        )
+                        .await
+                        {
+                            log::warn!(target: "citadel", "Unable to remove messages acknowledged by {}: {:?}", cid, err);
+                        }
+                    }
+
                     Ok(PrimaryProcessorResult::Void)
                 } else {
                     Err(NetworkError::InvalidPacket(
@@ -1,6 +1,8 @@
 use super::includes::*;
 use crate::error::NetworkError;
 use crate::proto::packet_processor::primary_group_packet::get_proper_hyper_ratchet;
+use crate::proto::state_container::{FileKey, StateContainerInner};
+use citadel_user::backend::utils::ObjectTransferOrientation;
 use std::sync::atomic::Ordering;
 
 #[cfg_attr(feature = "localhost-testing", tracing::instrument(target = "citadel", skip_all, ret, err, fields(is_server = session.is_server, src = packet.parse().unwrap().0.session_cid.get(), target = packet.parse().unwrap().0.target_cid.get())))]
@@ -47,6 +49,19 @@ pub fn process_file_packet(
                                     ),
                                 ),
 
+                                VirtualConnectionType::ExternalGroupPeer(
+                                    implicated_cid,
+                                    icid,
+                                    target_cid,
+                                ) => (
+                                    implicated_cid,
+                                    VirtualConnectionType::ExternalGroupPeer(
+                                        target_cid,
+                                        icid,
+                                        implicated_cid,
+                                    ),
+                                ),
+
                                 VirtualConnectionType::LocalGroupServer(implicated_cid) => {
                                     (0, VirtualConnectionType::LocalGroupServer(implicated_cid))
                                 }
@@ -75,6 +90,11 @@ pub fn process_file_packet(
                                 target_cid,
                                 v_target_flipped,
                                 preferred_primary_stream,
+                                &session
+                                    .account_manager
+                                    .get_misc_settings()
+                                    .forwarding_policy,
+                                session.session_manager.recipient_locks(),
                             ) {
                                 log::warn!(target: "citadel", "Failed to run on_file_header_received");
                             }
@@ -94,17 +114,19 @@ pub fn process_file_packet(
                 packet_flags::cmd::aux::file::FILE_HEADER_ACK => {
                     log::trace!(target: "citadel", "RECV FILE HEADER ACK");
                     match validation::file::validate_file_header_ack(&header, &payload[..]) {
-                        Some((success, object_id, v_target)) => {
+                        Some((start_group, object_id, v_target)) => {
                             // the target is the implicated cid of THIS receiving node
                             let implicated_cid = header.target_cid.get();
                             // conclude by passing this data into the state container
                             if state_container
                                 .on_file_header_ack_received(
-                                    success,
+                                    start_group,
                                     implicated_cid,
                                     header.context_info.get().into(),
                                     object_id,
                                     v_target,
+                                    security_level,
+                                    session.state_container.clone(),
                                 )
                                 .is_none()
                             {
@@ -121,6 +143,63 @@ pub fn process_file_packet(
                     }
                 }
 
+                packet_flags::cmd::aux::file::FILE_CONTROL => {
+                    log::trace!(target: "citadel", "RECV FILE CONTROL");
+                    match validation::file::validate_file_control(&header, &payload[..]) {
+                        Some((orientation, control, object_id, v_target)) => {
+                            let key = match orientation {
+                                // inbound transfers are keyed by the session cid of the FILE_HEADER, which the sender reuses here
+                                ObjectTransferOrientation::Receiver => {
+                                    FileKey::new(header.session_cid.get(), object_id)
+                                }
+
+                                ObjectTransferOrientation::Sender => {
+                                    return_if_none!(
+                                        StateContainerInner::outbound_file_key(v_target, object_id),
+                                        "Invalid FILE CONTROL target"
+                                    )
+                                    .0
+                                }
+                            };
+
+                            if !state_container.on_object_transfer_control(
+                                key,
+                                orientation,
+                                control,
+                            ) {
+                                log::warn!(target: "citadel", "Received {:?} for {:?}, but the transfer does not exist", control, key);
+                            }
+
+                            Ok(PrimaryProcessorResult::Void)
+                        }
+
+                        _ => {
+                            log::error!(target: "citadel", "Unable to validate FILE CONTROL");
+                            Ok(PrimaryProcessorResult::Void)
+                        }
+                    }
+                }
+
+                packet_flags::cmd::aux::file::FILE_DIGEST => {
+                    log::trace!(target: "citadel", "RECV FILE DIGEST");
+                    match validation::file::validate_file_digest(&header, &payload[..]) {
+                        Some((object_id, summary)) => {
+                            // inbound transfers are keyed by the session cid of the FILE_HEADER, which the sender reuses here
+                            let key = FileKey::new(header.session_cid.get(), object_id);
+                            if !state_container.on_file_digest_received(key, summary) {
+                                log::warn!(target: "citadel", "Received digest for {:?}, but the transfer does not exist", key);
+                            }
+
+                            Ok(PrimaryProcessorResult::Void)
+                        }
+
+                        _ => {
+                            log::error!(target: "citadel", "Unable to validate FILE DIGEST");
+                            Ok(PrimaryProcessorResult::Void)
+                        }
+                    }
+                }
+
                 _ => {
                     log::error!(target: "citadel", "Invalid FILE auxiliary command received");
                     Ok(PrimaryProcessorResult::Void)
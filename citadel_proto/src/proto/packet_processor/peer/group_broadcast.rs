@@ -4,7 +4,8 @@ use crate::functional::*;
 use crate::proto::node_result::{GroupChannelCreated, GroupEvent};
 use crate::proto::packet_crafter::peer_cmd::C2S_ENCRYPTION_ONLY;
 use crate::proto::peer::group_channel::GroupBroadcastPayload;
-use crate::proto::peer::message_group::{MessageGroupKey, MessageGroupOptions};
+use crate::proto::peer::message_group::{GroupRole, MessageGroupKey, MessageGroupOptions};
+use crate::proto::peer::sender_keys;
 use crate::proto::remote::Ticket;
 use citadel_crypt::stacked_ratchet::StackedRatchet;
 use citadel_user::serialization::SyncIO;
@@ -19,8 +20,8 @@ pub enum GroupBroadcast {
     End(MessageGroupKey),
     EndResponse(MessageGroupKey, bool),
     Disconnected(MessageGroupKey),
-    // sender cid, key, message
-    Message(u64, MessageGroupKey, SecBuffer),
+    // sender cid, key, sequence, message. The sequence is assigned by the server
+    Message(u64, MessageGroupKey, u64, SecBuffer),
     // not actually a "response message", but rather, just like the other response types, just what the server sends to the requesting client
     MessageResponse(MessageGroupKey, bool),
     Add(MessageGroupKey, Vec<u64>),
@@ -40,12 +41,66 @@ pub enum GroupBroadcast {
     MemberStateChanged(MessageGroupKey, MemberState),
     GroupNonExists(MessageGroupKey),
     SignalResponse(Result<(), String>),
+    /// Sent by a member upon reconnecting to rebind a channel to a persistent group
+    Reattach(MessageGroupKey),
+    ReattachResponse(MessageGroupKey, bool),
+    /// Lists the groups in which the sender is a member
+    ListMemberships,
+    /// Changes the role of a member. The sender must outrank both the member's current and new role
+    SetRole(MessageGroupKey, u64, GroupRole),
+    SetRoleResponse(MessageGroupKey, bool),
+    /// Mutes (true) or unmutes (false) the listed members
+    SetMuted(MessageGroupKey, Vec<u64>, bool),
+    SetMutedResponse(MessageGroupKey, bool),
+    /// Passes ownership of the group to the listed member. The sender must be the owner, and is demoted to admin
+    TransferOwnership(MessageGroupKey, u64),
+    TransferOwnershipResponse(MessageGroupKey, bool),
+    /// sender cid, key, sequence, sender key id, ciphertext. Used in place of [GroupBroadcast::Message] for confidential groups
+    SealedMessage(u64, MessageGroupKey, u64, u64, Vec<u8>),
+    /// Sent by the server to each member of a confidential group when its membership changes. Contains the current members
+    RotateSenderKey(MessageGroupKey, Vec<u64>),
+    /// Sent by a member after rotating its sender key. Contains the sender key sealed for each recipient
+    DistributeSenderKey(MessageGroupKey, Vec<(u64, Vec<u8>)>),
+    /// key, sender cid, sealed sender key
+    SenderKey(MessageGroupKey, u64, Vec<u8>),
+    /// Requests the messages retained by the server with a sequence number greater than the one given
+    FetchHistory(MessageGroupKey, u64),
+    /// The retained messages, in order
+    History(MessageGroupKey, Vec<GroupBroadcast>),
+}
+
+impl GroupBroadcast {
+    /// Returns the sequence number of a message, or None if the signal is not a message
+    pub fn sequence(&self) -> Option<u64> {
+        match self {
+            GroupBroadcast::Message(_, _, sequence, _)
+            | GroupBroadcast::SealedMessage(_, _, sequence, _, _) => Some(*sequence),
+            _ => None,
+        }
+    }
+
+    /// Replaces the sequence number of a message. Other signals are returned unaltered
+    pub(crate) fn with_sequence(self, sequence: u64) -> Self {
+        match self {
+            GroupBroadcast::Message(sender, key, _, message) => {
+                GroupBroadcast::Message(sender, key, sequence, message)
+            }
+            GroupBroadcast::SealedMessage(sender, key, _, key_id, ciphertext) => {
+                GroupBroadcast::SealedMessage(sender, key, sequence, key_id, ciphertext)
+            }
+            signal => signal,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MemberState {
     EnteredGroup(Vec<u64>),
     LeftGroup(Vec<u64>),
+    RoleChanged(u64, GroupRole),
+    MuteChanged(Vec<u64>, bool),
+    /// previous owner, new owner
+    OwnerChanged(u64, u64),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -126,6 +181,10 @@ pub async fn process_group_broadcast(
 
                     Some(true) => {
                         // user has been automatically added to the group via auto-accept.
+                        session
+                            .session_manager
+                            .rotate_group_sender_keys(timestamp, ticket, key, security_level)
+                            .await?;
                         let success = GroupBroadcast::AcceptMembershipResponse(key, true);
                         let return_packet = packet_crafter::peer_cmd::craft_group_message_packet(
                             sess_hyper_ratchet,
@@ -186,6 +245,66 @@ pub async fn process_group_broadcast(
             Ok(PrimaryProcessorResult::ReplyToSender(return_packet))
         }
 
+        GroupBroadcast::ListMemberships => {
+            let message_groups = session
+                .hypernode_peer_layer
+                .list_message_group_memberships_for(implicated_cid)
+                .await;
+            let signal = GroupBroadcast::ListResponse(message_groups);
+            let return_packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &signal,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(return_packet))
+        }
+
+        GroupBroadcast::Reattach(key) => {
+            let signal = match session
+                .hypernode_peer_layer
+                .is_message_group_member(key, implicated_cid)
+                .await
+            {
+                Some(is_member) => {
+                    // the reattaching member no longer holds any sender keys
+                    if is_member {
+                        session
+                            .session_manager
+                            .rotate_group_sender_keys(timestamp, ticket, key, security_level)
+                            .await?;
+                    }
+
+                    GroupBroadcast::ReattachResponse(key, is_member)
+                }
+                None => GroupBroadcast::GroupNonExists(key),
+            };
+            let return_packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &signal,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(return_packet))
+        }
+
+        GroupBroadcast::ReattachResponse(key, success) => {
+            if success {
+                create_group_channel(ticket, key, session)
+            } else {
+                forward_signal(
+                    session,
+                    ticket,
+                    Some(key),
+                    GroupBroadcast::ReattachResponse(key, success),
+                )
+            }
+        }
+
         GroupBroadcast::ListResponse(message_groups) => forward_signal(
             session,
             ticket,
@@ -201,7 +320,10 @@ pub async fn process_group_broadcast(
         ),
 
         GroupBroadcast::End(key) => {
-            return_if_none!(permission_gate(implicated_cid, key), "Permission denied");
+            return_if_none!(
+                role_gate(session, implicated_cid, key, GroupRole::Owner).await,
+                "Permission denied"
+            );
             let success = session
                 .session_manager
                 .remove_message_group(implicated_cid, timestamp, ticket, key, security_level)
@@ -225,9 +347,9 @@ pub async fn process_group_broadcast(
             GroupBroadcast::EndResponse(key, success),
         )
         .map(|res| {
-            let _ = inner_mut_state!(session.state_container)
-                .group_channels
-                .remove(&key);
+            let mut state_container = inner_mut_state!(session.state_container);
+            let _ = state_container.group_channels.remove(&key);
+            let _ = state_container.group_sender_keys.remove(&key);
             res
         }),
 
@@ -238,28 +360,46 @@ pub async fn process_group_broadcast(
             GroupBroadcast::Disconnected(key),
         )
         .map(|res| {
-            let _ = inner_mut_state!(session.state_container)
-                .group_channels
-                .remove(&key);
+            let mut state_container = inner_mut_state!(session.state_container);
+            let _ = state_container.group_channels.remove(&key);
+            let _ = state_container.group_sender_keys.remove(&key);
             res
         }),
 
-        GroupBroadcast::Message(username, key, message) => {
+        GroupBroadcast::Message(username, key, sequence, message) => {
             if session.is_server {
                 log::trace!(target: "citadel", "[Group/Server] Received message {:?}", message);
-                // The message will need to be broadcasted to every member in the group
-                let success = session
-                    .session_manager
-                    .broadcast_signal_to_group(
+                // observers and muted members may not post to the group, and confidential groups only accept sealed messages
+                let peer_layer = &session.hypernode_peer_layer;
+                let success = if peer_layer
+                    .can_post_to_message_group(key, implicated_cid)
+                    .await
+                    && peer_layer
+                        .get_confidential_group_members(key)
+                        .await
+                        .is_none()
+                {
+                    // The message will need to be numbered, then broadcasted to every member in the group. The sender is
+                    // stamped by the server, since the sender named by the client cannot be trusted
+                    let message = peer_layer
+                        .record_message_group_message(
+                            key,
+                            GroupBroadcast::Message(implicated_cid, key, sequence, message),
+                        )
+                        .await;
+                    relay_group_message(
+                        session,
                         implicated_cid,
                         timestamp,
                         ticket,
                         key,
-                        GroupBroadcast::Message(username, key, message),
+                        message,
                         security_level,
                     )
                     .await
-                    .unwrap_or(false);
+                } else {
+                    false
+                };
                 let resp = GroupBroadcast::MessageResponse(key, success);
                 let packet = packet_crafter::peer_cmd::craft_group_message_packet(
                     sess_hyper_ratchet,
@@ -276,11 +416,193 @@ pub async fn process_group_broadcast(
                     session,
                     ticket,
                     Some(key),
-                    GroupBroadcast::Message(username, key, message),
+                    GroupBroadcast::Message(username, key, sequence, message),
                 )
             }
         }
 
+        GroupBroadcast::SealedMessage(sender, key, sequence, key_id, ciphertext) => {
+            if session.is_server {
+                // the server relays the ciphertext without being able to read it. The sender is stamped by the server;
+                // since the ciphertext authenticates the sender, a member claiming to be another member yields a
+                // message that no recipient can open
+                let peer_layer = &session.hypernode_peer_layer;
+                let success = if peer_layer
+                    .can_post_to_message_group(key, implicated_cid)
+                    .await
+                {
+                    let message = peer_layer
+                        .record_message_group_message(
+                            key,
+                            GroupBroadcast::SealedMessage(
+                                implicated_cid,
+                                key,
+                                sequence,
+                                key_id,
+                                ciphertext,
+                            ),
+                        )
+                        .await;
+                    relay_group_message(
+                        session,
+                        implicated_cid,
+                        timestamp,
+                        ticket,
+                        key,
+                        message,
+                        security_level,
+                    )
+                    .await
+                } else {
+                    false
+                };
+                let resp = GroupBroadcast::MessageResponse(key, success);
+                let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                    sess_hyper_ratchet,
+                    &resp,
+                    ticket,
+                    C2S_ENCRYPTION_ONLY,
+                    timestamp,
+                    security_level,
+                );
+                Ok(PrimaryProcessorResult::ReplyToSender(packet))
+            } else {
+                match open_sealed_message(session, sender, key, sequence, key_id, &ciphertext) {
+                    Some(message) => forward_signal(session, ticket, Some(key), message),
+                    None => Ok(PrimaryProcessorResult::Void),
+                }
+            }
+        }
+
+        GroupBroadcast::FetchHistory(key, since) => {
+            let signal = match session
+                .hypernode_peer_layer
+                .get_message_group_history(key, implicated_cid, since)
+                .await
+            {
+                Some(history) => GroupBroadcast::History(key, history),
+                None => GroupBroadcast::GroupNonExists(key),
+            };
+            let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &signal,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(packet))
+        }
+
+        GroupBroadcast::History(key, history) => {
+            // replay each message through the channel as though it were just received
+            for message in history {
+                let message = match message {
+                    GroupBroadcast::SealedMessage(sender, key, sequence, key_id, ciphertext) => {
+                        open_sealed_message(session, sender, key, sequence, key_id, &ciphertext)
+                    }
+                    message @ GroupBroadcast::Message(..) => Some(message),
+                    _ => None,
+                };
+
+                if let Some(message) = message {
+                    let _ = forward_signal(session, ticket, Some(key), message)?;
+                }
+            }
+
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        GroupBroadcast::RotateSenderKey(key, members) => {
+            // sender keys are only ever held by clients
+            if session.is_server {
+                return Ok(PrimaryProcessorResult::Void);
+            }
+
+            let sender_key = inner_mut_state!(session.state_container)
+                .group_sender_keys
+                .entry(key)
+                .or_default()
+                .rotate(&members);
+            let sealed = sender_keys::seal_sender_key(
+                session.account_manager.get_persistence_handler(),
+                implicated_cid,
+                &members,
+                &sender_key,
+            )
+            .await?;
+            let signal = GroupBroadcast::DistributeSenderKey(key, sealed);
+            let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &signal,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(packet))
+        }
+
+        GroupBroadcast::DistributeSenderKey(key, sealed) => {
+            let members = session
+                .hypernode_peer_layer
+                .get_confidential_group_members(key)
+                .await
+                .unwrap_or_default();
+            if !members.contains(&implicated_cid) {
+                log::warn!(target: "citadel", "{} attempted to distribute a sender key to {} without membership", implicated_cid, key);
+                return Ok(PrimaryProcessorResult::Void);
+            }
+
+            for (recipient, sealed_key) in sealed {
+                if recipient == implicated_cid || !members.contains(&recipient) {
+                    continue;
+                }
+
+                let _ = session
+                    .session_manager
+                    .send_group_broadcast_signal_to(
+                        timestamp,
+                        ticket,
+                        std::iter::once((recipient, true)),
+                        true,
+                        GroupBroadcast::SenderKey(key, implicated_cid, sealed_key),
+                        security_level,
+                    )
+                    .await
+                    .map_err(NetworkError::Generic)?;
+            }
+
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        GroupBroadcast::SenderKey(key, sender, sealed) => {
+            if session.is_server {
+                return Ok(PrimaryProcessorResult::Void);
+            }
+
+            match sender_keys::open_sender_key(
+                session.account_manager.get_persistence_handler(),
+                implicated_cid,
+                sender,
+                &sealed,
+            )
+            .await
+            {
+                Ok(sender_key) => inner_mut_state!(session.state_container)
+                    .group_sender_keys
+                    .entry(key)
+                    .or_default()
+                    .insert_peer_key(sender, sender_key),
+
+                Err(err) => {
+                    log::warn!(target: "citadel", "Unable to open the sender key of {} for {}: {:?}", sender, key, err)
+                }
+            }
+
+            Ok(PrimaryProcessorResult::Void)
+        }
+
         GroupBroadcast::MessageResponse(key, success) => forward_signal(
             session,
             ticket,
@@ -314,6 +636,10 @@ pub async fn process_group_broadcast(
                     log::warn!(target: "citadel", "Unable to broadcast member acceptance to group {}", key);
                 }
                 log::trace!(target: "citadel", "Successfully upgraded {} for {:?}", implicated_cid, key);
+                session
+                    .session_manager
+                    .rotate_group_sender_keys(timestamp, ticket, key, security_level)
+                    .await?;
             }
 
             // tell the user who accepted the membership
@@ -343,7 +669,7 @@ pub async fn process_group_broadcast(
         }
 
         GroupBroadcast::LeaveRoom(key) => {
-            // TODO: If the user leaving the room is the message group owner, then leave
+            // if the owner leaves, the group either passes to a successor or ends
             let success = session
                 .session_manager
                 .kick_from_message_group(
@@ -388,14 +714,17 @@ pub async fn process_group_broadcast(
             GroupBroadcast::LeaveRoomResponse(key, success, response),
         )
         .map(|res| {
-            let _ = inner_mut_state!(session.state_container)
-                .group_channels
-                .remove(&key);
+            let mut state_container = inner_mut_state!(session.state_container);
+            let _ = state_container.group_channels.remove(&key);
+            let _ = state_container.group_sender_keys.remove(&key);
             res
         }),
 
         GroupBroadcast::Add(key, peers) => {
-            return_if_none!(permission_gate(implicated_cid, key), "Permission denied");
+            return_if_none!(
+                role_gate(session, implicated_cid, key, GroupRole::Admin).await,
+                "Permission denied"
+            );
             // the server receives this. It then sends an invitation
             // if peer is not online, leave some mail. If peer is online,
             // send invitation
@@ -462,7 +791,7 @@ pub async fn process_group_broadcast(
         ),
 
         GroupBroadcast::Kick(key, peers) => {
-            return_if_none!(permission_gate(implicated_cid, key), "Permission denied");
+            // permissions are enforced by the session manager
             let success = session
                 .session_manager
                 .kick_from_message_group(
@@ -496,6 +825,133 @@ pub async fn process_group_broadcast(
             GroupBroadcast::KickResponse(key, success),
         ),
 
+        GroupBroadcast::SetRole(key, peer_cid, role) => {
+            let success = session
+                .hypernode_peer_layer
+                .set_member_role(key, implicated_cid, peer_cid, role)
+                .await;
+
+            if success {
+                let _ = session
+                    .session_manager
+                    .broadcast_signal_to_group(
+                        implicated_cid,
+                        timestamp,
+                        ticket,
+                        key,
+                        GroupBroadcast::MemberStateChanged(
+                            key,
+                            MemberState::RoleChanged(peer_cid, role),
+                        ),
+                        security_level,
+                    )
+                    .await;
+            }
+
+            let resp = GroupBroadcast::SetRoleResponse(key, success);
+            let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &resp,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(packet))
+        }
+
+        GroupBroadcast::SetRoleResponse(key, success) => forward_signal(
+            session,
+            ticket,
+            Some(key),
+            GroupBroadcast::SetRoleResponse(key, success),
+        ),
+
+        GroupBroadcast::SetMuted(key, peers, muted) => {
+            let altered = session
+                .hypernode_peer_layer
+                .set_members_muted(key, implicated_cid, peers, muted)
+                .await;
+            let success = !altered.is_empty();
+
+            if success {
+                let _ = session
+                    .session_manager
+                    .broadcast_signal_to_group(
+                        implicated_cid,
+                        timestamp,
+                        ticket,
+                        key,
+                        GroupBroadcast::MemberStateChanged(
+                            key,
+                            MemberState::MuteChanged(altered, muted),
+                        ),
+                        security_level,
+                    )
+                    .await;
+            }
+
+            let resp = GroupBroadcast::SetMutedResponse(key, success);
+            let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &resp,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(packet))
+        }
+
+        GroupBroadcast::SetMutedResponse(key, success) => forward_signal(
+            session,
+            ticket,
+            Some(key),
+            GroupBroadcast::SetMutedResponse(key, success),
+        ),
+
+        GroupBroadcast::TransferOwnership(key, new_owner) => {
+            let success = session
+                .hypernode_peer_layer
+                .transfer_message_group_ownership(key, implicated_cid, new_owner)
+                .await;
+
+            if success {
+                let _ = session
+                    .session_manager
+                    .broadcast_signal_to_group(
+                        implicated_cid,
+                        timestamp,
+                        ticket,
+                        key,
+                        GroupBroadcast::MemberStateChanged(
+                            key,
+                            MemberState::OwnerChanged(implicated_cid, new_owner),
+                        ),
+                        security_level,
+                    )
+                    .await;
+            }
+
+            let resp = GroupBroadcast::TransferOwnershipResponse(key, success);
+            let packet = packet_crafter::peer_cmd::craft_group_message_packet(
+                sess_hyper_ratchet,
+                &resp,
+                ticket,
+                C2S_ENCRYPTION_ONLY,
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::ReplyToSender(packet))
+        }
+
+        GroupBroadcast::TransferOwnershipResponse(key, success) => forward_signal(
+            session,
+            ticket,
+            Some(key),
+            GroupBroadcast::TransferOwnershipResponse(key, success),
+        ),
+
         GroupBroadcast::Invitation(key) => {
             forward_signal(session, ticket, Some(key), GroupBroadcast::Invitation(key))
         }
@@ -521,6 +977,65 @@ pub async fn process_group_broadcast(
     }
 }
 
+/// Broadcasts a numbered message to the group, returning true if every member was reached
+async fn relay_group_message(
+    session: &HdpSession,
+    implicated_cid: u64,
+    timestamp: i64,
+    ticket: Ticket,
+    key: MessageGroupKey,
+    message: Option<GroupBroadcast>,
+    security_level: SecurityLevel,
+) -> bool {
+    match message {
+        Some(message) => session
+            .session_manager
+            .broadcast_signal_to_group(
+                implicated_cid,
+                timestamp,
+                ticket,
+                key,
+                message,
+                security_level,
+            )
+            .await
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Opens a message sealed under the sender key of `sender`. Messages that cannot be opened are dropped
+fn open_sealed_message(
+    session: &HdpSession,
+    sender: u64,
+    key: MessageGroupKey,
+    sequence: u64,
+    key_id: u64,
+    ciphertext: &[u8],
+) -> Option<GroupBroadcast> {
+    let opened = inner_state!(session.state_container)
+        .group_sender_keys
+        .get(&key)
+        .ok_or(NetworkError::InternalError(
+            "No sender keys are held for this group",
+        ))
+        .and_then(|keys| keys.open(key, sender, key_id, ciphertext));
+
+    match opened {
+        Ok(message) => Some(GroupBroadcast::Message(
+            sender,
+            key,
+            sequence,
+            message.into(),
+        )),
+
+        Err(err) => {
+            log::warn!(target: "citadel", "Dropping group message from {} that could not be opened: {:?}", sender, err);
+            None
+        }
+    }
+}
+
 fn create_group_channel(
     ticket: Ticket,
     key: MessageGroupKey,
@@ -538,8 +1053,12 @@ fn create_group_channel(
 impl From<GroupBroadcast> for GroupBroadcastPayload {
     fn from(broadcast: GroupBroadcast) -> Self {
         match broadcast {
-            GroupBroadcast::Message(sender, _key, payload) => {
-                GroupBroadcastPayload::Message { payload, sender }
+            GroupBroadcast::Message(sender, _key, sequence, payload) => {
+                GroupBroadcastPayload::Message {
+                    payload,
+                    sender,
+                    sequence,
+                }
             }
             evt => GroupBroadcastPayload::Event { payload: evt },
         }
@@ -579,13 +1098,18 @@ fn forward_signal(
     Ok(PrimaryProcessorResult::Void)
 }
 
-/// Returns None if the implicated_cid is NOT the key's cid.
+/// Returns None if the implicated_cid is not a member of the group with a role of at least `required`.
 ///
-/// Passing the permission gate requires that the implicated_cid is the key's owning cid
-fn permission_gate(implicated_cid: u64, key: MessageGroupKey) -> Option<()> {
-    if implicated_cid != key.cid {
-        None
-    } else {
-        Some(())
-    }
+/// Since ownership may be transferred, the key's cid is not necessarily the owner's cid
+async fn role_gate(
+    session: &HdpSession,
+    implicated_cid: u64,
+    key: MessageGroupKey,
+    required: GroupRole,
+) -> Option<()> {
+    session
+        .hypernode_peer_layer
+        .member_has_role(key, implicated_cid, required)
+        .await
+        .then_some(())
 }
@@ -13,7 +13,8 @@ use citadel_user::serialization::SyncIO;
 use netbeam::sync::RelativeNodeType;
 
 use crate::error::NetworkError;
-use crate::proto::node_result::{PeerChannelCreated, PeerEvent};
+use crate::proto::forwarded_objects;
+use crate::proto::node_result::{MailboxDelivery, PeerChannelCreated, PeerEvent};
 use crate::proto::outbound_sender::OutboundPrimaryStreamSender;
 use crate::proto::packet_processor::includes::*;
 use crate::proto::packet_processor::peer::group_broadcast;
@@ -24,11 +25,12 @@ use crate::proto::packet_processor::primary_group_packet::{
     get_proper_hyper_ratchet, get_resp_target_cid,
 };
 use crate::proto::peer::hole_punch_compat_sink_stream::ReliableOrderedCompatStream;
+use crate::proto::peer::offline_messages;
 use crate::proto::peer::p2p_conn_handler::attempt_simultaneous_hole_punch;
 use crate::proto::peer::peer_crypt::{KeyExchangeProcess, PeerNatInfo};
 use crate::proto::peer::peer_layer::{
-    HyperNodePeerLayerInner, HypernodeConnectionType, PeerConnectionType, PeerResponse, PeerSignal,
-    UdpMode,
+    HyperNodePeerLayerInner, HypernodeConnectionType, MailboxTransfer, PeerConnectionType,
+    PeerResponse, PeerSignal, QueuedMessage, UdpMode,
 };
 use crate::proto::remote::Ticket;
 use crate::proto::session_manager::HdpSessionManager;
@@ -94,6 +96,20 @@ pub async fn process_peer_cmd(
                 let timestamp = session.time_tracker.get_global_time_ns();
                 let ticket = header.context_info.get().into();
 
+                // signals relayed by an adjacent server are delivered to the local clients, regardless of which
+                // server initiated the federated link
+                if session.session_manager.is_federated_link(implicated_cid) {
+                    return super::server::hyperwan::process_signal_from_federated_server(
+                        session,
+                        signal,
+                        implicated_cid,
+                        ticket,
+                        timestamp,
+                        security_level,
+                    )
+                    .await;
+                }
+
                 if !session.is_server {
                     // forward the signal to the kernel, with some exceptions.
                     match &signal {
@@ -158,6 +174,25 @@ pub async fn process_peer_cmd(
                             return Ok(PrimaryProcessorResult::Void);
                         }
 
+                        PeerSignal::OfflineMessage(_, message) => {
+                            let messages = offline_messages::open_messages(
+                                session.account_manager.get_persistence_handler(),
+                                implicated_cid,
+                                vec![message.clone()],
+                            )
+                            .await;
+                            if !messages.is_empty() {
+                                session.send_to_kernel(NodeResult::MailboxDelivery(
+                                    MailboxDelivery {
+                                        implicated_cid,
+                                        ticket_opt: Some(ticket),
+                                        items: MailboxTransfer::Messages(messages),
+                                    },
+                                ))?;
+                            }
+                            return Ok(PrimaryProcessorResult::Void);
+                        }
+
                         PeerSignal::DisconnectUDP(vconn) => {
                             let target_cid = return_if_none!(get_resp_target_cid(vconn));
                             inner_mut_state!(session.state_container)
@@ -244,84 +279,61 @@ pub async fn process_peer_cmd(
                             // TODO: handle non-accept case
                             // the connection was mutually accepted. Now, we must begin the KEM subroutine
                             if accepted {
-                                return match conn {
-                                    PeerConnectionType::HyperLANPeerToHyperLANPeer(
-                                        original_implicated_cid,
-                                        original_target_cid,
-                                    ) => {
-                                        // this implies this node is receiving an accept_request. As such, we need to NOT
-                                        // forward the signal quite yet, and instead, begin the key-exchange process in order to
-                                        // establish a working [PeerChannel] system that has a custom post-quantum key and toolset
-                                        // unique to the session.
-                                        //let mut state_container = inner_mut!(session.state_container);
-                                        //let peer_cid = conn.get_original_implicated_cid();
-                                        let mut peer_kem_state_container =
-                                            PeerKemStateContainer::new(
-                                                *endpoint_security_settings,
-                                                *udp_enabled == UdpMode::Enabled,
-                                            );
-
-                                        let alice_constructor =
-                                            return_if_none!(StackedRatchetConstructor::new_alice(
-                                                ConstructorOpts::new_vec_init(
-                                                    Some(endpoint_security_settings.crypto_params),
-                                                    (endpoint_security_settings
-                                                        .security_level
-                                                        .value()
-                                                        + 1)
-                                                        as usize
-                                                ),
-                                                conn.get_original_target_cid(),
-                                                0,
-                                                Some(endpoint_security_settings.security_level)
-                                            ));
-                                        let transfer = return_if_none!(
-                                            alice_constructor.stage0_alice(),
-                                            "AliceConstructor None"
-                                        );
-                                        //log::trace!(target: "citadel", "0. Len: {}, {:?}", alice_pub_key.len(), &alice_pub_key[..10]);
-                                        let msg_bytes =
-                                            return_if_none!(transfer.serialize_to_vec());
-                                        peer_kem_state_container.constructor =
-                                            Some(alice_constructor);
-                                        inner_mut_state!(session.state_container)
-                                            .peer_kem_states
-                                            .insert(
-                                                *original_implicated_cid,
-                                                peer_kem_state_container,
-                                            );
-                                        // finally, prepare the signal and send outbound
-                                        // signal: PeerSignal, pqc: &Rc<PostQuantumContainer>, drill: &EntropyBank, ticket: Ticket, timestamp: i64
-                                        let signal = PeerSignal::Kem(
-                                            PeerConnectionType::HyperLANPeerToHyperLANPeer(
-                                                *original_target_cid,
-                                                *original_implicated_cid,
-                                            ),
-                                            KeyExchangeProcess::Stage0(
-                                                msg_bytes,
-                                                *endpoint_security_settings,
-                                                *udp_enabled,
-                                            ),
-                                        );
-
-                                        let stage0_peer_kem =
-                                            packet_crafter::peer_cmd::craft_peer_signal(
-                                                &sess_hyper_ratchet,
-                                                signal,
-                                                ticket,
-                                                timestamp,
-                                                security_level,
-                                            );
-                                        log::trace!(target: "citadel", "Sent peer KEM stage 0 outbound");
-                                        // send to central server
-                                        Ok(PrimaryProcessorResult::ReplyToSender(stage0_peer_kem))
-                                    }
-
-                                    _ => {
-                                        log::error!(target: "citadel", "HyperWAN Functionality not yet enabled");
-                                        Ok(PrimaryProcessorResult::Void)
-                                    }
-                                };
+                                let original_implicated_cid = conn.get_original_implicated_cid();
+                                // this implies this node is receiving an accept_request. As such, we need to NOT
+                                // forward the signal quite yet, and instead, begin the key-exchange process in order to
+                                // establish a working [PeerChannel] system that has a custom post-quantum key and toolset
+                                // unique to the session.
+                                //let mut state_container = inner_mut!(session.state_container);
+                                //let peer_cid = conn.get_original_implicated_cid();
+                                let mut peer_kem_state_container = PeerKemStateContainer::new(
+                                    *endpoint_security_settings,
+                                    *udp_enabled == UdpMode::Enabled,
+                                );
+
+                                let alice_constructor =
+                                    return_if_none!(StackedRatchetConstructor::new_alice(
+                                        ConstructorOpts::new_vec_init(
+                                            Some(endpoint_security_settings.crypto_params),
+                                            (endpoint_security_settings.security_level.value() + 1)
+                                                as usize
+                                        ),
+                                        conn.get_original_target_cid(),
+                                        0,
+                                        Some(endpoint_security_settings.security_level)
+                                    ));
+                                let transfer = return_if_none!(
+                                    alice_constructor.stage0_alice(),
+                                    "AliceConstructor None"
+                                );
+                                //log::trace!(target: "citadel", "0. Len: {}, {:?}", alice_pub_key.len(), &alice_pub_key[..10]);
+                                let msg_bytes = return_if_none!(transfer.serialize_to_vec());
+                                peer_kem_state_container.constructor = Some(alice_constructor);
+                                inner_mut_state!(session.state_container)
+                                    .peer_kem_states
+                                    .insert(original_implicated_cid, peer_kem_state_container);
+                                // finally, prepare the signal and send outbound
+                                // signal: PeerSignal, pqc: &Rc<PostQuantumContainer>, drill: &EntropyBank, ticket: Ticket, timestamp: i64
+                                // NOTE: for HyperWAN peers, the reversed conn retains the icid, allowing both servers to relay the KEM
+                                let signal = PeerSignal::Kem(
+                                    conn.reverse(),
+                                    KeyExchangeProcess::Stage0(
+                                        msg_bytes,
+                                        *endpoint_security_settings,
+                                        *udp_enabled,
+                                    ),
+                                );
+
+                                let stage0_peer_kem = packet_crafter::peer_cmd::craft_peer_signal(
+                                    &sess_hyper_ratchet,
+                                    signal,
+                                    ticket,
+                                    timestamp,
+                                    security_level,
+                                );
+                                log::trace!(target: "citadel", "Sent peer KEM stage 0 outbound");
+                                // send to central server
+                                return Ok(PrimaryProcessorResult::ReplyToSender(stage0_peer_kem));
                             }
                         }
 
@@ -432,9 +444,7 @@ pub async fn process_peer_cmd(
                                         let toolset = Toolset::new(this_cid, hyper_ratchet);
                                         // now, register the loaded PQC + toolset into the virtual conn
                                         let peer_crypto = PeerSessionCrypto::new(toolset, true);
-                                        let vconn_type = VirtualConnectionType::LocalGroupPeer(
-                                            this_cid, peer_cid,
-                                        );
+                                        let vconn_type = conn.reverse().as_virtual_connection();
                                         let (needs_turn, bob_predicted_socket_addr) = bob_nat_info
                                             .generate_proper_listener_connect_addr(
                                                 &session.local_nat_type,
@@ -519,7 +529,10 @@ pub async fn process_peer_cmd(
                                             udp_rx_opt,
                                         });
 
-                                    if needs_turn && !cfg!(feature = "localhost-testing") {
+                                    // HyperWAN peers are always relayed through both servers
+                                    if (needs_turn && !cfg!(feature = "localhost-testing"))
+                                        || conn.is_hyperwan()
+                                    {
                                         log::warn!(target: "citadel", "This p2p connection requires TURN-like routing");
                                         session.send_to_kernel(channel_signal)?;
                                     } else {
@@ -591,9 +604,7 @@ pub async fn process_peer_cmd(
                                         let peer_crypto = PeerSessionCrypto::new(toolset, false);
 
                                         // create an endpoint vconn
-                                        let vconn_type = VirtualConnectionType::LocalGroupPeer(
-                                            this_cid, peer_cid,
-                                        );
+                                        let vconn_type = conn.reverse().as_virtual_connection();
                                         let (needs_turn, alice_predicted_socket_addr) =
                                             alice_nat_info.generate_proper_listener_connect_addr(
                                                 &session.local_nat_type,
@@ -642,7 +653,10 @@ pub async fn process_peer_cmd(
                                             udp_rx_opt,
                                         });
 
-                                    if needs_turn && !cfg!(feature = "localhost-testing") {
+                                    // HyperWAN peers are always relayed through both servers
+                                    if (needs_turn && !cfg!(feature = "localhost-testing"))
+                                        || conn.is_hyperwan()
+                                    {
                                         log::warn!(target: "citadel", "This p2p connection requires TURN-like routing");
                                         session.send_to_kernel(channel_signal)?;
                                     } else {
@@ -713,6 +727,18 @@ pub async fn process_peer_cmd(
                             ticket,
                         }))?;
                     Ok(PrimaryProcessorResult::Void)
+                } else if let Some(icid) = super::server::hyperwan::get_signal_icid(&signal) {
+                    super::server::hyperwan::process_signal_to_federated_server(
+                        session,
+                        signal,
+                        implicated_cid,
+                        icid,
+                        ticket,
+                        &sess_hyper_ratchet,
+                        timestamp,
+                        security_level,
+                    )
+                    .await
                 } else {
                     process_signal_command_as_server(
                         session,
@@ -941,7 +967,7 @@ async fn process_signal_command_as_server(
                     _icid,
                     _target_cid,
                 ) => {
-                    log::warn!(target: "citadel", "HyperWAN functionality not implemented");
+                    log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
                     Ok(PrimaryProcessorResult::Void)
                 }
             }
@@ -1032,7 +1058,7 @@ async fn process_signal_command_as_server(
                     _icid,
                     _target_cid,
                 ) => {
-                    log::warn!(target: "citadel", "HyperWAN functionality not yet enabled");
+                    log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
                     Ok(PrimaryProcessorResult::Void)
                 }
             }
@@ -1124,7 +1150,7 @@ async fn process_signal_command_as_server(
                     _icid,
                     _target_cid,
                 ) => {
-                    log::error!(target: "citadel", "HyperWAN functionality not implemented");
+                    log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
                     Ok(PrimaryProcessorResult::Void)
                 }
             }
@@ -1203,7 +1229,7 @@ async fn process_signal_command_as_server(
                 }
 
                 _ => {
-                    log::error!(target: "citadel", "HyperWAN functionality not implemented");
+                    log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
                     Ok(PrimaryProcessorResult::Void)
                 }
             }
@@ -1246,7 +1272,7 @@ async fn process_signal_command_as_server(
                 }
 
                 HypernodeConnectionType::HyperLANPeerToHyperWANServer(_implicated_cid, _icid) => {
-                    log::error!(target: "citadel", "HyperWAN functionality not implemented");
+                    log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
                     Ok(PrimaryProcessorResult::Void)
                 }
             }
@@ -1282,7 +1308,80 @@ async fn process_signal_command_as_server(
             }
 
             HypernodeConnectionType::HyperLANPeerToHyperWANServer(_implicated_cid, _icid) => {
-                log::error!(target: "citadel", "HyperWAN functionality not implemented");
+                log::warn!(target: "citadel", "HyperWAN signals must be relayed through a federated link");
+                Ok(PrimaryProcessorResult::Void)
+            }
+        },
+
+        PeerSignal::OfflineMessage(peer_conn_type, message) => match peer_conn_type {
+            PeerConnectionType::HyperLANPeerToHyperLANPeer(_implicated_cid, target_cid) => {
+                // the sender is always the client of this session, regardless of what the signal states
+                let implicated_cid = return_if_none!(session.implicated_cid.get());
+                let account_manager = session.account_manager.clone();
+                let persistence_handler = account_manager.get_persistence_handler();
+
+                let are_mutuals = persistence_handler
+                    .hyperlan_peers_are_mutuals(implicated_cid, &[target_cid])
+                    .await?
+                    .first()
+                    .copied()
+                    .unwrap_or(false);
+                if !are_mutuals {
+                    return reply_to_sender_err(
+                        format!("{} is not a mutual peer of {}", target_cid, implicated_cid),
+                        &sess_hyper_ratchet,
+                        ticket,
+                        timestamp,
+                        security_level,
+                    );
+                }
+
+                let message = QueuedMessage {
+                    sender_cid: implicated_cid,
+                    queued_at: forwarded_objects::unix_time(),
+                    payload: message.payload,
+                };
+                let signal = PeerSignal::OfflineMessage(peer_conn_type, message.clone());
+
+                // if the peer is online, deliver the message at once. Otherwise, queue it until the peer connects
+                if !session.session_manager.send_signal_to_peer(
+                    target_cid,
+                    ticket,
+                    signal,
+                    timestamp,
+                    security_level,
+                ) {
+                    if let Err(err) = offline_messages::enqueue_message(
+                        persistence_handler,
+                        &account_manager.get_misc_settings().message_queue_policy,
+                        &session.session_manager.recipient_locks(),
+                        target_cid,
+                        message,
+                        timestamp,
+                    )
+                    .await
+                    {
+                        return reply_to_sender_err(
+                            err.into_string(),
+                            &sess_hyper_ratchet,
+                            ticket,
+                            timestamp,
+                            security_level,
+                        );
+                    }
+                }
+
+                reply_to_sender(
+                    PeerSignal::SignalReceived(ticket),
+                    &sess_hyper_ratchet,
+                    ticket,
+                    timestamp,
+                    security_level,
+                )
+            }
+
+            PeerConnectionType::HyperLANPeerToHyperWANPeer(..) => {
+                log::warn!(target: "citadel", "Offline messages are not yet supported between HyperWAN peers");
                 Ok(PrimaryProcessorResult::Void)
             }
         },
@@ -1330,7 +1429,7 @@ async fn process_signal_command_as_server(
 
 #[inline]
 /// This just makes the repeated operation above cleaner. By itself does not send anything; must return the result of this closure directly
-fn reply_to_sender(
+pub(crate) fn reply_to_sender(
     signal: PeerSignal,
     hyper_ratchet: &StackedRatchet,
     ticket: Ticket,
@@ -1347,7 +1446,7 @@ fn reply_to_sender(
     Ok(PrimaryProcessorResult::ReplyToSender(packet))
 }
 
-fn reply_to_sender_err<E: ToString>(
+pub(crate) fn reply_to_sender_err<E: ToString>(
     err: E,
     hyper_ratchet: &StackedRatchet,
     ticket: Ticket,
@@ -0,0 +1,471 @@
+use crate::error::NetworkError;
+use crate::prelude::{PeerConnectionType, PeerResponse, PeerSignal};
+use crate::proto::packet_processor::peer::peer_cmd_packet::{reply_to_sender, reply_to_sender_err};
+use crate::proto::packet_processor::PrimaryProcessorResult;
+use crate::proto::peer::peer_crypt::{KeyExchangeProcess, PeerNatInfo};
+use crate::proto::peer::peer_layer::HypernodeConnectionType;
+use crate::proto::remote::Ticket;
+use crate::proto::session::HdpSession;
+use citadel_crypt::entropy_bank::SecurityLevel;
+use citadel_crypt::stacked_ratchet::StackedRatchet;
+
+/// Returns the icid of the federated link the signal must traverse, if the signal is a HyperWAN signal
+pub(crate) fn get_signal_icid(signal: &PeerSignal) -> Option<u64> {
+    match signal {
+        PeerSignal::PostRegister(conn, ..)
+        | PeerSignal::Deregister(conn)
+        | PeerSignal::PostConnect(conn, ..)
+        | PeerSignal::Disconnect(conn, _)
+        | PeerSignal::Kem(conn, _) => conn.get_icid(),
+        PeerSignal::GetRegisteredPeers(
+            HypernodeConnectionType::HyperLANPeerToHyperWANServer(_, icid),
+            ..,
+        )
+        | PeerSignal::GetMutuals(
+            HypernodeConnectionType::HyperLANPeerToHyperWANServer(_, icid),
+            _,
+        ) => Some(*icid),
+        _ => None,
+    }
+}
+
+/// Relays a HyperWAN signal from a local client to the adjacent server across the federated link `icid`. Errors
+/// are rebounded to the local client
+#[cfg_attr(feature = "localhost-testing", tracing::instrument(target = "citadel", skip_all, ret, err, fields(is_server = session.is_server, implicated_cid = implicated_cid, icid = icid)))]
+#[allow(clippy::too_many_arguments)]
+pub async fn process_signal_to_federated_server(
+    session: &HdpSession,
+    signal: PeerSignal,
+    implicated_cid: u64,
+    icid: u64,
+    ticket: Ticket,
+    sess_hyper_ratchet: &StackedRatchet,
+    timestamp: i64,
+    security_level: SecurityLevel,
+) -> Result<PrimaryProcessorResult, NetworkError> {
+    relay_signal_to_federated_server(
+        session,
+        signal,
+        implicated_cid,
+        icid,
+        ticket,
+        sess_hyper_ratchet,
+        timestamp,
+        security_level,
+    )
+    .await
+    .or_else(|err| reply_to_sender_err(err, sess_hyper_ratchet, ticket, timestamp, security_level))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn relay_signal_to_federated_server(
+    session: &HdpSession,
+    signal: PeerSignal,
+    implicated_cid: u64,
+    icid: u64,
+    ticket: Ticket,
+    sess_hyper_ratchet: &StackedRatchet,
+    timestamp: i64,
+    security_level: SecurityLevel,
+) -> Result<PrimaryProcessorResult, NetworkError> {
+    let session_manager = &session.session_manager;
+
+    let (signal, rebound) = match signal {
+        PeerSignal::PostRegister(conn, username, peer_username_opt, ticket_opt, resp) => {
+            check_origin(&conn, implicated_cid)?;
+            // the ticket of the initiator is kept so that the response can be matched at the initiator
+            let ticket_opt = ticket_opt.or(Some(ticket));
+            let signal =
+                PeerSignal::PostRegister(conn, username, peer_username_opt, ticket_opt, resp);
+            (signal, Some(PeerSignal::SignalReceived(ticket)))
+        }
+
+        PeerSignal::PostConnect(conn, ticket_opt, resp, endpoint_security_settings, udp_mode) => {
+            check_origin(&conn, implicated_cid)?;
+            if let Some(PeerResponse::Accept(_)) = &resp {
+                // the local client accepted the connection. Begin relaying packets across the link
+                session_manager.forge_federated_virtual_connection(
+                    implicated_cid,
+                    icid,
+                    conn.get_original_target_cid(),
+                )?;
+            }
+
+            let ticket_opt = ticket_opt.or(Some(ticket));
+            let signal = PeerSignal::PostConnect(
+                conn,
+                ticket_opt,
+                resp,
+                endpoint_security_settings,
+                udp_mode,
+            );
+            (signal, Some(PeerSignal::SignalReceived(ticket)))
+        }
+
+        PeerSignal::Kem(conn, mut kep) => {
+            check_origin(&conn, implicated_cid)?;
+            // like HyperLAN peers, the NAT info of the sender gets attached to the stage1 and stage2 signals
+            let peer_nat = session
+                .adjacent_nat_type
+                .clone()
+                .ok_or(NetworkError::InternalError("Adjacent NAT type not loaded"))?;
+            let tls_domain = session
+                .peer_only_connect_protocol
+                .get()
+                .ok_or(NetworkError::InternalError(
+                    "Peer only connect protocol not loaded",
+                ))?
+                .get_domain();
+            let peer_nat_info = PeerNatInfo {
+                peer_remote_addr_visible_from_server: session.remote_peer,
+                peer_nat,
+                tls_domain,
+            };
+
+            match &mut kep {
+                KeyExchangeProcess::Stage1(_, val) | KeyExchangeProcess::Stage2(_, val) => {
+                    *val = Some(peer_nat_info);
+                }
+
+                _ => {}
+            }
+
+            (PeerSignal::Kem(conn, kep), None)
+        }
+
+        PeerSignal::Disconnect(conn, resp) => {
+            check_origin(&conn, implicated_cid)?;
+            let target_cid = conn.get_original_target_cid();
+            if !session_manager.remove_federated_virtual_connection(
+                implicated_cid,
+                icid,
+                target_cid,
+            ) {
+                // connection may already be dc'ed from another dc attempt. Just say nothing
+                return Ok(PrimaryProcessorResult::Void);
+            }
+
+            let resp = Some(resp.unwrap_or_else(|| {
+                PeerResponse::Disconnected(format!(
+                    "Peer {} closed the virtual connection to {}",
+                    implicated_cid, target_cid
+                ))
+            }));
+            (PeerSignal::Disconnect(conn, resp), None)
+        }
+
+        PeerSignal::Deregister(conn) => {
+            check_origin(&conn, implicated_cid)?;
+            // HyperWAN registrations are only stored at the endpoints
+            let success = PeerSignal::DeregistrationSuccess(conn.get_original_target_cid());
+            (PeerSignal::Deregister(conn), Some(success))
+        }
+
+        PeerSignal::GetRegisteredPeers(hypernode_conn_type, None, limit) => {
+            if hypernode_conn_type.get_implicated_cid() != implicated_cid {
+                return Err(NetworkError::InvalidRequest(
+                    "Signal does not originate from the sender",
+                ));
+            }
+
+            (
+                PeerSignal::GetRegisteredPeers(hypernode_conn_type, None, limit),
+                None,
+            )
+        }
+
+        PeerSignal::GetMutuals(hypernode_conn_type, _) => {
+            // mutuals between HyperWAN peers are only stored at the endpoints
+            return reply_to_sender(
+                PeerSignal::GetMutuals(hypernode_conn_type, None),
+                sess_hyper_ratchet,
+                ticket,
+                timestamp,
+                security_level,
+            );
+        }
+
+        signal => {
+            log::warn!(target: "citadel", "Signal {:?} cannot be relayed to a federated server", signal);
+            return Ok(PrimaryProcessorResult::Void);
+        }
+    };
+
+    session_manager.send_signal_to_federated_server(
+        icid,
+        ticket,
+        signal,
+        timestamp,
+        security_level,
+    )?;
+
+    if let Some(rebound) = rebound {
+        reply_to_sender(
+            rebound,
+            sess_hyper_ratchet,
+            ticket,
+            timestamp,
+            security_level,
+        )
+    } else {
+        Ok(PrimaryProcessorResult::Void)
+    }
+}
+
+/// Processes a HyperWAN signal relayed by the adjacent server across the federated link `icid`, delivering
+/// it to the local client. Errors are logged instead of ending the link
+#[cfg_attr(feature = "localhost-testing", tracing::instrument(target = "citadel", skip_all, ret, err, fields(is_server = session.is_server, icid = icid)))]
+pub async fn process_signal_from_federated_server(
+    session: &HdpSession,
+    signal: PeerSignal,
+    icid: u64,
+    ticket: Ticket,
+    timestamp: i64,
+    security_level: SecurityLevel,
+) -> Result<PrimaryProcessorResult, NetworkError> {
+    if let Err(err) = deliver_signal_from_federated_server(
+        session,
+        signal,
+        icid,
+        ticket,
+        timestamp,
+        security_level,
+    )
+    .await
+    {
+        log::warn!(target: "citadel", "Unable to process signal from federated link {}: {:?}", icid, err);
+    }
+
+    Ok(PrimaryProcessorResult::Void)
+}
+
+async fn deliver_signal_from_federated_server(
+    session: &HdpSession,
+    signal: PeerSignal,
+    icid: u64,
+    ticket: Ticket,
+    timestamp: i64,
+    security_level: SecurityLevel,
+) -> Result<PrimaryProcessorResult, NetworkError> {
+    let session_manager = &session.session_manager;
+
+    if get_signal_icid(&signal) != Some(icid) {
+        log::warn!(target: "citadel", "Federated link {} relayed an invalid signal: {:?}", icid, signal);
+        return Ok(PrimaryProcessorResult::Void);
+    }
+
+    match signal {
+        PeerSignal::PostRegister(conn, username, peer_username_opt, ticket_opt, resp) => {
+            // during the request phase, the adjacent server may only know the username of the local client
+            let conn = match (&resp, peer_username_opt.as_ref()) {
+                (None, Some(peer_username)) => {
+                    let target_cid = session
+                        .account_manager
+                        .get_persistence_handler()
+                        .get_cid_by_username(peer_username.as_str());
+                    PeerConnectionType::HyperLANPeerToHyperWANPeer(
+                        conn.get_original_implicated_cid(),
+                        icid,
+                        target_cid,
+                    )
+                }
+
+                _ => conn,
+            };
+
+            let signal =
+                PeerSignal::PostRegister(conn, username, peer_username_opt, ticket_opt, resp);
+            deliver_or_rebound(session, conn, signal, ticket, timestamp, security_level).await
+        }
+
+        PeerSignal::PostConnect(conn, ticket_opt, resp, endpoint_security_settings, udp_mode) => {
+            if let Some(PeerResponse::Accept(_)) = &resp {
+                // the remote client accepted. Begin relaying packets across the link to the local client
+                session_manager.forge_federated_virtual_connection(
+                    conn.get_original_target_cid(),
+                    icid,
+                    conn.get_original_implicated_cid(),
+                )?;
+            }
+
+            let signal = PeerSignal::PostConnect(
+                conn,
+                ticket_opt,
+                resp,
+                endpoint_security_settings,
+                udp_mode,
+            );
+            deliver_or_rebound(session, conn, signal, ticket, timestamp, security_level).await
+        }
+
+        PeerSignal::Kem(conn, kep) => {
+            let target_cid = conn.get_original_target_cid();
+            if !session_manager.send_signal_to_peer(
+                target_cid,
+                ticket,
+                PeerSignal::Kem(conn, kep),
+                timestamp,
+                security_level,
+            ) {
+                log::warn!(target: "citadel", "Unable to relay federated KEM to {} (maybe not connected)", target_cid);
+            }
+
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        PeerSignal::Disconnect(conn, resp) => {
+            let target_cid = conn.get_original_target_cid();
+            let _ = session_manager.remove_federated_virtual_connection(
+                target_cid,
+                icid,
+                conn.get_original_implicated_cid(),
+            );
+            let _ = session_manager.send_signal_to_peer(
+                target_cid,
+                ticket,
+                PeerSignal::Disconnect(conn, resp),
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        PeerSignal::Deregister(conn) => {
+            let signal = PeerSignal::DeregistrationSuccess(conn.get_original_implicated_cid());
+            if let Err(err) = session_manager
+                .deliver_federated_signal(
+                    conn.get_original_target_cid(),
+                    ticket,
+                    signal,
+                    timestamp,
+                    security_level,
+                )
+                .await
+            {
+                log::warn!(target: "citadel", "Unable to deliver federated deregistration: {:?}", err);
+            }
+
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        PeerSignal::GetRegisteredPeers(hypernode_conn_type, None, limit) => {
+            // the adjacent server is requesting the list of clients registered to this node
+            let registered_local_clients = session
+                .account_manager
+                .get_registered_impersonal_cids(limit)
+                .await?
+                .unwrap_or_default();
+            let online_status = session_manager.check_online_status(&registered_local_clients);
+            let response = PeerSignal::GetRegisteredPeers(
+                hypernode_conn_type,
+                Some(PeerResponse::RegisteredCids(
+                    registered_local_clients,
+                    online_status,
+                )),
+                limit,
+            );
+
+            session_manager.send_signal_to_federated_server(
+                icid,
+                ticket,
+                response,
+                timestamp,
+                security_level,
+            )?;
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        PeerSignal::GetRegisteredPeers(hypernode_conn_type, resp, limit) => {
+            let target_cid = hypernode_conn_type.get_implicated_cid();
+            let _ = session_manager.send_signal_to_peer(
+                target_cid,
+                ticket,
+                PeerSignal::GetRegisteredPeers(hypernode_conn_type, resp, limit),
+                timestamp,
+                security_level,
+            );
+            Ok(PrimaryProcessorResult::Void)
+        }
+
+        signal => {
+            log::warn!(target: "citadel", "Federated link {} relayed an unsupported signal: {:?}", icid, signal);
+            Ok(PrimaryProcessorResult::Void)
+        }
+    }
+}
+
+/// Delivers the signal to the local client. If the client does not exist, an error is rebounded across the link
+/// to the sender
+async fn deliver_or_rebound(
+    session: &HdpSession,
+    conn: PeerConnectionType,
+    signal: PeerSignal,
+    ticket: Ticket,
+    timestamp: i64,
+    security_level: SecurityLevel,
+) -> Result<PrimaryProcessorResult, NetworkError> {
+    let session_manager = &session.session_manager;
+    if let Err(err) = session_manager
+        .deliver_federated_signal(
+            conn.get_original_target_cid(),
+            ticket,
+            signal.clone(),
+            timestamp,
+            security_level,
+        )
+        .await
+    {
+        let err = Some(PeerResponse::Err(Some(err.into_string())));
+        let rebound = match signal {
+            PeerSignal::PostRegister(conn, username, peer_username_opt, ticket_opt, None) => {
+                PeerSignal::PostRegister(
+                    conn.reverse(),
+                    username,
+                    peer_username_opt,
+                    ticket_opt,
+                    err,
+                )
+            }
+
+            PeerSignal::PostConnect(
+                conn,
+                ticket_opt,
+                None,
+                endpoint_security_settings,
+                udp_mode,
+            ) => PeerSignal::PostConnect(
+                conn.reverse(),
+                ticket_opt,
+                err,
+                endpoint_security_settings,
+                udp_mode,
+            ),
+
+            // responses are not rebounded
+            _ => return Ok(PrimaryProcessorResult::Void),
+        };
+
+        if let Some(icid) = conn.get_icid() {
+            session_manager.send_signal_to_federated_server(
+                icid,
+                ticket,
+                rebound,
+                timestamp,
+                security_level,
+            )?;
+        }
+    }
+
+    Ok(PrimaryProcessorResult::Void)
+}
+
+/// Ensures that the local client is not attempting to speak on behalf of another client
+fn check_origin(conn: &PeerConnectionType, implicated_cid: u64) -> Result<(), NetworkError> {
+    if conn.get_original_implicated_cid() == implicated_cid {
+        Ok(())
+    } else {
+        Err(NetworkError::InvalidRequest(
+            "Signal does not originate from the sender",
+        ))
+    }
+}
@@ -1,3 +1,5 @@
 ///
+pub mod hyperwan;
+///
 pub mod post_connect;
 pub mod post_register;
@@ -76,6 +76,7 @@ pub async fn process_preconnect(
                         &cnac,
                         packet,
                         &session.session_manager,
+                        &account_manager.get_misc_settings().crypto_policy,
                     ) {
                         Ok((
                             static_aux_ratchet,
@@ -114,6 +115,7 @@ pub async fn process_preconnect(
                                 &static_aux_ratchet,
                                 transfer,
                                 session.local_nat_type.clone(),
+                                session_security_settings,
                                 timestamp,
                                 security_level,
                             );
@@ -161,13 +163,16 @@ pub async fn process_preconnect(
                             "Alice constructor not loaded"
                         );
                         let implicated_cid = header.session_cid.get();
-                        if let Some((new_hyper_ratchet, nat_type)) =
+                        if let Some((new_hyper_ratchet, nat_type, session_security_settings)) =
                             validation::pre_connect::validate_syn_ack(
                                 cnac,
                                 alice_constructor,
                                 packet,
                             )
                         {
+                            // adopt any settings the server negotiated under its crypto policy
+                            state_container.session_security_settings =
+                                Some(session_security_settings);
                             // The toolset, at this point, has already been updated. The CNAC can be used to
                             //let ref drill = cnac.get_drill_blocking(None)?;
                             session.adjacent_nat_type.set_once(Some(nat_type));
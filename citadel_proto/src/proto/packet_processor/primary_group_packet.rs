@@ -8,7 +8,9 @@ use crate::proto::node_result::OutboundRequestRejected;
 use crate::proto::packet_crafter::peer_cmd::C2S_ENCRYPTION_ONLY;
 use crate::proto::peer::peer_layer::UdpMode;
 use crate::proto::session_queue_handler::QueueWorkerResult;
-use crate::proto::state_container::{FileKey, GroupKey, StateContainerInner};
+use crate::proto::state_container::{
+    FileKey, GroupKey, StateContainerInner, EXTERNAL_SERVER_UNREACHABLE,
+};
 use crate::proto::validation::group::{GroupHeader, GroupHeaderAck, WaveAck};
 use citadel_crypt::endpoint_crypto_container::{
     EndpointRatchetConstructor, KemTransferStatus, PeerSessionCrypto,
@@ -300,6 +302,14 @@ pub fn process_primary_packet(
                                             .store(Some(Instant::now()), Ordering::SeqCst);
                                     }
 
+                                    if fast_msg {
+                                        // the receiving endpoint only acknowledges messages once they are forwarded to its channel
+                                        state_container.on_message_delivered(
+                                            resp_target_cid,
+                                            header.context_info.get().into(),
+                                        );
+                                    }
+
                                     // TODO: make the below function return a result, not bools
                                     if state_container.on_group_header_ack_received(
                                         secrecy_mode,
@@ -485,7 +495,8 @@ pub(super) fn get_proper_hyper_ratchet(
 /// returns the relative `resp_target_cid`
 pub fn get_resp_target_cid(virtual_target: &VirtualConnectionType) -> Option<u64> {
     match virtual_target {
-        VirtualConnectionType::LocalGroupPeer(implicated_cid, _target_cid) => {
+        VirtualConnectionType::LocalGroupPeer(implicated_cid, _target_cid)
+        | VirtualConnectionType::ExternalGroupPeer(implicated_cid, _, _target_cid) => {
             // by logic of the network, target_cid must equal this node's CID
             // since we have entered this process function
             //debug_assert_eq!(sess_implicated_cid, target_cid);
@@ -498,8 +509,8 @@ pub fn get_resp_target_cid(virtual_target: &VirtualConnectionType) -> Option<u64
             Some(0) // ZERO, since we don't use ordinary p2p encryption
         }
 
-        _ => {
-            log::error!(target: "citadel", "HyperWAN functionality is not yet implemented");
+        VirtualConnectionType::ExternalGroupServer(..) => {
+            log::warn!(target: "citadel", "Dropping packet addressed to a HyperWAN server: {:?}", EXTERNAL_SERVER_UNREACHABLE);
             None
         }
     }
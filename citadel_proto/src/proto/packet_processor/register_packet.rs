@@ -60,6 +60,24 @@ pub async fn process_register(
                                     return Ok(PrimaryProcessorResult::ReplyToSender(err));
                                 }
 
+                                let crypto_policy =
+                                    &session.account_manager.get_misc_settings().crypto_policy;
+                                if let Err(err) = crypto_policy
+                                    .check_crypto_params(&transfer.params)
+                                    .and_then(|_| {
+                                        crypto_policy.check_security_level(transfer.security_level)
+                                    })
+                                {
+                                    // the registration does not satisfy the server's crypto policy
+                                    let err = packet_crafter::do_register::craft_failure(
+                                        algorithm,
+                                        timestamp,
+                                        err.into_string(),
+                                        header.session_cid.get(),
+                                    );
+                                    return Ok(PrimaryProcessorResult::ReplyToSender(err));
+                                }
+
                                 std::mem::drop(state_container);
 
                                 async move {
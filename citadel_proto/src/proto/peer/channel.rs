@@ -1,4 +1,6 @@
+use crate::constants::DEFAULT_MESSAGE_ACK_TIMEOUT;
 use crate::error::NetworkError;
+use crate::proto::node::SecrecyMode;
 use crate::proto::node_request::{NodeRequest, PeerCommand};
 use crate::proto::outbound_sender::{OutboundUdpSender, Sender, UnboundedReceiver};
 use crate::proto::packet_crafter::SecureProtocolPacket;
@@ -9,13 +11,16 @@ use crate::proto::session::SessionRequest;
 use crate::proto::state_container::VirtualConnectionType;
 use citadel_crypt::entropy_bank::SecurityLevel;
 use citadel_crypt::prelude::SecBuffer;
+use citadel_crypt::stacked_ratchet::StackedRatchet;
 use citadel_user::re_exports::__private::Formatter;
 use futures::task::{Context, Poll};
 use futures::Stream;
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::macros::support::Pin;
+use tokio::sync::broadcast;
 
 // 1 peer channel per virtual connection. This enables high-level communication between the [HdpServer] and the API-layer.
 #[derive(Debug)]
@@ -32,9 +37,12 @@ impl PeerChannel {
         vconn_type: VirtualConnectionType,
         channel_id: Ticket,
         security_level: SecurityLevel,
+        secrecy_mode: SecrecyMode,
         is_alive: Arc<AtomicBool>,
         receiver: UnboundedReceiver<SecBuffer>,
         to_outbound_stream: Sender<SessionRequest>,
+        exporter: KeyingMaterialExporter,
+        delivery_receipts: broadcast::Sender<DeliveryReceipt>,
     ) -> Self {
         let implicated_cid = vconn_type.get_implicated_cid();
         let recv_type = ReceivePortType::OrderedReliable;
@@ -46,6 +54,10 @@ impl PeerChannel {
             implicated_cid,
             channel_id,
             security_level,
+            secrecy_mode,
+            exporter,
+            delivery_receipts,
+            ack_timeout: DEFAULT_MESSAGE_ACK_TIMEOUT,
         };
 
         let recv_half = PeerChannelRecvHalf {
@@ -79,6 +91,27 @@ impl PeerChannel {
         self.send_half.vconn_type.try_as_peer_connection()
     }
 
+    /// Gets the secrecy mode of the session. This may differ from the requested secrecy mode if the
+    /// server's crypto policy upgraded it
+    pub fn get_secrecy_mode(&self) -> SecrecyMode {
+        self.send_half.secrecy_mode
+    }
+
+    /// Derives keying material bound to this channel. See [`KeyingMaterialExporter::export_keying_material`]
+    pub fn export_keying_material(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, NetworkError> {
+        self.send_half.export_keying_material(label, context, len)
+    }
+
+    /// Returns the exporter for this channel, which remains usable after the channel is split or dropped
+    pub fn keying_material_exporter(&self) -> &KeyingMaterialExporter {
+        &self.send_half.exporter
+    }
+
     /// In order to use the [PeerChannel] properly, split must be called in order to receive
     /// an asynchronous interface. The SendHalf implements Sink, whereas the RecvHalf implements
     /// Stream
@@ -96,6 +129,10 @@ pub struct PeerChannelSendHalf {
     vconn_type: VirtualConnectionType,
     channel_id: Ticket,
     security_level: SecurityLevel,
+    secrecy_mode: SecrecyMode,
+    exporter: KeyingMaterialExporter,
+    delivery_receipts: broadcast::Sender<DeliveryReceipt>,
+    ack_timeout: Duration,
 }
 
 impl Debug for PeerChannelSendHalf {
@@ -109,9 +146,92 @@ impl PeerChannelSendHalf {
         self.security_level = security_level;
     }
 
+    /// Sets the duration that [`Self::send_message_acked`] waits for the remote endpoint to confirm delivery
+    pub fn set_ack_timeout(&mut self, ack_timeout: Duration) {
+        self.ack_timeout = ack_timeout;
+    }
+
     /// Sends a message through the channel
     pub async fn send_message(&self, message: SecureProtocolPacket) -> Result<(), NetworkError> {
         let (ticket, packet, target, security_level) = self.get_args(message);
+        self.send_request(ticket, packet, target, security_level)
+            .await
+    }
+
+    /// Sends a message through the channel without waiting for delivery. Once the remote endpoint receives and decrypts
+    /// the message, a [`DeliveryReceipt`] with the returned ticket is emitted through [`Self::delivery_receipts`]
+    pub async fn send_message_tracked(
+        &self,
+        message: SecureProtocolPacket,
+    ) -> Result<Ticket, NetworkError> {
+        let (_, packet, target, security_level) = self.get_args(message);
+        let ticket = Ticket::from(uuid::Uuid::new_v4().as_u128());
+        self.send_request(ticket, packet, target, security_level)
+            .await?;
+        Ok(ticket)
+    }
+
+    /// Sends a message through the channel, returning once the remote endpoint confirms that it received and decrypted
+    /// the message. Returns an error if no confirmation arrives before the ack timeout (see [`Self::set_ack_timeout`])
+    pub async fn send_message_acked(
+        &self,
+        message: SecureProtocolPacket,
+    ) -> Result<(), NetworkError> {
+        // subscribe before sending to ensure the receipt cannot arrive before we begin listening
+        let mut receipts = self.delivery_receipts.subscribe();
+        let ticket = self.send_message_tracked(message).await?;
+
+        let wait_for_receipt = async move {
+            loop {
+                match receipts.recv().await {
+                    Ok(receipt) if receipt.ticket == ticket => return Ok(()),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        log::warn!(target: "citadel", "[PeerChannelSendHalf] Skipped {} delivery receipts", count);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(NetworkError::InternalError(
+                            "The channel closed before delivery was confirmed",
+                        ))
+                    }
+                }
+            }
+        };
+
+        tokio::time::timeout(self.ack_timeout, wait_for_receipt)
+            .await
+            .map_err(|_| {
+                NetworkError::Generic(format!(
+                    "Delivery of message {} was not confirmed within {:?}",
+                    ticket, self.ack_timeout
+                ))
+            })?
+    }
+
+    /// Returns a stream of receipts for messages sent via [`Self::send_message_tracked`] or [`Self::send_message_acked`].
+    /// Only receipts for messages whose delivery is confirmed after this call are yielded
+    pub fn delivery_receipts(&self) -> impl Stream<Item = DeliveryReceipt> + Send + 'static {
+        let receipts = self.delivery_receipts.subscribe();
+        futures::stream::unfold(receipts, |mut receipts| async move {
+            loop {
+                match receipts.recv().await {
+                    Ok(receipt) => return Some((receipt, receipts)),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        log::warn!(target: "citadel", "[PeerChannelSendHalf] Skipped {} delivery receipts", count);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    async fn send_request(
+        &self,
+        ticket: Ticket,
+        packet: SecureProtocolPacket,
+        target: VirtualConnectionType,
+        security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
         let request = SessionRequest::SendMessage {
             ticket,
             packet,
@@ -129,6 +249,16 @@ impl PeerChannelSendHalf {
         self.channel_id
     }
 
+    /// Derives keying material bound to this channel. See [`KeyingMaterialExporter::export_keying_material`]
+    pub fn export_keying_material(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, NetworkError> {
+        self.exporter.export_keying_material(label, context, len)
+    }
+
     #[inline]
     fn get_args(
         &self,
@@ -148,6 +278,52 @@ impl PeerChannelSendHalf {
     }
 }
 
+/// Emitted once the remote endpoint confirms that it received and decrypted a message
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DeliveryReceipt {
+    /// The ticket returned by [`PeerChannelSendHalf::send_message_tracked`]
+    pub ticket: Ticket,
+    /// The CID of the endpoint that received the message
+    pub peer_cid: u64,
+}
+
+/// Derives symmetric keys bound to a session, in the style of RFC 5705 (e.g., to encrypt a local database or an auxiliary
+/// media stream). Keys are derived from the ratchet version that was active when the channel was created, allowing both endpoints
+/// to derive identical keys regardless of any re-keying that occurs afterwards
+#[derive(Clone)]
+pub struct KeyingMaterialExporter {
+    ratchet: Option<StackedRatchet>,
+}
+
+impl KeyingMaterialExporter {
+    pub(crate) fn new(ratchet: Option<StackedRatchet>) -> Self {
+        Self { ratchet }
+    }
+
+    /// Derives `len` bytes of keying material. Both endpoints derive identical output for the same `label` and `context`,
+    /// whereas distinct labels or contexts yield independent keys
+    pub fn export_keying_material(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, NetworkError> {
+        self.ratchet
+            .as_ref()
+            .ok_or(NetworkError::InternalError(
+                "No ratchet available for exporting keying material",
+            ))?
+            .export_keying_material(label, context, len)
+            .map_err(NetworkError::from)
+    }
+}
+
+impl Debug for KeyingMaterialExporter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "KeyingMaterialExporter")
+    }
+}
+
 impl Unpin for PeerChannelRecvHalf {}
 
 /// A stream interface for receiving secure packets
@@ -193,7 +369,8 @@ impl Stream for PeerChannelRecvHalf {
 
 impl Drop for PeerChannelRecvHalf {
     fn drop(&mut self) {
-        if let VirtualConnectionType::LocalGroupPeer(local_cid, peer_cid) = self.vconn_type {
+        if let Some(peer_conn_type) = self.vconn_type.try_as_peer_connection() {
+            let local_cid = peer_conn_type.get_original_implicated_cid();
             log::trace!(target: "citadel", "[PeerChannelRecvHalf] Dropping {:?} type. Will maybe set is_alive to false if this is a tcp p2p connection", self.recv_type);
 
             let command = match self.recv_type {
@@ -201,10 +378,7 @@ impl Drop for PeerChannelRecvHalf {
                     self.is_alive.store(false, Ordering::SeqCst);
                     NodeRequest::PeerCommand(PeerCommand {
                         implicated_cid: local_cid,
-                        command: PeerSignal::Disconnect(
-                            PeerConnectionType::HyperLANPeerToHyperLANPeer(local_cid, peer_cid),
-                            None,
-                        ),
+                        command: PeerSignal::Disconnect(peer_conn_type, None),
                     })
                 }
 
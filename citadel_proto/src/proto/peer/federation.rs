@@ -0,0 +1,117 @@
+//! Server-to-server federation (HyperWAN)
+//!
+//! A federated link is an ordinary, authenticated session between two servers: the initiating server
+//! logs into an account it owns on the adjacent server, and the CID of that account (the `icid`) identifies
+//! the link on *both* ends. Since each server keys its sessions by CID, the session for `icid` is the link
+//! session regardless of which side initiated it. HyperWAN [`PeerSignal`]s are relayed across the link using
+//! the link's C2S ratchet, while proxied (endpoint-encrypted) packets are forwarded as-is through the link's
+//! primary stream.
+//!
+//! The adjacent server only treats an inbound session as a link if the username of the account is listed inside
+//! [`ServerMiscSettings::federated_servers`](citadel_user::server_misc_settings::ServerMiscSettings), since a
+//! link is trusted to speak on behalf of the clients of the adjacent server.
+//!
+//! [`PeerSignal`]: crate::proto::peer::peer_layer::PeerSignal
+use crate::proto::remote::Ticket;
+use std::collections::{HashMap, HashSet};
+
+/// Keeps track of the federated links for the local server
+#[derive(Default)]
+pub(crate) struct FederationTable {
+    /// The tickets of outbound connections that, once connected, become federated links
+    pending_outbound: HashSet<Ticket>,
+    links: HashMap<u64, FederatedLink>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// A single authenticated server-to-server session
+pub(crate) struct FederatedLink {
+    /// The CID of the account on the adjacent server used to authenticate the link
+    pub icid: u64,
+    /// True if the local node logged into the adjacent server, false if the adjacent server logged into the local node
+    pub is_initiator: bool,
+}
+
+impl FederationTable {
+    /// Marks the outbound connection with `ticket` as a future federated link
+    pub fn expect_outbound_link(&mut self, ticket: Ticket) {
+        let _ = self.pending_outbound.insert(ticket);
+    }
+
+    /// Cancels an outbound link attempt that failed before the session was upgraded
+    pub fn cancel_outbound_link(&mut self, ticket: Ticket) {
+        let _ = self.pending_outbound.remove(&ticket);
+    }
+
+    /// Called when a session is upgraded from a provisional to a protected connection. Returns true
+    /// if the session was registered as a federated link
+    ///
+    /// `trusted_inbound` should be true when the local node is the server of the session and the
+    /// authenticated account belongs to a trusted adjacent server
+    pub fn on_session_upgraded(
+        &mut self,
+        cid: u64,
+        ticket: Ticket,
+        is_server: bool,
+        trusted_inbound: bool,
+    ) -> bool {
+        let is_link = if is_server {
+            trusted_inbound
+        } else {
+            self.pending_outbound.remove(&ticket)
+        };
+
+        if is_link {
+            log::trace!(target: "citadel", "Federated link established with icid {} (initiator: {})", cid, !is_server);
+            let _ = self.links.insert(
+                cid,
+                FederatedLink {
+                    icid: cid,
+                    is_initiator: !is_server,
+                },
+            );
+        }
+
+        is_link
+    }
+
+    /// Determines if the session for `cid` is a federated link
+    pub fn is_link(&self, cid: u64) -> bool {
+        self.links.contains_key(&cid)
+    }
+
+    /// Removes the link, returning it if it existed
+    pub fn remove_link(&mut self, icid: u64) -> Option<FederatedLink> {
+        self.links.remove(&icid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::peer::federation::FederationTable;
+    use crate::proto::remote::Ticket;
+
+    #[test]
+    fn outbound_link_requires_expectation() {
+        let mut table = FederationTable::default();
+        assert!(!table.on_session_upgraded(10, Ticket(1), false, false));
+        table.expect_outbound_link(Ticket(2));
+        assert!(table.on_session_upgraded(11, Ticket(2), false, false));
+        assert!(table.is_link(11));
+        assert!(!table.is_link(10));
+        // the expectation is consumed
+        assert!(!table.on_session_upgraded(12, Ticket(2), false, false));
+    }
+
+    #[test]
+    fn inbound_link_requires_trust() {
+        let mut table = FederationTable::default();
+        table.expect_outbound_link(Ticket(1));
+        // inbound sessions never consume outbound expectations
+        assert!(!table.on_session_upgraded(10, Ticket(1), true, false));
+        assert!(table.on_session_upgraded(11, Ticket(1), true, true));
+        let link = table.remove_link(11).unwrap();
+        assert!(!link.is_initiator);
+        assert!(!table.is_link(11));
+    }
+}
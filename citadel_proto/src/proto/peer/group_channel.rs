@@ -1,5 +1,5 @@
 use crate::error::NetworkError;
-use crate::prelude::{MessageGroupKey, SecBuffer};
+use crate::prelude::{GroupRole, MessageGroupKey, SecBuffer};
 use crate::proto::outbound_sender::{Sender, UnboundedReceiver};
 use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
 use crate::proto::remote::{NodeRemote, Ticket};
@@ -71,8 +71,15 @@ impl GroupChannel {
 
 #[derive(Debug)]
 pub enum GroupBroadcastPayload {
-    Message { payload: SecBuffer, sender: u64 },
-    Event { payload: GroupBroadcast },
+    /// The sequence is assigned by the server, and is unique and increasing within the group
+    Message {
+        payload: SecBuffer,
+        sender: u64,
+        sequence: u64,
+    },
+    Event {
+        payload: GroupBroadcast,
+    },
 }
 
 pub struct GroupChannelSendHalf {
@@ -95,24 +102,33 @@ impl Debug for GroupChannelSendHalf {
 }
 
 impl GroupChannelSendHalf {
-    /// Broadcasts a message to the group
+    /// Broadcasts a message to the group. If the group is confidential, the message is sealed under the local sender key,
+    /// such that only the members of the group are able to read it
     pub async fn send_message(&self, message: SecBuffer) -> Result<(), NetworkError> {
+        // the sequence is assigned by the server
         self.send_group_command(GroupBroadcast::Message(
             self.implicated_cid,
             self.key,
+            0,
             message,
         ))
         .await
     }
 
-    /// Kicks a peer from the group. User must be owner
+    /// Requests the messages retained by the server with a sequence number greater than `since`. The messages are
+    /// delivered through the receiving half in order. Passing zero requests the entire retained history
+    pub async fn fetch_history_since(&self, since: u64) -> Result<(), NetworkError> {
+        self.send_group_command(GroupBroadcast::FetchHistory(self.key, since))
+            .await
+    }
+
+    /// Kicks a peer from the group. User must be an admin or owner, and may only kick members of a lesser role
     pub async fn kick(&self, peer: u64) -> Result<(), NetworkError> {
         self.kick_all(vec![peer]).await
     }
 
-    /// Kicks a set of peers from the group. User must be owner
+    /// Kicks a set of peers from the group. User must be an admin or owner
     pub async fn kick_all<T: Into<Vec<u64>>>(&self, peers: T) -> Result<(), NetworkError> {
-        self.permission_gate()?;
         self.send_group_command(GroupBroadcast::Kick(self.key, peers.into()))
             .await
     }
@@ -122,13 +138,42 @@ impl GroupChannelSendHalf {
         self.invite_all(vec![peer_cid]).await
     }
 
-    /// Invites all listed members to the group
+    /// Invites all listed members to the group. User must be an admin or owner
     pub async fn invite_all<T: Into<Vec<u64>>>(&self, peers: T) -> Result<(), NetworkError> {
-        self.permission_gate()?;
         self.send_group_command(GroupBroadcast::Add(self.key, peers.into()))
             .await
     }
 
+    /// Changes the role of a member. User must outrank both the member's current and new role
+    pub async fn set_role(&self, peer_cid: u64, role: GroupRole) -> Result<(), NetworkError> {
+        if role == GroupRole::Owner {
+            return Err(NetworkError::InvalidRequest(
+                "Ownership cannot be granted by changing roles",
+            ));
+        }
+
+        self.send_group_command(GroupBroadcast::SetRole(self.key, peer_cid, role))
+            .await
+    }
+
+    /// Passes ownership of the group to another member. User must be owner, and is thereafter demoted to admin
+    pub async fn transfer_ownership(&self, new_owner: u64) -> Result<(), NetworkError> {
+        self.send_group_command(GroupBroadcast::TransferOwnership(self.key, new_owner))
+            .await
+    }
+
+    /// Mutes the listed members, preventing them from posting. User must be an admin or owner
+    pub async fn mute<T: Into<Vec<u64>>>(&self, peers: T) -> Result<(), NetworkError> {
+        self.send_group_command(GroupBroadcast::SetMuted(self.key, peers.into(), true))
+            .await
+    }
+
+    /// Unmutes the listed members. User must be an admin or owner
+    pub async fn unmute<T: Into<Vec<u64>>>(&self, peers: T) -> Result<(), NetworkError> {
+        self.send_group_command(GroupBroadcast::SetMuted(self.key, peers.into(), false))
+            .await
+    }
+
     async fn send_group_command(&self, broadcast: GroupBroadcast) -> Result<(), NetworkError> {
         self.tx
             .send(SessionRequest::Group {
@@ -138,16 +183,6 @@ impl GroupChannelSendHalf {
             .await
             .map_err(|err| NetworkError::msg(err.to_string()))
     }
-
-    fn permission_gate(&self) -> Result<(), NetworkError> {
-        if self.implicated_cid == self.key.cid {
-            Ok(())
-        } else {
-            Err(NetworkError::InvalidRequest(
-                "User does not have permissions to make this call",
-            ))
-        }
-    }
 }
 
 pub struct GroupChannelRecvHalf {
@@ -1,5 +1,10 @@
+use crate::error::NetworkError;
+use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
+use citadel_crypt::sealed_object::{self, SealingKey};
+use citadel_user::serialization::SyncIO;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Formatter;
 
 /// A [MessageGroup] is a set of HyperLAN Clients communicating through the HyperLAN Server.
@@ -18,14 +23,29 @@ use std::fmt::Formatter;
 /// Axis of consent: P_0
 /// Peripheral Users: all users connected to P_0 *and* agreed to enter G(P_0)
 ///
-/// [MessageGroup]s should be seen as short-lived messaging frames. They stay alive as long as the axis of consent
+/// By default, [MessageGroup]s should be seen as short-lived messaging frames. They stay alive as long as the axis of consent
 /// keeps the group alive or disconnects from the HyperLAN Server. When P_0 leaves, users will still have local messages
-/// of the chat, but won't receive anymore chats from the group
+/// of the chat, but won't receive anymore chats from the group. If [MessageGroupOptions::persistent] is set, the group is
+/// instead stored in the backend, outliving both P_0's session and server restarts. Members may then reattach to the group
+/// each time they reconnect. The axis may also be moved: the owner may transfer ownership to another member, and if
+/// [MessageGroupOptions::auto_succession] is set, ownership passes to the most senior remaining member when the owner
+/// leaves or disconnects, rather than the group disintegrating
+///
+/// The server numbers each message relayed through the group. If [MessageGroupOptions::history_len] is nonzero, the
+/// server additionally retains the most recent messages, such that late joiners and reconnecting members may fetch the
+/// messages they missed. Retained messages are sealed under a key generated for the group, and are only opened when
+/// fetched; the history of persistent groups is stored entry by entry in its sealed form. Confidential groups retain no
+/// history, since members only hold the sender keys distributed while they are attached, and thus could not open the
+/// messages sealed before they joined or reconnected
 pub struct MessageGroup {
     // peer cid, entry (entry will contain metadata in the future)
     pub(crate) concurrent_peers: HashMap<u64, MessageGroupPeer>,
     pub(crate) pending_peers: HashMap<u64, MessageGroupPeer>,
     pub(crate) options: MessageGroupOptions,
+    pub(crate) next_sequence: u64,
+    // sequence number, sealed message
+    pub(crate) history: VecDeque<(u64, Vec<u8>)>,
+    history_key: SealingKey,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,6 +53,30 @@ pub struct MessageGroup {
 pub struct MessageGroupOptions {
     pub group_type: GroupType,
     pub id: u128,
+    /// If true, the definition, membership and pending invitations of the group are persisted in the backend
+    pub persistent: bool,
+    /// If true, ownership passes to the oldest admin (or, lacking admins, the oldest member) when the owner leaves
+    /// or disconnects. Otherwise, the group ends with the departure of the owner
+    pub auto_succession: bool,
+    /// If true, messages are end-to-end encrypted between members under sender keys, and the server relays only
+    /// ciphertext. Members must have connected with each other at least once to exchange sender keys
+    pub confidential: bool,
+    /// The number of most recent messages retained by the server for members to fetch. If zero, no history is kept.
+    /// Must be zero for confidential groups
+    pub history_len: usize,
+}
+
+impl MessageGroupOptions {
+    /// Returns an error if the options are contradictory
+    pub fn validate(&self) -> Result<(), NetworkError> {
+        if self.confidential && self.history_len != 0 {
+            return Err(NetworkError::InternalError(
+                "Confidential groups cannot retain history, since members could not open past messages",
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for MessageGroupOptions {
@@ -40,6 +84,10 @@ impl Default for MessageGroupOptions {
         Self {
             group_type: GroupType::Private,
             id: uuid::Uuid::new_v4().as_u128(),
+            persistent: false,
+            auto_succession: false,
+            confidential: false,
+            history_len: 0,
         }
     }
 }
@@ -53,14 +101,244 @@ pub enum GroupType {
     Private,
 }
 
-/// TODO: Attributed data (e.g., permissions)
+/// The role of a member within a [MessageGroup]. Roles are ordered by privilege, such that a member may only moderate
+/// members of a strictly lesser role
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+pub enum GroupRole {
+    /// May receive, but not post, messages
+    Observer,
+    /// May receive and post messages
+    Member,
+    /// May additionally invite, kick, and mute members, as well as change the role of members below admin
+    Admin,
+    /// The creator of the group, or the member to whom ownership was passed. May additionally appoint admins and end the group
+    Owner,
+}
+
+impl GroupRole {
+    /// Returns true if the role permits posting messages to the group
+    pub fn can_post(&self) -> bool {
+        *self >= GroupRole::Member
+    }
+
+    /// Returns true if the role permits inviting, kicking, and muting members
+    pub fn can_moderate(&self) -> bool {
+        *self >= GroupRole::Admin
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct MessageGroupPeer {
-    #[allow(dead_code)]
     pub peer_cid: u64,
+    pub role: GroupRole,
+    /// Muted members may not post messages, regardless of their role
+    pub muted: bool,
+    /// The order in which the peer entered the group. Lower values denote longer-standing members
+    pub joined: u64,
+}
+
+impl MessageGroupPeer {
+    pub fn new(peer_cid: u64, role: GroupRole) -> Self {
+        Self {
+            peer_cid,
+            role,
+            muted: false,
+            joined: 0,
+        }
+    }
+
+    /// Returns true if the peer may currently post messages to the group
+    pub fn can_post(&self) -> bool {
+        self.role.can_post() && !self.muted
+    }
+}
+
+/// The form in which the definition and membership of persistent [MessageGroup]s are stored in the backend. Since the
+/// sequence number and history change with every message, they are stored separately (see
+/// [MessageGroup::restore_sequence])
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PersistedMessageGroup {
+    options: MessageGroupOptions,
+    concurrent_peers: Vec<MessageGroupPeer>,
+    pending_peers: Vec<MessageGroupPeer>,
+    history_key: SealingKey,
+}
+
+impl From<&MessageGroup> for PersistedMessageGroup {
+    fn from(group: &MessageGroup) -> Self {
+        Self {
+            options: group.options.clone(),
+            concurrent_peers: group.concurrent_peers.values().cloned().collect(),
+            pending_peers: group.pending_peers.values().cloned().collect(),
+            history_key: group.history_key,
+        }
+    }
+}
+
+impl From<PersistedMessageGroup> for MessageGroup {
+    fn from(group: PersistedMessageGroup) -> Self {
+        let into_peers = |peers: Vec<MessageGroupPeer>| {
+            peers
+                .into_iter()
+                .map(|peer| (peer.peer_cid, peer))
+                .collect()
+        };
+
+        let mut message_group = Self::new(group.options);
+        message_group.concurrent_peers = into_peers(group.concurrent_peers);
+        message_group.pending_peers = into_peers(group.pending_peers);
+        message_group.history_key = group.history_key;
+        message_group
+    }
+}
+
+impl MessageGroup {
+    /// Creates a group without any members
+    pub(crate) fn new(options: MessageGroupOptions) -> Self {
+        let mut history_key = SealingKey::default();
+        rand::thread_rng().fill_bytes(&mut history_key);
+
+        Self {
+            concurrent_peers: HashMap::new(),
+            pending_peers: HashMap::new(),
+            history: VecDeque::with_capacity(options.history_len),
+            next_sequence: 1,
+            history_key,
+            options,
+        }
+    }
+
+    /// Assigns the next sequence number to the message, retaining it in the history if enabled. Sequence numbers
+    /// begin at 1
+    pub(crate) fn record_message(&mut self, message: GroupBroadcast) -> GroupBroadcast {
+        let sequence = self.next_sequence;
+        let message = message.with_sequence(sequence);
+        self.next_sequence += 1;
+
+        if self.options.history_len != 0 {
+            match self.seal_history_entry(&message) {
+                Ok(sealed) => {
+                    if self.history.len() >= self.options.history_len {
+                        let _ = self.history.pop_front();
+                    }
+
+                    self.history.push_back((sequence, sealed));
+                }
+
+                Err(err) => {
+                    log::warn!(target: "citadel", "Unable to retain message in group history: {:?}", err)
+                }
+            }
+        }
+
+        message
+    }
+
+    /// Returns the most recently retained message, in its sealed form
+    pub(crate) fn latest_history_entry(&self) -> Option<&(u64, Vec<u8>)> {
+        self.history.back()
+    }
+
+    /// Returns the retained messages with a sequence number greater than `since`, in order
+    pub(crate) fn history_since(&self, since: u64) -> Vec<GroupBroadcast> {
+        self.history
+            .iter()
+            .filter(|(sequence, _)| *sequence > since)
+            .filter_map(|(sequence, sealed)| match self.open_history_entry(sealed) {
+                Ok(message) => Some(message),
+                Err(err) => {
+                    log::warn!(target: "citadel", "Unable to open retained message {}: {:?}", sequence, err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Restores the sequence number and the sealed history entries of a persistent group loaded from the backend. Only
+    /// the most recent entries within the history length are kept
+    pub(crate) fn restore_sequence(
+        &mut self,
+        next_sequence: u64,
+        mut history: Vec<(u64, Vec<u8>)>,
+    ) {
+        history.sort_unstable_by_key(|(sequence, _)| *sequence);
+        let skip = history.len().saturating_sub(self.options.history_len);
+        self.next_sequence = next_sequence;
+        self.history = history.into_iter().skip(skip).collect();
+    }
+
+    fn seal_history_entry(&self, message: &GroupBroadcast) -> Result<Vec<u8>, NetworkError> {
+        let serialized = message
+            .serialize_to_vector()
+            .map_err(|err| NetworkError::Generic(err.into_string()))?;
+        sealed_object::seal(&self.history_key, &serialized)
+            .map_err(|err| NetworkError::Generic(err.into_string()))
+    }
+
+    fn open_history_entry(&self, sealed: &[u8]) -> Result<GroupBroadcast, NetworkError> {
+        let serialized = sealed_object::open(&self.history_key, sealed)
+            .map_err(|err| NetworkError::Generic(err.into_string()))?;
+        GroupBroadcast::deserialize_from_owned_vector(serialized)
+            .map_err(|err| NetworkError::Generic(err.into_string()))
+    }
+
+    /// Inserts the peer into the set of concurrent peers, ordering it after every current member
+    pub(crate) fn insert_concurrent_peer(&mut self, mut peer: MessageGroupPeer) {
+        peer.joined = self
+            .concurrent_peers
+            .values()
+            .map(|peer| peer.joined + 1)
+            .max()
+            .unwrap_or(0);
+        let _ = self.concurrent_peers.insert(peer.peer_cid, peer);
+    }
+
+    /// Returns the cid of the current owner, if any
+    pub(crate) fn owner(&self) -> Option<u64> {
+        self.concurrent_peers
+            .values()
+            .find(|peer| peer.role == GroupRole::Owner)
+            .map(|peer| peer.peer_cid)
+    }
+
+    /// Moves ownership from `owner` to the oldest admin, or, lacking admins, the oldest member. The previous owner
+    /// is demoted to admin. Returns the new owner, or None if no member is eligible
+    pub(crate) fn promote_successor(&mut self, owner: u64) -> Option<u64> {
+        let successor = self
+            .concurrent_peers
+            .values()
+            .filter(|peer| peer.peer_cid != owner && peer.role.can_post())
+            .max_by_key(|peer| (peer.role, std::cmp::Reverse(peer.joined)))?
+            .peer_cid;
+        self.transfer_ownership(owner, successor)
+            .then_some(successor)
+    }
+
+    /// Moves ownership from `owner` to `new_owner`, demoting the previous owner to admin. Returns false if `owner` is
+    /// not the owner, or if `new_owner` is not a member of the group
+    pub(crate) fn transfer_ownership(&mut self, owner: u64, new_owner: u64) -> bool {
+        if owner == new_owner
+            || self.owner() != Some(owner)
+            || !self.concurrent_peers.contains_key(&new_owner)
+        {
+            return false;
+        }
+
+        if let Some(peer) = self.concurrent_peers.get_mut(&owner) {
+            peer.role = GroupRole::Admin;
+        }
+
+        if let Some(peer) = self.concurrent_peers.get_mut(&new_owner) {
+            peer.role = GroupRole::Owner;
+        }
+
+        true
+    }
 }
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct MessageGroupKey {
+    /// The cid of the peer that created the group. The key stays fixed even if ownership is later transferred
     pub cid: u64,
     pub mgid: u128,
 }
@@ -82,3 +360,163 @@ impl std::fmt::Display for MessageGroupKey {
         write!(f, "[{}:{}]", self.cid, self.mgid)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        GroupRole, MessageGroup, MessageGroupKey, MessageGroupOptions, MessageGroupPeer,
+        PersistedMessageGroup,
+    };
+    use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
+    use citadel_user::serialization::SyncIO;
+
+    fn sequences(history: Vec<GroupBroadcast>) -> Vec<u64> {
+        history
+            .iter()
+            .filter_map(GroupBroadcast::sequence)
+            .collect()
+    }
+
+    /// Creates a group whose members joined in the given order
+    fn group_of(peers: &[(u64, GroupRole)]) -> MessageGroup {
+        let mut group = MessageGroup::new(MessageGroupOptions::default());
+        for (peer_cid, role) in peers {
+            group.insert_concurrent_peer(MessageGroupPeer::new(*peer_cid, *role));
+        }
+
+        group
+    }
+
+    fn role_of(group: &MessageGroup, peer_cid: u64) -> Option<GroupRole> {
+        group.concurrent_peers.get(&peer_cid).map(|peer| peer.role)
+    }
+
+    #[test]
+    fn transfer_ownership() {
+        let mut group = group_of(&[
+            (1, GroupRole::Owner),
+            (2, GroupRole::Member),
+            (3, GroupRole::Observer),
+        ]);
+        // only the owner may transfer ownership, and only to another member
+        assert!(!group.transfer_ownership(2, 3));
+        assert!(!group.transfer_ownership(1, 1));
+        assert!(!group.transfer_ownership(1, 4));
+        assert_eq!(group.owner(), Some(1));
+
+        assert!(group.transfer_ownership(1, 2));
+        assert_eq!(group.owner(), Some(2));
+        assert_eq!(role_of(&group, 1), Some(GroupRole::Admin));
+        assert!(!group.transfer_ownership(1, 3));
+    }
+
+    #[test]
+    fn promote_successor_ordering() {
+        // the oldest admin is preferred over older members
+        let mut group = group_of(&[
+            (1, GroupRole::Owner),
+            (2, GroupRole::Member),
+            (3, GroupRole::Admin),
+            (4, GroupRole::Admin),
+        ]);
+        assert_eq!(group.promote_successor(1), Some(3));
+        assert_eq!(group.owner(), Some(3));
+        assert_eq!(role_of(&group, 1), Some(GroupRole::Admin));
+
+        // lacking admins, the oldest member is chosen. Observers are never eligible
+        let mut group = group_of(&[
+            (1, GroupRole::Owner),
+            (2, GroupRole::Observer),
+            (3, GroupRole::Member),
+            (4, GroupRole::Member),
+        ]);
+        assert_eq!(group.promote_successor(1), Some(3));
+
+        let mut group = group_of(&[(1, GroupRole::Owner), (2, GroupRole::Observer)]);
+        assert_eq!(group.promote_successor(1), None);
+        assert_eq!(group.owner(), Some(1));
+    }
+
+    #[test]
+    fn history_is_numbered_and_bounded() {
+        let mut group = MessageGroup::new(MessageGroupOptions {
+            history_len: 2,
+            ..Default::default()
+        });
+        let key = MessageGroupKey::new(1, 2);
+
+        for _ in 0..3 {
+            let message = group.record_message(GroupBroadcast::Message(1, key, 0, "hi".into()));
+            assert!(message.sequence().is_some());
+        }
+
+        assert_eq!(sequences(group.history_since(0)), vec![2, 3]);
+        assert_eq!(sequences(group.history_since(2)), vec![3]);
+    }
+
+    #[test]
+    fn history_is_sealed() {
+        let mut group = MessageGroup::new(MessageGroupOptions {
+            history_len: 1,
+            ..Default::default()
+        });
+        let key = MessageGroupKey::new(1, 2);
+        let _ = group.record_message(GroupBroadcast::Message(1, key, 0, "secret".into()));
+
+        let (sequence, sealed) = group.latest_history_entry().unwrap();
+        assert_eq!(*sequence, 1);
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        match group.history_since(0).as_slice() {
+            [GroupBroadcast::Message(1, _, 1, message)] => assert_eq!(message.as_ref(), b"secret"),
+            _ => panic!("Unexpected history"),
+        }
+    }
+
+    #[test]
+    fn persisted_group_keeps_sequence_and_history() {
+        let mut group = MessageGroup::new(MessageGroupOptions {
+            persistent: true,
+            history_len: 2,
+            ..Default::default()
+        });
+        let key = MessageGroupKey::new(1, group.options.id);
+        let mut stored = Vec::new();
+        for _ in 0..3 {
+            let _ = group.record_message(GroupBroadcast::Message(1, key, 0, "hi".into()));
+            stored.push(group.latest_history_entry().cloned().unwrap());
+        }
+
+        // the definition is stored apart from the sequence number and the history entries
+        let serialized = PersistedMessageGroup::from(&group)
+            .serialize_to_vector()
+            .unwrap();
+        let mut reloaded = MessageGroup::from(
+            PersistedMessageGroup::deserialize_from_owned_vector(serialized).unwrap(),
+        );
+        // entries may be loaded in any order, and entries beyond the history length are dropped
+        stored.reverse();
+        reloaded.restore_sequence(group.next_sequence, stored);
+        assert_eq!(sequences(reloaded.history_since(0)), vec![2, 3]);
+
+        // numbering continues where it left off, rather than restarting at 1
+        let message = reloaded.record_message(GroupBroadcast::Message(1, key, 0, "hi".into()));
+        assert_eq!(message.sequence(), Some(4));
+        assert_eq!(sequences(reloaded.history_since(0)), vec![3, 4]);
+    }
+
+    #[test]
+    fn confidential_groups_retain_no_history() {
+        let options = MessageGroupOptions {
+            confidential: true,
+            history_len: 1,
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+        assert!(MessageGroupOptions {
+            history_len: 0,
+            ..options
+        }
+        .validate()
+        .is_ok());
+    }
+}
@@ -12,3 +12,11 @@ pub mod message_group;
 pub mod p2p_conn_handler;
 
 pub(crate) mod hole_punch_compat_sink_stream;
+
+pub(crate) mod federation;
+
+/// Queues messages on the server for offline peers
+pub(crate) mod offline_messages;
+
+/// End-to-end encryption of messages within confidential groups
+pub(crate) mod sender_keys;
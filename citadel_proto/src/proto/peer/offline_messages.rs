@@ -0,0 +1,183 @@
+//! Messages queued by the server for offline peers
+//!
+//! The sender seals each message under the key it shares with the recipient (see `forwarded_objects`), such that the
+//! server is unable to read the messages it queues. The server persists a queue for each recipient, subject to the
+//! [`MessageQueuePolicy`], and delivers the queue, in order, through a [`MailboxDelivery`](crate::proto::node_result::MailboxDelivery)
+//! once the recipient next connects. Queued messages are removed only once the recipient acknowledges the packet that
+//! carries them, such that a recipient that disconnects while connecting receives the messages upon a later connection.
+//! If the recipient is online when a message arrives, the message is delivered at once
+use crate::error::NetworkError;
+use crate::proto::forwarded_objects;
+use crate::proto::misc::recipient_locks::RecipientLocks;
+use crate::proto::peer::peer_layer::QueuedMessage;
+use citadel_crypt::sealed_object;
+use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_user::backend::PersistenceHandler;
+use citadel_user::serialization::SyncIO;
+use citadel_user::server_misc_settings::MessageQueuePolicy;
+
+/// The byte map key under which the server stores the queue of each recipient. Each sub key begins with the time at
+/// which the message was queued, such that sorting the sub keys yields the order in which the messages were sent
+const OFFLINE_MESSAGES: &str = "offline_messages";
+
+/// Run by the sender. Seals the payload for `peer_cid`
+pub(crate) async fn seal_message<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    peer_cid: u64,
+    message: QueuedMessage,
+) -> Result<QueuedMessage, NetworkError> {
+    let key =
+        forwarded_objects::load_offline_delivery_key(persistence_handler, implicated_cid, peer_cid)
+            .await?;
+    let payload = sealed_object::seal(&key, &message.payload)
+        .map_err(|err| NetworkError::Generic(err.into_string()))?;
+
+    Ok(QueuedMessage {
+        sender_cid: implicated_cid,
+        payload,
+        ..message
+    })
+}
+
+/// Run by the recipient. Opens each message, in order. Messages that cannot be opened are dropped
+pub(crate) async fn open_messages<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    messages: Vec<QueuedMessage>,
+) -> Vec<QueuedMessage> {
+    let mut opened = Vec::with_capacity(messages.len());
+
+    for message in messages {
+        let res = forwarded_objects::open_sealed(
+            persistence_handler,
+            implicated_cid,
+            message.sender_cid,
+            &message.payload,
+        )
+        .await;
+
+        match res {
+            Ok(payload) => opened.push(QueuedMessage { payload, ..message }),
+            Err(err) => {
+                log::warn!(target: "citadel", "Dropping message from {} that could not be opened: {:?}", message.sender_cid, err)
+            }
+        }
+    }
+
+    opened
+}
+
+/// Run by the server. Appends the message to the queue of `recipient_cid`, after removing any expired messages. The
+/// recipient's lock is held while the queue is updated, such that concurrent senders cannot together exceed the limit
+pub(crate) async fn enqueue_message<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    policy: &MessageQueuePolicy,
+    recipient_locks: &RecipientLocks,
+    recipient_cid: u64,
+    message: QueuedMessage,
+    timestamp: i64,
+) -> Result<(), NetworkError> {
+    if message.payload.len() > policy.max_message_len {
+        return Err(NetworkError::msg(
+            "The message exceeds the maximum length of queued messages",
+        ));
+    }
+
+    let _queue = recipient_locks.lock(recipient_cid).await;
+    let queued = persistence_handler
+        .get_byte_map_values_by_key(recipient_cid, 0, OFFLINE_MESSAGES)
+        .await?;
+    let mut queue_len = 0;
+
+    for (sub_key, value) in queued {
+        if is_expired(&value, policy, message.queued_at) {
+            let _ = persistence_handler
+                .remove_byte_map_value(recipient_cid, 0, OFFLINE_MESSAGES, &sub_key)
+                .await?;
+        } else {
+            queue_len += 1;
+        }
+    }
+
+    if queue_len >= policy.max_messages_per_recipient {
+        return Err(NetworkError::Generic(format!(
+            "The message queue of {} is full",
+            recipient_cid
+        )));
+    }
+
+    let sub_key = format!("{:020}-{}", timestamp, uuid::Uuid::new_v4());
+    let value = message
+        .serialize_to_vector()
+        .map_err(|err| NetworkError::Generic(err.into_string()))?;
+    let _ = persistence_handler
+        .store_byte_map_value(recipient_cid, 0, OFFLINE_MESSAGES, &sub_key, value)
+        .await?;
+    Ok(())
+}
+
+/// Run by the server once `recipient_cid` connects. Returns the unexpired messages in the order they were sent, each
+/// alongside the sub key under which it remains queued until the recipient acknowledges it. Expired messages are removed
+pub(crate) async fn load_message_queue<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    policy: &MessageQueuePolicy,
+    recipient_locks: &RecipientLocks,
+    recipient_cid: u64,
+) -> Result<Vec<(String, QueuedMessage)>, NetworkError> {
+    let _queue = recipient_locks.lock(recipient_cid).await;
+    let now = forwarded_objects::unix_time();
+    let mut queued = persistence_handler
+        .get_byte_map_values_by_key(recipient_cid, 0, OFFLINE_MESSAGES)
+        .await?
+        .into_iter()
+        .collect::<Vec<_>>();
+    queued.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut messages = Vec::with_capacity(queued.len());
+
+    for (sub_key, value) in queued {
+        match QueuedMessage::deserialize_from_owned_vector(value) {
+            Ok(message) if !is_message_expired(&message, policy, now) => {
+                messages.push((sub_key, message))
+            }
+            _ => {
+                let _ = persistence_handler
+                    .remove_byte_map_value(recipient_cid, 0, OFFLINE_MESSAGES, &sub_key)
+                    .await?;
+            }
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Run by the server once `recipient_cid` acknowledges the messages loaded by [`load_message_queue`]. Removes the
+/// messages from the queue
+pub(crate) async fn acknowledge_messages<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    recipient_locks: &RecipientLocks,
+    recipient_cid: u64,
+    sub_keys: Vec<String>,
+) -> Result<(), NetworkError> {
+    let _queue = recipient_locks.lock(recipient_cid).await;
+    for sub_key in sub_keys {
+        let _ = persistence_handler
+            .remove_byte_map_value(recipient_cid, 0, OFFLINE_MESSAGES, &sub_key)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn is_expired(value: &[u8], policy: &MessageQueuePolicy, now: u64) -> bool {
+    QueuedMessage::deserialize_from_vector(value)
+        .map(|message| is_message_expired(&message, policy, now))
+        .unwrap_or(true)
+}
+
+fn is_message_expired(message: &QueuedMessage, policy: &MessageQueuePolicy, now: u64) -> bool {
+    message
+        .queued_at
+        .saturating_add(policy.time_to_live.as_secs())
+        <= now
+}
@@ -3,13 +3,15 @@ use crate::macros::SyncContextRequirements;
 use crate::proto::misc::session_security_settings::SessionSecuritySettings;
 use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
 use crate::proto::peer::message_group::{
-    GroupType, MessageGroup, MessageGroupKey, MessageGroupOptions, MessageGroupPeer,
+    GroupRole, GroupType, MessageGroup, MessageGroupKey, MessageGroupOptions, MessageGroupPeer,
+    PersistedMessageGroup,
 };
 use crate::proto::peer::peer_crypt::KeyExchangeProcess;
 use crate::proto::remote::Ticket;
 use crate::proto::state_container::VirtualConnectionType;
 use citadel_user::backend::utils::VirtualObjectMetadata;
 use citadel_user::backend::PersistenceHandler;
+use citadel_user::misc::AccountError;
 use citadel_user::serialization::SyncIO;
 use futures::task::AtomicWaker;
 use futures::task::{Context, Poll};
@@ -44,10 +46,19 @@ struct SharedInner {
     delay_queue: DelayQueue<(u64, Ticket)>,
 }
 
-// message group byte map key layout:
-// implicated cid = implicated cid -> key = (u128."concurrent" OR u128."pending") -> u64 (peer cid)
+// persistent message group byte map key layout:
+// implicated cid = owner cid -> peer cid = 0 -> key = MESSAGE_GROUPS -> sub key = mgid -> PersistedMessageGroup
+// implicated cid = owner cid -> peer cid = 0 -> key = MESSAGE_GROUP_SEQUENCES -> sub key = mgid -> next sequence number
+// implicated cid = owner cid -> peer cid = 0 -> key = MESSAGE_GROUP_HISTORY.mgid -> sub key = sequence -> sealed message
 
 const MAILBOX: &str = "mailbox";
+const MESSAGE_GROUPS: &str = "message_groups";
+const MESSAGE_GROUP_SEQUENCES: &str = "message_group_sequences";
+const MESSAGE_GROUP_HISTORY: &str = "message_group_history";
+
+fn message_group_history_key(mgid: u128) -> String {
+    format!("{}.{}", MESSAGE_GROUP_HISTORY, mgid)
+}
 
 #[derive(Clone)]
 pub struct HyperNodePeerLayer {
@@ -103,6 +114,134 @@ impl HyperNodePeerLayer {
         }
     }
 
+    /// Loads the persistent [MessageGroup]s of every registered client from the backend. This should be called once
+    /// when the server starts
+    pub async fn load_persistent_message_groups(&self) -> Result<(), NetworkError> {
+        let pers = { self.inner.read().await.persistence_handler.clone() };
+        let owners = pers
+            .get_registered_impersonal_cids(None)
+            .await?
+            .unwrap_or_default();
+        let mut loaded = HashMap::new();
+
+        for owner in owners {
+            let mut groups = pers
+                .get_byte_map_values_by_key(owner, 0, MESSAGE_GROUPS)
+                .await?
+                .into_values()
+                .filter_map(|group| {
+                    PersistedMessageGroup::deserialize_from_owned_vector(group).ok()
+                })
+                .map(MessageGroup::from)
+                .map(|group| (group.options.id, group))
+                .collect::<HashMap<u128, MessageGroup>>();
+            let sequences = pers
+                .get_byte_map_values_by_key(owner, 0, MESSAGE_GROUP_SEQUENCES)
+                .await?;
+
+            for (mgid, group) in groups.iter_mut() {
+                let next_sequence = sequences
+                    .get(&mgid.to_string())
+                    .and_then(|next_sequence| next_sequence.as_slice().try_into().ok())
+                    .map(u64::from_be_bytes)
+                    .unwrap_or(1);
+                let history = pers
+                    .get_byte_map_values_by_key(owner, 0, &message_group_history_key(*mgid))
+                    .await?
+                    .into_iter()
+                    .filter_map(|(sequence, sealed)| Some((sequence.parse().ok()?, sealed)))
+                    .collect();
+                group.restore_sequence(next_sequence, history);
+            }
+
+            if !groups.is_empty() {
+                log::trace!(target: "citadel", "Loaded {} persistent message groups for {}", groups.len(), owner);
+                let _ = loaded.insert(owner, groups);
+            }
+        }
+
+        self.inner.write().await.message_groups.extend(loaded);
+        Ok(())
+    }
+
+    /// Stores the sequence number of the group, along with the message just retained in its history, if the group is
+    /// persistent. The message that fell out of the history is removed
+    async fn persist_message_group_sequence(
+        &self,
+        key: MessageGroupKey,
+        next_sequence: u64,
+        entry: Option<(u64, Vec<u8>)>,
+        history_len: usize,
+    ) {
+        let pers = { self.inner.read().await.persistence_handler.clone() };
+        let res: Result<(), AccountError> = async {
+            let _ = pers
+                .store_byte_map_value(
+                    key.cid,
+                    0,
+                    MESSAGE_GROUP_SEQUENCES,
+                    &key.mgid.to_string(),
+                    next_sequence.to_be_bytes().to_vec(),
+                )
+                .await?;
+
+            if let Some((sequence, sealed)) = entry {
+                let history_key = message_group_history_key(key.mgid);
+                let _ = pers
+                    .store_byte_map_value(key.cid, 0, &history_key, &sequence.to_string(), sealed)
+                    .await?;
+
+                if sequence > history_len as u64 {
+                    let expired = sequence - history_len as u64;
+                    let _ = pers
+                        .remove_byte_map_value(key.cid, 0, &history_key, &expired.to_string())
+                        .await?;
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = res {
+            log::warn!(target: "citadel", "Unable to persist the sequence of message group {}: {:?}", key, err);
+        }
+    }
+
+    /// Stores the latest definition and membership of the group in the backend, if the group is persistent
+    async fn persist_message_group(&self, key: MessageGroupKey) {
+        let (pers, group) = {
+            let this = self.inner.read().await;
+            let group = this
+                .message_groups
+                .get(&key.cid)
+                .and_then(|map| map.get(&key.mgid))
+                .filter(|group| group.options.persistent)
+                .map(PersistedMessageGroup::from);
+            (this.persistence_handler.clone(), group)
+        };
+
+        if let Some(group) = group {
+            let res = match group.serialize_to_vector() {
+                Ok(serialized) => pers
+                    .store_byte_map_value(
+                        key.cid,
+                        0,
+                        MESSAGE_GROUPS,
+                        &key.mgid.to_string(),
+                        serialized,
+                    )
+                    .await
+                    .map(|_| ()),
+                Err(err) => Err(err),
+            };
+
+            if let Err(err) = res {
+                log::warn!(target: "citadel", "Unable to persist message group {}: {:?}", key, err);
+            }
+        }
+    }
+
     #[allow(unused_results)]
     /// This should be called during the DO_CONNECT phase
     pub async fn register_peer(&self, cid: u64) -> Result<Option<MailboxTransfer>, NetworkError> {
@@ -141,12 +280,43 @@ impl HyperNodePeerLayer {
         }
     }
 
-    /// Cleans up the internal entries
+    /// Cleans up the internal entries. Non-persistent groups owned by `implicated_cid` either end, or, if permitted,
+    /// pass to a successor. Returns the key and new owner of each group that changed hands
     #[allow(unused_results)]
-    pub async fn on_session_shutdown(&self, implicated_cid: u64) -> Result<(), NetworkError> {
+    pub async fn on_session_shutdown(
+        &self,
+        implicated_cid: u64,
+    ) -> Result<Vec<(MessageGroupKey, u64)>, NetworkError> {
+        let mut successions = Vec::new();
         let pers = {
             let mut this = self.inner.write().await;
-            this.message_groups.remove(&implicated_cid);
+            for (owner_key, groups) in this.message_groups.iter_mut() {
+                groups.retain(|mgid, group| {
+                    // persistent groups outlive the session of their owner
+                    if group.options.persistent || group.owner() != Some(implicated_cid) {
+                        return true;
+                    }
+
+                    if group.options.auto_succession {
+                        if let Some(new_owner) = group.promote_successor(implicated_cid) {
+                            successions.push((MessageGroupKey::new(*owner_key, *mgid), new_owner));
+                            return true;
+                        }
+                    }
+
+                    false
+                });
+            }
+
+            if this
+                .message_groups
+                .get(&implicated_cid)
+                .map(|groups| groups.is_empty())
+                .unwrap_or(false)
+            {
+                this.message_groups.remove(&implicated_cid);
+            }
+
             this.inner.write().observed_postings.remove(&implicated_cid);
             this.persistence_handler.clone()
         };
@@ -154,7 +324,7 @@ impl HyperNodePeerLayer {
         let _ = pers
             .remove_byte_map_values_by_key(implicated_cid, 0, MAILBOX)
             .await?;
-        Ok(())
+        Ok(successions)
     }
 
     /// Creates a new [MessageGroup]. Returns the key upon completion
@@ -165,31 +335,44 @@ impl HyperNodePeerLayer {
         initial_peers: &Vec<u64>,
         options: MessageGroupOptions,
     ) -> Option<MessageGroupKey> {
+        let key = self
+            .insert_new_message_group(implicated_cid, initial_peers, options)
+            .await?;
+        self.persist_message_group(key).await;
+        Some(key)
+    }
+
+    #[allow(unused_results)]
+    async fn insert_new_message_group(
+        &self,
+        implicated_cid: u64,
+        initial_peers: &Vec<u64>,
+        options: MessageGroupOptions,
+    ) -> Option<MessageGroupKey> {
+        if let Err(err) = options.validate() {
+            log::warn!(target: "citadel", "Refusing to create group for {}: {:?}", implicated_cid, err);
+            return None;
+        }
+
         let mut this = self.inner.write().await;
         let map = this.message_groups.get_mut(&implicated_cid)?;
         let mgid = options.id;
         if map.len() <= u8::MAX as usize {
             if let std::collections::hash_map::Entry::Vacant(e) = map.entry(mgid) {
-                let mut message_group = MessageGroup {
-                    concurrent_peers: HashMap::new(),
-                    pending_peers: HashMap::with_capacity(initial_peers.len()),
-                    options,
-                };
+                let mut message_group = MessageGroup::new(options);
                 // insert peers into the pending_peers map to allow/process AcceptMembership signals
                 for peer_cid in initial_peers {
                     let peer_cid = *peer_cid;
                     message_group
                         .pending_peers
-                        .insert(peer_cid, MessageGroupPeer { peer_cid });
+                        .insert(peer_cid, MessageGroupPeer::new(peer_cid, GroupRole::Member));
                 }
 
                 // add the implicated_cid to the concurrent peers
-                message_group.concurrent_peers.insert(
+                message_group.insert_concurrent_peer(MessageGroupPeer::new(
                     implicated_cid,
-                    MessageGroupPeer {
-                        peer_cid: implicated_cid,
-                    },
-                );
+                    GroupRole::Owner,
+                ));
 
                 e.insert(message_group);
                 Some(MessageGroupKey {
@@ -207,40 +390,77 @@ impl HyperNodePeerLayer {
 
     /// removes a [MessageGroup]
     pub async fn remove_message_group(&self, key: MessageGroupKey) -> Option<MessageGroup> {
-        let mut this = self.inner.write().await;
-        let map = this.message_groups.get_mut(&key.cid)?;
-        map.remove(&key.mgid)
+        let (pers, group) = {
+            let mut this = self.inner.write().await;
+            let map = this.message_groups.get_mut(&key.cid)?;
+            let group = map.remove(&key.mgid)?;
+            (this.persistence_handler.clone(), group)
+        };
+
+        if group.options.persistent {
+            let mgid = key.mgid.to_string();
+            let res: Result<(), AccountError> = async {
+                let _ = pers
+                    .remove_byte_map_value(key.cid, 0, MESSAGE_GROUPS, &mgid)
+                    .await?;
+                let _ = pers
+                    .remove_byte_map_value(key.cid, 0, MESSAGE_GROUP_SEQUENCES, &mgid)
+                    .await?;
+                let _ = pers
+                    .remove_byte_map_values_by_key(key.cid, 0, &message_group_history_key(key.mgid))
+                    .await?;
+                Ok(())
+            }
+            .await;
+
+            if let Err(err) = res {
+                log::warn!(target: "citadel", "Unable to remove persisted message group {}: {:?}", key, err);
+            }
+        }
+
+        Some(group)
     }
 
     #[allow(unused_results)]
     pub async fn add_pending_peers_to_group(&self, key: MessageGroupKey, peers: Vec<u64>) {
-        let mut this = self.inner.write().await;
-        if let Some(map) = this.message_groups.get_mut(&key.cid) {
-            if let Some(entry) = map.get_mut(&key.mgid) {
-                for peer_cid in peers {
-                    let insert = MessageGroupPeer { peer_cid };
-                    entry.pending_peers.insert(peer_cid, insert);
+        {
+            let mut this = self.inner.write().await;
+            if let Some(map) = this.message_groups.get_mut(&key.cid) {
+                if let Some(entry) = map.get_mut(&key.mgid) {
+                    for peer_cid in peers {
+                        let insert = MessageGroupPeer::new(peer_cid, GroupRole::Member);
+                        entry.pending_peers.insert(peer_cid, insert);
+                    }
+                } else {
+                    log::warn!(target: "citadel", "Unable to locate MGID. Peers will not be able to accept");
                 }
-            } else {
-                log::warn!(target: "citadel", "Unable to locate MGID. Peers will not be able to accept");
             }
         }
+
+        self.persist_message_group(key).await;
     }
 
     #[allow(unused_results)]
     // Upgrades a peer from pending to concurrent (enabled reception of broadcasts)
     pub async fn upgrade_peer_in_group(&self, key: MessageGroupKey, peer_cid: u64) -> bool {
-        let mut this = self.inner.write().await;
-        if let Some(map) = this.message_groups.get_mut(&key.cid) {
-            if let Some(entry) = map.get_mut(&key.mgid) {
-                if let Some(peer) = entry.pending_peers.remove(&peer_cid) {
-                    entry.concurrent_peers.insert(peer_cid, peer);
-                    return true;
-                }
-            }
+        let upgraded = {
+            let mut this = self.inner.write().await;
+            this.message_groups
+                .get_mut(&key.cid)
+                .and_then(|map| map.get_mut(&key.mgid))
+                .and_then(|entry| {
+                    let peer = entry.pending_peers.remove(&peer_cid)?;
+                    entry.insert_concurrent_peer(peer);
+                    Some(())
+                })
+                .is_some()
+        };
+
+        if upgraded {
+            self.persist_message_group(key).await;
         }
 
-        false
+        upgraded
     }
 
     /// Determines if the [MessageGroupKey] maps to a [MessageGroup]
@@ -276,21 +496,27 @@ impl HyperNodePeerLayer {
         key: MessageGroupKey,
         mut peers: Vec<u64>,
     ) -> Result<(Vec<u64>, Vec<u64>), ()> {
-        let mut this = self.inner.write().await;
-        let map = this.message_groups.get_mut(&key.cid).ok_or(())?;
-        let message_group = map.get_mut(&key.mgid).ok_or(())?;
-        //let mut peers_removed = Vec::new();
-        // Keep all the peers that were not removed. I.e., if the remove operation returns None
-        // then that peer wasn't removed and hence should stay in the vec
-        peers.retain(|peer| message_group.concurrent_peers.remove(peer).is_some());
-
-        let peers_remaining = message_group
-            .concurrent_peers
-            .keys()
-            .cloned()
-            .collect::<Vec<u64>>();
+        let peers_remaining = {
+            let mut this = self.inner.write().await;
+            let map = this.message_groups.get_mut(&key.cid).ok_or(())?;
+            let message_group = map.get_mut(&key.mgid).ok_or(())?;
+            //let mut peers_removed = Vec::new();
+            // Keep all the peers that were not removed. I.e., if the remove operation returns None
+            // then that peer wasn't removed and hence should stay in the vec
+            peers.retain(|peer| message_group.concurrent_peers.remove(peer).is_some());
+
+            message_group
+                .concurrent_peers
+                .keys()
+                .cloned()
+                .collect::<Vec<u64>>()
+        };
         let peers_successfully_removed = peers;
 
+        if !peers_successfully_removed.is_empty() {
+            self.persist_message_group(key).await;
+        }
+
         Ok((peers_successfully_removed, peers_remaining))
     }
 
@@ -308,19 +534,318 @@ impl HyperNodePeerLayer {
         )
     }
 
+    /// Lists the groups in which `cid` is a member. Pending invitations are excluded
+    pub async fn list_message_group_memberships_for(&self, cid: u64) -> Vec<MessageGroupKey> {
+        self.inner
+            .read()
+            .await
+            .message_groups
+            .iter()
+            .flat_map(|(owner, map)| {
+                map.iter()
+                    .filter(|(_, group)| group.concurrent_peers.contains_key(&cid))
+                    .map(|(mgid, _)| MessageGroupKey {
+                        cid: *owner,
+                        mgid: *mgid,
+                    })
+            })
+            .collect()
+    }
+
+    /// returns true if `peer_cid` is a member of the group (e.g., when reattaching to a persistent group)
+    /// returns None if the key does not match an active group
+    pub async fn is_message_group_member(
+        &self,
+        key: MessageGroupKey,
+        peer_cid: u64,
+    ) -> Option<bool> {
+        let this = self.inner.read().await;
+        let group = this.message_groups.get(&key.cid)?.get(&key.mgid)?;
+        Some(group.concurrent_peers.contains_key(&peer_cid))
+    }
+
+    /// Returns the members of the group if the group is confidential. Returns None if the group does not exist, or if
+    /// the group is not confidential
+    pub async fn get_confidential_group_members(&self, key: MessageGroupKey) -> Option<Vec<u64>> {
+        let this = self.inner.read().await;
+        let group = this.message_groups.get(&key.cid)?.get(&key.mgid)?;
+        if group.options.confidential {
+            Some(group.concurrent_peers.keys().copied().collect())
+        } else {
+            None
+        }
+    }
+
+    /// Assigns the next sequence number of the group to the message, retaining it in the history of the group if enabled.
+    /// Returns None if the group does not exist
+    pub async fn record_message_group_message(
+        &self,
+        key: MessageGroupKey,
+        message: GroupBroadcast,
+    ) -> Option<GroupBroadcast> {
+        let (message, persisted) = {
+            let mut this = self.inner.write().await;
+            let group = this.message_groups.get_mut(&key.cid)?.get_mut(&key.mgid)?;
+            let message = group.record_message(message);
+            let persisted = group.options.persistent.then(|| {
+                let entry = group
+                    .latest_history_entry()
+                    .filter(|(sequence, _)| Some(*sequence) == message.sequence())
+                    .cloned();
+                (group.next_sequence, entry, group.options.history_len)
+            });
+            (message, persisted)
+        };
+
+        // persistent groups must not reuse sequence numbers after a restart. Only the sequence number and the newly
+        // retained message are written, rather than the entire group
+        if let Some((next_sequence, entry, history_len)) = persisted {
+            self.persist_message_group_sequence(key, next_sequence, entry, history_len)
+                .await;
+        }
+
+        Some(message)
+    }
+
+    /// Returns the retained messages of the group with a sequence number greater than `since`. Returns None if the
+    /// group does not exist. Peers that are not members receive no messages
+    pub async fn get_message_group_history(
+        &self,
+        key: MessageGroupKey,
+        peer_cid: u64,
+        since: u64,
+    ) -> Option<Vec<GroupBroadcast>> {
+        let this = self.inner.read().await;
+        let group = this.message_groups.get(&key.cid)?.get(&key.mgid)?;
+        if group.concurrent_peers.contains_key(&peer_cid) {
+            Some(group.history_since(since))
+        } else {
+            Some(Vec::new())
+        }
+    }
+
+    /// Returns the role of `peer_cid` within the group. Returns None if the group does not exist, or if the peer is not a member
+    pub async fn get_member_role(&self, key: MessageGroupKey, peer_cid: u64) -> Option<GroupRole> {
+        let this = self.inner.read().await;
+        let group = this.message_groups.get(&key.cid)?.get(&key.mgid)?;
+        group.concurrent_peers.get(&peer_cid).map(|peer| peer.role)
+    }
+
+    /// Returns true if `peer_cid` is a member that may currently post messages to the group
+    pub async fn can_post_to_message_group(&self, key: MessageGroupKey, peer_cid: u64) -> bool {
+        let this = self.inner.read().await;
+        this.message_groups
+            .get(&key.cid)
+            .and_then(|map| map.get(&key.mgid))
+            .and_then(|group| group.concurrent_peers.get(&peer_cid))
+            .map(|peer| peer.can_post())
+            .unwrap_or(false)
+    }
+
+    /// Returns true if `peer_cid` is a member of the group with a role of at least `required`
+    pub async fn member_has_role(
+        &self,
+        key: MessageGroupKey,
+        peer_cid: u64,
+        required: GroupRole,
+    ) -> bool {
+        self.get_member_role(key, peer_cid)
+            .await
+            .map(|role| role >= required)
+            .unwrap_or(false)
+    }
+
+    /// Returns the subset of `peers` that `requester_cid` may kick from the group. The requester must be able to
+    /// moderate, and may only kick members of a strictly lesser role
+    pub async fn filter_kickable_peers(
+        &self,
+        key: MessageGroupKey,
+        requester_cid: u64,
+        peers: Vec<u64>,
+    ) -> Vec<u64> {
+        let requester_role = self
+            .get_member_role(key, requester_cid)
+            .await
+            .filter(GroupRole::can_moderate);
+        match requester_role {
+            Some(role) => self.filter_peers_outranked_by(key, peers, role).await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the subset of `peers` that are members of a role strictly lesser than `role`
+    pub async fn filter_peers_outranked_by(
+        &self,
+        key: MessageGroupKey,
+        mut peers: Vec<u64>,
+        role: GroupRole,
+    ) -> Vec<u64> {
+        let this = self.inner.read().await;
+        if let Some(group) = this
+            .message_groups
+            .get(&key.cid)
+            .and_then(|map| map.get(&key.mgid))
+        {
+            peers.retain(|peer_cid| {
+                group
+                    .concurrent_peers
+                    .get(peer_cid)
+                    .map(|peer| peer.role < role)
+                    .unwrap_or(false)
+            });
+            peers
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Changes the role of `peer_cid` to `role`. The requester must be able to moderate, and must outrank both the
+    /// current and the new role of the peer. Returns true if the role was changed
+    pub async fn set_member_role(
+        &self,
+        key: MessageGroupKey,
+        requester_cid: u64,
+        peer_cid: u64,
+        role: GroupRole,
+    ) -> bool {
+        let changed = {
+            let mut this = self.inner.write().await;
+            this.message_groups
+                .get_mut(&key.cid)
+                .and_then(|map| map.get_mut(&key.mgid))
+                .and_then(|group| {
+                    let requester_role = group.concurrent_peers.get(&requester_cid)?.role;
+                    let peer = group.concurrent_peers.get_mut(&peer_cid)?;
+                    if requester_role.can_moderate()
+                        && peer.role < requester_role
+                        && role < requester_role
+                    {
+                        peer.role = role;
+                        Some(())
+                    } else {
+                        None
+                    }
+                })
+                .is_some()
+        };
+
+        if changed {
+            self.persist_message_group(key).await;
+        }
+
+        changed
+    }
+
+    /// Mutes or unmutes each peer in `peers` outranked by the requester, who must be able to moderate. Returns the
+    /// peers that were altered
+    pub async fn set_members_muted(
+        &self,
+        key: MessageGroupKey,
+        requester_cid: u64,
+        mut peers: Vec<u64>,
+        muted: bool,
+    ) -> Vec<u64> {
+        {
+            let mut this = self.inner.write().await;
+            let group = this
+                .message_groups
+                .get_mut(&key.cid)
+                .and_then(|map| map.get_mut(&key.mgid));
+            let requester_role = group
+                .as_ref()
+                .and_then(|group| group.concurrent_peers.get(&requester_cid))
+                .map(|peer| peer.role)
+                .filter(GroupRole::can_moderate);
+
+            match (group, requester_role) {
+                (Some(group), Some(requester_role)) => {
+                    peers.retain(|peer_cid| {
+                        if let Some(peer) = group.concurrent_peers.get_mut(peer_cid) {
+                            if peer.role < requester_role {
+                                peer.muted = muted;
+                                return true;
+                            }
+                        }
+
+                        false
+                    });
+                }
+
+                _ => peers.clear(),
+            }
+        }
+
+        if !peers.is_empty() {
+            self.persist_message_group(key).await;
+        }
+
+        peers
+    }
+
+    /// Moves ownership of the group from `owner_cid` to `new_owner`, demoting the previous owner to admin. Returns
+    /// true if `owner_cid` was the owner and `new_owner` is a member of the group
+    pub async fn transfer_message_group_ownership(
+        &self,
+        key: MessageGroupKey,
+        owner_cid: u64,
+        new_owner: u64,
+    ) -> bool {
+        let transferred = {
+            let mut this = self.inner.write().await;
+            this.message_groups
+                .get_mut(&key.cid)
+                .and_then(|map| map.get_mut(&key.mgid))
+                .map(|group| group.transfer_ownership(owner_cid, new_owner))
+                .unwrap_or(false)
+        };
+
+        if transferred {
+            self.persist_message_group(key).await;
+        }
+
+        transferred
+    }
+
+    /// Called before the owner leaves the group. If the group permits automatic succession and an eligible member
+    /// exists, ownership passes to that member, who is returned. Otherwise, returns None, and the group should be ended
+    pub async fn promote_message_group_successor(
+        &self,
+        key: MessageGroupKey,
+        owner_cid: u64,
+    ) -> Option<u64> {
+        let new_owner = {
+            let mut this = self.inner.write().await;
+            let group = this.message_groups.get_mut(&key.cid)?.get_mut(&key.mgid)?;
+            if group.options.auto_succession {
+                group.promote_successor(owner_cid)?
+            } else {
+                return None;
+            }
+        };
+
+        self.persist_message_group(key).await;
+        Some(new_owner)
+    }
+
     /// returns true if auto-accepted, false if requires the owner to accept
     /// returns None if the key does not match an active group
     pub async fn request_join(&self, peer_cid: u64, key: MessageGroupKey) -> Option<bool> {
-        let mut write = self.inner.write().await;
-        let group = write.message_groups.get_mut(&key.cid)?.get_mut(&key.mgid)?;
-        if group.options.group_type == GroupType::Public {
-            let _ = group
-                .concurrent_peers
-                .insert(peer_cid, MessageGroupPeer { peer_cid });
-            Some(true)
-        } else {
-            Some(false)
+        let accepted = {
+            let mut write = self.inner.write().await;
+            let group = write.message_groups.get_mut(&key.cid)?.get_mut(&key.mgid)?;
+            if group.options.group_type == GroupType::Public {
+                group.insert_concurrent_peer(MessageGroupPeer::new(peer_cid, GroupRole::Member));
+                true
+            } else {
+                false
+            }
+        };
+
+        if accepted {
+            self.persist_message_group(key).await;
         }
+
+        Some(accepted)
     }
 
     /// returns true if added successfully, or false if not (mailbox may be overloaded)
@@ -560,6 +1085,8 @@ pub enum PeerSignal {
     SignalReceived(Ticket),
     // for key-exchange
     Kem(PeerConnectionType, KeyExchangeProcess),
+    // implicated_cid, icid, target cid. Queued by the server if the target is offline
+    OfflineMessage(PeerConnectionType, QueuedMessage),
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
@@ -623,6 +1150,20 @@ impl PeerConnectionType {
         }
     }
 
+    /// Returns the CID of the federated link between the two servers, if this is a HyperWAN connection
+    pub fn get_icid(&self) -> Option<u64> {
+        match self {
+            PeerConnectionType::HyperLANPeerToHyperLANPeer(..) => None,
+            PeerConnectionType::HyperLANPeerToHyperWANPeer(_implicated_cid, icid, _target_cid) => {
+                Some(*icid)
+            }
+        }
+    }
+
+    pub fn is_hyperwan(&self) -> bool {
+        self.get_icid().is_some()
+    }
+
     pub fn as_virtual_connection(self) -> VirtualConnectionType {
         match self {
             PeerConnectionType::HyperLANPeerToHyperLANPeer(implicated_cid, target_cid) => {
@@ -702,6 +1243,18 @@ impl From<PeerSignal> for Vec<u8> {
 #[derive(Serialize, Deserialize, Debug)]
 pub enum MailboxTransfer {
     Signals(Vec<PeerSignal>),
+    /// Messages sent while the recipient was offline, in the order they were sent
+    Messages(Vec<QueuedMessage>),
+}
+
+/// A message sent to a peer who may be offline. While in transit and queued by the server, the payload is sealed for the
+/// recipient, and thus cannot be read by the server. The payload is opened before the message is delivered to the kernel
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedMessage {
+    pub sender_cid: u64,
+    /// Unix time, in seconds, at which the server received the message
+    pub queued_at: u64,
+    pub payload: Vec<u8>,
 }
 
 impl From<Vec<PeerSignal>> for MailboxTransfer {
@@ -709,3 +1262,377 @@ impl From<Vec<PeerSignal>> for MailboxTransfer {
         MailboxTransfer::Signals(signals)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::peer::message_group::{GroupRole, MessageGroupKey, MessageGroupOptions};
+    use crate::proto::peer::peer_layer::HyperNodePeerLayer;
+    use citadel_user::account_manager::AccountManager;
+    use citadel_user::backend::BackendType;
+
+    const OWNER: u64 = 1;
+    const ADMIN: u64 = 2;
+    const MEMBER: u64 = 3;
+    const OBSERVER: u64 = 4;
+    const OUTSIDER: u64 = 5;
+
+    /// Creates a group in memory with an owner, an admin, a member, and an observer, who joined in that order
+    async fn group_with_roles(
+        options: MessageGroupOptions,
+    ) -> (HyperNodePeerLayer, MessageGroupKey) {
+        let acc_mgr = AccountManager::new(BackendType::InMemory, None, None, None)
+            .await
+            .unwrap();
+        let peer_layer = HyperNodePeerLayer::new(acc_mgr.get_persistence_handler().clone());
+        let _ = peer_layer.register_peer(OWNER).await.unwrap();
+        let key = peer_layer
+            .create_new_message_group(OWNER, &vec![ADMIN, MEMBER, OBSERVER], options)
+            .await
+            .unwrap();
+
+        for peer_cid in [ADMIN, MEMBER, OBSERVER] {
+            assert!(peer_layer.upgrade_peer_in_group(key, peer_cid).await);
+        }
+
+        assert!(
+            peer_layer
+                .set_member_role(key, OWNER, ADMIN, GroupRole::Admin)
+                .await
+        );
+        assert!(
+            peer_layer
+                .set_member_role(key, OWNER, OBSERVER, GroupRole::Observer)
+                .await
+        );
+        (peer_layer, key)
+    }
+
+    #[tokio::test]
+    async fn role_gate() {
+        let (peer_layer, key) = group_with_roles(MessageGroupOptions::default()).await;
+        assert!(
+            peer_layer
+                .member_has_role(key, OWNER, GroupRole::Owner)
+                .await
+        );
+        assert!(
+            peer_layer
+                .member_has_role(key, ADMIN, GroupRole::Admin)
+                .await
+        );
+        assert!(
+            !peer_layer
+                .member_has_role(key, ADMIN, GroupRole::Owner)
+                .await
+        );
+        assert!(
+            !peer_layer
+                .member_has_role(key, MEMBER, GroupRole::Admin)
+                .await
+        );
+        assert!(
+            peer_layer
+                .member_has_role(key, OBSERVER, GroupRole::Observer)
+                .await
+        );
+        assert!(
+            !peer_layer
+                .member_has_role(key, OUTSIDER, GroupRole::Observer)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn set_role_requires_outranking_both_roles() {
+        let (peer_layer, key) = group_with_roles(MessageGroupOptions::default()).await;
+        // members may not moderate
+        assert!(
+            !peer_layer
+                .set_member_role(key, MEMBER, OBSERVER, GroupRole::Member)
+                .await
+        );
+        // admins may neither appoint their equals nor demote their superiors
+        assert!(
+            !peer_layer
+                .set_member_role(key, ADMIN, MEMBER, GroupRole::Admin)
+                .await
+        );
+        assert!(
+            !peer_layer
+                .set_member_role(key, ADMIN, OWNER, GroupRole::Member)
+                .await
+        );
+        assert!(
+            peer_layer
+                .set_member_role(key, ADMIN, OBSERVER, GroupRole::Member)
+                .await
+        );
+        assert_eq!(
+            peer_layer.get_member_role(key, OBSERVER).await,
+            Some(GroupRole::Member)
+        );
+    }
+
+    #[tokio::test]
+    async fn kick_requires_outranking_the_peer() {
+        let (peer_layer, key) = group_with_roles(MessageGroupOptions::default()).await;
+        let everyone = vec![OWNER, ADMIN, MEMBER, OBSERVER, OUTSIDER];
+        assert!(peer_layer
+            .filter_kickable_peers(key, MEMBER, everyone.clone())
+            .await
+            .is_empty());
+        assert!(peer_layer
+            .filter_kickable_peers(key, OUTSIDER, everyone.clone())
+            .await
+            .is_empty());
+        assert_eq!(
+            peer_layer
+                .filter_kickable_peers(key, ADMIN, everyone.clone())
+                .await,
+            vec![MEMBER, OBSERVER]
+        );
+        assert_eq!(
+            peer_layer.filter_kickable_peers(key, OWNER, everyone).await,
+            vec![ADMIN, MEMBER, OBSERVER]
+        );
+    }
+
+    #[tokio::test]
+    async fn muted_members_cannot_post() {
+        let (peer_layer, key) = group_with_roles(MessageGroupOptions::default()).await;
+        assert!(peer_layer.can_post_to_message_group(key, MEMBER).await);
+        assert!(!peer_layer.can_post_to_message_group(key, OBSERVER).await);
+        assert!(!peer_layer.can_post_to_message_group(key, OUTSIDER).await);
+
+        // members may not mute, and admins may only mute those they outrank
+        assert!(peer_layer
+            .set_members_muted(key, MEMBER, vec![OBSERVER], true)
+            .await
+            .is_empty());
+        assert_eq!(
+            peer_layer
+                .set_members_muted(key, ADMIN, vec![OWNER, MEMBER], true)
+                .await,
+            vec![MEMBER]
+        );
+        assert!(!peer_layer.can_post_to_message_group(key, MEMBER).await);
+        assert!(peer_layer.can_post_to_message_group(key, OWNER).await);
+        // muting leaves the role intact
+        assert_eq!(
+            peer_layer.get_member_role(key, MEMBER).await,
+            Some(GroupRole::Member)
+        );
+
+        assert_eq!(
+            peer_layer
+                .set_members_muted(key, OWNER, vec![MEMBER], false)
+                .await,
+            vec![MEMBER]
+        );
+        assert!(peer_layer.can_post_to_message_group(key, MEMBER).await);
+    }
+
+    #[tokio::test]
+    async fn owner_shutdown_passes_ownership_to_successor() {
+        let options = MessageGroupOptions {
+            auto_succession: true,
+            ..Default::default()
+        };
+        let (peer_layer, key) = group_with_roles(options).await;
+
+        // the departure of other members leaves ownership intact
+        assert!(peer_layer
+            .on_session_shutdown(MEMBER)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            peer_layer.on_session_shutdown(OWNER).await.unwrap(),
+            vec![(key, ADMIN)]
+        );
+        assert!(peer_layer.message_group_exists(key).await);
+        assert_eq!(
+            peer_layer.get_member_role(key, ADMIN).await,
+            Some(GroupRole::Owner)
+        );
+        assert!(
+            !peer_layer
+                .member_has_role(key, OWNER, GroupRole::Owner)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn owner_shutdown_ends_group_without_succession() {
+        let (peer_layer, key) = group_with_roles(MessageGroupOptions::default()).await;
+        assert!(peer_layer
+            .on_session_shutdown(OWNER)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!peer_layer.message_group_exists(key).await);
+    }
+
+    #[cfg(feature = "filesystem")]
+    async fn register_client(acc_mgr: &AccountManager, username: &str) -> u64 {
+        use citadel_crypt::prelude::{ConstructorOpts, SecBuffer};
+        use citadel_crypt::stacked_ratchet::constructor::{
+            BobToAliceTransferType, StackedRatchetConstructor,
+        };
+        use citadel_pqcrypto::algorithm_dictionary::{EncryptionAlgorithm, KemAlgorithm};
+        use citadel_user::auth::proposed_credentials::ProposedCredentials;
+        use citadel_user::prelude::ConnectionInfo;
+
+        let cid = acc_mgr
+            .get_persistence_handler()
+            .get_cid_by_username(username);
+        let opts = ConstructorOpts::new_vec_init(
+            Some(KemAlgorithm::Kyber + EncryptionAlgorithm::AES_GCM_256_SIV),
+            1,
+        );
+        let mut alice = StackedRatchetConstructor::new_alice(opts.clone(), cid, 0, None).unwrap();
+        let bob = StackedRatchetConstructor::new_bob(cid, 0, opts, alice.stage0_alice().unwrap())
+            .unwrap();
+        alice
+            .stage1_alice(BobToAliceTransferType::Default(bob.stage0_bob().unwrap()))
+            .unwrap();
+        let conn_info = ConnectionInfo {
+            addr: "127.0.0.1:12345".parse().unwrap(),
+        };
+        let creds =
+            ProposedCredentials::new_register("Test User", username, SecBuffer::from("password"))
+                .await
+                .unwrap();
+
+        acc_mgr
+            .register_impersonal_hyperlan_client_network_account(
+                conn_info,
+                creds,
+                bob.finish().unwrap(),
+            )
+            .await
+            .unwrap()
+            .get_cid()
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[tokio::test]
+    async fn persistent_group_survives_restart() {
+        use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
+        use crate::proto::peer::peer_layer::message_group_history_key;
+        use itertools::Itertools;
+
+        let mut home = std::env::temp_dir();
+        home.push(format!("citadel-peer-layer-{}/", uuid::Uuid::new_v4()));
+        let backend = BackendType::filesystem(home.display().to_string());
+
+        let (owner, member, key) = {
+            let acc_mgr = AccountManager::new(backend.clone(), None, None, None)
+                .await
+                .unwrap();
+            let owner = register_client(&acc_mgr, "group_owner").await;
+            let member = register_client(&acc_mgr, "group_member").await;
+            let peer_layer = HyperNodePeerLayer::new(acc_mgr.get_persistence_handler().clone());
+
+            let _ = peer_layer.register_peer(owner).await.unwrap();
+            let options = MessageGroupOptions {
+                persistent: true,
+                history_len: 2,
+                ..Default::default()
+            };
+            let key = peer_layer
+                .create_new_message_group(owner, &vec![member], options)
+                .await
+                .unwrap();
+            assert!(peer_layer.upgrade_peer_in_group(key, member).await);
+
+            for idx in 0..3 {
+                let message = format!("secret message {}", idx);
+                let _ = peer_layer
+                    .record_message_group_message(
+                        key,
+                        GroupBroadcast::Message(owner, key, 0, message.into()),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            // only the retained messages are stored, and only in their sealed form
+            let stored = acc_mgr
+                .get_persistence_handler()
+                .get_byte_map_values_by_key(owner, 0, &message_group_history_key(key.mgid))
+                .await
+                .unwrap();
+            assert_eq!(stored.keys().sorted().collect::<Vec<_>>(), vec!["2", "3"]);
+            assert!(stored
+                .values()
+                .all(|sealed| !sealed.windows(6).any(|window| window == b"secret")));
+
+            // the group outlives the session of its owner
+            let _ = peer_layer.on_session_shutdown(owner).await.unwrap();
+            assert!(peer_layer.message_group_exists(key).await);
+            (owner, member, key)
+        };
+
+        // restart the server: both the accounts and the groups are loaded anew from the filesystem
+        let acc_mgr = AccountManager::new(backend, None, None, None)
+            .await
+            .unwrap();
+        let peer_layer = HyperNodePeerLayer::new(acc_mgr.get_persistence_handler().clone());
+        assert!(!peer_layer.message_group_exists(key).await);
+        peer_layer.load_persistent_message_groups().await.unwrap();
+        assert!(peer_layer.message_group_exists(key).await);
+
+        // the member reattaches upon reconnecting
+        let _ = peer_layer.register_peer(member).await.unwrap();
+        assert_eq!(
+            peer_layer.list_message_group_memberships_for(member).await,
+            vec![key]
+        );
+        assert_eq!(
+            peer_layer.is_message_group_member(key, member).await,
+            Some(true)
+        );
+        assert_eq!(
+            peer_layer.get_member_role(key, owner).await,
+            Some(GroupRole::Owner)
+        );
+        assert!(peer_layer.can_post_to_message_group(key, member).await);
+        assert_eq!(
+            peer_layer
+                .is_message_group_member(key, owner ^ member)
+                .await,
+            Some(false)
+        );
+
+        // the retained messages can still be opened, and numbering continues where it left off
+        let history = peer_layer
+            .get_message_group_history(key, member, 0)
+            .await
+            .unwrap();
+        let history = history
+            .into_iter()
+            .map(|message| match message {
+                GroupBroadcast::Message(_, _, sequence, message) => (
+                    sequence,
+                    String::from_utf8(message.as_ref().to_vec()).unwrap(),
+                ),
+                _ => panic!("Unexpected history entry"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                (2, "secret message 1".to_string()),
+                (3, "secret message 2".to_string())
+            ]
+        );
+        let message = peer_layer
+            .record_message_group_message(key, GroupBroadcast::Message(member, key, 0, "hi".into()))
+            .await
+            .unwrap();
+        assert_eq!(message.sequence(), Some(4));
+
+        let _ = std::fs::remove_dir_all(home);
+    }
+}
@@ -0,0 +1,292 @@
+//! Sender-key end-to-end encryption for confidential message groups
+//!
+//! Each member of a confidential group seals its messages under a sender key known only to the members of the group.
+//! The member distributes its sender key to each other member by sealing it under the key it shares with that member
+//! (see `forwarded_objects`), such that the server relays both the sender keys and the messages without being able to
+//! read either. Whenever the membership of the group changes, the server asks each member to rotate its sender key, and
+//! each member discards the sender keys of departed members. Thus, departed members cannot read later messages, and new
+//! members cannot read earlier messages. Each sender key is named by a random id rather than a counter, since a member
+//! that reconnects starts anew with an empty set of keys, and its next key must still supersede the key it held before.
+//!
+//! Each message authenticates the group, the cid of its sender, and the id of the sender key it was sealed under. The
+//! server stamps the cid of the member that actually sent each message, such that a member cannot relabel a message as
+//! having been sent by another member, nor pass off a message sealed under another member's key as its own.
+//!
+//! Since sender keys are sealed under the pairwise key, a member must have connected with another member at least once
+//! beforehand in order to exchange sender keys with it. Messages from senders whose key is unknown are dropped
+use crate::error::NetworkError;
+use crate::proto::forwarded_objects;
+use crate::proto::peer::message_group::MessageGroupKey;
+use citadel_crypt::sealed_object::{self, SealingKey};
+use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_user::backend::PersistenceHandler;
+use citadel_user::serialization::SyncIO;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A sender key, along with the random id that names it
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub(crate) struct SenderKey {
+    id: u64,
+    key: SealingKey,
+}
+
+/// The sender keys held by a member of a confidential group. For each peer, the previous key is retained alongside the
+/// current key, such that messages sent just before a rotation may still be opened
+#[derive(Default)]
+pub(crate) struct GroupSenderKeys {
+    local: Option<SenderKey>,
+    peers: HashMap<u64, (SenderKey, Option<SenderKey>)>,
+}
+
+impl GroupSenderKeys {
+    /// Replaces the local sender key with a new key, and discards the keys of any peers no longer listed in `members`.
+    /// Returns the new local sender key
+    pub fn rotate(&mut self, members: &[u64]) -> SenderKey {
+        let mut rng = rand::thread_rng();
+        let mut key = SealingKey::default();
+        rng.fill_bytes(&mut key);
+        let local = SenderKey {
+            id: rng.next_u64(),
+            key,
+        };
+
+        self.local = Some(local);
+        self.peers.retain(|peer_cid, _| members.contains(peer_cid));
+        local
+    }
+
+    /// Stores the sender key of `peer_cid` as its current key, demoting the key previously held to the previous key.
+    /// Keys already held are ignored
+    pub fn insert_peer_key(&mut self, peer_cid: u64, key: SenderKey) {
+        let existing = self.peers.get(&peer_cid);
+        let already_held = existing
+            .map(|(current, previous)| {
+                std::iter::once(current)
+                    .chain(previous.as_ref())
+                    .any(|held| held.id == key.id)
+            })
+            .unwrap_or(false);
+
+        if !already_held {
+            let previous = existing.map(|(current, _)| *current);
+            let _ = self.peers.insert(peer_cid, (key, previous));
+        }
+    }
+
+    /// Seals a message sent by `sender_cid` to `group` under the local sender key. Returns the id of the key alongside
+    /// the ciphertext
+    pub fn seal(
+        &self,
+        group: MessageGroupKey,
+        sender_cid: u64,
+        plaintext: &[u8],
+    ) -> Result<(u64, Vec<u8>), NetworkError> {
+        let local = self.local.as_ref().ok_or(NetworkError::InternalError(
+            "No sender key has been generated for this group",
+        ))?;
+        let associated_data = associated_data(group, sender_cid, local.id);
+        let ciphertext =
+            sealed_object::seal_with_associated_data(&local.key, &associated_data, plaintext)
+                .map_err(|err| NetworkError::Generic(err.into_string()))?;
+        Ok((local.id, ciphertext))
+    }
+
+    /// Opens a message sealed by `sender_cid` under its sender key named `key_id`
+    pub fn open(
+        &self,
+        group: MessageGroupKey,
+        sender_cid: u64,
+        key_id: u64,
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, NetworkError> {
+        let key = self
+            .peers
+            .get(&sender_cid)
+            .and_then(|(current, previous)| {
+                std::iter::once(current)
+                    .chain(previous.as_ref())
+                    .find(|key| key.id == key_id)
+            })
+            .ok_or_else(|| {
+                NetworkError::Generic(format!(
+                    "No sender key with id {} is held for peer {}",
+                    key_id, sender_cid
+                ))
+            })?;
+        let associated_data = associated_data(group, sender_cid, key_id);
+        sealed_object::open_with_associated_data(&key.key, &associated_data, ciphertext)
+            .map_err(|err| NetworkError::Generic(err.into_string()))
+    }
+}
+
+/// Binds a sealed message to its group, its sender, and the sender key it was sealed under
+fn associated_data(group: MessageGroupKey, sender_cid: u64, key_id: u64) -> [u8; 40] {
+    let mut associated_data = [0u8; 40];
+    associated_data[..8].copy_from_slice(&group.cid.to_be_bytes());
+    associated_data[8..24].copy_from_slice(&group.mgid.to_be_bytes());
+    associated_data[24..32].copy_from_slice(&sender_cid.to_be_bytes());
+    associated_data[32..].copy_from_slice(&key_id.to_be_bytes());
+    associated_data
+}
+
+/// Run by a member after rotating its sender key. Seals the key for each other member. Members with whom no pairwise
+/// key is shared are skipped
+pub(crate) async fn seal_sender_key<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    members: &[u64],
+    sender_key: &SenderKey,
+) -> Result<Vec<(u64, Vec<u8>)>, NetworkError> {
+    let serialized = sender_key
+        .serialize_to_vector()
+        .map_err(|err| NetworkError::Generic(err.into_string()))?;
+    let mut sealed = Vec::with_capacity(members.len());
+
+    for peer_cid in members.iter().copied().filter(|cid| *cid != implicated_cid) {
+        match forwarded_objects::load_offline_delivery_key(
+            persistence_handler,
+            implicated_cid,
+            peer_cid,
+        )
+        .await
+        {
+            Ok(key) => sealed.push((
+                peer_cid,
+                sealed_object::seal(&key, &serialized)
+                    .map_err(|err| NetworkError::Generic(err.into_string()))?,
+            )),
+
+            Err(err) => {
+                log::warn!(target: "citadel", "Unable to send sender key to {}: {:?}", peer_cid, err)
+            }
+        }
+    }
+
+    Ok(sealed)
+}
+
+/// Run by a member upon receiving the sender key of `sender_cid`
+pub(crate) async fn open_sender_key<R: Ratchet, Fcm: Ratchet>(
+    persistence_handler: &PersistenceHandler<R, Fcm>,
+    implicated_cid: u64,
+    sender_cid: u64,
+    sealed: &[u8],
+) -> Result<SenderKey, NetworkError> {
+    let serialized =
+        forwarded_objects::open_sealed(persistence_handler, implicated_cid, sender_cid, sealed)
+            .await?;
+    SenderKey::deserialize_from_owned_vector(serialized)
+        .map_err(|err| NetworkError::Generic(err.into_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GroupSenderKeys;
+    use crate::proto::peer::message_group::MessageGroupKey;
+
+    const GROUP: MessageGroupKey = MessageGroupKey { cid: 1, mgid: 0 };
+
+    #[test]
+    fn sender_key_roundtrip_and_rotation() {
+        let mut alice = GroupSenderKeys::default();
+        let mut bob = GroupSenderKeys::default();
+        let first = alice.rotate(&[1, 2]);
+        bob.insert_peer_key(1, first);
+
+        let (key_id, ciphertext) = alice.seal(GROUP, 1, b"hello").unwrap();
+        assert_eq!(bob.open(GROUP, 1, key_id, &ciphertext).unwrap(), b"hello");
+
+        // after rotation, messages under the new key cannot be opened until the new key arrives
+        let second = alice.rotate(&[1, 2]);
+        let (new_key_id, new_ciphertext) = alice.seal(GROUP, 1, b"world").unwrap();
+        assert!(bob.open(GROUP, 1, new_key_id, &new_ciphertext).is_err());
+
+        // messages sealed under the previous key may still be opened after the new key arrives
+        bob.insert_peer_key(1, second);
+        assert_eq!(
+            bob.open(GROUP, 1, new_key_id, &new_ciphertext).unwrap(),
+            b"world"
+        );
+        assert_eq!(bob.open(GROUP, 1, key_id, &ciphertext).unwrap(), b"hello");
+
+        // receiving a key twice does not discard the previous key
+        bob.insert_peer_key(1, second);
+        assert_eq!(bob.open(GROUP, 1, key_id, &ciphertext).unwrap(), b"hello");
+
+        // departed members are pruned
+        let _ = bob.rotate(&[2]);
+        assert!(bob.open(GROUP, 1, new_key_id, &new_ciphertext).is_err());
+    }
+
+    #[test]
+    fn sender_key_rotation_after_reconnect() {
+        let mut alice = GroupSenderKeys::default();
+        let mut bob = GroupSenderKeys::default();
+        for _ in 0..3 {
+            bob.insert_peer_key(1, alice.rotate(&[1, 2]));
+        }
+
+        // upon reconnecting and reattaching, alice starts anew, and her next key must still replace the keys bob holds
+        let mut alice = GroupSenderKeys::default();
+        bob.insert_peer_key(1, alice.rotate(&[1, 2]));
+        let (key_id, ciphertext) = alice.seal(GROUP, 1, b"hello again").unwrap();
+        assert_eq!(
+            bob.open(GROUP, 1, key_id, &ciphertext).unwrap(),
+            b"hello again"
+        );
+
+        // later rotations in the new session supersede the key as usual
+        bob.insert_peer_key(1, alice.rotate(&[1, 2]));
+        let (key_id, ciphertext) = alice.seal(GROUP, 1, b"rotated").unwrap();
+        assert_eq!(bob.open(GROUP, 1, key_id, &ciphertext).unwrap(), b"rotated");
+    }
+
+    #[test]
+    fn impersonation_is_rejected() {
+        const ALICE: u64 = 1;
+        const BOB: u64 = 2;
+        const MALLORY: u64 = 3;
+        let members = [ALICE, BOB, MALLORY];
+
+        let mut alice = GroupSenderKeys::default();
+        let mut mallory = GroupSenderKeys::default();
+        let mut bob = GroupSenderKeys::default();
+        let alice_key = alice.rotate(&members);
+        let mallory_key = mallory.rotate(&members);
+        bob.insert_peer_key(ALICE, alice_key);
+        bob.insert_peer_key(MALLORY, mallory_key);
+
+        // mallory claims to be alice. The server stamps mallory as the sender, and the message cannot be opened under
+        // either member's name
+        let (key_id, ciphertext) = mallory.seal(GROUP, ALICE, b"from alice").unwrap();
+        assert!(bob.open(GROUP, MALLORY, key_id, &ciphertext).is_err());
+        assert!(bob.open(GROUP, ALICE, key_id, &ciphertext).is_err());
+
+        // every member holds alice's sender key, yet a message sealed under it by mallory is attributed to mallory, who
+        // holds no such key
+        let forger = GroupSenderKeys {
+            local: Some(alice_key),
+            ..Default::default()
+        };
+        let (key_id, ciphertext) = forger.seal(GROUP, ALICE, b"from alice").unwrap();
+        assert!(bob.open(GROUP, MALLORY, key_id, &ciphertext).is_err());
+
+        // messages cannot be moved between groups
+        let (key_id, ciphertext) = alice.seal(GROUP, ALICE, b"hello").unwrap();
+        let other_group = MessageGroupKey { cid: 1, mgid: 1 };
+        assert!(bob.open(other_group, ALICE, key_id, &ciphertext).is_err());
+        assert_eq!(
+            bob.open(GROUP, ALICE, key_id, &ciphertext).unwrap(),
+            b"hello"
+        );
+
+        // mallory's own messages are still accepted
+        let (key_id, ciphertext) = mallory.seal(GROUP, MALLORY, b"from mallory").unwrap();
+        assert_eq!(
+            bob.open(GROUP, MALLORY, key_id, &ciphertext).unwrap(),
+            b"from mallory"
+        );
+    }
+}
@@ -8,9 +8,10 @@ use futures::{SinkExt, StreamExt, TryFutureExt, TryStreamExt};
 use tokio::time::Instant;
 use tokio_util::codec::LengthDelimitedCodec;
 
+use citadel_crypt::directory_source::DirectoryManifest;
 use citadel_crypt::entropy_bank::SecurityLevel;
 use citadel_crypt::stacked_ratchet::constructor::StackedRatchetConstructor;
-use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_crypt::stacked_ratchet::{Ratchet, StackedRatchet};
 use citadel_user::account_manager::AccountManager;
 use citadel_user::auth::proposed_credentials::ProposedCredentials;
 use citadel_user::client_account::ClientNetworkAccount;
@@ -22,13 +23,16 @@ use netbeam::time_tracker::TimeTracker;
 use crate::constants::{
     DRILL_UPDATE_FREQUENCY_LOW_BASE, FIREWALL_KEEP_ALIVE_UDP, GROUP_EXPIRE_TIME_MS,
     HDP_HEADER_BYTE_LEN, INITIAL_RECONNECT_LOCKOUT_TIME_NS, KEEP_ALIVE_INTERVAL_MS,
-    KEEP_ALIVE_TIMEOUT_NS, LOGIN_EXPIRATION_TIME,
+    KEEP_ALIVE_TIMEOUT_NS, LOGIN_EXPIRATION_TIME, UNKNOWN_LENGTH_GROUP_RESERVATION,
 };
 use crate::error::NetworkError;
 use crate::proto::packet::{packet_flags, HdpPacket};
 use crate::proto::packet_crafter::peer_cmd::C2S_ENCRYPTION_ONLY;
 use crate::proto::packet_crafter::{self, GroupTransmitter, RatchetPacketCrafterContainer};
-use citadel_user::backend::utils::VirtualObjectMetadata;
+use citadel_user::backend::utils::{
+    ObjectForwarding, ObjectTransferControl, ObjectTransferOrientation, ObjectTransferProgress,
+    ObjectTransferStatus, ResumePoint, VirtualObjectMetadata,
+};
 //use futures_codec::Framed;
 use crate::proto::misc;
 use crate::proto::misc::clean_shutdown::{CleanShutdownSink, CleanShutdownStream};
@@ -54,6 +58,7 @@ use crate::proto::outbound_sender::{
     OutboundPrimaryStreamReceiver, OutboundPrimaryStreamSender, OutboundUdpSender,
 };
 use crate::proto::packet_processor::raw_primary_packet::{check_proxy, ReceivePortType};
+use crate::proto::peer::offline_messages;
 use crate::proto::peer::p2p_conn_handler::P2PInboundHandle;
 use crate::proto::peer::peer_layer::{HyperNodePeerLayer, PeerSignal, UdpMode};
 use crate::proto::session_queue_handler::{
@@ -63,14 +68,14 @@ use crate::proto::session_queue_handler::{
 };
 use crate::proto::state_container::{
     FileKey, GroupKey, OutboundFileTransfer, OutboundTransmitterContainer, StateContainer,
-    StateContainerInner, VirtualConnectionType, VirtualTargetType,
+    StateContainerInner, VirtualConnectionType, VirtualTargetType, EXTERNAL_SERVER_UNREACHABLE,
 };
 use crate::proto::state_subcontainers::preconnect_state_container::UdpChannelSender;
 use crate::proto::state_subcontainers::rekey_container::calculate_update_frequency;
 use crate::proto::transfer_stats::TransferStats;
 use atomic::Atomic;
 use citadel_crypt::prelude::ConstructorOpts;
-use citadel_crypt::streaming_crypt_scrambler::{scramble_encrypt_source, ObjectSource};
+use citadel_crypt::streaming_crypt_scrambler::{scramble_encrypt_source_resumable, ObjectSource};
 use citadel_user::backend::PersistenceHandler;
 use citadel_wire::exports::tokio_rustls::rustls;
 use citadel_wire::exports::NewConnection;
@@ -78,7 +83,10 @@ use citadel_wire::nat_identification::NatType;
 use std::ops::Deref;
 use std::pin::Pin;
 //use futures_codec::Framed;
-use crate::proto::node_result::{Disconnect, InternalServerError, NodeResult};
+use crate::proto::node_result::{
+    Disconnect, InternalServerError, NodeResult, ObjectTransferHandle,
+};
+use crate::proto::object_transfer_progress::{load_resume_point, ObjectTransferProgressTracker};
 use crate::proto::remote::{NodeRemote, Ticket};
 
 //use crate::define_struct;
@@ -1166,6 +1174,30 @@ impl HdpSession {
         source: Box<dyn ObjectSource>,
         virtual_target: VirtualTargetType,
         security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
+        self.process_outbound_object(
+            ticket,
+            max_group_size,
+            source,
+            virtual_target,
+            security_level,
+            None,
+            None,
+        )
+    }
+
+    /// Sends an object. If `forwarding` is set, the object is sealed for an offline peer, and is to be stored and
+    /// forwarded by the server. If `handle_tx` is set, the handle to the transfer is sent there instead of to the kernel
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn process_outbound_object(
+        &self,
+        ticket: Ticket,
+        max_group_size: Option<usize>,
+        source: Box<dyn ObjectSource>,
+        virtual_target: VirtualTargetType,
+        security_level: SecurityLevel,
+        forwarding: Option<ObjectForwarding>,
+        handle_tx: Option<tokio::sync::oneshot::Sender<ObjectTransferHandle>>,
     ) -> Result<(), NetworkError> {
         let this = self;
 
@@ -1175,12 +1207,15 @@ impl HdpSession {
             let file_name = source
                 .get_source_name()
                 .map_err(|err| NetworkError::msg(err.into_string()))?;
+            let mut manifest = source.get_manifest().cloned();
 
             let time_tracker = this.time_tracker;
             let timestamp = this.time_tracker.get_global_time_ns();
             let (group_sender, group_sender_rx) = channel(5);
             let mut group_sender_rx = tokio_stream::wrappers::ReceiverStream::new(group_sender_rx);
             let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+            let (start_group_tx, start_group_rx) = tokio::sync::oneshot::channel();
+            let (summary_tx, summary_rx) = tokio::sync::oneshot::channel();
             // the above are the same for all vtarget types. Now, we need to get the proper drill and pqc
 
             let mut state_container = inner_mut_state!(this.state_container);
@@ -1188,162 +1223,192 @@ impl HdpSession {
             log::trace!(target: "citadel", "Transmit file name: {}", &file_name);
             // the key cid must be differentiated from the target cid because the target_cid needs to be zero if
             // there is no proxying. the key cid cannot be zero; if client -> server, key uses implicated cid
-            let (to_primary_stream, file_header, object_id, target_cid, key_cid, groups_needed) =
-                match virtual_target {
-                    VirtualTargetType::LocalGroupServer(implicated_cid) => {
-                        // if we are sending this just to the HyperLAN server (in the case of file uploads),
-                        // then, we use this session's pqc, the cnac's latest drill, and 0 for target_cid
-                        let crypt_container = &mut state_container
-                            .c2s_channel_container
-                            .as_mut()
-                            .unwrap()
-                            .peer_session_crypto;
-                        let object_id = crypt_container.get_and_increment_object_id();
-                        let group_id_start = crypt_container.get_and_increment_group_id();
-                        let latest_hr = crypt_container.get_hyper_ratchet(None).cloned().unwrap();
-
-                        let to_primary_stream = this.to_primary_stream.clone().unwrap();
-                        let target_cid = 0;
-                        let (file_size, groups_needed) = scramble_encrypt_source(
-                            source,
-                            max_group_size,
-                            object_id,
-                            group_sender,
-                            stop_rx,
-                            security_level,
-                            latest_hr.clone(),
-                            HDP_HEADER_BYTE_LEN,
-                            target_cid,
-                            group_id_start,
-                            packet_crafter::group::craft_wave_payload_packet_into,
-                        )
-                        .map_err(|err| NetworkError::Generic(err.to_string()))?;
-
-                        let file_metadata = VirtualObjectMetadata {
-                            object_id,
-                            name: file_name,
-                            date_created: "".to_string(),
-                            author: "N/A".to_string(),
-                            plaintext_length: file_size,
-                            group_count: groups_needed,
-                        };
-
-                        // if 1 group, we don't need to reserve any more group IDs. If 2, then we reserve just one. 3, then 2
-                        let amt_to_reserve = groups_needed - 1;
-                        crypt_container.rolling_group_id += amt_to_reserve as u64;
-                        let file_header = packet_crafter::file::craft_file_header_packet(
-                            &latest_hr,
-                            group_id_start,
-                            ticket,
-                            security_level,
-                            virtual_target,
-                            file_metadata,
-                            timestamp,
-                        );
-                        (
-                            to_primary_stream,
-                            file_header,
-                            object_id,
-                            target_cid,
-                            implicated_cid,
-                            groups_needed,
-                        )
-                    }
-
-                    VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid) => {
-                        log::trace!(target: "citadel", "Sending HyperLAN peer ({}) <-> HyperLAN Peer ({})", implicated_cid, target_cid);
-                        // here, we don't use the base session's PQC. Instead, we use the vconn's pqc and
-                        if let Some(vconn) = state_container
-                            .active_virtual_connections
-                            .get_mut(&target_cid)
-                        {
-                            if let Some(endpoint_container) = vconn.endpoint_container.as_mut() {
-                                let object_id = endpoint_container
-                                    .endpoint_crypto
-                                    .get_and_increment_object_id();
-                                // reserve group ids
-                                let start_group_id = endpoint_container
-                                    .endpoint_crypto
-                                    .get_and_increment_group_id();
-
-                                let latest_usable_ratchet = endpoint_container
-                                    .endpoint_crypto
-                                    .get_hyper_ratchet(None)
-                                    .unwrap();
-
-                                let preferred_primary_stream = endpoint_container
-                                    .get_direct_p2p_primary_stream()
-                                    .cloned()
-                                    .unwrap_or_else(|| this.to_primary_stream.clone().unwrap());
-
-                                let (file_size, groups_needed) = scramble_encrypt_source(
-                                    source,
-                                    max_group_size,
-                                    object_id,
-                                    group_sender,
-                                    stop_rx,
-                                    security_level,
-                                    latest_usable_ratchet.clone(),
-                                    HDP_HEADER_BYTE_LEN,
-                                    target_cid,
-                                    start_group_id,
-                                    packet_crafter::group::craft_wave_payload_packet_into,
-                                )
-                                .map_err(|err| NetworkError::Generic(err.to_string()))?;
-
-                                let file_metadata = VirtualObjectMetadata {
-                                    object_id,
-                                    name: file_name,
-                                    date_created: "".to_string(),
-                                    author: "".to_string(),
-                                    plaintext_length: file_size,
-                                    group_count: groups_needed,
-                                };
+            let (
+                to_primary_stream,
+                header_ratchet,
+                file_metadata,
+                group_id_start,
+                object_id,
+                target_cid,
+                key_cid,
+                groups_needed,
+            ) = match virtual_target {
+                VirtualTargetType::LocalGroupServer(implicated_cid) => {
+                    // if we are sending this just to the HyperLAN server (in the case of file uploads),
+                    // then, we use this session's pqc, the cnac's latest drill, and 0 for target_cid
+                    let crypt_container = &mut state_container
+                        .c2s_channel_container
+                        .as_mut()
+                        .unwrap()
+                        .peer_session_crypto;
+                    let object_id = crypt_container.get_and_increment_object_id();
+                    let group_id_start = crypt_container.get_and_increment_group_id();
+                    let latest_hr = crypt_container.get_hyper_ratchet(None).cloned().unwrap();
+                    sign_manifest(manifest.as_mut(), &latest_hr)?;
+
+                    let to_primary_stream = this.to_primary_stream.clone().unwrap();
+                    let target_cid = 0;
+                    let (file_size, groups_needed) = scramble_encrypt_source_resumable(
+                        source,
+                        max_group_size,
+                        object_id,
+                        group_sender,
+                        stop_rx,
+                        start_group_rx,
+                        summary_tx,
+                        security_level,
+                        latest_hr.clone(),
+                        HDP_HEADER_BYTE_LEN,
+                        target_cid,
+                        group_id_start,
+                        packet_crafter::group::craft_wave_payload_packet_into,
+                    )
+                    .map_err(|err| NetworkError::Generic(err.to_string()))?;
 
-                                let file_header = packet_crafter::file::craft_file_header_packet(
-                                    latest_usable_ratchet,
-                                    start_group_id,
-                                    ticket,
-                                    security_level,
-                                    virtual_target,
-                                    file_metadata,
-                                    timestamp,
-                                );
+                    let transfer_id = VirtualObjectMetadata::generate_transfer_id(
+                        &file_name,
+                        file_size,
+                        groups_needed,
+                    );
+                    let file_metadata = VirtualObjectMetadata {
+                        object_id,
+                        name: file_name,
+                        date_created: "".to_string(),
+                        author: "N/A".to_string(),
+                        plaintext_length: file_size,
+                        group_count: groups_needed,
+                        transfer_id,
+                        resume_point: None,
+                        target_path: None,
+                        manifest,
+                        forwarding,
+                    };
+
+                    // if 1 group, we don't need to reserve any more group IDs. If 2, then we reserve just one. 3, then 2
+                    let amt_to_reserve = if groups_needed == 0 {
+                        UNKNOWN_LENGTH_GROUP_RESERVATION
+                    } else {
+                        groups_needed as u64 - 1
+                    };
+                    crypt_container.rolling_group_id = crypt_container
+                        .rolling_group_id
+                        .wrapping_add(amt_to_reserve);
+                    (
+                        to_primary_stream,
+                        latest_hr,
+                        file_metadata,
+                        group_id_start,
+                        object_id,
+                        target_cid,
+                        implicated_cid,
+                        groups_needed,
+                    )
+                }
 
-                                // if 1 group, we don't need to reserve any more group IDs. If 2, then we reserve just one. 3, then 2
-                                let amt_to_reserve = groups_needed - 1;
-                                endpoint_container.endpoint_crypto.rolling_group_id +=
-                                    amt_to_reserve as u64;
+                VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid)
+                | VirtualConnectionType::ExternalGroupPeer(implicated_cid, _, target_cid) => {
+                    log::trace!(target: "citadel", "Sending HyperLAN peer ({}) <-> Peer ({})", implicated_cid, target_cid);
+                    // here, we don't use the base session's PQC. Instead, we use the vconn's pqc and
+                    if let Some(vconn) = state_container
+                        .active_virtual_connections
+                        .get_mut(&target_cid)
+                    {
+                        if let Some(endpoint_container) = vconn.endpoint_container.as_mut() {
+                            let object_id = endpoint_container
+                                .endpoint_crypto
+                                .get_and_increment_object_id();
+                            // reserve group ids
+                            let start_group_id = endpoint_container
+                                .endpoint_crypto
+                                .get_and_increment_group_id();
+
+                            let latest_usable_ratchet = endpoint_container
+                                .endpoint_crypto
+                                .get_hyper_ratchet(None)
+                                .unwrap();
+                            sign_manifest(manifest.as_mut(), latest_usable_ratchet)?;
+
+                            let preferred_primary_stream = endpoint_container
+                                .get_direct_p2p_primary_stream()
+                                .cloned()
+                                .unwrap_or_else(|| this.to_primary_stream.clone().unwrap());
+
+                            let (file_size, groups_needed) = scramble_encrypt_source_resumable(
+                                source,
+                                max_group_size,
+                                object_id,
+                                group_sender,
+                                stop_rx,
+                                start_group_rx,
+                                summary_tx,
+                                security_level,
+                                latest_usable_ratchet.clone(),
+                                HDP_HEADER_BYTE_LEN,
+                                target_cid,
+                                start_group_id,
+                                packet_crafter::group::craft_wave_payload_packet_into,
+                            )
+                            .map_err(|err| NetworkError::Generic(err.to_string()))?;
+
+                            let transfer_id = VirtualObjectMetadata::generate_transfer_id(
+                                &file_name,
+                                file_size,
+                                groups_needed,
+                            );
+                            let file_metadata = VirtualObjectMetadata {
+                                object_id,
+                                name: file_name,
+                                date_created: "".to_string(),
+                                author: "".to_string(),
+                                plaintext_length: file_size,
+                                group_count: groups_needed,
+                                transfer_id,
+                                resume_point: None,
+                                target_path: None,
+                                manifest,
+                                forwarding,
+                            };
 
-                                (
-                                    preferred_primary_stream,
-                                    file_header,
-                                    object_id,
-                                    target_cid,
-                                    target_cid,
-                                    groups_needed,
-                                )
+                            // if 1 group, we don't need to reserve any more group IDs. If 2, then we reserve just one. 3, then 2
+                            let amt_to_reserve = if groups_needed == 0 {
+                                UNKNOWN_LENGTH_GROUP_RESERVATION
                             } else {
-                                log::error!(target: "citadel", "Endpoint container not found");
-                                return Err(NetworkError::InternalError(
-                                    "Endpoint container not found",
-                                ));
-                            }
+                                groups_needed as u64 - 1
+                            };
+                            endpoint_container.endpoint_crypto.rolling_group_id =
+                                endpoint_container
+                                    .endpoint_crypto
+                                    .rolling_group_id
+                                    .wrapping_add(amt_to_reserve);
+
+                            (
+                                preferred_primary_stream,
+                                latest_usable_ratchet.clone(),
+                                file_metadata,
+                                start_group_id,
+                                object_id,
+                                target_cid,
+                                target_cid,
+                                groups_needed,
+                            )
                         } else {
-                            log::error!(target: "citadel", "Unable to find active vconn for the channel");
+                            log::error!(target: "citadel", "Endpoint container not found");
                             return Err(NetworkError::InternalError(
-                                "Virtual connection not found for channel",
+                                "Endpoint container not found",
                             ));
                         }
-                    }
-
-                    _ => {
-                        log::error!(target: "citadel", "HyperWAN functionality not yet implemented");
+                    } else {
+                        log::error!(target: "citadel", "Unable to find active vconn for the channel");
                         return Err(NetworkError::InternalError(
-                            "HyperWAN functionality not yet implemented",
+                            "Virtual connection not found for channel",
                         ));
                     }
-                };
+                }
+
+                VirtualConnectionType::ExternalGroupServer(..) => {
+                    return Err(EXTERNAL_SERVER_UNREACHABLE);
+                }
+            };
 
             // now that the async cryptscrambler tasks have been spawned on the threadpool, we need to also
             // spawn tasks that read the [GroupSenders] from there. We also need to store an [OutboundFileMetadataTransmitter]
@@ -1352,22 +1417,24 @@ impl HdpSession {
             // to, the GROUP HEADER ACK needs to return the group start idx. It is expected the adjacent node reserve enough groups
             // on its end to take into account
 
-            // send the FILE_HEADER
-            to_primary_stream
-                .unbounded_send(file_header)
-                .map_err(|_| NetworkError::InternalError("Primary stream disconnected"))?;
             // create the outbound file container
             let kernel_tx = state_container.kernel_tx.clone();
             let (next_gs_alerter, next_gs_alerter_rx) = unbounded();
             let mut next_gs_alerter_rx =
                 tokio_stream::wrappers::UnboundedReceiverStream::new(next_gs_alerter_rx);
             let (start, start_rx) = tokio::sync::oneshot::channel();
+            let (pause_tx, mut pause_rx) = tokio::sync::watch::channel(false);
             let outbound_file_transfer_container = OutboundFileTransfer {
                 stop_tx: Some(stop_tx),
                 object_id,
                 ticket,
                 next_gs_alerter: next_gs_alerter.clone(),
                 start: Some(start),
+                pause_tx,
+                total_groups: groups_needed,
+                progress: None,
+                handle_tx,
+                control_tx: None,
             };
             let file_key = FileKey::new(key_cid, object_id);
             let _ = state_container
@@ -1377,12 +1444,51 @@ impl HdpSession {
             std::mem::drop(state_container);
 
             let this = self.clone();
+            let persistence_handler = this.account_manager.get_persistence_handler().clone();
             let future = async move {
                 let this = &this;
                 let next_gs_alerter = &next_gs_alerter;
+                let mut file_metadata = file_metadata;
+                // if a previous attempt to send this object was interrupted, propose resuming from where it left off.
+                // A stream of unknown length cannot be read again, and thus cannot be resumed. Nor can a directory,
+                // whose files are unpacked as they arrive, nor a sealed object, whose every attempt is sealed anew
+                let implicated_cid = virtual_target.get_implicated_cid();
+                let is_resumable = groups_needed != 0
+                    && file_metadata.manifest.is_none()
+                    && file_metadata.forwarding.is_none();
+                let resume_point = if is_resumable {
+                    load_resume_point(
+                        &persistence_handler,
+                        implicated_cid,
+                        target_cid,
+                        ObjectTransferOrientation::Sender,
+                        file_metadata.transfer_id,
+                    )
+                    .await
+                } else {
+                    None
+                };
+                file_metadata.resume_point = resume_point;
+
+                // send the FILE_HEADER
+                let file_header = packet_crafter::file::craft_file_header_packet(
+                    &header_ratchet,
+                    group_id_start,
+                    ticket,
+                    security_level,
+                    virtual_target,
+                    file_metadata.clone(),
+                    timestamp,
+                );
+
+                if to_primary_stream.unbounded_send(file_header).is_err() {
+                    log::error!(target: "citadel", "Primary stream disconnected");
+                    return;
+                }
+
                 // this future will resolve when the sender drops in the file_crypt_scrambler
-                match start_rx.await {
-                    Ok(false) => {
+                let start_group = match start_rx.await {
+                    Ok(None) => {
                         log::warn!(target: "citadel", "start_rx signalled to NOT begin streaming process. Ending async subroutine");
                         return;
                     }
@@ -1391,11 +1497,74 @@ impl HdpSession {
                         return;
                     }
 
+                    Ok(Some(start)) => {
+                        log::trace!(target: "citadel", "Outbound file transfer async subroutine signalled to begin at group {}!", start.group);
+                        start
+                    }
+                };
+                let start_group = start.group;
+
+                // the receiver may only resume from a point at or before the one proposed
+                let completed = match resume_point {
+                    _ if start_group == 0 => ResumePoint::default(),
+                    Some(resume_point) if start_group <= resume_point.groups => {
+                        resume_point.truncate(start_group)
+                    }
                     _ => {
-                        log::trace!(target: "citadel", "Outbound file transfer async subroutine signalled to begin!");
+                        log::error!(target: "citadel", "Receiver requested to resume from group {}, which was not proposed", start_group);
+                        let mut state_container = inner_mut_state!(this.state_container);
+                        if let Some(tx) = state_container.file_transfer_handles.remove(&file_key) {
+                            let _ = tx.unbounded_send(ObjectTransferStatus::Fail(
+                                "Invalid resume point".to_string(),
+                            ));
+                        }
+                        let _ = state_container.outbound_files.remove(&file_key);
+                        return;
                     }
+                };
+
+                if start_group_tx.send(start).is_err() {
+                    log::error!(target: "citadel", "The async cryptscrambler stopped before the transfer began");
+                    return;
                 }
 
+                if let Some(file_transfer) = inner_mut_state!(this.state_container)
+                    .outbound_files
+                    .get_mut(&file_key)
+                    .filter(|_| is_resumable)
+                {
+                    file_transfer.progress = Some(ObjectTransferProgressTracker::spawn(
+                        persistence_handler,
+                        implicated_cid,
+                        target_cid,
+                        ObjectTransferProgress::new(
+                            ObjectTransferOrientation::Sender,
+                            file_metadata,
+                            completed,
+                        ),
+                    ));
+                }
+
+                // once the async cryptscrambler reads the entire object, its summary is sent to the receiver, allowing
+                // the receiver to verify the object. The summary arrives before the final group, identifying the final
+                // group of a stream of unknown length
+                let mut summary_rx = Some(summary_rx);
+                let mut total_groups = groups_needed;
+                let send_summary = |summary_rx: &mut Option<tokio::sync::oneshot::Receiver<_>>,
+                                    total_groups: &mut usize| {
+                    if let Some(summary) = summary_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+                        *summary_rx = None;
+                        *total_groups = summary.group_count;
+                        inner_state!(this.state_container).send_file_digest(
+                            file_key,
+                            target_cid,
+                            summary,
+                            ticket,
+                            security_level,
+                        );
+                    }
+                };
+
                 // TODO: planning/overhaul of file transmission process
                 // By now, the file container has been created remotely and locally
                 // We have been signalled to begin polling the group sender
@@ -1405,11 +1574,27 @@ impl HdpSession {
                 // end tells us it finished that group, and, we poll the next() group sender below.
                 //
 
-                let mut relative_group_id = 0;
+                // groups skipped when resuming keep their relative group ids
+                let mut relative_group_id = start_group as u32;
                 // while waiting, we likely have a set of GroupSenders to process
                 while let Some(sender) = group_sender_rx.next().await {
                     match sender {
                         Ok(sender) => {
+                            send_summary(&mut summary_rx, &mut total_groups);
+
+                            // hold back the next group while the transfer is paused
+                            loop {
+                                let paused = *pause_rx.borrow();
+                                if !paused {
+                                    break;
+                                }
+
+                                if pause_rx.changed().await.is_err() {
+                                    log::trace!(target: "citadel", "Outbound file transfer cancelled while paused");
+                                    return;
+                                }
+                            }
+
                             let (group_id, key) = {
                                 // construct the OutboundTransmitters
                                 let sess = this;
@@ -1420,6 +1605,11 @@ impl HdpSession {
 
                                 let mut state_container = inner_mut_state!(sess.state_container);
 
+                                if !state_container.outbound_files.contains_key(&file_key) {
+                                    log::trace!(target: "citadel", "Outbound file transfer cancelled");
+                                    return;
+                                }
+
                                 let proper_latest_hyper_ratchet = match virtual_target {
                                     VirtualConnectionType::LocalGroupServer(_) => state_container
                                         .c2s_channel_container
@@ -1427,7 +1617,8 @@ impl HdpSession {
                                         .unwrap()
                                         .peer_session_crypto
                                         .get_hyper_ratchet(None),
-                                    VirtualConnectionType::LocalGroupPeer(_, peer_cid) => {
+                                    VirtualConnectionType::LocalGroupPeer(_, peer_cid)
+                                    | VirtualConnectionType::ExternalGroupPeer(_, _, peer_cid) => {
                                         match state_container.get_peer_session_crypto(peer_cid) {
                                             Some(peer_sess_crypt) => {
                                                 peer_sess_crypt.get_hyper_ratchet(None)
@@ -1479,7 +1670,7 @@ impl HdpSession {
                                     Some(next_gs_alerter.clone()),
                                     transmitter,
                                     group_byte_len,
-                                    groups_needed,
+                                    total_groups,
                                     relative_group_id,
                                     ticket,
                                 );
@@ -1559,18 +1750,50 @@ impl HdpSession {
                         }
 
                         Err(err) => {
-                            let _ =
-                                kernel_tx
-                                    .clone()
-                                    .unbounded_send(NodeResult::InternalServerError(
-                                        InternalServerError {
-                                            ticket_opt: Some(ticket),
-                                            message: err.to_string(),
-                                        },
-                                    ));
+                            // the transfer cannot continue, including if the source changed since an interrupted
+                            // transfer. Cancelling discards the progress on both ends, such that the next attempt
+                            // begins from the first group
+                            log::warn!(target: "citadel", "Unable to render the next group of {:?}: {:?}", file_key, err);
+                            let mut state_container = inner_mut_state!(this.state_container);
+                            let control_tx = state_container
+                                .outbound_files
+                                .get_mut(&file_key)
+                                .and_then(|file_transfer| file_transfer.control_tx.take());
+                            if let Some(tx) =
+                                state_container.file_transfer_handles.remove(&file_key)
+                            {
+                                let _ =
+                                    tx.unbounded_send(ObjectTransferStatus::Fail(err.to_string()));
+                            } else {
+                                let _ = kernel_tx.clone().unbounded_send(
+                                    NodeResult::InternalServerError(InternalServerError {
+                                        ticket_opt: Some(ticket),
+                                        message: err.to_string(),
+                                    }),
+                                );
+                            }
+
+                            if let Some(control_tx) = control_tx {
+                                let _ = control_tx.send(ObjectTransferControl::Cancel);
+                            }
+
+                            return;
                         }
                     }
                 }
+
+                // an empty object has no final group, and thus its summary is only available once the async
+                // cryptscrambler finishes
+                send_summary(&mut summary_rx, &mut total_groups);
+                if summary_rx.is_none() && total_groups == 0 {
+                    // with no groups to acknowledge, the transfer is complete once the summary is sent
+                    if let Some(tx) = inner_mut_state!(this.state_container)
+                        .file_transfer_handles
+                        .remove(&file_key)
+                    {
+                        let _ = tx.unbounded_send(ObjectTransferStatus::TransferComplete);
+                    }
+                }
             };
 
             spawn!(future);
@@ -1672,6 +1895,22 @@ impl HdpSession {
 
         let timestamp = this.time_tracker.get_global_time_ns();
 
+        // messages for peers who may be offline are sealed for the peer before leaving this node
+        let peer_command = match peer_command {
+            PeerSignal::OfflineMessage(conn, message) => {
+                let message = offline_messages::seal_message(
+                    this.account_manager.get_persistence_handler(),
+                    conn.get_original_implicated_cid(),
+                    conn.get_original_target_cid(),
+                    message,
+                )
+                .await?;
+                PeerSignal::OfflineMessage(conn, message)
+            }
+
+            n => n,
+        };
+
         let mut state_container = inner_mut_state!(this.state_container);
 
         if let Some(to_primary_stream) = this.to_primary_stream.as_ref() {
@@ -2031,6 +2270,25 @@ impl HdpSessionInner {
     }
 }
 
+/// Signs the manifest of a directory, if any, with the signature key of the ratchet the header is sent under, allowing
+/// the receiver to verify the manifest before offering the directory to the local user. Sessions without a signature
+/// algorithm send the manifest unsigned
+fn sign_manifest(
+    manifest: Option<&mut DirectoryManifest>,
+    ratchet: &StackedRatchet,
+) -> Result<(), NetworkError> {
+    if let Some(manifest) = manifest {
+        manifest.sign(ratchet.get_scramble_pqc()).map_err(|err| {
+            NetworkError::Generic(format!(
+                "Unable to sign the directory's manifest: {}",
+                err.into_string()
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
 impl Drop for HdpSessionInner {
     fn drop(&mut self) {
         log::trace!(target: "citadel", "*** Dropping HdpSession {:?} ***", self.implicated_cid.get());
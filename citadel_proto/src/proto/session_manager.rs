@@ -20,7 +20,9 @@ use crate::error::NetworkError;
 use crate::kernel::RuntimeFuture;
 use crate::macros::SyncContextRequirements;
 use crate::proto::endpoint_crypto_accessor::EndpointCryptoAccessor;
+use crate::proto::forwarded_objects;
 use crate::proto::misc::net::GenericNetworkStream;
+use crate::proto::misc::recipient_locks::RecipientLocks;
 use crate::proto::misc::session_security_settings::SessionSecuritySettings;
 use crate::proto::misc::underlying_proto::ServerUnderlyingProtocol;
 use crate::proto::node::{ConnectMode, HdpServer};
@@ -32,7 +34,8 @@ use crate::proto::packet_processor::peer::group_broadcast::{
     GroupBroadcast, GroupMemberAlterMode, MemberState,
 };
 use crate::proto::packet_processor::PrimaryProcessorResult;
-use crate::proto::peer::message_group::{MessageGroupKey, MessageGroupOptions};
+use crate::proto::peer::federation::FederationTable;
+use crate::proto::peer::message_group::{GroupRole, MessageGroupKey, MessageGroupOptions};
 use crate::proto::peer::peer_layer::{
     HyperNodePeerLayer, HyperNodePeerLayerInner, MailboxTransfer, PeerConnectionType, PeerResponse,
     PeerSignal, UdpMode,
@@ -42,7 +45,9 @@ use crate::proto::session::{
     ClientOnlySessionInitSettings, HdpSession, HdpSessionInitMode, SessionInitParams,
 };
 use crate::proto::state_container::{VirtualConnectionType, VirtualTargetType};
+use citadel_crypt::sealed_object::SealedSource;
 use citadel_crypt::streaming_crypt_scrambler::ObjectSource;
+use citadel_user::backend::utils::ObjectForwarding;
 use citadel_wire::exports::tokio_rustls::rustls;
 use citadel_wire::exports::tokio_rustls::rustls::ClientConfig;
 use std::sync::Arc;
@@ -67,6 +72,8 @@ pub struct HdpSessionManagerInner {
     clean_shutdown_tracker_tx: UnboundedSender<()>,
     clean_shutdown_tracker: Option<UnboundedReceiver<()>>,
     client_config: Arc<rustls::ClientConfig>,
+    federation: FederationTable,
+    recipient_locks: RecipientLocks,
 }
 
 impl HdpSessionManager {
@@ -95,11 +102,18 @@ impl HdpSessionManager {
             kernel_tx,
             time_tracker,
             client_config,
+            federation: FederationTable::default(),
+            recipient_locks: RecipientLocks::default(),
         };
 
         Self::from(inner)
     }
 
+    /// Returns the locks that serialize updates to the state this node keeps on behalf of each recipient
+    pub(crate) fn recipient_locks(&self) -> RecipientLocks {
+        inner!(self).recipient_locks.clone()
+    }
+
     /// Loads the server remote, and gets the time tracker for the calling [HdpServer]
     /// Used during the init stage
     pub(crate) fn load_server_remote_get_tt(&self, server_remote: NodeRemote) -> TimeTracker {
@@ -377,16 +391,39 @@ impl HdpSessionManager {
             // if this is the case, ignore safe-shutdown of the session since no possible vconns
             // exist
             if let Some(implicated_cid) = sess.implicated_cid.get() {
-                let task = async move { peer_layer.on_session_shutdown(implicated_cid).await };
-
-                spawn!(task);
-
                 let timestamp = sess.time_tracker.get_global_time_ns();
                 let security_level = state_container
                     .session_security_settings
                     .map(|r| r.security_level)
                     .unwrap_or(SecurityLevel::Standard);
 
+                let groups_sess_mgr = session_manager.clone();
+                let task = async move {
+                    let successions = peer_layer.on_session_shutdown(implicated_cid).await?;
+                    // alert the members of each group that passed to a new owner
+                    for (key, new_owner) in successions {
+                        let signal = GroupBroadcast::MemberStateChanged(
+                            key,
+                            MemberState::OwnerChanged(implicated_cid, new_owner),
+                        );
+                        let _ = groups_sess_mgr
+                            .broadcast_signal_to_group(
+                                implicated_cid,
+                                timestamp,
+                                Ticket(0),
+                                key,
+                                signal,
+                                security_level,
+                            )
+                            .await
+                            .map_err(NetworkError::Generic)?;
+                    }
+
+                    Ok::<_, NetworkError>(())
+                };
+
+                spawn!(task);
+
                 state_container.active_virtual_connections.drain().for_each(|(peer_id, vconn)| {
                     let peer_cid = peer_id;
                     // toggling this off ensures that any higher-level channels are disabled
@@ -412,6 +449,22 @@ impl HdpSessionManager {
                                     }
                                 }
                             }
+                        } else if let VirtualConnectionType::ExternalGroupPeer(_, icid, _) = vconn {
+                            // the vconns of a federated link session are cleaned up when the link is cleared
+                            if icid != implicated_cid {
+                                log::trace!(target: "citadel", "Alerting {} (via federated link {}) that {} disconnected", peer_cid, icid, implicated_cid);
+                                let peer_conn_type = PeerConnectionType::HyperLANPeerToHyperWANPeer(implicated_cid, icid, peer_cid);
+                                let signal = PeerSignal::Disconnect(peer_conn_type, Some(PeerResponse::Disconnected(format!("{} disconnected from {} forcibly", peer_cid, implicated_cid))));
+                                if let Err(err) = sess_mgr.send_signal_to_peer_direct(icid, |link_hyper_ratchet| {
+                                    super::packet_crafter::peer_cmd::craft_peer_signal(link_hyper_ratchet, signal, Ticket(0), timestamp, security_level)
+                                }) {
+                                    log::warn!(target: "citadel", "Unable to alert federated link {}: {:?}", icid, err);
+                                }
+
+                                if let Some(link_sess) = sess_mgr.sessions.get(&icid) {
+                                    let _ = inner_mut_state!(link_sess.1.state_container).active_virtual_connections.remove(&implicated_cid);
+                                }
+                            }
                         }
                     }
                 });
@@ -434,7 +487,17 @@ impl HdpSessionManager {
     pub async fn run_peer_container(
         hdp_session_manager: HdpSessionManager,
     ) -> Result<(), NetworkError> {
-        let peer_container = { inner!(hdp_session_manager).hypernode_peer_layer.clone() };
+        let (peer_container, is_server) = {
+            let this = inner!(hdp_session_manager);
+            (
+                this.hypernode_peer_layer.clone(),
+                this.local_node_type.bind_addr().is_some(),
+            )
+        };
+
+        if is_server {
+            peer_container.load_persistent_message_groups().await?;
+        }
 
         peer_container.create_executor().await.await
     }
@@ -549,6 +612,62 @@ impl HdpSessionManager {
         }
     }
 
+    /// Seals the object for `recipient_cid`, then uploads it to the server, which stores the object until the recipient
+    /// connects
+    pub async fn process_outbound_forwarded_object(
+        &self,
+        ticket: Ticket,
+        max_group_size: Option<usize>,
+        source: Box<dyn ObjectSource>,
+        implicated_cid: u64,
+        recipient_cid: u64,
+        security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
+        if recipient_cid == 0 {
+            return Err(NetworkError::msg(
+                "Objects may only be stored and forwarded to a peer",
+            ));
+        }
+
+        let (session, persistence_handler) = {
+            let this = inner!(self);
+            let session = this
+                .sessions
+                .get(&implicated_cid)
+                .map(|sess| sess.1.clone())
+                .ok_or_else(|| {
+                    NetworkError::Generic(format!(
+                        "Hypernode session for {} does not exist! Not going to send data ...",
+                        implicated_cid
+                    ))
+                })?;
+            (
+                session,
+                this.account_manager.get_persistence_handler().clone(),
+            )
+        };
+
+        let key = forwarded_objects::load_offline_delivery_key(
+            &persistence_handler,
+            implicated_cid,
+            recipient_cid,
+        )
+        .await?;
+
+        session.process_outbound_object(
+            ticket,
+            max_group_size,
+            Box::new(SealedSource::new(source, key)),
+            VirtualTargetType::LocalGroupServer(implicated_cid),
+            security_level,
+            Some(ObjectForwarding {
+                sender_cid: implicated_cid,
+                recipient_cid,
+            }),
+            None,
+        )
+    }
+
     /// Returns true if the process continued successfully
     pub fn initiate_update_drill_subroutine(
         &self,
@@ -652,12 +771,204 @@ impl HdpSessionManager {
                 prev_conn.do_static_hr_refresh_atexit.set(false);
             }
 
+            let session = &this.sessions.get(&implicated_cid).unwrap().1;
+            let is_server = session.is_server;
+            let ticket = session.kernel_ticket.get();
+            // an inbound session is only trusted as a federated link if the account belongs to an adjacent server
+            let trusted_inbound = is_server
+                && inner_state!(session.state_container)
+                    .cnac
+                    .as_ref()
+                    .map(|cnac| {
+                        this.account_manager
+                            .get_misc_settings()
+                            .federated_servers
+                            .contains(&cnac.get_username())
+                    })
+                    .unwrap_or(false);
+            let _ = this.federation.on_session_upgraded(
+                implicated_cid,
+                ticket,
+                is_server,
+                trusted_inbound,
+            );
+
             true
         } else {
             false
         }
     }
 
+    /// Marks the outbound connection with `ticket` as a future federated link to an adjacent server
+    pub fn expect_federated_link(&self, ticket: Ticket) {
+        inner_mut!(self).federation.expect_outbound_link(ticket)
+    }
+
+    /// Called when an outbound federated link fails before the session could be established
+    pub fn cancel_federated_link(&self, ticket: Ticket) {
+        inner_mut!(self).federation.cancel_outbound_link(ticket)
+    }
+
+    /// Determines if the session for `cid` is a federated link to an adjacent server
+    pub fn is_federated_link(&self, cid: u64) -> bool {
+        inner!(self).federation.is_link(cid)
+    }
+
+    /// Sends a HyperWAN signal to the adjacent server across the federated link `icid`
+    pub fn send_signal_to_federated_server(
+        &self,
+        icid: u64,
+        ticket: Ticket,
+        signal: PeerSignal,
+        timestamp: i64,
+        security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
+        let this = inner!(self);
+        if !this.federation.is_link(icid) {
+            return Err(NetworkError::Generic(format!(
+                "{} is not a federated link",
+                icid
+            )));
+        }
+
+        this.send_signal_to_peer_direct(icid, |link_hyper_ratchet| {
+            super::packet_crafter::peer_cmd::craft_peer_signal(
+                link_hyper_ratchet,
+                signal,
+                ticket,
+                timestamp,
+                security_level,
+            )
+        })
+    }
+
+    /// Delivers a signal relayed by an adjacent server to the local client `target_cid`. If the client is
+    /// not connected, the signal is stored inside its mailbox. Returns an error if `target_cid` is not
+    /// registered to this node
+    pub async fn deliver_federated_signal(
+        &self,
+        target_cid: u64,
+        ticket: Ticket,
+        signal: PeerSignal,
+        timestamp: i64,
+        security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
+        if self.send_signal_to_peer(
+            target_cid,
+            ticket,
+            signal.clone(),
+            timestamp,
+            security_level,
+        ) {
+            return Ok(());
+        }
+
+        let account_manager = { inner!(self).account_manager.clone() };
+        if account_manager
+            .hyperlan_cid_is_registered(target_cid)
+            .await?
+        {
+            log::trace!(target: "citadel", "{} is offline; storing federated signal in mailbox", target_cid);
+            HyperNodePeerLayer::try_add_mailbox(
+                account_manager.get_persistence_handler(),
+                target_cid,
+                signal,
+            )
+            .await
+        } else {
+            Err(NetworkError::Generic(format!(
+                "{} is not registered to the adjacent server",
+                target_cid
+            )))
+        }
+    }
+
+    /// Forges the server-side virtual connections between the local client `local_cid` and `remote_cid`, a client
+    /// of the adjacent server reachable through the federated link `icid`. Proxied packets from `local_cid` are
+    /// forwarded through the link, and proxied packets arriving from the link are forwarded to `local_cid`
+    pub fn forge_federated_virtual_connection(
+        &self,
+        local_cid: u64,
+        icid: u64,
+        remote_cid: u64,
+    ) -> Result<(), NetworkError> {
+        let this = inner!(self);
+        let (_, local_sess) = this.sessions.get(&local_cid).ok_or_else(|| {
+            NetworkError::Generic(format!("Local client {} is not connected", local_cid))
+        })?;
+        let (_, link_sess) = this.sessions.get(&icid).ok_or_else(|| {
+            NetworkError::Generic(format!("Federated link {} is not connected", icid))
+        })?;
+        let local_tcp_sender = local_sess
+            .to_primary_stream
+            .clone()
+            .ok_or(NetworkError::InternalError("Local stream absent"))?;
+        let link_tcp_sender = link_sess
+            .to_primary_stream
+            .clone()
+            .ok_or(NetworkError::InternalError("Link stream absent"))?;
+
+        inner_mut_state!(local_sess.state_container).insert_new_virtual_connection_as_server(
+            remote_cid,
+            VirtualConnectionType::ExternalGroupPeer(local_cid, icid, remote_cid),
+            None,
+            link_tcp_sender,
+        );
+
+        // the link may already be relaying for `local_cid` on behalf of another remote peer
+        let mut link_state_container = inner_mut_state!(link_sess.state_container);
+        if !link_state_container
+            .active_virtual_connections
+            .contains_key(&local_cid)
+        {
+            link_state_container.insert_new_virtual_connection_as_server(
+                local_cid,
+                VirtualConnectionType::ExternalGroupPeer(remote_cid, icid, local_cid),
+                None,
+                local_tcp_sender,
+            );
+        }
+
+        log::trace!(target: "citadel", "Federated virtual connection between {} <-> {} (via {}) forged", local_cid, remote_cid, icid);
+        Ok(())
+    }
+
+    /// Removes the server-side virtual connection from the local client `local_cid` to `remote_cid`. The link's
+    /// virtual connection to `local_cid` is only removed once `local_cid` has no other peers through the link.
+    /// Returns true if the virtual connection existed
+    pub fn remove_federated_virtual_connection(
+        &self,
+        local_cid: u64,
+        icid: u64,
+        remote_cid: u64,
+    ) -> bool {
+        let this = inner!(self);
+        let (removed, still_linked) = if let Some((_, local_sess)) = this.sessions.get(&local_cid) {
+            let mut state_container = inner_mut_state!(local_sess.state_container);
+            let removed = state_container
+                .active_virtual_connections
+                .remove(&remote_cid)
+                .map(|vconn| vconn.is_active.store(false, Ordering::SeqCst))
+                .is_some();
+            let still_linked = state_container.active_virtual_connections.values().any(
+                    |vconn| matches!(vconn.connection_type, VirtualConnectionType::ExternalGroupPeer(_, link, _) if link == icid),
+                );
+            (removed, still_linked)
+        } else {
+            (false, false)
+        };
+
+        if !still_linked {
+            if let Some((_, link_sess)) = this.sessions.get(&icid) {
+                let _ = inner_mut_state!(link_sess.state_container)
+                    .active_virtual_connections
+                    .remove(&local_cid);
+            }
+        }
+
+        removed
+    }
+
     /// Returns true if the disconnect was a success, false if not. An error returns if something else occurs
     pub fn initiate_disconnect(
         &self,
@@ -724,6 +1035,13 @@ impl HdpSessionManager {
             }
         }
 
+        if let Err(err) = self
+            .rotate_group_sender_keys(timestamp, ticket, key, security_level)
+            .await
+        {
+            log::warn!(target: "citadel", "Unable to rotate sender keys for {}: {:?}", key, err);
+        }
+
         Some(key)
     }
 
@@ -781,6 +1099,44 @@ impl HdpSessionManager {
     ) -> Result<bool, NetworkError> {
         let peer_layer = { inner!(self).hypernode_peer_layer.clone() };
 
+        // when kicking, the requester must be a moderator and may only remove members of a lesser role
+        let peers = if mode == GroupMemberAlterMode::Kick {
+            peer_layer
+                .filter_kickable_peers(key, implicated_cid, peers)
+                .await
+        } else {
+            peers
+        };
+
+        if peers.is_empty() {
+            return Ok(false);
+        }
+
+        // when the owner leaves, ownership either passes to a successor, or the group ends
+        let new_owner = if mode == GroupMemberAlterMode::Leave
+            && peer_layer.get_member_role(key, implicated_cid).await == Some(GroupRole::Owner)
+        {
+            match peer_layer
+                .promote_message_group_successor(key, implicated_cid)
+                .await
+            {
+                Some(new_owner) => Some(new_owner),
+                None => {
+                    return Ok(self
+                        .remove_message_group(
+                            implicated_cid,
+                            timestamp,
+                            ticket,
+                            key,
+                            security_level,
+                        )
+                        .await)
+                }
+            }
+        } else {
+            None
+        };
+
         let mut to_broadcast_dc = vec![];
         let mut to_broadcast_left = vec![];
         let dc_signal = GroupBroadcast::Disconnected(key);
@@ -846,9 +1202,60 @@ impl HdpSessionManager {
             .await
             .map_err(NetworkError::Generic)?;
 
+        self.rotate_group_sender_keys(timestamp, ticket, key, security_level)
+            .await?;
+
+        if let Some(new_owner) = new_owner {
+            let signal = GroupBroadcast::MemberStateChanged(
+                key,
+                MemberState::OwnerChanged(implicated_cid, new_owner),
+            );
+            let _ = self
+                .broadcast_signal_to_group(
+                    implicated_cid,
+                    timestamp,
+                    ticket,
+                    key,
+                    signal,
+                    security_level,
+                )
+                .await
+                .map_err(NetworkError::Generic)?;
+        }
+
         Ok(true)
     }
 
+    /// If the group is confidential, asks each member to rotate its sender key. Should be called whenever the membership
+    /// of a group changes
+    pub async fn rotate_group_sender_keys(
+        &self,
+        timestamp: i64,
+        ticket: Ticket,
+        key: MessageGroupKey,
+        security_level: SecurityLevel,
+    ) -> Result<(), NetworkError> {
+        let peer_layer = { inner!(self).hypernode_peer_layer.clone() };
+
+        if let Some(members) = peer_layer.get_confidential_group_members(key).await {
+            let len = members.len();
+            let signal = GroupBroadcast::RotateSenderKey(key, members.clone());
+            let _ = self
+                .send_group_broadcast_signal_to(
+                    timestamp,
+                    ticket,
+                    members.into_iter().zip(std::iter::repeat(true).take(len)),
+                    true,
+                    signal,
+                    security_level,
+                )
+                .await
+                .map_err(NetworkError::Generic)?;
+        }
+
+        Ok(())
+    }
+
     /// Broadcasts a message to a target group
     /// Note: uses mail_if_offline: true. This allows a member to disconnect, but to still receive messages later-on
     pub async fn broadcast_signal_to_group(
@@ -1203,6 +1610,67 @@ impl HdpSessionManagerInner {
         if self.sessions.remove(&cid).is_none() {
             log::warn!(target: "citadel", "Tried removing a session (non-provisional), but did not find it ...");
         }
+
+        if let Some(link) = self.federation.remove_link(cid) {
+            log::trace!(target: "citadel", "Federated link {} closed (initiator: {})", link.icid, link.is_initiator);
+            self.sever_federated_link(link.icid);
+        }
+    }
+
+    /// Removes all the virtual connections that run through the federated link `icid`, alerting the local clients
+    fn sever_federated_link(&self, icid: u64) {
+        let timestamp = self.time_tracker.get_global_time_ns();
+        let severed = self
+            .sessions
+            .iter()
+            .map(|(cid, (_, sess))| {
+                let mut state_container = inner_mut_state!(sess.state_container);
+                let remote_peers = state_container
+                    .active_virtual_connections
+                    .iter()
+                    .filter_map(|(peer_cid, vconn)| match vconn.connection_type {
+                        VirtualConnectionType::ExternalGroupPeer(_, link, _) if link == icid => {
+                            Some(*peer_cid)
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<u64>>();
+
+                for peer_cid in &remote_peers {
+                    if let Some(vconn) = state_container.active_virtual_connections.remove(peer_cid)
+                    {
+                        vconn.is_active.store(false, Ordering::SeqCst);
+                    }
+                }
+
+                (*cid, remote_peers)
+            })
+            .collect::<Vec<(u64, Vec<u64>)>>();
+
+        for (cid, remote_peers) in severed {
+            for peer_cid in remote_peers {
+                let peer_conn_type =
+                    PeerConnectionType::HyperLANPeerToHyperWANPeer(peer_cid, icid, cid);
+                let signal = PeerSignal::Disconnect(
+                    peer_conn_type,
+                    Some(PeerResponse::Disconnected(format!(
+                        "The federated link to {} closed",
+                        peer_cid
+                    ))),
+                );
+                if let Err(err) = self.send_signal_to_peer_direct(cid, |peer_hyper_ratchet| {
+                    super::packet_crafter::peer_cmd::craft_peer_signal(
+                        peer_hyper_ratchet,
+                        signal,
+                        Ticket(0),
+                        timestamp,
+                        SecurityLevel::Standard,
+                    )
+                }) {
+                    log::warn!(target: "citadel", "Unable to alert {} of severed federated link: {:?}", cid, err);
+                }
+            }
+        }
     }
 
     // for use by the server. This skips the whole ticket-tracking processes intermediate to the routing above
@@ -7,6 +7,9 @@ use crate::proto::packet_processor::primary_group_packet::{
     attempt_kem_as_alice_finish, get_resp_target_cid_from_header,
 };
 use citadel_crypt::stacked_ratchet::constructor::{ConstructorType, StackedRatchetConstructor};
+use citadel_crypt::streaming_crypt_scrambler::{
+    group_digest, object_digest, ObjectDigest, ObjectSummary, StartGroup,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::proto::outbound_sender::{unbounded, UnboundedSender};
@@ -20,16 +23,21 @@ use netbeam::time_tracker::TimeTracker;
 
 use crate::constants::{
     GROUP_EXPIRE_TIME_MS, GROUP_TIMEOUT_MS, INDIVIDUAL_WAVE_TIMEOUT_MS, KEEP_ALIVE_INTERVAL_MS,
-    MAX_OUTGOING_UNPROCESSED_REQUESTS,
+    MAX_BUFFERED_DELIVERY_RECEIPTS, MAX_OUTGOING_UNPROCESSED_REQUESTS,
 };
 use crate::error::NetworkError;
 use crate::functional::IfEqConditional;
 use crate::prelude::{MessageGroupKey, ReKeyResult, ReKeyReturnType};
+use crate::proto::forwarded_objects;
 use crate::proto::misc::dual_late_init::DualLateInit;
 use crate::proto::misc::ordered_channel::OrderedChannel;
+use crate::proto::misc::recipient_locks::RecipientLocks;
 use crate::proto::misc::session_security_settings::SessionSecuritySettings;
 use crate::proto::node::SecrecyMode;
 use crate::proto::node_result::{NodeResult, ObjectTransferHandle};
+use crate::proto::object_transfer_progress::{
+    negotiate_receiver_resume_point, ObjectTransferProgressTracker,
+};
 use crate::proto::outbound_sender::{OutboundPrimaryStreamSender, OutboundUdpSender};
 use crate::proto::packet::packet_flags;
 use crate::proto::packet::HdpHeader;
@@ -41,10 +49,13 @@ use crate::proto::packet_crafter::{
 use crate::proto::packet_processor::includes::{HdpSession, Instant, SocketAddr};
 use crate::proto::packet_processor::peer::group_broadcast::GroupBroadcast;
 use crate::proto::packet_processor::PrimaryProcessorResult;
-use crate::proto::peer::channel::{PeerChannel, UdpChannel};
+use crate::proto::peer::channel::{
+    DeliveryReceipt, KeyingMaterialExporter, PeerChannel, UdpChannel,
+};
 use crate::proto::peer::group_channel::{GroupBroadcastPayload, GroupChannel};
 use crate::proto::peer::p2p_conn_handler::DirectP2PRemote;
 use crate::proto::peer::peer_layer::{PeerConnectionType, UdpMode};
+use crate::proto::peer::sender_keys::GroupSenderKeys;
 use crate::proto::remote::{NodeRemote, Ticket};
 use crate::proto::session::SessionState;
 use crate::proto::session_queue_handler::{QueueWorkerResult, SessionQueueWorkerHandle};
@@ -65,6 +76,7 @@ use citadel_crypt::stacked_ratchet::{Ratchet, StackedRatchet};
 use citadel_user::backend::utils::*;
 use citadel_user::backend::PersistenceHandler;
 use citadel_user::serialization::SyncIO;
+use citadel_user::server_misc_settings::ForwardingPolicy;
 use either::Either;
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -121,6 +133,7 @@ pub struct StateContainerInner {
     pub(super) session_security_settings: Option<SessionSecuritySettings>,
     pub(super) queue_handle: DualLateInit<SessionQueueWorkerHandle>,
     pub(super) group_channels: HashMap<MessageGroupKey, UnboundedSender<GroupBroadcastPayload>>,
+    pub(super) group_sender_keys: HashMap<MessageGroupKey, GroupSenderKeys>,
     pub(super) transfer_stats: TransferStats,
     pub(super) udp_mode: UdpMode,
     is_server: bool,
@@ -153,7 +166,28 @@ pub(crate) struct InboundFileTransfer {
     pub virtual_target: VirtualTargetType,
     pub metadata: VirtualObjectMetadata,
     pub stream_to_hd: UnboundedSender<Vec<u8>>,
-    pub reception_complete_tx: tokio::sync::oneshot::Sender<()>,
+    // alerts the task streaming the object to its sink once every group is received, or once the transfer is cancelled
+    pub reception_complete_tx: tokio::sync::oneshot::Sender<InboundFileConclusion>,
+    // set once the local user accepts the transfer
+    pub progress: Option<ObjectTransferProgressTracker>,
+    // the digest of each group received, including those received before the transfer was resumed
+    pub group_digests: Vec<ObjectDigest>,
+    // the plaintext length of every group but the last
+    pub group_len: usize,
+    // sent by the sender once it has read the entire object
+    pub expected_digest: Option<ObjectDigest>,
+}
+
+/// Sent to the task streaming an inbound object to its sink once the protocol is done with the transfer
+pub(crate) enum InboundFileConclusion {
+    /// Every group was received. Once written, the object is verified against the sender's digest
+    Received {
+        expected_digest: ObjectDigest,
+        // the digest of each group as received, used if the sink cannot be read back
+        group_digests: Vec<ObjectDigest>,
+        group_len: usize,
+    },
+    Cancelled,
 }
 
 #[allow(dead_code)]
@@ -162,10 +196,19 @@ pub(crate) struct OutboundFileTransfer {
     pub ticket: Ticket,
     // for alerting the group sender to begin sending the next group
     pub next_gs_alerter: UnboundedSender<()>,
-    // for alerting the async task to begin creating GroupSenders
-    pub start: Option<tokio::sync::oneshot::Sender<bool>>,
+    // for alerting the async task to begin creating GroupSenders, starting at the given relative group. None if declined
+    pub start: Option<tokio::sync::oneshot::Sender<Option<StartGroup>>>,
     // This sends a shutdown signal to the async cryptscambler
     pub stop_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    // while true, the async task holds back the next group
+    pub pause_tx: tokio::sync::watch::Sender<bool>,
+    pub total_groups: usize,
+    // set once the receiver accepts the transfer
+    pub progress: Option<ObjectTransferProgressTracker>,
+    // if set, the handle is sent here instead of to the kernel
+    pub handle_tx: Option<tokio::sync::oneshot::Sender<ObjectTransferHandle>>,
+    // set once the receiver accepts the transfer, allowing the transfer to be cancelled on both ends if it fails locally
+    pub control_tx: Option<tokio::sync::mpsc::UnboundedSender<ObjectTransferControl>>,
 }
 
 impl GroupKey {
@@ -218,6 +261,7 @@ pub struct EndpointChannelContainer<R: Ratchet = StackedRatchet> {
     pub(crate) to_unordered_channel: Option<UnorderedChannelContainer>,
     #[allow(dead_code)]
     pub(crate) peer_socket_addr: SocketAddr,
+    delivery_receipts: tokio::sync::broadcast::Sender<DeliveryReceipt>,
 }
 
 pub struct C2SChannelContainer<R: Ratchet = StackedRatchet> {
@@ -227,7 +271,11 @@ pub struct C2SChannelContainer<R: Ratchet = StackedRatchet> {
     is_active: Arc<AtomicBool>,
     to_primary_stream: OutboundPrimaryStreamSender,
     pub(crate) channel_signal: Option<NodeResult>,
+    /// Used by the server. The sub keys of the queued messages sent to the client, which are removed from the queue once
+    /// the client acknowledges them
+    pub(crate) unacknowledged_messages: Vec<String>,
     pub(crate) peer_session_crypto: PeerSessionCrypto<R>,
+    delivery_receipts: tokio::sync::broadcast::Sender<DeliveryReceipt>,
 }
 
 pub(crate) struct UnorderedChannelContainer {
@@ -258,6 +306,13 @@ pub enum VirtualConnectionType {
 
 /// For readability
 pub type VirtualTargetType = VirtualConnectionType;
+
+/// Returned when a request targets a [VirtualConnectionType::ExternalGroupServer]. Clients reach the clients of adjacent
+/// servers as [VirtualConnectionType::ExternalGroupPeer]s through the federated link of their own server, and never
+/// hold a session with the adjacent server itself
+pub(crate) const EXTERNAL_SERVER_UNREACHABLE: NetworkError = NetworkError::InvalidRequest(
+    "HyperWAN servers are only reachable through the federated link of the local server",
+);
 impl VirtualConnectionType {
     pub fn serialize(&self) -> Vec<u8> {
         Self::serialize_to_vector(self).unwrap()
@@ -497,6 +552,7 @@ impl StateContainerInner {
             outgoing_peer_connect_attempts: Default::default(),
             file_transfer_handles: HashMap::new(),
             group_channels: Default::default(),
+            group_sender_keys: Default::default(),
             udp_mode,
             transfer_stats,
             queue_handle: Default::default(),
@@ -555,6 +611,26 @@ impl StateContainerInner {
             .unwrap()
     }
 
+    /// Called once the endpoint `peer_cid` acknowledges a message sent through the channel. A `peer_cid` of zero implies
+    /// the c2s channel
+    pub fn on_message_delivered(&self, peer_cid: u64, ticket: Ticket) {
+        let delivery_receipts = if peer_cid == 0 {
+            self.c2s_channel_container
+                .as_ref()
+                .map(|c2s_container| &c2s_container.delivery_receipts)
+        } else {
+            self.active_virtual_connections
+                .get(&peer_cid)
+                .and_then(|vconn| vconn.endpoint_container.as_ref())
+                .map(|channel| &channel.delivery_receipts)
+        };
+
+        if let Some(delivery_receipts) = delivery_receipts {
+            // an error only implies that nobody is subscribed to receipts
+            let _ = delivery_receipts.send(DeliveryReceipt { ticket, peer_cid });
+        }
+    }
+
     /// This assumes the data has reached its destination endpoint, and must be forwarded to the channel
     /// (thus bypassing the unordered kernel)
     pub fn forward_data_to_ordered_channel(
@@ -733,21 +809,34 @@ impl StateContainerInner {
     ) -> PeerChannel {
         let (channel_tx, channel_rx) = unbounded();
         let (tx, rx) = crate::proto::outbound_sender::channel(MAX_OUTGOING_UNPROCESSED_REQUESTS);
+        let (delivery_receipts, _) =
+            tokio::sync::broadcast::channel(MAX_BUFFERED_DELIVERY_RECEIPTS);
         let is_active = Arc::new(AtomicBool::new(true));
 
         self.updates_in_progress
             .insert(target_cid, endpoint_crypto.update_in_progress.clone());
 
         //let (tx, rx) = futures::channel::mpsc::channel(MAX_OUTGOING_UNPROCESSED_REQUESTS);
+        let exporter =
+            KeyingMaterialExporter::new(endpoint_crypto.get_hyper_ratchet(None).cloned());
+        forwarded_objects::spawn_store_offline_delivery_key(
+            sess.account_manager.get_persistence_handler().clone(),
+            &exporter,
+            connection_type.get_implicated_cid(),
+            target_cid,
+        );
         let peer_channel = PeerChannel::new(
             self.hdp_server_remote.clone(),
             target_cid,
             connection_type,
             channel_ticket,
             default_security_settings.security_level,
+            default_security_settings.secrecy_mode,
             is_active.clone(),
             channel_rx,
             tx,
+            exporter,
+            delivery_receipts.clone(),
         );
         let to_channel = OrderedChannel::new(channel_tx);
         HdpSession::spawn_message_sender_function(sess.clone(), rx);
@@ -759,6 +848,7 @@ impl StateContainerInner {
             to_default_channel: to_channel,
             to_unordered_channel: None,
             peer_socket_addr,
+            delivery_receipts,
         });
 
         let vconn = VirtualConnection {
@@ -787,16 +877,26 @@ impl StateContainerInner {
     ) -> PeerChannel {
         let (channel_tx, channel_rx) = unbounded();
         let (tx, rx) = crate::proto::outbound_sender::channel(MAX_OUTGOING_UNPROCESSED_REQUESTS);
+        let (delivery_receipts, _) =
+            tokio::sync::broadcast::channel(MAX_BUFFERED_DELIVERY_RECEIPTS);
         let is_active = Arc::new(AtomicBool::new(true));
+        let peer_session_crypto = cnac.read().crypt_container.new_session();
+        let exporter =
+            KeyingMaterialExporter::new(peer_session_crypto.get_hyper_ratchet(None).cloned());
         let peer_channel = PeerChannel::new(
             self.hdp_server_remote.clone(),
             implicated_cid,
             VirtualConnectionType::LocalGroupServer(implicated_cid),
             channel_ticket,
             security_level,
+            self.session_security_settings
+                .map(|settings| settings.secrecy_mode)
+                .unwrap_or_default(),
             is_active.clone(),
             channel_rx,
             tx,
+            exporter,
+            delivery_receipts.clone(),
         );
         HdpSession::spawn_message_sender_function(session.clone(), rx);
 
@@ -806,7 +906,9 @@ impl StateContainerInner {
             is_active,
             to_primary_stream: session.to_primary_stream.clone().unwrap(),
             channel_signal: None,
-            peer_session_crypto: cnac.read().crypt_container.new_session(),
+            unacknowledged_messages: Vec::new(),
+            peer_session_crypto,
+            delivery_receipts,
         };
 
         let updates_in_progress = c2s.peer_session_crypto.update_in_progress.clone();
@@ -1000,14 +1102,23 @@ impl StateContainerInner {
         target_cid: u64,
         v_target_flipped: VirtualTargetType,
         preferred_primary_stream: OutboundPrimaryStreamSender,
+        forwarding_policy: &ForwardingPolicy,
+        recipient_locks: RecipientLocks,
     ) -> bool {
         let key = FileKey::new(header.session_cid.get(), metadata_orig.object_id);
         let ticket = header.context_info.get().into();
+        // transfer progress is stored under the local account. The server stores progress under the sending client's account
+        let (progress_implicated_cid, progress_peer_cid) = if header.target_cid.get() == 0 {
+            (header.session_cid.get(), 0)
+        } else {
+            (header.target_cid.get(), header.session_cid.get())
+        };
 
         // TODO: Add file transfer accept request here. Once local accepts, then begin this subroutine
         if let std::collections::hash_map::Entry::Vacant(e) = self.inbound_files.entry(key) {
             let (stream_to_hd, stream_to_hd_rx) = unbounded::<Vec<u8>>();
-            let (start_recv_tx, start_recv_rx) = tokio::sync::oneshot::channel::<bool>();
+            let (start_recv_tx, start_recv_rx) =
+                tokio::sync::oneshot::channel::<Option<ObjectTransferSink>>();
 
             let security_level_rebound: SecurityLevel = header.security_level.into();
             let timestamp = self.time_tracker.get_global_time_ns();
@@ -1015,7 +1126,7 @@ impl StateContainerInner {
             let pers = pers.clone();
             let metadata = metadata_orig.clone();
             let (reception_complete_tx, success_receiving_rx) =
-                tokio::sync::oneshot::channel::<()>();
+                tokio::sync::oneshot::channel::<InboundFileConclusion>();
             let entry = InboundFileTransfer {
                 last_group_finish_time: Instant::now(),
                 last_group_window_len: 0,
@@ -1027,35 +1138,160 @@ impl StateContainerInner {
                 metadata: metadata.clone(),
                 reception_complete_tx,
                 stream_to_hd,
+                progress: None,
+                group_digests: Vec::with_capacity(metadata_orig.group_count),
+                group_len: 0,
+                expected_digest: None,
             };
 
             e.insert(entry);
+            let (control_tx, control_rx) = tokio::sync::mpsc::unbounded_channel();
             let (handle, tx_status) = ObjectTransferHandler::new(
                 header.session_cid.get(),
                 header.target_cid.get(),
                 ObjectTransferOrientation::Receiver,
                 Some(start_recv_tx),
+                Some(control_tx),
+            );
+            Self::spawn_object_transfer_controller(
+                state_container.clone(),
+                key,
+                ObjectTransferOrientation::Receiver,
+                target_cid,
+                ticket,
+                security_level_rebound,
+                v_target_flipped,
+                control_rx,
             );
             self.file_transfer_handles.insert(
                 key,
                 crate::proto::outbound_sender::UnboundedSender(tx_status.clone()),
             );
-            // finally, alert the kernel (receiver)
-            let _ = self
-                .kernel_tx
-                .unbounded_send(NodeResult::ObjectTransferHandle(ObjectTransferHandle {
-                    ticket,
-                    handle,
-                }));
+            let handle = ObjectTransferHandle { ticket, handle };
+            // an object uploaded for an offline peer is stored by the server, rather than handed to the kernel. The
+            // sender is always the client of this session, regardless of what the metadata states
+            let is_uploaded_for_forwarding = self.is_server && header.target_cid.get() == 0;
+            match metadata_orig.forwarding {
+                Some(forwarding) if is_uploaded_for_forwarding => {
+                    let forwarding = ObjectForwarding {
+                        sender_cid: header.session_cid.get(),
+                        recipient_cid: forwarding.recipient_cid,
+                    };
+                    spawn!(forwarded_objects::receive_forwarded_object(
+                        pers.clone(),
+                        forwarding_policy.clone(),
+                        recipient_locks,
+                        handle,
+                        forwarding,
+                        metadata_orig.clone(),
+                    ));
+                }
+
+                _ => {
+                    // a directory is offered to the local user only if its manifest was verified
+                    let verified = metadata_orig
+                        .manifest
+                        .as_ref()
+                        .map(|manifest| manifest.verify(hyper_ratchet.get_scramble_pqc()))
+                        .transpose();
+
+                    if let Err(err) = verified {
+                        log::warn!(target: "citadel", "Declining directory {:?}, since its manifest could not be verified: {}", metadata_orig.name, err.into_string());
+                        let mut handle = handle;
+                        let _ = handle.handle.decline();
+                    } else {
+                        // finally, alert the kernel (receiver)
+                        let _ = self
+                            .kernel_tx
+                            .unbounded_send(NodeResult::ObjectTransferHandle(handle));
+                    }
+                }
+            }
+
+            // objects forwarded by the server are opened by the recipient as they arrive
+            let forwarded_to_local = metadata_orig
+                .forwarding
+                .filter(|_| !self.is_server && header.target_cid.get() == 0);
+            let implicated_cid = header.session_cid.get();
 
             let task = async move {
                 let res = start_recv_rx.await;
-                let accepted = res.as_ref().map(|r| *r).unwrap_or(false);
+                let mut metadata = metadata;
+                let mut start_group = None;
+                let mut prefix_digest = None;
+
+                // the sink itself is not Sync, and thus cannot be borrowed across await points
+                let accepted_sink = res
+                    .as_ref()
+                    .ok()
+                    .and_then(|sink| sink.as_ref())
+                    .map(|sink| (sink.path().cloned(), sink.is_writer()));
+
+                if let Some((target_path, is_writer)) = accepted_sink {
+                    metadata.target_path = target_path;
+                    // if a previous attempt at receiving this object was interrupted, continue where it left off.
+                    // Writers cannot be appended to, and thus always begin from the first group
+                    let stored_progress = if is_writer {
+                        None
+                    } else {
+                        negotiate_receiver_resume_point(
+                            &pers,
+                            progress_implicated_cid,
+                            progress_peer_cid,
+                            &metadata,
+                        )
+                        .await
+                    };
+                    metadata.resume_point =
+                        stored_progress.as_ref().map(|progress| progress.completed);
+                    let resume_point = metadata.resume_point.unwrap_or_default();
+                    start_group = Some(resume_point.groups);
+
+                    // the progress must be tracked before the sender is alerted, since groups may arrive immediately after
+                    let mut state_container = inner_mut_state!(state_container);
+                    if let Some(inbound_file_transfer) = state_container.inbound_files.get_mut(&key)
+                    {
+                        let mut progress = stored_progress.unwrap_or_else(|| {
+                            ObjectTransferProgress::new(
+                                ObjectTransferOrientation::Receiver,
+                                metadata.clone(),
+                                resume_point,
+                            )
+                        });
+                        progress.metadata = metadata.clone();
+                        // the sender verifies that its source still matches the groups already received
+                        prefix_digest = (resume_point.groups != 0)
+                            .then(|| object_digest(&progress.group_digests));
+                        inbound_file_transfer.groups_rendered = resume_point.groups;
+                        if resume_point.groups != 0 {
+                            inbound_file_transfer.group_len =
+                                (resume_point.bytes / resume_point.groups as u64) as usize;
+                        }
+                        inbound_file_transfer.group_digests = progress.group_digests.clone();
+                        inbound_file_transfer.metadata = metadata.clone();
+                        // streams of unknown length, directories, and forwarded objects cannot be resumed, and thus their
+                        // progress is not tracked
+                        let is_resumable = !is_writer
+                            && metadata.group_count != 0
+                            && metadata.manifest.is_none()
+                            && metadata.forwarding.is_none();
+                        inbound_file_transfer.progress = is_resumable.then(|| {
+                            ObjectTransferProgressTracker::spawn(
+                                pers.clone(),
+                                progress_implicated_cid,
+                                progress_peer_cid,
+                                progress,
+                            )
+                        });
+                    }
+                }
+
                 // first, send a rebound signal immediately to the sender
                 // to ensure the sender knows if the user accepted or not
                 let file_header_ack = packet_crafter::file::craft_file_header_ack_packet(
                     &hyper_ratchet,
-                    accepted,
+                    start_group,
+                    prefix_digest,
                     object_id,
                     target_cid,
                     ticket,
@@ -1070,22 +1306,94 @@ impl StateContainerInner {
                 }
 
                 match res {
-                    Ok(accepted) => {
-                        if accepted {
+                    Ok(sink) => {
+                        if let Some(sink) = sink {
+                            if let Some(resume_point) = metadata.resume_point {
+                                let _ = tx_status.send(ObjectTransferStatus::Resumed(
+                                    resume_point.groups,
+                                    metadata.group_count,
+                                ));
+                            }
+
                             // local user accepts the file transfer. Alert the adjacent end
                             // and get ready to begin streaming
-                            match pers
-                                .stream_object_to_backend(
+                            let is_writer = sink.is_writer();
+                            let is_forwarded = forwarded_to_local.is_some();
+                            let metadata = Arc::new(metadata);
+                            let res = if let Some(forwarding) = forwarded_to_local {
+                                forwarded_objects::stream_forwarded_object_to_sink(
+                                    &pers,
+                                    implicated_cid,
+                                    forwarding,
                                     stream_to_hd_rx,
-                                    Arc::new(metadata),
+                                    metadata.clone(),
+                                    sink,
                                     tx_status.clone(),
                                 )
                                 .await
-                            {
+                            } else {
+                                pers.stream_object_to_sink(
+                                    stream_to_hd_rx,
+                                    metadata.clone(),
+                                    sink,
+                                    tx_status.clone(),
+                                )
+                                .await
+                            };
+
+                            match res {
                                 Ok(()) => {
                                     log::trace!(target: "citadel", "Successfully synced file to backend");
                                     let status = match success_receiving_rx.await {
-                                        Ok(_) => ObjectTransferStatus::ReceptionComplete,
+                                        Ok(InboundFileConclusion::Cancelled) => {
+                                            // the transfer was cancelled, and the Cancelled status was already sent.
+                                            // Data written into a writer is left to the local user
+                                            if !is_writer {
+                                                if let Err(err) = pers
+                                                    .remove_partial_object(metadata.as_ref())
+                                                    .await
+                                                {
+                                                    log::warn!(target: "citadel", "Unable to remove cancelled object: {:?}", err);
+                                                }
+                                            }
+                                            return;
+                                        }
+
+                                        Ok(InboundFileConclusion::Received {
+                                            expected_digest,
+                                            group_digests,
+                                            group_len,
+                                        }) => {
+                                            // objects forwarded by the server are written as opened, rather than as
+                                            // sealed by the sender, and thus are verified as received
+                                            let written = if is_forwarded {
+                                                Ok(None)
+                                            } else {
+                                                pers.get_stored_object_digests(
+                                                    metadata.as_ref(),
+                                                    group_len,
+                                                )
+                                                .await
+                                            };
+
+                                            match written {
+                                                // objects written into a sink that cannot be read back were written
+                                                // as received, since every write succeeded
+                                                Ok(written) => {
+                                                    let digests = written.unwrap_or(group_digests);
+                                                    if object_digest(&digests) == expected_digest {
+                                                        ObjectTransferStatus::ReceptionComplete
+                                                    } else {
+                                                        log::warn!(target: "citadel", "Object {:?} does not match the digest sent by the sender", key);
+                                                        ObjectTransferStatus::Fail("The received object does not match the sender's digest".to_string())
+                                                    }
+                                                }
+
+                                                Err(err) => {
+                                                    ObjectTransferStatus::Fail(err.into_string())
+                                                }
+                                            }
+                                        }
 
                                         Err(_) => ObjectTransferStatus::Fail(
                                             "An unknown error occurred while receiving file"
@@ -1097,6 +1405,8 @@ impl StateContainerInner {
                                 }
                                 Err(err) => {
                                     log::error!(target: "citadel", "Unable to sync file to backend: {:?}", err);
+                                    let _ = tx_status
+                                        .send(ObjectTransferStatus::Fail(err.into_string()));
                                 }
                             }
                         } else {
@@ -1121,52 +1431,89 @@ impl StateContainerInner {
         }
     }
 
-    pub fn on_file_header_ack_received(
-        &mut self,
-        success: bool,
-        implicated_cid: u64,
-        ticket: Ticket,
-        object_id: u32,
+    /// Returns the key of the outbound transfer, and the cid of the receiver, given the `v_target` sent by the receiver
+    pub fn outbound_file_key(
         v_target: VirtualTargetType,
-    ) -> Option<()> {
-        let (key, receiver_cid) = match v_target {
-            VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid) => {
+        object_id: u32,
+    ) -> Option<(FileKey, u64)> {
+        match v_target {
+            VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid)
+            | VirtualConnectionType::ExternalGroupPeer(implicated_cid, _, target_cid) => {
                 // since the order hasn't flipped yet, get the implicated cid
-                (FileKey::new(implicated_cid, object_id), target_cid)
+                Some((FileKey::new(implicated_cid, object_id), target_cid))
             }
 
             VirtualConnectionType::LocalGroupServer(implicated_cid) => {
-                (FileKey::new(implicated_cid, object_id), 0)
+                Some((FileKey::new(implicated_cid, object_id), 0))
             }
 
             _ => {
                 log::error!(target: "citadel", "HyperWAN functionality not yet enabled");
-                return None;
+                None
             }
-        };
+        }
+    }
 
-        if success {
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_file_header_ack_received(
+        &mut self,
+        start_group: Option<StartGroup>,
+        implicated_cid: u64,
+        ticket: Ticket,
+        object_id: u32,
+        v_target: VirtualTargetType,
+        security_level: SecurityLevel,
+        state_container: StateContainer,
+    ) -> Option<()> {
+        let (key, receiver_cid) = Self::outbound_file_key(v_target, object_id)?;
+
+        if let Some(start_group) = start_group {
             // remove the inbound file transfer, send the signals to end async loops, and tell the kernel
             if let Some(file_transfer) = self.outbound_files.get_mut(&key) {
                 // start the async task pulling from the async cryptscrambler
-                file_transfer.start.take()?.send(true).ok()?;
+                file_transfer.start.take()?.send(Some(start_group)).ok()?;
+                let handle_tx = file_transfer.handle_tx.take();
+                let (control_tx, control_rx) = tokio::sync::mpsc::unbounded_channel();
+                file_transfer.control_tx = Some(control_tx.clone());
                 let (handle, tx) = ObjectTransferHandler::new(
                     implicated_cid,
                     receiver_cid,
                     ObjectTransferOrientation::Sender,
                     None,
+                    Some(control_tx),
+                );
+                // between peers, the outbound transfer is keyed by the receiver's cid
+                let remote_cid = if receiver_cid == 0 { 0 } else { key.target_cid };
+                Self::spawn_object_transfer_controller(
+                    state_container,
+                    key,
+                    ObjectTransferOrientation::Sender,
+                    remote_cid,
+                    ticket,
+                    security_level,
+                    v_target,
+                    control_rx,
                 );
                 tx.send(ObjectTransferStatus::TransferBeginning).ok()?;
+                if start_group.group != 0 {
+                    tx.send(ObjectTransferStatus::Resumed(
+                        start_group.group,
+                        file_transfer.total_groups,
+                    ))
+                    .ok()?;
+                }
                 let _ = self
                     .file_transfer_handles
                     .insert(key, crate::proto::outbound_sender::UnboundedSender(tx));
-                // alert the kernel that file transfer has begun
-                self.kernel_tx
-                    .unbounded_send(NodeResult::ObjectTransferHandle(ObjectTransferHandle {
-                        ticket,
-                        handle,
-                    }))
-                    .ok()?;
+                let handle = ObjectTransferHandle { ticket, handle };
+                if let Some(handle_tx) = handle_tx {
+                    handle_tx.send(handle).ok()?;
+                } else {
+                    // alert the kernel that file transfer has begun
+                    self.kernel_tx
+                        .unbounded_send(NodeResult::ObjectTransferHandle(handle))
+                        .ok()?;
+                }
             } else {
                 log::error!(target: "citadel", "Attempted to obtain OutboundFileTransfer for {:?}, but it didn't exist", key);
             }
@@ -1176,7 +1523,7 @@ impl StateContainerInner {
                 // stop the async cryptscrambler
                 file_transfer.stop_tx?.send(()).ok()?;
                 // stop the async task pulling from the async cryptscrambler
-                file_transfer.start?.send(false).ok()?;
+                file_transfer.start?.send(None).ok()?;
             } else {
                 log::error!(target: "citadel", "Attempted to remove OutboundFileTransfer for {:?}, but it didn't exist", key);
             }
@@ -1185,6 +1532,282 @@ impl StateContainerInner {
         Some(())
     }
 
+    /// Forwards cancel, pause and resume requests from the local [`ObjectTransferHandler`], applying them locally
+    /// before alerting the adjacent node
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_object_transfer_controller(
+        state_container: StateContainer,
+        key: FileKey,
+        orientation: ObjectTransferOrientation,
+        remote_cid: u64,
+        ticket: Ticket,
+        security_level: SecurityLevel,
+        v_target: VirtualTargetType,
+        mut control_rx: tokio::sync::mpsc::UnboundedReceiver<ObjectTransferControl>,
+    ) {
+        let task = async move {
+            while let Some(control) = control_rx.recv().await {
+                let mut state_container = inner_mut_state!(state_container);
+                if !state_container.on_object_transfer_control(key, orientation, control) {
+                    log::warn!(target: "citadel", "Attempted to {:?} {:?}, but the transfer already ended", control, key);
+                    return;
+                }
+
+                let hyper_ratchet = if remote_cid == 0 {
+                    state_container.get_c2s_crypto()
+                } else {
+                    state_container.get_peer_session_crypto(remote_cid)
+                }
+                .and_then(|crypto| crypto.get_hyper_ratchet(None));
+
+                if let Some(hyper_ratchet) = hyper_ratchet {
+                    let packet = packet_crafter::file::craft_file_control_packet(
+                        hyper_ratchet,
+                        orientation.flip(),
+                        control,
+                        key.object_id,
+                        remote_cid,
+                        ticket,
+                        security_level,
+                        v_target,
+                        state_container.time_tracker.get_global_time_ns(),
+                    );
+
+                    if let Err(err) = state_container
+                        .get_preferred_stream(remote_cid)
+                        .unbounded_send(packet)
+                    {
+                        log::warn!(target: "citadel", "Unable to send FILE_CONTROL packet: {:?}", err);
+                    }
+                } else {
+                    log::warn!(target: "citadel", "Unable to alert adjacent node of {:?}; session no longer active", control);
+                }
+
+                if control == ObjectTransferControl::Cancel {
+                    return;
+                }
+            }
+        };
+
+        spawn!(task);
+    }
+
+    /// Sends the summary of an outbound object to the receiver once the async cryptscrambler has read the entire object.
+    /// `remote_cid` is zero when uploading to the server
+    pub fn send_file_digest(
+        &self,
+        key: FileKey,
+        remote_cid: u64,
+        summary: ObjectSummary,
+        ticket: Ticket,
+        security_level: SecurityLevel,
+    ) {
+        if !self.outbound_files.contains_key(&key) {
+            log::trace!(target: "citadel", "Will not send digest for {:?}, since the transfer ended", key);
+            return;
+        }
+
+        let hyper_ratchet = if remote_cid == 0 {
+            self.get_c2s_crypto()
+        } else {
+            self.get_peer_session_crypto(remote_cid)
+        }
+        .and_then(|crypto| crypto.get_hyper_ratchet(None));
+
+        if let Some(hyper_ratchet) = hyper_ratchet {
+            let packet = packet_crafter::file::craft_file_digest_packet(
+                hyper_ratchet,
+                summary,
+                key.object_id,
+                remote_cid,
+                ticket,
+                security_level,
+                self.time_tracker.get_global_time_ns(),
+            );
+
+            if let Err(err) = self.get_preferred_stream(remote_cid).unbounded_send(packet) {
+                log::warn!(target: "citadel", "Unable to send FILE_DIGEST packet: {:?}", err);
+            }
+        } else {
+            log::warn!(target: "citadel", "Unable to send digest for {:?}; session no longer active", key);
+        }
+    }
+
+    /// Records the digest the sender computed over the entire object, concluding the transfer if every group was
+    /// already received. For streams of unknown length, this is when the receiver learns the number of groups.
+    /// Returns false if the transfer does not exist
+    pub fn on_file_digest_received(&mut self, key: FileKey, summary: ObjectSummary) -> bool {
+        if let Some(file_transfer) = self.inbound_files.get_mut(&key) {
+            if file_transfer.total_groups != 0 && file_transfer.total_groups != summary.group_count
+            {
+                log::warn!(target: "citadel", "The sender of {:?} announced {} groups, but sent {}", key, file_transfer.total_groups, summary.group_count);
+            }
+
+            file_transfer.total_groups = summary.group_count;
+            file_transfer.metadata.plaintext_length = summary.plaintext_length;
+            file_transfer.metadata.group_count = summary.group_count;
+            file_transfer.expected_digest = Some(summary.digest);
+            if self.try_conclude_inbound_file(key) {
+                log::trace!(target: "citadel", "Finished receiving file {:?}", key);
+                let _ = self.file_transfer_handles.remove(&key);
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Once every group is received and the sender's digest is known, ends the transfer and alerts the task
+    /// streaming the object to its sink, which verifies the object as written. Returns false if the transfer cannot
+    /// yet conclude
+    fn try_conclude_inbound_file(&mut self, key: FileKey) -> bool {
+        let ready = self
+            .inbound_files
+            .get(&key)
+            .map(|file_transfer| {
+                file_transfer.groups_rendered == file_transfer.total_groups
+                    && file_transfer.expected_digest.is_some()
+            })
+            .unwrap_or(false);
+
+        if !ready {
+            return false;
+        }
+
+        let file_transfer = self.inbound_files.remove(&key).unwrap();
+        // a corrupted object cannot be repaired by resuming, so the progress is removed either way
+        if let Some(progress) = file_transfer.progress.as_ref() {
+            progress.on_transfer_complete();
+        }
+
+        // the object is verified once the streaming to HD completes, since only then is the object written in full
+        let conclusion = InboundFileConclusion::Received {
+            expected_digest: file_transfer.expected_digest.unwrap(),
+            group_digests: file_transfer.group_digests,
+            group_len: file_transfer.group_len,
+        };
+        if file_transfer
+            .reception_complete_tx
+            .send(conclusion)
+            .is_err()
+        {
+            log::warn!(target: "citadel", "Unable to conclude {:?}; the receiving task already ended", key);
+        }
+
+        true
+    }
+
+    /// Applies a cancel, pause or resume request to the local end of the transfer. Returns false if the transfer does
+    /// not exist
+    pub fn on_object_transfer_control(
+        &mut self,
+        key: FileKey,
+        orientation: ObjectTransferOrientation,
+        control: ObjectTransferControl,
+    ) -> bool {
+        log::trace!(target: "citadel", "Applying {:?} to {:?} ({:?})", control, key, orientation);
+        let status = match (orientation, control) {
+            (ObjectTransferOrientation::Sender, ObjectTransferControl::Cancel) => {
+                let file_transfer = if let Some(file_transfer) = self.outbound_files.remove(&key) {
+                    file_transfer
+                } else {
+                    return false;
+                };
+
+                // stop the async cryptscrambler
+                if let Some(stop_tx) = file_transfer.stop_tx {
+                    let _ = stop_tx.send(());
+                }
+                // if the transfer has not yet begun, stop the async task pulling from the async cryptscrambler
+                if let Some(start) = file_transfer.start {
+                    let _ = start.send(None);
+                }
+                // wake the async task, if waiting for the next group, so that it notices the transfer ended
+                let _ = file_transfer.next_gs_alerter.unbounded_send(());
+                if let Some(progress) = file_transfer.progress.as_ref() {
+                    progress.on_transfer_complete();
+                }
+
+                self.outbound_transmitters.retain(|group_key, container| {
+                    group_key.target_cid != key.target_cid
+                        || container.burst_transmitter.get_object_id() != key.object_id
+                });
+
+                ObjectTransferStatus::Cancelled
+            }
+
+            (ObjectTransferOrientation::Sender, control) => {
+                let file_transfer = if let Some(file_transfer) = self.outbound_files.get(&key) {
+                    file_transfer
+                } else {
+                    return false;
+                };
+
+                let paused = control == ObjectTransferControl::Pause;
+                if file_transfer.pause_tx.send_replace(paused) == paused {
+                    // no change
+                    return true;
+                }
+
+                if paused {
+                    ObjectTransferStatus::Paused
+                } else {
+                    ObjectTransferStatus::Unpaused
+                }
+            }
+
+            (ObjectTransferOrientation::Receiver, ObjectTransferControl::Cancel) => {
+                let file_transfer = if let Some(file_transfer) = self.inbound_files.remove(&key) {
+                    file_transfer
+                } else {
+                    return false;
+                };
+
+                if let Some(progress) = file_transfer.progress.as_ref() {
+                    progress.on_transfer_complete();
+                }
+                // dropping stream_to_hd ends the stream to the backend, after which the partial object is removed
+                let _ = file_transfer
+                    .reception_complete_tx
+                    .send(InboundFileConclusion::Cancelled);
+
+                self.inbound_groups.retain(|group_key, container| {
+                    group_key.target_cid != key.target_cid || container.object_id != key.object_id
+                });
+
+                ObjectTransferStatus::Cancelled
+            }
+
+            // pausing is enforced by the sender
+            (ObjectTransferOrientation::Receiver, ObjectTransferControl::Pause) => {
+                if !self.inbound_files.contains_key(&key) {
+                    return false;
+                }
+
+                ObjectTransferStatus::Paused
+            }
+
+            (ObjectTransferOrientation::Receiver, ObjectTransferControl::Resume) => {
+                if !self.inbound_files.contains_key(&key) {
+                    return false;
+                }
+
+                ObjectTransferStatus::Unpaused
+            }
+        };
+
+        if status.is_finished_type() {
+            if let Some(tx) = self.file_transfer_handles.remove(&key) {
+                let _ = tx.unbounded_send(status);
+            }
+        } else if let Some(tx) = self.file_transfer_handles.get(&key) {
+            let _ = tx.unbounded_send(status);
+        }
+
+        true
+    }
+
     /// This tells us that we should burst-send the packets now. Returns false if the UDP sockets disconnected
     /// `to_primary_stream`: If None, will use the Burst Transmitter
     /// `proposed_window`: In TCP only mode, this won't matter since reliability is handled by the TCP layer. As such, in TCP only mode
@@ -1311,27 +1934,31 @@ impl StateContainerInner {
                     .unwrap()
                     .receiver
                     .finalize();
+                let chunk_len = chunk.len();
+                let chunk_digest = group_digest(&chunk);
                 file_container
                     .stream_to_hd
                     .unbounded_send(chunk)
                     .map_err(|err| NetworkError::Generic(err.to_string()))?;
 
                 send_wave_ack = true;
+                // groups skipped when resuming are already counted
+                file_container.groups_rendered += 1;
+                file_container.group_digests.push(chunk_digest);
+                file_container.group_len = file_container.group_len.max(chunk_len);
+                if let Some(progress) = file_container.progress.as_mut() {
+                    progress.on_group_completed(chunk_len, Some(chunk_digest));
+                }
 
-                if group_id as usize == file_container.total_groups - 1 {
-                    complete = true;
-                    let file_container = self.inbound_files.remove(&file_key).unwrap();
-                    // status of reception complete now located where the streaming to HD completes
-                    // we need only take the sender and send a signal to prove that we finished correctly here
-                    file_container
-                        .reception_complete_tx
-                        .send(())
-                        .map_err(|_| NetworkError::msg("reception_complete_tx err"))?;
+                // the number of groups in a stream of unknown length is only known once the sender's summary arrives
+                if file_container.groups_rendered == file_container.total_groups {
+                    // if the sender's digest has not yet arrived, the transfer concludes once it does
+                    complete = self.try_conclude_inbound_file(file_key);
                 } else {
                     file_container.last_group_finish_time = Instant::now();
                     // TODO: Compute Mb/s
                     let status = ObjectTransferStatus::ReceptionTick(
-                        group_id as usize,
+                        file_container.groups_rendered - 1,
                         file_container.total_groups,
                         0 as f32,
                     );
@@ -1420,11 +2047,23 @@ impl StateContainerInner {
                 }
 
                 let file_key = FileKey::new(target_cid, object_id as u32);
+                // for streams of unknown length, the total is only known once the final group is enqueued
+                let is_last_group = relative_group_id as usize + 1
+                    == transmitter_container.parent_object_total_groups;
+
+                if let Some(progress) = self
+                    .outbound_files
+                    .get_mut(&file_key)
+                    .and_then(|file_transfer| file_transfer.progress.as_mut())
+                {
+                    progress.on_group_completed(transmitter_container.group_plaintext_length, None);
+                    if is_last_group {
+                        progress.on_transfer_complete();
+                    }
+                }
 
                 if let Some(tx) = self.file_transfer_handles.get(&file_key) {
-                    let status = if relative_group_id as usize
-                        != transmitter_container.parent_object_total_groups - 1
-                    {
+                    let status = if !is_last_group {
                         ObjectTransferStatus::TransferTick(
                             relative_group_id as usize,
                             transmitter_container.parent_object_total_groups,
@@ -1702,8 +2341,9 @@ impl StateContainerInner {
                     }
                 }
 
-                VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid) => {
-                    log::trace!(target: "citadel", "Maybe sending HyperLAN peer ({}) <-> HyperLAN Peer ({})", implicated_cid, target_cid);
+                VirtualConnectionType::LocalGroupPeer(implicated_cid, target_cid)
+                | VirtualConnectionType::ExternalGroupPeer(implicated_cid, _, target_cid) => {
+                    log::trace!(target: "citadel", "Maybe sending HyperLAN peer ({}) <-> Peer ({})", implicated_cid, target_cid);
                     // here, we don't use the base session's PQC. Instead, we use the vconn's pqc and Toolset
                     let default_primary_stream = this.get_primary_stream().cloned().unwrap();
 
@@ -1823,10 +2463,8 @@ impl StateContainerInner {
                     }
                 }
 
-                _ => {
-                    return Err(NetworkError::InvalidRequest(
-                        "HyperWAN functionality not yet implemented",
-                    ));
+                VirtualConnectionType::ExternalGroupServer(..) => {
+                    return Err(EXTERNAL_SERVER_UNREACHABLE);
                 }
             };
 
@@ -1959,7 +2597,8 @@ impl StateContainerInner {
                 }
             }
 
-            VirtualConnectionType::LocalGroupPeer(_, peer_cid) => {
+            VirtualConnectionType::LocalGroupPeer(_, peer_cid)
+            | VirtualConnectionType::ExternalGroupPeer(_, _, peer_cid) => {
                 const MISSING: NetworkError = NetworkError::InvalidRequest("Peer not connected");
                 let endpoint_container = &mut self
                     .active_virtual_connections
@@ -2027,7 +2666,7 @@ impl StateContainerInner {
                 }
             }
 
-            _ => Err(NetworkError::InternalError("HyperWAN Not implemented")),
+            VirtualConnectionType::ExternalGroupServer(..) => Err(EXTERNAL_SERVER_UNREACHABLE),
         }
     }
 
@@ -2053,15 +2692,37 @@ impl StateContainerInner {
         let to_primary_stream = self.get_primary_stream().unwrap();
 
         let timestamp = self.time_tracker.get_global_time_ns();
+
+        // messages to confidential groups are sealed under the local sender key, such that the server relays only ciphertext
+        let sealed = match command {
+            GroupBroadcast::Message(sender, key, sequence, message) => self
+                .group_sender_keys
+                .get(key)
+                .map(|keys| keys.seal(*key, *sender, message.as_ref()))
+                .transpose()?
+                .map(|(key_id, ciphertext)| {
+                    GroupBroadcast::SealedMessage(*sender, *key, *sequence, key_id, ciphertext)
+                }),
+            _ => None,
+        };
+        let command = sealed.as_ref().unwrap_or(command);
+
         let packet = match command {
             GroupBroadcast::Create(..)
             | GroupBroadcast::End(_)
             | GroupBroadcast::Kick(..)
             | GroupBroadcast::Message(..)
+            | GroupBroadcast::SealedMessage(..)
             | GroupBroadcast::Add(..)
             | GroupBroadcast::AcceptMembership(_)
             | GroupBroadcast::RequestJoin(..)
             | GroupBroadcast::ListGroupsFor(..)
+            | GroupBroadcast::ListMemberships
+            | GroupBroadcast::Reattach(_)
+            | GroupBroadcast::SetRole(..)
+            | GroupBroadcast::SetMuted(..)
+            | GroupBroadcast::TransferOwnership(..)
+            | GroupBroadcast::FetchHistory(..)
             | GroupBroadcast::LeaveRoom(_) => packet_crafter::peer_cmd::craft_group_message_packet(
                 hyper_ratchet,
                 command,
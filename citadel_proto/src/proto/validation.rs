@@ -208,17 +208,20 @@ pub(crate) mod pre_connect {
     use crate::error::NetworkError;
     use crate::proto::misc::session_security_settings::SessionSecuritySettings;
     use crate::proto::node::ConnectMode;
+    use crate::proto::node::SecrecyMode;
     use crate::proto::packet::HdpPacket;
     use crate::proto::packet_crafter::pre_connect::{PreConnectStage0, SynPacket};
     use crate::proto::packet_processor::includes::packet_crafter::pre_connect::SynAckPacket;
     use crate::proto::peer::peer_layer::UdpMode;
     use crate::proto::session_manager::HdpSessionManager;
+    use citadel_crypt::prelude::ConstructorOpts;
     use citadel_crypt::stacked_ratchet::constructor::{
         BobToAliceTransfer, BobToAliceTransferType, StackedRatchetConstructor,
     };
     use citadel_crypt::stacked_ratchet::{Ratchet, StackedRatchet};
     use citadel_user::prelude::ConnectProtocol;
     use citadel_user::serialization::SyncIO;
+    use citadel_user::server_misc_settings::CryptoPolicy;
     use citadel_wire::nat_identification::NatType;
 
     pub(crate) type SynValidationResult = (
@@ -236,6 +239,7 @@ pub(crate) mod pre_connect {
         cnac: &ClientNetworkAccount,
         packet: HdpPacket,
         session_manager: &HdpSessionManager,
+        crypto_policy: &CryptoPolicy,
     ) -> Result<SynValidationResult, NetworkError> {
         // TODO: NOTE: This can interrupt any active session's. This should be moved up after checking the connect mode
         let static_auxiliary_ratchet = cnac.refresh_static_hyper_ratchet();
@@ -262,7 +266,20 @@ pub(crate) mod pre_connect {
             _ => {}
         }
 
-        let session_security_settings = transfer.session_security_settings;
+        let mut session_security_settings = transfer.session_security_settings;
+        // both endpoints must agree on valid crypto parameters before the KEM proceeds
+        citadel_pqcrypto::validate_crypto_params(&session_security_settings.crypto_params)
+            .map_err(|err| NetworkError::Generic(err.to_string()))?;
+        // The algorithms and security level are fixed at registration, so those can only be rejected
+        // if they violate the server's policy. The secrecy mode, however, may be negotiated upward
+        crypto_policy.check_crypto_params(&session_security_settings.crypto_params)?;
+        crypto_policy.check_security_level(session_security_settings.security_level)?;
+        if crypto_policy.require_perfect_secrecy
+            && session_security_settings.secrecy_mode != SecrecyMode::Perfect
+        {
+            log::trace!(target: "citadel", "Server crypto policy requires perfect secrecy; upgrading session secrecy mode");
+            session_security_settings.secrecy_mode = SecrecyMode::Perfect;
+        }
         let peer_only_connect_mode = transfer.peer_only_connect_protocol;
         let nat_type = transfer.nat_type;
         let udp_mode = transfer.udp_mode;
@@ -270,11 +287,15 @@ pub(crate) mod pre_connect {
         let _ = static_auxiliary_ratchet
             .verify_level(Some(transfer.session_security_settings.security_level))
             .map_err(|err| NetworkError::Generic(err.into_string()))?;
-        let opts = static_auxiliary_ratchet
+        let opts: Vec<ConstructorOpts> = static_auxiliary_ratchet
             .get_next_constructor_opts()
             .into_iter()
             .take((transfer.session_security_settings.security_level.value() + 1) as usize)
             .collect();
+        // the parameters actually used by the KEM are those established during registration
+        for params in opts.iter().filter_map(|opts| opts.cryptography.as_ref()) {
+            crypto_policy.check_crypto_params(params)?;
+        }
         //let opts = ConstructorOpts::new_vec_init(Some(transfer.transfer.params), (transfer.transfer.security_level.value() + 1) as usize).into_i;
         let bob_constructor = StackedRatchetConstructor::new_bob(
             header.session_cid.get(),
@@ -316,7 +337,7 @@ pub(crate) mod pre_connect {
         cnac: &ClientNetworkAccount,
         mut alice_constructor: StackedRatchetConstructor,
         packet: HdpPacket,
-    ) -> Option<(StackedRatchet, NatType)> {
+    ) -> Option<(StackedRatchet, NatType, SessionSecuritySettings)> {
         let static_auxiliary_ratchet = cnac.get_static_auxiliary_hyper_ratchet();
         let (header, payload, _, _) = packet.decompose();
         let (_, payload) =
@@ -336,7 +357,11 @@ pub(crate) mod pre_connect {
         let _ = new_hyper_ratchet.verify_level(lvl.into()).ok()?;
         let toolset = Toolset::from((static_auxiliary_ratchet, new_hyper_ratchet.clone()));
         cnac.replace_toolset(toolset);
-        Some((new_hyper_ratchet, packet.nat_type))
+        Some((
+            new_hyper_ratchet,
+            packet.nat_type,
+            packet.session_security_settings,
+        ))
     }
 
     // Returns the adjacent node type, wave ports, and external IP. Serverside, we do not update the CNAC's toolset until this point
@@ -353,7 +378,11 @@ pub(crate) mod file {
     use crate::proto::packet::HdpHeader;
     use crate::proto::packet_processor::includes::LayoutVerified;
     use crate::proto::state_container::VirtualTargetType;
-    use citadel_user::backend::utils::VirtualObjectMetadata;
+    use citadel_crypt::streaming_crypt_scrambler::{ObjectDigest, ObjectSummary, StartGroup};
+    use citadel_user::backend::utils::{
+        ObjectTransferControl, ObjectTransferOrientation, VirtualObjectMetadata,
+    };
+    use citadel_user::serialization::SyncIO;
 
     pub fn validate_file_header(
         header: &LayoutVerified<&[u8], HdpHeader>,
@@ -370,21 +399,79 @@ pub(crate) mod file {
         }
     }
 
-    /// return Some(success, object_id) if valid, or None if invalid
+    /// return Some(start_group, object_id) if valid, or None if invalid. The start group is None if the transfer was declined
     pub fn validate_file_header_ack(
         header: &LayoutVerified<&[u8], HdpHeader>,
         payload: &[u8],
-    ) -> Option<(bool, u32, VirtualTargetType)> {
+    ) -> Option<(Option<StartGroup>, u32, VirtualTargetType)> {
         // 16 bytes for the signature
         if !payload.is_empty() {
             let object_id = header.wave_id.get();
-            let success = header.group.get() != 0;
+            let start_group = header
+                .group
+                .get()
+                .checked_sub(1)
+                .map(|start_group| start_group as usize);
+            // when resuming, the digest of the groups already received follows the virtual target
+            let (payload, start_group) = match start_group {
+                Some(group) if group != 0 => {
+                    let split_idx = payload
+                        .len()
+                        .checked_sub(std::mem::size_of::<ObjectDigest>())?;
+                    let (payload, prefix_digest) = payload.split_at(split_idx);
+                    let prefix_digest = ObjectDigest::try_from(prefix_digest).ok()?;
+                    (
+                        payload,
+                        Some(StartGroup {
+                            group,
+                            prefix_digest: Some(prefix_digest),
+                        }),
+                    )
+                }
+
+                start_group => (
+                    payload,
+                    start_group.map(|group| StartGroup {
+                        group,
+                        prefix_digest: None,
+                    }),
+                ),
+            };
             let v_target = VirtualTargetType::deserialize_from(payload)?;
-            Some((success, object_id, v_target))
+            Some((start_group, object_id, v_target))
         } else {
             None
         }
     }
+
+    /// return Some(orientation, control, object_id, v_target) if valid, or None if invalid. The orientation is that of
+    /// the local end of the transfer
+    pub fn validate_file_control(
+        header: &LayoutVerified<&[u8], HdpHeader>,
+        payload: &[u8],
+    ) -> Option<(
+        ObjectTransferOrientation,
+        ObjectTransferControl,
+        u32,
+        VirtualTargetType,
+    )> {
+        let (orientation, control, v_target) = <(
+            ObjectTransferOrientation,
+            ObjectTransferControl,
+            VirtualTargetType,
+        )>::deserialize_from_vector(payload)
+        .ok()?;
+        Some((orientation, control, header.wave_id.get(), v_target))
+    }
+
+    /// returns the object ID and the summary of the object
+    pub fn validate_file_digest(
+        header: &LayoutVerified<&[u8], HdpHeader>,
+        payload: &[u8],
+    ) -> Option<(u32, ObjectSummary)> {
+        let summary = ObjectSummary::deserialize_from_vector(payload).ok()?;
+        Some((header.wave_id.get(), summary))
+    }
 }
 
 pub(crate) mod aead {
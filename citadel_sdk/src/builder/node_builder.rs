@@ -181,6 +181,15 @@ impl NodeBuilder {
         self
     }
 
+    /// Sets the crypto policy that inbound registrations and connections must satisfy. Connections requesting disallowed
+    /// algorithms or an insufficient security level are rejected, whereas sessions may be upgraded to perfect secrecy if required
+    pub fn with_crypto_policy(&mut self, crypto_policy: CryptoPolicy) -> &mut Self {
+        self.server_misc_settings
+            .get_or_insert_with(Default::default)
+            .crypto_policy = crypto_policy;
+        self
+    }
+
     /// Creates a Google Realtime Database configuration given the project URL and API Key. Requires the use of [`Self::with_google_services_json_path`] to allow minting of JsonWebTokens
     /// at the central server
     #[cfg(feature = "google-services")]
@@ -134,6 +134,7 @@ pub mod prelude {
     pub use crate::remote_ext::user_ids::*;
     pub use crate::remote_ext::*;
     pub use crate::responses;
+    pub use crate::typed_channel::*;
     pub use citadel_proto::prelude::*;
 }
 
@@ -148,3 +149,5 @@ pub mod remote_ext;
 pub mod responses;
 #[doc(hidden)]
 pub mod test_common;
+/// Channels that send and receive serializable types instead of raw bytes
+pub mod typed_channel;
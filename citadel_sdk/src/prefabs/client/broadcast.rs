@@ -118,6 +118,10 @@ where
                     MessageGroupOptions {
                         group_type: GroupType::Public,
                         id: group_id.as_u128(),
+                        persistent: false,
+                        auto_succession: false,
+                        confidential: false,
+                        history_len: 0,
                     },
                 )
             }
@@ -355,6 +359,10 @@ mod tests {
                         assert_eq!(owned_groups.len(), 0);
                     }
 
+                    let joined_groups = remote.list_joined_groups().await.unwrap();
+                    assert_eq!(joined_groups.len(), 1);
+                    assert_eq!(joined_groups[0].mgid, group_id.as_u128());
+
                     wait_for_peers().await;
                     std::mem::drop(channel);
                     remote.shutdown_kernel().await
@@ -632,7 +632,7 @@ mod tests {
                                     }
 
                                     ObjectTransferStatus::ReceptionBeginning(file_path, vfm) => {
-                                        path = Some(file_path);
+                                        path = file_path;
                                         assert_eq!(vfm.get_target_name(), "TheBridge.pdf")
                                     }
 
@@ -225,6 +225,16 @@ where
             }
         };
 
+        // the filter must exist before connecting, since objects and messages forwarded while this client was offline
+        // are delivered immediately after the connection succeeds
+        let unprocessed_signal_filter = if cfg!(feature = "localhost-testing") {
+            let (reroute_tx, reroute_rx) = tokio::sync::mpsc::unbounded_channel();
+            *self.unprocessed_signal_filter_tx.lock() = Some(reroute_tx);
+            Some(reroute_rx)
+        } else {
+            None
+        };
+
         let connect_success = remote
             .connect(
                 auth,
@@ -235,14 +245,7 @@ where
             )
             .await?;
         let conn_type = VirtualTargetType::LocalGroupServer(connect_success.cid);
-
-        let unprocessed_signal_filter = if cfg!(feature = "localhost-testing") {
-            let (reroute_tx, reroute_rx) = tokio::sync::mpsc::unbounded_channel();
-            *self.unprocessed_signal_filter_tx.lock() = Some(reroute_tx);
-            Some(reroute_rx)
-        } else {
-            None
-        };
+        let exporter = connect_success.channel.keying_material_exporter().clone();
 
         (handler)(
             connect_success,
@@ -250,6 +253,7 @@ where
                 inner: remote,
                 unprocessed_signals_rx: Arc::new(Mutex::new(unprocessed_signal_filter)),
                 conn_type,
+                exporter,
             },
         )
         .await
@@ -465,6 +469,178 @@ mod tests {
         assert!(server_success.load(Ordering::Relaxed));
     }
 
+    #[rstest]
+    #[timeout(std::time::Duration::from_secs(90))]
+    #[tokio::test(flavor = "multi_thread")]
+    #[cfg(feature = "filesystem")]
+    async fn test_single_connection_crypto_policy() {
+        use crate::prefabs::server::client_connect_listener::ClientConnectListenerKernel;
+        use crate::prefabs::server::empty::EmptyKernel;
+        use crate::test_common::{get_unused_tcp_port, server_test_node};
+        use std::net::SocketAddr;
+        use std::str::FromStr;
+
+        let _ = citadel_logging::setup_log();
+        TestBarrier::setup(2);
+
+        let udp_mode = UdpMode::Disabled;
+        let client_success = &AtomicBool::new(false);
+        let server_success = &AtomicBool::new(false);
+        // both nodes are restarted below, so each keeps its accounts on disk
+        let backend = || {
+            let mut dir = std::env::temp_dir();
+            dir.push(format!("citadel_policy_{}", Uuid::new_v4().as_u128()));
+            BackendType::Filesystem(dir.to_str().unwrap().to_string())
+        };
+        let (server_backend, client_backend) = (backend(), backend());
+        let server_addr =
+            SocketAddr::from_str(&format!("127.0.0.1:{}", get_unused_tcp_port())).unwrap();
+        let username = format!("policy.{}", Uuid::new_v4().as_u128());
+
+        let session_security_settings = SessionSecuritySettingsBuilder::default()
+            .with_secrecy_mode(SecrecyMode::BestEffort)
+            .with_crypto_params(EncryptionAlgorithm::AES_GCM_256_SIV + KemAlgorithm::Kyber)
+            .build()
+            .unwrap();
+
+        // The first policy allows any algorithm, but upgrades the session to perfect secrecy
+        let server = server_test_node(
+            server_addr,
+            Box::new(ClientConnectListenerKernel::new(
+                |conn: ConnectionSuccess, remote: ClientServerRemote| async move {
+                    assert_eq!(conn.channel.get_secrecy_mode(), SecrecyMode::Perfect);
+                    wait_for_peers().await;
+                    server_success.store(true, Ordering::Relaxed);
+                    remote.shutdown_kernel().await
+                },
+            )) as Box<dyn NetKernel>,
+            |builder| {
+                let _ = builder
+                    .with_backend(server_backend.clone())
+                    .with_crypto_policy(CryptoPolicy {
+                        require_perfect_secrecy: true,
+                        ..Default::default()
+                    });
+            },
+        );
+
+        let client_kernel = SingleClientServerConnectionKernel::new_register(
+            "Thomas P Braun",
+            username.clone(),
+            "password",
+            server_addr,
+            udp_mode,
+            session_security_settings,
+            |conn, remote| async move {
+                assert_eq!(conn.channel.get_secrecy_mode(), SecrecyMode::Perfect);
+                wait_for_peers().await;
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default()
+            .with_backend(client_backend.clone())
+            .build(client_kernel)
+            .unwrap();
+
+        let _ = futures::future::try_join(server, client).await.unwrap();
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+
+        // The restarted server only allows hybrid KEMs. Since registration already happened, the
+        // algorithm can only be rejected when the SYN is validated
+        let server = server_test_node(server_addr, EmptyKernel, |builder| {
+            let _ = builder
+                .with_backend(server_backend)
+                .with_crypto_policy(CryptoPolicy {
+                    allowed_kem_algorithms: vec![KemAlgorithm::HybridX25519Kyber],
+                    ..Default::default()
+                });
+        });
+
+        let client_kernel = SingleClientServerConnectionKernel::new_connect(
+            username,
+            "password",
+            udp_mode,
+            session_security_settings,
+            |_channel, remote| async move { remote.shutdown_kernel().await },
+        );
+
+        let client = NodeBuilder::default()
+            .with_backend(client_backend)
+            .build(client_kernel)
+            .unwrap();
+
+        tokio::select! {
+            server_res = server => panic!("Server ended prematurely: {:?}", server_res.map(|_| ())),
+            client_res = client => {
+                let reason = client_res.map(|_| ()).unwrap_err().into_string();
+                assert!(
+                    reason.contains("Server crypto policy does not allow the KEM algorithm Kyber"),
+                    "Unexpected connect failure reason: {reason}"
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    #[timeout(std::time::Duration::from_secs(90))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_single_connection_export_keying_material() {
+        let _ = citadel_logging::setup_log();
+        TestBarrier::setup(2);
+
+        const LABEL: &[u8] = b"local database";
+        const CONTEXT: &[u8] = b"test";
+
+        let udp_mode = UdpMode::Disabled;
+        let client_key = &parking_lot::Mutex::new(None);
+        let server_key = &parking_lot::Mutex::new(None);
+
+        let (server, server_addr) = server_info_reactive(
+            |conn, remote| async move {
+                let exported = remote.export_keying_material(LABEL, CONTEXT, 32)?;
+                assert_eq!(
+                    exported,
+                    conn.channel.export_keying_material(LABEL, CONTEXT, 32)?
+                );
+                *server_key.lock() = Some(exported);
+                wait_for_peers().await;
+                remote.shutdown_kernel().await
+            },
+            |_| (),
+        );
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless(
+            Uuid::new_v4(),
+            server_addr,
+            udp_mode,
+            Default::default(),
+            |channel, remote| async move {
+                let exported = channel.channel.export_keying_material(LABEL, CONTEXT, 32)?;
+                assert_ne!(
+                    exported,
+                    remote.export_keying_material(b"other label", CONTEXT, 32)?
+                );
+                *client_key.lock() = Some(exported);
+                wait_for_peers().await;
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        let client_key = client_key.lock().take().unwrap();
+        let server_key = server_key.lock().take().unwrap();
+        assert_eq!(client_key.len(), 32);
+        assert_eq!(client_key, server_key);
+    }
+
     #[rstest]
     #[timeout(std::time::Duration::from_secs(90))]
     #[tokio::test(flavor = "multi_thread")]
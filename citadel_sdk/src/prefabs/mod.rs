@@ -18,6 +18,7 @@ pub struct ClientServerRemote {
     #[allow(dead_code)]
     unprocessed_signals_rx: Arc<Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<NodeResult>>>>,
     conn_type: VirtualTargetType,
+    exporter: KeyingMaterialExporter,
 }
 
 impl ClientServerRemote {
@@ -50,6 +51,17 @@ impl ClientServerRemote {
         self.inner.shutdown().await
     }
 
+    /// Derives keying material bound to the client-to-server session. Both the client and server derive identical
+    /// output for the same `label` and `context`. See [`KeyingMaterialExporter::export_keying_material`]
+    pub fn export_keying_material(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, NetworkError> {
+        self.exporter.export_keying_material(label, context, len)
+    }
+
     pub async fn get_peers(
         &mut self,
         limit: Option<usize>,
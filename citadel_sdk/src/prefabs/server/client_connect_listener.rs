@@ -58,6 +58,7 @@ where
                     inner: self.node_remote.clone().unwrap(),
                     unprocessed_signals_rx: Default::default(),
                     conn_type,
+                    exporter: channel.keying_material_exporter().clone(),
                 };
                 (self.on_channel_received)(
                     ConnectionSuccess {
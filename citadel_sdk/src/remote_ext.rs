@@ -254,6 +254,39 @@ pub trait ProtocolRemoteExt: Remote {
         .await
     }
 
+    /// Connects the local server to an adjacent server, establishing a federated (HyperWAN) link. The credentials
+    /// must belong to an account on the adjacent server whose username is listed inside the adjacent server's
+    /// [`ServerMiscSettings::federated_servers`]. Returns the icid of the link, which is the CID of the account
+    async fn connect_to_federated_server(
+        &mut self,
+        auth: AuthenticationRequest,
+        session_security_settings: SessionSecuritySettings,
+    ) -> Result<u64, NetworkError> {
+        let connect_request = NodeRequest::ConnectToFederatedServer(ConnectToHypernode {
+            auth_request: auth,
+            connect_mode: Default::default(),
+            udp_mode: UdpMode::Disabled,
+            keep_alive_timeout: None,
+            session_security_settings,
+        });
+
+        match map_errors(self.send_callback(connect_request).await?)? {
+            NodeResult::ConnectSuccess(ConnectSuccess {
+                implicated_cid: icid,
+                ..
+            }) => Ok(icid),
+            NodeResult::ConnectFail(ConnectFail {
+                ticket: _,
+                cid_opt: _,
+                error_message: err,
+            }) => Err(NetworkError::Generic(err)),
+            res => Err(NetworkError::msg(format!(
+                "[connect_to_federated_server] An unexpected response occurred: {:?}",
+                res
+            ))),
+        }
+    }
+
     /// Creates a valid target identifier used to make protocol requests. Raw user IDs or usernames can be used
     /// ```
     /// use citadel_proto::prelude::*;
@@ -309,6 +342,32 @@ pub trait ProtocolRemoteExt: Remote {
         }
     }
 
+    /// Creates a proposed target from the valid local user to a peer of an adjacent server reachable through the
+    /// federated link `icid`. Peers may be identified by CID, or, by their username on the adjacent server
+    async fn propose_federated_target<
+        T: Into<UserIdentifier> + Send,
+        P: Into<UserIdentifier> + Send,
+    >(
+        &mut self,
+        local_user: T,
+        icid: u64,
+        peer: P,
+    ) -> Result<SymmetricIdentifierHandleRef<'_>, NetworkError> {
+        let local_cid = self.get_implicated_cid(local_user).await?;
+        match peer.into() {
+            UserIdentifier::ID(peer_cid) => Ok(SymmetricIdentifierHandleRef {
+                user: VirtualTargetType::ExternalGroupPeer(local_cid, icid, peer_cid),
+                remote: self.remote_ref_mut(),
+                target_username: None,
+            }),
+            UserIdentifier::Username(uname) => Ok(SymmetricIdentifierHandleRef {
+                user: VirtualTargetType::ExternalGroupPeer(local_cid, icid, 0),
+                remote: self.remote_ref_mut(),
+                target_username: Some(uname),
+            }),
+        }
+    }
+
     /// Returns a list of hyperlan peers on the network for local_user. May or may not be registered to the user. To get a list of registered users to local_user, run [`Self::get_hyperlan_mutual_peers`]
     /// - limit: if None, all peers are obtained. If Some, at most the specified number of peers will be obtained
     async fn get_hyperlan_peers<T: Into<UserIdentifier> + Send>(
@@ -350,6 +409,48 @@ pub trait ProtocolRemoteExt: Remote {
         Err(NetworkError::InternalError("Internal kernel stream died"))
     }
 
+    /// Returns a list of peers registered to the adjacent server reachable through the federated link `icid`
+    /// - limit: if None, all peers are obtained. If Some, at most the specified number of peers will be obtained
+    async fn get_hyperwan_peers<T: Into<UserIdentifier> + Send>(
+        &mut self,
+        local_user: T,
+        icid: u64,
+        limit: Option<usize>,
+    ) -> Result<Vec<HyperlanPeer>, NetworkError> {
+        let local_cid = self.get_implicated_cid(local_user).await?;
+        let command = NodeRequest::PeerCommand(PeerCommand {
+            implicated_cid: local_cid,
+            command: PeerSignal::GetRegisteredPeers(
+                HypernodeConnectionType::HyperLANPeerToHyperWANServer(local_cid, icid),
+                None,
+                limit.map(|r| r as i32),
+            ),
+        });
+
+        let mut stream = self.send_callback_subscription(command).await?;
+
+        while let Some(status) = stream.next().await {
+            if let NodeResult::PeerEvent(PeerEvent {
+                event:
+                    PeerSignal::GetRegisteredPeers(
+                        _,
+                        Some(PeerResponse::RegisteredCids(cids, is_onlines)),
+                        _,
+                    ),
+                ticket: _,
+            }) = map_errors(status)?
+            {
+                return Ok(cids
+                    .into_iter()
+                    .zip(is_onlines.into_iter())
+                    .map(|(cid, is_online)| HyperlanPeer { cid, is_online })
+                    .collect());
+            }
+        }
+
+        Err(NetworkError::InternalError("Internal kernel stream died"))
+    }
+
     /// Returns a list of mutually-registered peers with the local_user
     async fn get_hyperlan_mutual_peers<T: Into<UserIdentifier> + Send>(
         &mut self,
@@ -428,6 +529,31 @@ pub trait ProtocolRemoteTargetExt: TargetLockedRemote {
         &mut self,
         path: T,
         chunk_size: usize,
+    ) -> Result<(), NetworkError> {
+        let path: PathBuf = path.into();
+        self.send_object_with_custom_chunking(path, chunk_size)
+            .await
+    }
+
+    /// Sends an object from any [`ObjectSource`], such as in-memory bytes or an [`AsyncReadSource`] whose length is
+    /// unknown until EOF, with a custom chunk size. A chunk size of zero will use the default
+    async fn send_object_with_custom_chunking<S: ObjectSource>(
+        &mut self,
+        source: S,
+        chunk_size: usize,
+    ) -> Result<(), NetworkError> {
+        let user = *self.user();
+        self.send_object_request(source, chunk_size, user, false)
+            .await
+    }
+
+    #[doc(hidden)]
+    async fn send_object_request<S: ObjectSource>(
+        &mut self,
+        source: S,
+        chunk_size: usize,
+        v_conn_type: VirtualTargetType,
+        store_and_forward: bool,
     ) -> Result<(), NetworkError> {
         let chunk_size = if chunk_size == 0 {
             None
@@ -435,15 +561,15 @@ pub trait ProtocolRemoteTargetExt: TargetLockedRemote {
             Some(chunk_size)
         };
         let implicated_cid = self.user().get_implicated_cid();
-        let user = *self.user();
         let remote = self.remote();
 
         let result = remote
             .send_callback(NodeRequest::SendObject(SendObject {
-                source: Box::new(path.into()),
+                source: Box::new(source),
                 chunk_size,
                 implicated_cid,
-                v_conn_type: user,
+                v_conn_type,
+                store_and_forward,
             }))
             .await?;
         match map_errors(result)? {
@@ -472,6 +598,98 @@ pub trait ProtocolRemoteTargetExt: TargetLockedRemote {
         self.send_file_with_custom_chunking(path, 0).await
     }
 
+    /// Sends an object from any [`ObjectSource`] to the provided target using the default chunking size
+    async fn send_object<S: ObjectSource>(&mut self, source: S) -> Result<(), NetworkError> {
+        self.send_object_with_custom_chunking(source, 0).await
+    }
+
+    /// Sends a directory, and everything beneath it, as a single transfer with a custom chunk size. The receiver accepts
+    /// the directory once, after which each file is unpacked and verified against the directory's manifest as it arrives.
+    /// If the session uses a signature algorithm, the manifest is signed with the session's signature key. Otherwise, the
+    /// manifest is protected only by the session's encryption. A chunk size of zero will use the default
+    async fn send_directory_with_custom_chunking<T: Into<PathBuf> + Send>(
+        &mut self,
+        path: T,
+        chunk_size: usize,
+    ) -> Result<(), NetworkError> {
+        let path: PathBuf = path.into();
+        // building the manifest hashes every file, and thus must not block the runtime
+        let source = tokio::task::spawn_blocking(move || DirectorySource::new(path))
+            .await
+            .map_err(|err| NetworkError::msg(err.to_string()))?
+            .map_err(|err| NetworkError::msg(err.into_string()))?;
+        self.send_object_with_custom_chunking(source, chunk_size)
+            .await
+    }
+
+    /// Sends a directory to the provided target using the default chunking size
+    async fn send_directory<T: Into<PathBuf> + Send>(
+        &mut self,
+        path: T,
+    ) -> Result<(), NetworkError> {
+        self.send_directory_with_custom_chunking(path, 0).await
+    }
+
+    /// Sends an object to a peer who may be offline. The object is sealed under a key shared only with the peer, then
+    /// uploaded to the server, which delivers the object once the peer next connects. The server is unable to read the
+    /// object. Returns once the server stores the object. The peer must be a mutual, and the two peers must have
+    /// connected at least once beforehand
+    async fn store_and_forward_object<S: ObjectSource>(
+        &mut self,
+        source: S,
+    ) -> Result<(), NetworkError> {
+        let peer_target = self.try_as_peer_connection().await?;
+        self.send_object_request(source, 0, peer_target.as_virtual_connection(), true)
+            .await
+    }
+
+    /// Sends a file to a peer who may be offline. See [`Self::store_and_forward_object`]
+    async fn store_and_forward_file<T: Into<PathBuf> + Send>(
+        &mut self,
+        path: T,
+    ) -> Result<(), NetworkError> {
+        let path: PathBuf = path.into();
+        self.store_and_forward_object(path).await
+    }
+
+    /// Sends a message to a peer who may be offline. The message is sealed under a key shared only with the peer, such
+    /// that the server is unable to read it. If the peer is offline, the server queues the message, and delivers the queue,
+    /// in order, once the peer next connects. The peer receives the message through a [`MailboxDelivery`]. Returns once the
+    /// server delivers or queues the message.
+    ///
+    /// Unlike [`PeerChannelSendHalf::send_message`](crate::prelude::PeerChannelSendHalf::send_message), which requires a
+    /// channel with the peer, and thus that the peer is online, the message is relayed by the server. Hence, the message
+    /// is not ordered with respect to the messages of any channel, and [`PeerChannel`] does not fall back to queueing
+    async fn send_offline_message<T: Into<Vec<u8>> + Send>(
+        &mut self,
+        message: T,
+    ) -> Result<(), NetworkError> {
+        let peer_conn = self.try_as_peer_connection().await?;
+        let implicated_cid = self.user().get_implicated_cid();
+        let message = QueuedMessage {
+            sender_cid: implicated_cid,
+            queued_at: 0,
+            payload: message.into(),
+        };
+        let request = NodeRequest::PeerCommand(PeerCommand {
+            implicated_cid,
+            command: PeerSignal::OfflineMessage(peer_conn, message),
+        });
+
+        let mut subscription = self.remote().send_callback_subscription(request).await?;
+        while let Some(result) = subscription.next().await {
+            if let NodeResult::PeerEvent(PeerEvent {
+                event: PeerSignal::SignalReceived(..),
+                ticket: _,
+            }) = map_errors(result)?
+            {
+                return Ok(());
+            }
+        }
+
+        Err(NetworkError::InternalError("Offline message stream died"))
+    }
+
     /// Connects to the peer with custom settings
     async fn connect_to_peer_custom(
         &mut self,
@@ -639,11 +857,22 @@ pub trait ProtocolRemoteTargetExt: TargetLockedRemote {
         &mut self,
         initial_users_to_invite: Option<Vec<UserIdentifier>>,
     ) -> Result<GroupChannel, NetworkError> {
+        self.create_group_with_options(initial_users_to_invite, MessageGroupOptions::default())
+            .await
+    }
+
+    /// Creates a group with custom options. Setting [`MessageGroupOptions::persistent`] allows the group to survive
+    /// both the owner disconnecting and server restarts. After reconnecting, members rebind to the group via
+    /// [`Self::reattach_group`]. Returns an error if the options are contradictory (see [`MessageGroupOptions::validate`])
+    async fn create_group_with_options(
+        &mut self,
+        initial_users_to_invite: Option<Vec<UserIdentifier>>,
+        options: MessageGroupOptions,
+    ) -> Result<GroupChannel, NetworkError> {
+        options.validate()?;
         let implicated_cid = self.user().get_implicated_cid();
 
         let mut initial_users = vec![];
-        // TODO: allow for custom message group options. For now, don't
-        let options = MessageGroupOptions::default();
         // TODO/NOTE: default is PRIVATE mode, meaning all users in group must be registered to the owner
         // in the future, allow for private/public modes by adjusting the below. Initial users should be
         // a UserIdentifier
@@ -711,6 +940,66 @@ pub trait ProtocolRemoteTargetExt: TargetLockedRemote {
         ))
     }
 
+    /// Lists all groups in which the current peer is a member, including the groups it owns
+    async fn list_joined_groups(&mut self) -> Result<Vec<MessageGroupKey>, NetworkError> {
+        let implicated_cid = self.user().get_implicated_cid();
+        let request = NodeRequest::GroupBroadcastCommand(GroupBroadcastCommand {
+            implicated_cid,
+            command: GroupBroadcast::ListMemberships,
+        });
+        let mut subscription = self.remote().send_callback_subscription(request).await?;
+
+        while let Some(evt) = subscription.next().await {
+            if let NodeResult::GroupEvent(GroupEvent {
+                implicated_cid: _,
+                ticket: _,
+                event: GroupBroadcast::ListResponse(groups),
+            }) = evt
+            {
+                return Ok(groups);
+            }
+        }
+
+        Err(NetworkError::InternalError(
+            "List_joined_groups ended unexpectedly",
+        ))
+    }
+
+    /// Rebinds a channel to a persistent group in which the current peer is a member. Since channels end with the
+    /// session, this should be called for each group of interest after reconnecting
+    async fn reattach_group(&mut self, key: MessageGroupKey) -> Result<GroupChannel, NetworkError> {
+        let implicated_cid = self.user().get_implicated_cid();
+        let request = NodeRequest::GroupBroadcastCommand(GroupBroadcastCommand {
+            implicated_cid,
+            command: GroupBroadcast::Reattach(key),
+        });
+        let mut subscription = self.remote().send_callback_subscription(request).await?;
+
+        while let Some(evt) = subscription.next().await {
+            match map_errors(evt)? {
+                NodeResult::GroupChannelCreated(GroupChannelCreated { ticket: _, channel }) => {
+                    return Ok(channel)
+                }
+
+                NodeResult::GroupEvent(GroupEvent {
+                    event: GroupBroadcast::ReattachResponse(_, false),
+                    ..
+                }) => return Err(NetworkError::msg(format!("Not a member of group {}", key))),
+
+                NodeResult::GroupEvent(GroupEvent {
+                    event: GroupBroadcast::GroupNonExists(_),
+                    ..
+                }) => return Err(NetworkError::msg(format!("Group {} does not exist", key))),
+
+                _ => {}
+            }
+        }
+
+        Err(NetworkError::InternalError(
+            "Reattach_group ended unexpectedly",
+        ))
+    }
+
     /// Begins a re-key, updating the container in the process.
     /// Returns the new key matrix version. Does not return the new key version
     /// if the rekey fails, or, if a current rekey is already executing
@@ -844,6 +1133,7 @@ pub mod remote_specialization {
 mod tests {
     use crate::builder::node_builder::{NodeBuilder, NodeFuture};
     use crate::prefabs::client::single_connection::SingleClientServerConnectionKernel;
+    use crate::prefabs::ClientServerRemote;
     use crate::prelude::ProtocolRemoteTargetExt;
     use crate::prelude::*;
     use rstest::rstest;
@@ -901,7 +1191,7 @@ mod tests {
                         }
 
                         ObjectTransferStatus::ReceptionBeginning(file_path, vfm) => {
-                            path = Some(file_path);
+                            path = file_path;
                             assert_eq!(vfm.get_target_name(), "TheBridge.pdf")
                         }
 
@@ -918,66 +1208,491 @@ mod tests {
         }
     }
 
-    pub fn server_info<'a>(
-        switch: Arc<AtomicBool>,
-    ) -> (NodeFuture<'a, ReceiverFileTransferKernel>, SocketAddr) {
-        let port = crate::test_common::get_unused_tcp_port();
-        let bind_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
-        let server = crate::test_common::server_test_node(
-            bind_addr,
-            ReceiverFileTransferKernel(None, switch),
-            |_| {},
-        );
-        (server, bind_addr)
-    }
+    /// Accepts the transfer, then cancels it once the first group arrives
+    pub struct CancellingReceiverKernel(pub Option<NodeRemote>, pub Arc<AtomicBool>);
 
-    #[rstest]
-    #[timeout(std::time::Duration::from_secs(90))]
-    #[case(
-        EncryptionAlgorithm::AES_GCM_256_SIV,
-        KemAlgorithm::Kyber,
-        SigAlgorithm::None
-    )]
-    #[case(
-        EncryptionAlgorithm::Kyber,
-        KemAlgorithm::Kyber,
-        SigAlgorithm::Falcon1024
-    )]
-    #[tokio::test]
-    async fn test_c2s_file_transfer(
-        #[case] enx: EncryptionAlgorithm,
-        #[case] kem: KemAlgorithm,
-        #[case] sig: SigAlgorithm,
-    ) {
-        let _ = citadel_logging::setup_log();
-        let ref client_success = AtomicBool::new(false);
-        let ref server_success = Arc::new(AtomicBool::new(false));
-        let (server, server_addr) = server_info(server_success.clone());
-        let uuid = Uuid::new_v4();
+    #[async_trait]
+    impl NetKernel for CancellingReceiverKernel {
+        fn load_remote(&mut self, node_remote: NodeRemote) -> Result<(), NetworkError> {
+            self.0 = Some(node_remote);
+            Ok(())
+        }
 
-        let session_security_settings = SessionSecuritySettingsBuilder::default()
-            .with_crypto_params(enx + kem + sig)
-            .build()
-            .unwrap();
+        async fn on_start(&self) -> Result<(), NetworkError> {
+            Ok(())
+        }
 
-        let client_kernel = SingleClientServerConnectionKernel::new_passwordless(
-            uuid,
-            server_addr,
-            UdpMode::Disabled,
-            session_security_settings,
-            |_channel, mut remote| async move {
-                log::trace!(target: "citadel", "***CLIENT LOGIN SUCCESS :: File transfer next ***");
-                remote
-                    .send_file_with_custom_chunking("../resources/TheBridge.pdf", 32 * 1024)
-                    .await
-                    .unwrap();
-                log::trace!(target: "citadel", "***CLIENT FILE TRANSFER SUCCESS***");
-                client_success.store(true, Ordering::Relaxed);
-                remote.shutdown_kernel().await
-            },
-        );
+        async fn on_node_event_received(&self, message: NodeResult) -> Result<(), NetworkError> {
+            if let NodeResult::ObjectTransferHandle(ObjectTransferHandle {
+                ticket: _,
+                mut handle,
+            }) = map_errors(message)?
+            {
+                let mut path = None;
+                let mut cancelled = false;
+                handle
+                    .accept()
+                    .map_err(|err| NetworkError::msg(err.into_string()))?;
 
-        let client = NodeBuilder::default().build(client_kernel).unwrap();
+                use futures::StreamExt;
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::ReceptionBeginning(file_path, _) => {
+                            path = file_path;
+                        }
+
+                        ObjectTransferStatus::ReceptionTick(..) if !cancelled => {
+                            cancelled = true;
+                            handle
+                                .cancel()
+                                .map_err(|err| NetworkError::msg(err.into_string()))?;
+                        }
+
+                        ObjectTransferStatus::Cancelled => {
+                            // the partially-received file is deleted once the stream to the backend ends
+                            let path = path.take().unwrap();
+                            for _ in 0..50 {
+                                if !path.exists() {
+                                    break;
+                                }
+                                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                            }
+
+                            assert!(!path.exists(), "The partial file was not deleted");
+                            self.1.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        ObjectTransferStatus::ReceptionComplete => {
+                            panic!("The transfer completed despite being cancelled")
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                self.0.clone().unwrap().shutdown().await?;
+            }
+
+            Ok(())
+        }
+
+        async fn on_stop(&mut self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+    }
+
+    /// Accepts each transfer. The first attempt is expected to be interrupted, after which the resumed transfer must
+    /// only receive the groups that are missing
+    #[cfg(feature = "filesystem")]
+    pub struct ResumingReceiverKernel(pub Option<NodeRemote>, pub Arc<AtomicBool>);
+
+    #[cfg(feature = "filesystem")]
+    #[async_trait]
+    impl NetKernel for ResumingReceiverKernel {
+        fn load_remote(&mut self, node_remote: NodeRemote) -> Result<(), NetworkError> {
+            self.0 = Some(node_remote);
+            Ok(())
+        }
+
+        async fn on_start(&self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+
+        async fn on_node_event_received(&self, message: NodeResult) -> Result<(), NetworkError> {
+            if let NodeResult::ObjectTransferHandle(ObjectTransferHandle {
+                ticket: _,
+                mut handle,
+            }) = map_errors(message)?
+            {
+                let mut path = None;
+                let mut resumed = None;
+                let mut ticks = Vec::new();
+                handle
+                    .accept()
+                    .map_err(|err| NetworkError::msg(err.into_string()))?;
+
+                use futures::StreamExt;
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::ReceptionBeginning(file_path, _) => {
+                            path = file_path;
+                        }
+
+                        ObjectTransferStatus::Resumed(start_group, total_groups) => {
+                            resumed = Some((start_group, total_groups));
+                        }
+
+                        ObjectTransferStatus::ReceptionTick(relative_group_id, ..) => {
+                            ticks.push(relative_group_id);
+                        }
+
+                        ObjectTransferStatus::ReceptionComplete => {
+                            let (start_group, total_groups) =
+                                resumed.expect("The transfer completed without resuming");
+                            assert!(start_group > 0);
+                            // the final group completes the transfer instead of ticking
+                            assert_eq!(ticks, (start_group..total_groups - 1).collect::<Vec<_>>());
+                            // the object, including the groups received before the interruption, was verified
+                            // against the sender's digest before completing
+                            let cmp = include_bytes!("../../resources/TheBridge.pdf");
+                            let streamed_data =
+                                tokio::fs::read(path.clone().unwrap()).await.unwrap();
+                            assert_eq!(
+                                cmp,
+                                streamed_data.as_slice(),
+                                "Original data and streamed data does not match"
+                            );
+
+                            self.1.store(true, Ordering::Relaxed);
+                            self.0.clone().unwrap().shutdown().await?;
+                        }
+
+                        ObjectTransferStatus::Fail(err) => {
+                            // the first attempt fails once the sender disconnects
+                            assert!(resumed.is_none(), "The resumed transfer failed: {}", err);
+                            break;
+                        }
+
+                        _ => {}
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        async fn on_stop(&mut self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+    }
+
+    /// Accepts a directory, then compares it to the directory at `.2`. If `.3` is set, a file was modified after the
+    /// directory's manifest was built, and thus the directory must be rejected
+    #[cfg(feature = "filesystem")]
+    pub struct DirectoryReceiverKernel(
+        pub Option<NodeRemote>,
+        pub Arc<AtomicBool>,
+        pub std::path::PathBuf,
+        pub bool,
+    );
+
+    #[cfg(feature = "filesystem")]
+    #[async_trait]
+    impl NetKernel for DirectoryReceiverKernel {
+        fn load_remote(&mut self, node_remote: NodeRemote) -> Result<(), NetworkError> {
+            self.0 = Some(node_remote);
+            Ok(())
+        }
+
+        async fn on_start(&self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+
+        async fn on_node_event_received(&self, message: NodeResult) -> Result<(), NetworkError> {
+            if let NodeResult::ObjectTransferHandle(ObjectTransferHandle {
+                ticket: _,
+                mut handle,
+            }) = map_errors(message)?
+            {
+                let mut path = None;
+                handle
+                    .accept()
+                    .map_err(|err| NetworkError::msg(err.into_string()))?;
+
+                use futures::StreamExt;
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::ReceptionBeginning(directory_path, vfm) => {
+                            path = directory_path;
+                            let paths = |manifest: &DirectoryManifest| {
+                                manifest
+                                    .entries
+                                    .iter()
+                                    .map(|entry| entry.path.clone())
+                                    .collect::<Vec<_>>()
+                            };
+                            assert_eq!(
+                                paths(vfm.get_manifest().unwrap()),
+                                paths(&DirectoryManifest::build(&self.2).unwrap())
+                            );
+                        }
+
+                        ObjectTransferStatus::ReceptionComplete => {
+                            assert!(!self.3, "The modified directory was received");
+                            assert_directories_eq(&self.2, &path.clone().unwrap());
+                            self.1.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        ObjectTransferStatus::Fail(err) => {
+                            assert!(self.3, "Unable to receive the directory: {}", err);
+                            assert!(
+                                err.contains(
+                                    "does not match the digest in the directory's manifest"
+                                ),
+                                "Unexpected failure: {}",
+                                err
+                            );
+                            self.1.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                self.0.clone().unwrap().shutdown().await?;
+            }
+
+            Ok(())
+        }
+
+        async fn on_stop(&mut self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+    }
+
+    /// Creates a directory containing files at several depths, along with an empty directory
+    #[cfg(feature = "filesystem")]
+    fn create_nested_directory() -> std::path::PathBuf {
+        let mut root = std::env::temp_dir();
+        root.push(format!("citadel_directory_{}", Uuid::new_v4().as_u128()));
+        std::fs::create_dir_all(root.join("nested/deeper")).unwrap();
+        std::fs::create_dir_all(root.join("empty")).unwrap();
+        std::fs::write(root.join("README.md"), b"top level").unwrap();
+        std::fs::write(
+            root.join("nested/data.bin"),
+            (0..100_000).map(|r| (r % 256) as u8).collect::<Vec<u8>>(),
+        )
+        .unwrap();
+        std::fs::copy(
+            "../resources/TheBridge.pdf",
+            root.join("nested/deeper/TheBridge.pdf"),
+        )
+        .unwrap();
+        std::fs::write(root.join("z_last.txt"), b"the final file").unwrap();
+        root
+    }
+
+    #[cfg(feature = "filesystem")]
+    fn assert_directories_eq(expected: &std::path::Path, received: &std::path::Path) {
+        let mut entries = std::fs::read_dir(expected)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.file_name());
+        let mut received_entries = std::fs::read_dir(received)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        received_entries.sort();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.file_name())
+                .collect::<Vec<_>>(),
+            received_entries
+        );
+
+        for entry in entries {
+            let received = received.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                assert_directories_eq(&entry.path(), &received);
+            } else {
+                assert_eq!(
+                    std::fs::read(entry.path()).unwrap(),
+                    std::fs::read(received).unwrap()
+                );
+            }
+        }
+    }
+
+    /// Begins sending the file, returning the sender's handle instead of awaiting completion
+    async fn send_file_with_handle(
+        remote: &mut ClientServerRemote,
+    ) -> Result<ObjectTransferHandler, NetworkError> {
+        let implicated_cid = remote.user().get_implicated_cid();
+        let v_conn_type = *remote.user();
+        let result = remote
+            .remote()
+            .send_callback(NodeRequest::SendObject(SendObject {
+                source: Box::new(std::path::PathBuf::from("../resources/TheBridge.pdf")),
+                chunk_size: Some(32 * 1024),
+                implicated_cid,
+                v_conn_type,
+                store_and_forward: false,
+            }))
+            .await?;
+
+        match map_errors(result)? {
+            NodeResult::ObjectTransferHandle(ObjectTransferHandle { ticket: _, handle }) => {
+                Ok(handle)
+            }
+            res => Err(NetworkError::Generic(format!(
+                "Unexpected response to SendObject: {:?}",
+                res
+            ))),
+        }
+    }
+
+    pub fn server_info<'a>(
+        switch: Arc<AtomicBool>,
+    ) -> (NodeFuture<'a, ReceiverFileTransferKernel>, SocketAddr) {
+        let port = crate::test_common::get_unused_tcp_port();
+        let bind_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
+        let server = crate::test_common::server_test_node(
+            bind_addr,
+            ReceiverFileTransferKernel(None, switch),
+            |_| {},
+        );
+        (server, bind_addr)
+    }
+
+    #[rstest]
+    #[timeout(std::time::Duration::from_secs(90))]
+    #[case(
+        EncryptionAlgorithm::AES_GCM_256_SIV,
+        KemAlgorithm::Kyber,
+        SigAlgorithm::None
+    )]
+    #[case(
+        EncryptionAlgorithm::Kyber,
+        KemAlgorithm::Kyber,
+        SigAlgorithm::Falcon1024
+    )]
+    #[case(
+        EncryptionAlgorithm::AES_GCM_256_SIV,
+        KemAlgorithm::HybridX25519Kyber,
+        SigAlgorithm::None
+    )]
+    #[tokio::test]
+    async fn test_c2s_file_transfer(
+        #[case] enx: EncryptionAlgorithm,
+        #[case] kem: KemAlgorithm,
+        #[case] sig: SigAlgorithm,
+    ) {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let (server, server_addr) = server_info(server_success.clone());
+        let uuid = Uuid::new_v4();
+
+        let session_security_settings = SessionSecuritySettingsBuilder::default()
+            .with_crypto_params(enx + kem + sig)
+            .build()
+            .unwrap();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless(
+            uuid,
+            server_addr,
+            UdpMode::Disabled,
+            session_security_settings,
+            |_channel, mut remote| async move {
+                log::trace!(target: "citadel", "***CLIENT LOGIN SUCCESS :: File transfer next ***");
+                remote
+                    .send_file_with_custom_chunking("../resources/TheBridge.pdf", 32 * 1024)
+                    .await
+                    .unwrap();
+                log::trace!(target: "citadel", "***CLIENT FILE TRANSFER SUCCESS***");
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_c2s_object_transfer_of_unknown_length() {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let (server, server_addr) = server_info(server_success.clone());
+        let uuid = Uuid::new_v4();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless_defaults(
+            uuid,
+            server_addr,
+            |_channel, mut remote| async move {
+                // the async reader's length is unknown until EOF
+                let reader = tokio::fs::File::open("../resources/TheBridge.pdf")
+                    .await
+                    .unwrap();
+                remote
+                    .send_object_with_custom_chunking(
+                        AsyncReadSource::new("TheBridge.pdf", reader),
+                        32 * 1024,
+                    )
+                    .await
+                    .unwrap();
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_c2s_object_transfer_cancelled_by_receiver() {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let port = crate::test_common::get_unused_tcp_port();
+        let server_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
+        let server = crate::test_common::server_test_node(
+            server_addr,
+            CancellingReceiverKernel(None, server_success.clone()),
+            |_| {},
+        );
+        let uuid = Uuid::new_v4();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless_defaults(
+            uuid,
+            server_addr,
+            |_channel, mut remote| async move {
+                use futures::StreamExt;
+                let mut handle = send_file_with_handle(&mut remote).await?;
+                let mut cancelled = false;
+                // the handle only ends once the sender tears down its end of the transfer
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::Cancelled => cancelled = true,
+                        ObjectTransferStatus::TransferComplete => {
+                            panic!("The transfer completed despite being cancelled")
+                        }
+                        ObjectTransferStatus::TransferTick(..) => {
+                            assert!(
+                                !cancelled,
+                                "Groups were sent after the transfer was cancelled"
+                            )
+                        }
+                        _ => {}
+                    }
+                }
+
+                assert!(cancelled);
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
 
         let joined = futures::future::try_join(server, client);
 
@@ -986,4 +1701,288 @@ mod tests {
         assert!(client_success.load(Ordering::Relaxed));
         assert!(server_success.load(Ordering::Relaxed));
     }
+
+    #[tokio::test]
+    async fn test_c2s_object_transfer_paused_by_sender() {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let (server, server_addr) = server_info(server_success.clone());
+        let uuid = Uuid::new_v4();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless_defaults(
+            uuid,
+            server_addr,
+            |_channel, mut remote| async move {
+                use futures::{FutureExt, StreamExt};
+                let mut handle = send_file_with_handle(&mut remote).await?;
+                let mut ticks = 0;
+                let mut paused = false;
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::TransferTick(_, total_groups, _) if !paused => {
+                            ticks += 1;
+                            if ticks == 1 {
+                                assert!(total_groups > 2);
+                                handle.pause().unwrap();
+                            }
+                        }
+
+                        ObjectTransferStatus::TransferTick(..) => ticks += 1,
+
+                        ObjectTransferStatus::Paused => {
+                            paused = true;
+                            let ticks_when_paused = ticks;
+                            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                            while let Some(Some(status)) = handle.next().now_or_never() {
+                                assert!(!matches!(status, ObjectTransferStatus::TransferComplete));
+                                if let ObjectTransferStatus::TransferTick(..) = status {
+                                    ticks += 1;
+                                }
+                            }
+
+                            // only the group in transit when paused may finish
+                            assert!(ticks - ticks_when_paused <= 1);
+                            handle.resume().unwrap();
+                        }
+
+                        ObjectTransferStatus::TransferComplete => {
+                            assert!(paused);
+                            client_success.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        ObjectTransferStatus::Fail(err) => panic!("Transfer failed: {}", err),
+
+                        _ => {}
+                    }
+                }
+
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
+
+    #[rstest]
+    #[case(SigAlgorithm::Falcon1024)]
+    #[case(SigAlgorithm::None)]
+    #[timeout(std::time::Duration::from_secs(90))]
+    #[tokio::test]
+    #[cfg(feature = "filesystem")]
+    async fn test_c2s_directory_transfer(#[case] sig: SigAlgorithm) {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let directory = create_nested_directory();
+        let port = crate::test_common::get_unused_tcp_port();
+        let server_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
+        let server = crate::test_common::server_test_node(
+            server_addr,
+            DirectoryReceiverKernel(None, server_success.clone(), directory.clone(), false),
+            |_| {},
+        );
+
+        // without a signature algorithm, the manifest is sent unsigned
+        let session_security_settings = SessionSecuritySettingsBuilder::default()
+            .with_crypto_params(EncryptionAlgorithm::AES_GCM_256_SIV + KemAlgorithm::Kyber + sig)
+            .build()
+            .unwrap();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless(
+            Uuid::new_v4(),
+            server_addr,
+            UdpMode::Disabled,
+            session_security_settings,
+            |_channel, mut remote| async move {
+                remote
+                    .send_directory_with_custom_chunking(&directory, 32 * 1024)
+                    .await
+                    .unwrap();
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "filesystem")]
+    async fn test_c2s_directory_transfer_modified_after_manifest() {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let directory = create_nested_directory();
+        // the manifest is built before the final file is modified
+        let source = DirectorySource::new(&directory).unwrap();
+        std::fs::write(directory.join("z_last.txt"), b"THE FINAL FILE").unwrap();
+
+        let port = crate::test_common::get_unused_tcp_port();
+        let server_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
+        let server = crate::test_common::server_test_node(
+            server_addr,
+            DirectoryReceiverKernel(None, server_success.clone(), directory.clone(), true),
+            |_| {},
+        );
+
+        let session_security_settings = SessionSecuritySettingsBuilder::default()
+            .with_crypto_params(
+                EncryptionAlgorithm::AES_GCM_256_SIV
+                    + KemAlgorithm::Kyber
+                    + SigAlgorithm::Falcon1024,
+            )
+            .build()
+            .unwrap();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_passwordless(
+            Uuid::new_v4(),
+            server_addr,
+            UdpMode::Disabled,
+            session_security_settings,
+            |_channel, mut remote| async move {
+                // the file's length is unchanged, so every group is sent, but the receiver rejects the file
+                remote
+                    .send_object_with_custom_chunking(source, 32 * 1024)
+                    .await
+                    .unwrap();
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client = NodeBuilder::default().build(client_kernel).unwrap();
+
+        let joined = futures::future::try_join(server, client);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "filesystem")]
+    async fn test_c2s_file_transfer_resumed_after_reconnect() {
+        let _ = citadel_logging::setup_log();
+        let ref client_success = AtomicBool::new(false);
+        let ref server_success = Arc::new(AtomicBool::new(false));
+        let port = crate::test_common::get_unused_tcp_port();
+        let server_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap();
+        let server = crate::test_common::server_test_node(
+            server_addr,
+            ResumingReceiverKernel(None, server_success.clone()),
+            |_| {},
+        );
+        // the progress of the transfer is stored in the client's account, which must outlive the first node
+        let mut client_dir = std::env::temp_dir();
+        client_dir.push(format!("citadel_resume_{}", Uuid::new_v4().as_u128()));
+        let client_backend = BackendType::Filesystem(client_dir.to_str().unwrap().to_string());
+        let username = format!("resume.{}", Uuid::new_v4().as_u128());
+
+        let client_kernel = SingleClientServerConnectionKernel::new_register_defaults(
+            "Thomas P Braun",
+            username.clone(),
+            "password",
+            server_addr,
+            |_channel, mut remote| async move {
+                use futures::StreamExt;
+                let mut handle = send_file_with_handle(&mut remote).await?;
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::TransferTick(2, total_groups, _) => {
+                            assert!(total_groups > 6);
+                            handle.pause().unwrap();
+                        }
+
+                        ObjectTransferStatus::Paused => break,
+
+                        ObjectTransferStatus::TransferComplete => {
+                            panic!("The transfer completed before it was interrupted")
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                // give both ends time to persist their progress, then interrupt the session
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let interrupted_client = NodeBuilder::default()
+            .with_backend(client_backend.clone())
+            .build(client_kernel)
+            .unwrap();
+
+        let client_kernel = SingleClientServerConnectionKernel::new_connect_defaults(
+            username,
+            "password",
+            |_channel, mut remote| async move {
+                use futures::StreamExt;
+                let mut handle = send_file_with_handle(&mut remote).await?;
+                let mut resumed = None;
+                let mut ticks = Vec::new();
+                while let Some(status) = handle.next().await {
+                    match status {
+                        ObjectTransferStatus::Resumed(start_group, total_groups) => {
+                            resumed = Some((start_group, total_groups));
+                        }
+
+                        ObjectTransferStatus::TransferTick(relative_group_id, ..) => {
+                            ticks.push(relative_group_id);
+                        }
+
+                        ObjectTransferStatus::TransferComplete => break,
+
+                        ObjectTransferStatus::Fail(err) => panic!("Transfer failed: {}", err),
+
+                        _ => {}
+                    }
+                }
+
+                // at least the three groups acknowledged before pausing were skipped
+                let (start_group, total_groups) = resumed.expect("The transfer did not resume");
+                assert!(start_group >= 3);
+                assert_eq!(ticks, (start_group..total_groups - 1).collect::<Vec<_>>());
+                client_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let resumed_client = NodeBuilder::default()
+            .with_backend(client_backend)
+            .build(client_kernel)
+            .unwrap();
+
+        let clients = async move {
+            let _ = interrupted_client.await?;
+            // the server must notice the interruption before the client logs in again
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            resumed_client.await
+        };
+
+        let joined = futures::future::try_join(server, clients);
+
+        let _ = joined.await.unwrap();
+
+        assert!(client_success.load(Ordering::Relaxed));
+        assert!(server_success.load(Ordering::Relaxed));
+    }
 }
@@ -0,0 +1,630 @@
+//! Typed wrappers around [`PeerChannel`] and [`GroupChannel`]
+//!
+//! Instead of sending and receiving raw [`SecBuffer`]s, the wrappers send and receive any type that implements
+//! [`Serialize`] and [`DeserializeOwned`]. Values are encoded with a [`ChannelCodec`] ([`BincodeCodec`] by default),
+//! and each encoded value is prefixed with a small frame header containing the framing version, the codec, and a tag
+//! identifying the type. Each type declares its tag through [`ChannelMessage`]. If the remote endpoint sends a different
+//! type, or uses a different codec, the receiving half yields an error instead of attempting to deserialize the payload.
+//!
+//! ```
+//! use citadel_sdk::prelude::*;
+//! use citadel_sdk::typed_channel::{type_tag, ChannelMessage, TypedPeerChannel};
+//! use futures::{SinkExt, StreamExt};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum ChatMessage {
+//!     Text(String),
+//!     Typing,
+//! }
+//!
+//! impl ChannelMessage for ChatMessage {
+//!     const TYPE_TAG: u64 = type_tag("chat.message.v1");
+//! }
+//!
+//! # async fn example(channel: PeerChannel) -> Result<(), NetworkError> {
+//! let (mut tx, mut rx) = TypedPeerChannel::<ChatMessage>::new(channel).split();
+//! tx.send(ChatMessage::Text("Hello".to_string())).await?;
+//!
+//! while let Some(message) = rx.next().await {
+//!     let message = message?;
+//!     // ...
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::prelude::*;
+use citadel_proto::re_imports::bincode_config;
+use futures::{Sink, Stream};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Incremented whenever the layout of the frame header changes
+const FRAME_VERSION: u8 = 1;
+/// The version byte, the codec byte, and the 8-byte type tag
+const FRAME_HEADER_LEN: usize = 10;
+
+type PendingSend = Pin<Box<dyn Future<Output = Result<(), NetworkError>> + Send>>;
+
+/// Encodes and decodes the values sent through a typed channel
+pub trait ChannelCodec: Send + Sync + 'static {
+    /// Uniquely identifies the codec inside the frame header. Both endpoints must use the same codec
+    const CODEC_ID: u8;
+    /// A human-readable name used in error messages
+    const NAME: &'static str;
+
+    /// Encodes the value into bytes
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, NetworkError>;
+    /// Decodes the bytes into a value
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, NetworkError>;
+}
+
+/// Encodes values using bincode. This is the default codec
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BincodeCodec;
+
+impl ChannelCodec for BincodeCodec {
+    const CODEC_ID: u8 = 0;
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, NetworkError> {
+        bincode_config()
+            .serialize(value)
+            .map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, NetworkError> {
+        bincode_config()
+            .deserialize(bytes)
+            .map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+}
+
+/// Encodes values using JSON. Useful when the remote endpoint is not written in Rust
+#[cfg(feature = "json")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json")]
+impl ChannelCodec for JsonCodec {
+    const CODEC_ID: u8 = 1;
+    const NAME: &'static str = "json";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, NetworkError> {
+        serde_json::to_vec(value).map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, NetworkError> {
+        serde_json::from_slice(bytes).map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+}
+
+/// Encodes values using postcard, a compact format well-suited for constrained devices
+#[cfg(feature = "postcard")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PostcardCodec;
+
+#[cfg(feature = "postcard")]
+impl ChannelCodec for PostcardCodec {
+    const CODEC_ID: u8 = 2;
+    const NAME: &'static str = "postcard";
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, NetworkError> {
+        postcard::to_allocvec(value).map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, NetworkError> {
+        postcard::from_bytes(bytes).map_err(|err| NetworkError::Generic(err.to_string()))
+    }
+}
+
+/// A type sent through a typed channel
+pub trait ChannelMessage: Serialize + DeserializeOwned {
+    /// Identifies the type inside each frame. Both endpoints must use the same tag, which should change whenever the type
+    /// changes in a way that breaks compatibility, such that outdated endpoints fail loudly. See [`type_tag`]
+    const TYPE_TAG: u64;
+}
+
+/// Derives a type tag from a name chosen by the application, such as `"chat.message.v1"`. The tag depends only upon the
+/// name (it is the FNV-1a hash of the name), and thus is identical across platforms and compiler versions
+pub const fn type_tag(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        hash = (hash ^ bytes[idx] as u64).wrapping_mul(0x100000001b3);
+        idx += 1;
+    }
+
+    hash
+}
+
+fn encode_frame<T: Serialize, C: ChannelCodec>(
+    type_tag: u64,
+    value: &T,
+) -> Result<Vec<u8>, NetworkError> {
+    let payload = C::encode(value)?;
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.push(FRAME_VERSION);
+    frame.push(C::CODEC_ID);
+    frame.extend_from_slice(&type_tag.to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+fn decode_frame<T: DeserializeOwned, C: ChannelCodec>(
+    type_tag: u64,
+    frame: &[u8],
+) -> Result<T, NetworkError> {
+    if frame.len() < FRAME_HEADER_LEN {
+        return Err(NetworkError::msg(format!(
+            "Received a frame of {} bytes, which is too short to be sent by a typed channel",
+            frame.len()
+        )));
+    }
+
+    let (header, payload) = frame.split_at(FRAME_HEADER_LEN);
+
+    if header[0] != FRAME_VERSION {
+        return Err(NetworkError::msg(format!(
+            "Received a frame with version {}, but this channel uses version {}",
+            header[0], FRAME_VERSION
+        )));
+    }
+
+    if header[1] != C::CODEC_ID {
+        return Err(NetworkError::msg(format!(
+            "Received a frame encoded with codec {}, but this channel uses {} (codec {})",
+            header[1],
+            C::NAME,
+            C::CODEC_ID
+        )));
+    }
+
+    let mut received_tag = [0u8; 8];
+    received_tag.copy_from_slice(&header[2..]);
+    let received_tag = u64::from_be_bytes(received_tag);
+
+    if received_tag != type_tag {
+        return Err(NetworkError::msg(format!(
+            "Received a frame with type tag {:#x}, but this channel expects {} (type tag {:#x})",
+            received_tag,
+            std::any::type_name::<T>(),
+            type_tag
+        )));
+    }
+
+    C::decode(payload)
+}
+
+/// Polls the pending send, if any, to completion
+fn poll_pending_send(
+    pending: &mut Option<PendingSend>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<(), NetworkError>> {
+    if let Some(send) = pending.as_mut() {
+        let result = futures::ready!(send.as_mut().poll(cx));
+        *pending = None;
+        Poll::Ready(result)
+    } else {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A [`PeerChannel`] that sends and receives values of type `T`, encoded with `C`
+pub struct TypedPeerChannel<T, C = BincodeCodec> {
+    channel: PeerChannel,
+    type_tag: u64,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: ChannelMessage, C: ChannelCodec> TypedPeerChannel<T, C> {
+    /// Wraps the channel. Both endpoints must wrap their channels with the same type and codec
+    pub fn new(channel: PeerChannel) -> Self {
+        Self {
+            channel,
+            type_tag: T::TYPE_TAG,
+            _pd: Default::default(),
+        }
+    }
+
+    /// Overrides the tag identifying `T`, as declared by [`ChannelMessage::TYPE_TAG`]. Useful when a single type is sent
+    /// through several channels that must not accept each other's frames
+    pub fn with_type_tag(mut self, type_tag: u64) -> Self {
+        self.type_tag = type_tag;
+        self
+    }
+
+    /// Gets the CID of the endpoint
+    pub fn get_peer_cid(&self) -> u64 {
+        self.channel.get_peer_cid()
+    }
+
+    /// Gets the CID of the local user
+    pub fn get_implicated_cid(&self) -> u64 {
+        self.channel.get_implicated_cid()
+    }
+
+    /// Splits the channel into a [`Sink`] and a [`Stream`]
+    pub fn split(
+        self,
+    ) -> (
+        TypedPeerChannelSendHalf<T, C>,
+        TypedPeerChannelRecvHalf<T, C>,
+    ) {
+        let (tx, rx) = self.channel.split();
+        let send_half = TypedPeerChannelSendHalf {
+            tx,
+            type_tag: self.type_tag,
+            pending: None,
+            _pd: Default::default(),
+        };
+        let recv_half = TypedPeerChannelRecvHalf {
+            rx,
+            type_tag: self.type_tag,
+            _pd: Default::default(),
+        };
+
+        (send_half, recv_half)
+    }
+}
+
+impl<T, C> Debug for TypedPeerChannel<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypedPeerChannel {:?}", self.channel)
+    }
+}
+
+/// The sending half of a [`TypedPeerChannel`]
+pub struct TypedPeerChannelSendHalf<T, C = BincodeCodec> {
+    tx: PeerChannelSendHalf,
+    type_tag: u64,
+    pending: Option<PendingSend>,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: Serialize, C: ChannelCodec> TypedPeerChannelSendHalf<T, C> {
+    /// Sends a value through the channel
+    pub async fn send_message(&self, value: &T) -> Result<(), NetworkError> {
+        let frame = encode_frame::<T, C>(self.type_tag, value)?;
+        self.tx.send_message(frame.into()).await
+    }
+
+    /// Sends a value through the channel, returning once the remote endpoint confirms delivery.
+    /// See [`PeerChannelSendHalf::send_message_acked`]
+    pub async fn send_message_acked(&self, value: &T) -> Result<(), NetworkError> {
+        let frame = encode_frame::<T, C>(self.type_tag, value)?;
+        self.tx.send_message_acked(frame.into()).await
+    }
+
+    /// Returns the underlying, untyped send half
+    pub fn inner(&self) -> &PeerChannelSendHalf {
+        &self.tx
+    }
+}
+
+impl<T: Serialize, C: ChannelCodec> Sink<T> for TypedPeerChannelSendHalf<T, C> {
+    type Error = NetworkError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let frame = encode_frame::<T, C>(this.type_tag, &item)?;
+        let tx = this.tx.clone();
+        this.pending = Some(Box::pin(async move { tx.send_message(frame.into()).await }));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+}
+
+impl<T, C> Debug for TypedPeerChannelSendHalf<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Typed{:?}", self.tx)
+    }
+}
+
+/// The receiving half of a [`TypedPeerChannel`]. Yields an error for each frame that cannot be decoded as `T`
+pub struct TypedPeerChannelRecvHalf<T, C = BincodeCodec> {
+    rx: PeerChannelRecvHalf,
+    type_tag: u64,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: DeserializeOwned, C: ChannelCodec> Stream for TypedPeerChannelRecvHalf<T, C> {
+    type Item = Result<T, NetworkError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let type_tag = this.type_tag;
+        Pin::new(&mut this.rx)
+            .poll_next(cx)
+            .map(|frame| frame.map(|frame| decode_frame::<T, C>(type_tag, frame.as_ref())))
+    }
+}
+
+impl<T, C> Debug for TypedPeerChannelRecvHalf<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Typed{:?}", self.rx)
+    }
+}
+
+/// A [`GroupChannel`] that broadcasts and receives values of type `T`, encoded with `C`
+pub struct TypedGroupChannel<T, C = BincodeCodec> {
+    channel: GroupChannel,
+    type_tag: u64,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+/// An item received through a [`TypedGroupChannel`]
+#[derive(Debug)]
+pub enum TypedGroupPayload<T> {
+    /// A value broadcast by `sender`, numbered by the server
+    Message {
+        payload: T,
+        sender: u64,
+        sequence: u64,
+    },
+    /// An event pertaining to the group (e.g., a member leaving)
+    Event { payload: GroupBroadcast },
+}
+
+impl<T: ChannelMessage, C: ChannelCodec> TypedGroupChannel<T, C> {
+    /// Wraps the channel. All group members must wrap their channels with the same type and codec
+    pub fn new(channel: GroupChannel) -> Self {
+        Self {
+            channel,
+            type_tag: T::TYPE_TAG,
+            _pd: Default::default(),
+        }
+    }
+
+    /// Overrides the tag identifying `T`. See [`TypedPeerChannel::with_type_tag`]
+    pub fn with_type_tag(mut self, type_tag: u64) -> Self {
+        self.type_tag = type_tag;
+        self
+    }
+
+    /// Gets the CID of the local user
+    pub fn cid(&self) -> u64 {
+        self.channel.cid()
+    }
+
+    /// Splits the channel into a [`Sink`] and a [`Stream`]
+    pub fn split(
+        self,
+    ) -> (
+        TypedGroupChannelSendHalf<T, C>,
+        TypedGroupChannelRecvHalf<T, C>,
+    ) {
+        let (tx, rx) = self.channel.split();
+        let send_half = TypedGroupChannelSendHalf {
+            tx: Arc::new(tx),
+            type_tag: self.type_tag,
+            pending: None,
+            _pd: Default::default(),
+        };
+        let recv_half = TypedGroupChannelRecvHalf {
+            rx,
+            type_tag: self.type_tag,
+            _pd: Default::default(),
+        };
+
+        (send_half, recv_half)
+    }
+}
+
+impl<T, C> Debug for TypedGroupChannel<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypedGroupChannel {:?}", self.channel)
+    }
+}
+
+/// The sending half of a [`TypedGroupChannel`]
+pub struct TypedGroupChannelSendHalf<T, C = BincodeCodec> {
+    tx: Arc<GroupChannelSendHalf>,
+    type_tag: u64,
+    pending: Option<PendingSend>,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: Serialize, C: ChannelCodec> TypedGroupChannelSendHalf<T, C> {
+    /// Broadcasts a value to the group
+    pub async fn send_message(&self, value: &T) -> Result<(), NetworkError> {
+        let frame = encode_frame::<T, C>(self.type_tag, value)?;
+        self.tx.send_message(frame.into()).await
+    }
+
+    /// Returns the underlying, untyped send half (e.g., to invite or kick members)
+    pub fn inner(&self) -> &GroupChannelSendHalf {
+        &self.tx
+    }
+}
+
+impl<T: Serialize, C: ChannelCodec> Sink<T> for TypedGroupChannelSendHalf<T, C> {
+    type Error = NetworkError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let frame = encode_frame::<T, C>(this.type_tag, &item)?;
+        let tx = this.tx.clone();
+        this.pending = Some(Box::pin(async move { tx.send_message(frame.into()).await }));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_pending_send(&mut self.get_mut().pending, cx)
+    }
+}
+
+impl<T, C> Debug for TypedGroupChannelSendHalf<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Typed{:?}", self.tx)
+    }
+}
+
+/// The receiving half of a [`TypedGroupChannel`]. Yields an error for each message that cannot be decoded as `T`
+pub struct TypedGroupChannelRecvHalf<T, C = BincodeCodec> {
+    rx: GroupChannelRecvHalf,
+    type_tag: u64,
+    _pd: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: DeserializeOwned, C: ChannelCodec> Stream for TypedGroupChannelRecvHalf<T, C> {
+    type Item = Result<TypedGroupPayload<T>, NetworkError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let type_tag = this.type_tag;
+        Pin::new(&mut this.rx).poll_next(cx).map(|payload| {
+            payload.map(|payload| match payload {
+                GroupBroadcastPayload::Message {
+                    payload,
+                    sender,
+                    sequence,
+                } => decode_frame::<T, C>(type_tag, payload.as_ref()).map(|payload| {
+                    TypedGroupPayload::Message {
+                        payload,
+                        sender,
+                        sequence,
+                    }
+                }),
+
+                GroupBroadcastPayload::Event { payload } => {
+                    Ok(TypedGroupPayload::Event { payload })
+                }
+            })
+        })
+    }
+}
+
+impl<T, C> Debug for TypedGroupChannelRecvHalf<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Typed{:?}", self.rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::NetworkError;
+    use crate::typed_channel::{decode_frame, encode_frame, type_tag, BincodeCodec};
+    use crate::typed_channel::{ChannelCodec, ChannelMessage, FRAME_HEADER_LEN};
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Ping {
+        idx: u64,
+        body: String,
+    }
+
+    impl ChannelMessage for Ping {
+        const TYPE_TAG: u64 = type_tag("ping");
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Pong {
+        idx: u64,
+        body: String,
+    }
+
+    impl ChannelMessage for Pong {
+        const TYPE_TAG: u64 = type_tag("pong");
+    }
+
+    struct OtherCodec;
+
+    impl ChannelCodec for OtherCodec {
+        const CODEC_ID: u8 = 100;
+        const NAME: &'static str = "other";
+
+        fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, NetworkError> {
+            BincodeCodec::encode(value)
+        }
+
+        fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, NetworkError> {
+            BincodeCodec::decode(bytes)
+        }
+    }
+
+    fn ping() -> Ping {
+        Ping {
+            idx: 10,
+            body: "Hello world".to_string(),
+        }
+    }
+
+    #[test]
+    fn type_tag_is_stable() {
+        // the FNV-1a test vectors
+        assert_eq!(type_tag(""), 0xcbf29ce484222325);
+        assert_eq!(type_tag("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(type_tag("foobar"), 0x85944171f73967e8);
+        assert_ne!(Ping::TYPE_TAG, Pong::TYPE_TAG);
+    }
+
+    #[test]
+    fn typed_frame_roundtrip() {
+        let tag = Ping::TYPE_TAG;
+        let frame = encode_frame::<_, BincodeCodec>(tag, &ping()).unwrap();
+        let decoded = decode_frame::<Ping, BincodeCodec>(tag, &frame).unwrap();
+        assert_eq!(decoded, ping());
+    }
+
+    #[test]
+    fn encoding_only_requires_serialize() {
+        // a borrowed view, which cannot implement DeserializeOwned, encodes identically to the owned type
+        #[derive(Serialize)]
+        struct PingView<'a> {
+            idx: u64,
+            body: &'a str,
+        }
+
+        let view = PingView {
+            idx: 10,
+            body: "Hello world",
+        };
+
+        let frame = encode_frame::<_, BincodeCodec>(Ping::TYPE_TAG, &view).unwrap();
+        let decoded = decode_frame::<Ping, BincodeCodec>(Ping::TYPE_TAG, &frame).unwrap();
+        assert_eq!(decoded, ping());
+    }
+
+    #[test]
+    fn typed_frame_rejects_mismatches() {
+        let tag = Ping::TYPE_TAG;
+        let frame = encode_frame::<_, BincodeCodec>(tag, &ping()).unwrap();
+
+        // identically-shaped types would otherwise deserialize successfully
+        assert!(decode_frame::<Pong, BincodeCodec>(Pong::TYPE_TAG, &frame).is_err());
+        assert!(decode_frame::<Ping, OtherCodec>(tag, &frame).is_err());
+        assert!(decode_frame::<Ping, BincodeCodec>(tag, &frame[..FRAME_HEADER_LEN - 1]).is_err());
+
+        let mut future_version = frame.clone();
+        future_version[0] += 1;
+        assert!(decode_frame::<Ping, BincodeCodec>(tag, &future_version).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod tests {
+    use citadel_proto::prelude::{NetworkError, ServerMiscSettings, UdpMode};
+    use citadel_sdk::prefabs::client::single_connection::SingleClientServerConnectionKernel;
+    use citadel_sdk::prelude::*;
+    use citadel_sdk::test_common::{get_unused_tcp_port, server_test_node};
+    use futures::StreamExt;
+    use parking_lot::Mutex;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use uuid::Uuid;
+
+    const MESSAGE: &[u8] = b"Hello from across the federation";
+
+    /// A server kernel that, on start, establishes a federated link to an adjacent server
+    struct FederatingKernel {
+        remote: Option<NodeRemote>,
+        link: Uuid,
+        adjacent_server_addr: SocketAddr,
+        icid_tx: Mutex<Option<oneshot::Sender<u64>>>,
+    }
+
+    #[async_trait]
+    impl NetKernel for FederatingKernel {
+        fn load_remote(&mut self, server_remote: NodeRemote) -> Result<(), NetworkError> {
+            self.remote = Some(server_remote);
+            Ok(())
+        }
+
+        async fn on_start(&self) -> Result<(), NetworkError> {
+            let mut remote = self.remote.clone().unwrap();
+            let icid = remote
+                .connect_to_federated_server(
+                    AuthenticationRequest::passwordless(self.link, self.adjacent_server_addr),
+                    Default::default(),
+                )
+                .await?;
+            log::trace!(target: "citadel", "Federated link established with icid {}", icid);
+            let _ = self.icid_tx.lock().take().unwrap().send(icid);
+            Ok(())
+        }
+
+        async fn on_node_event_received(&self, _message: NodeResult) -> Result<(), NetworkError> {
+            Ok(())
+        }
+
+        async fn on_stop(&mut self) -> Result<(), NetworkError> {
+            Ok(())
+        }
+    }
+
+    fn server_addr() -> SocketAddr {
+        SocketAddr::from_str(&format!("127.0.0.1:{}", get_unused_tcp_port())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hyperwan_p2p_through_federated_servers() {
+        let _ = citadel_logging::setup_log();
+        let client_a_success = &AtomicBool::new(false);
+        let client_b_success = &AtomicBool::new(false);
+        let file_received = &AtomicBool::new(false);
+
+        let link = Uuid::new_v4();
+        let uuid_a = Uuid::new_v4();
+        let uuid_b = Uuid::new_v4();
+
+        // server B only trusts the account that server A logs into
+        let server_b_addr = server_addr();
+        let server_b = server_test_node(server_b_addr, EmptyKernel::default(), |builder| {
+            let _ = builder.with_server_misc_settings(ServerMiscSettings {
+                allow_passwordless: true,
+                federated_servers: vec![link.to_string()],
+                ..Default::default()
+            });
+        });
+
+        let (icid_tx, icid_rx) = oneshot::channel();
+        let (client_b_ready_tx, client_b_ready_rx) = oneshot::channel::<()>();
+        let (file_received_tx, file_received_rx) = oneshot::channel::<()>();
+        let server_a_addr = server_addr();
+        let server_a = server_test_node(
+            server_a_addr,
+            FederatingKernel {
+                remote: None,
+                link,
+                adjacent_server_addr: server_b_addr,
+                icid_tx: Mutex::new(Some(icid_tx)),
+            },
+            |_| {},
+        );
+
+        // client B lives on server B, accepts any request from client A, echoes the first message, and receives a file
+        let client_kernel_b = SingleClientServerConnectionKernel::new_passwordless_defaults(
+            uuid_b,
+            server_b_addr,
+            move |_connection, mut remote| async move {
+                let mut signals = remote.get_unprocessed_signals_receiver().unwrap();
+                let _ = client_b_ready_tx.send(());
+                // dropping the channel would disconnect from client A before the file arrives
+                let mut _channel = None;
+
+                while let Some(signal) = signals.recv().await {
+                    match signal {
+                        NodeResult::PeerEvent(PeerEvent {
+                            event: signal @ PeerSignal::PostRegister(.., None),
+                            ..
+                        }) => {
+                            let _ = responses::peer_register(signal, true, &mut remote).await?;
+                        }
+
+                        NodeResult::PeerEvent(PeerEvent {
+                            event: signal @ PeerSignal::PostConnect(_, _, None, ..),
+                            ..
+                        }) => {
+                            let _ = responses::peer_connect(signal, true, &mut remote).await?;
+                        }
+
+                        NodeResult::PeerChannelCreated(PeerChannelCreated { channel, .. }) => {
+                            let (tx, mut rx) = channel.split();
+                            let message = rx.next().await.unwrap();
+                            assert_eq!(message.as_ref(), MESSAGE);
+                            // echo the message back across the federation
+                            tx.send_message(message.as_ref().to_vec().into()).await?;
+                            client_b_success.store(true, Ordering::Relaxed);
+                            _channel = Some((tx, rx));
+                        }
+
+                        NodeResult::ObjectTransferHandle(ObjectTransferHandle {
+                            mut handle,
+                            ..
+                        }) => {
+                            handle
+                                .accept()
+                                .map_err(|err| NetworkError::msg(err.into_string()))?;
+                            let mut path = None;
+                            while let Some(status) = handle.next().await {
+                                match status {
+                                    ObjectTransferStatus::ReceptionBeginning(file_path, vfm) => {
+                                        assert_eq!(vfm.get_target_name(), "TheBridge.pdf");
+                                        path = file_path;
+                                    }
+
+                                    ObjectTransferStatus::ReceptionComplete => {
+                                        let received =
+                                            tokio::fs::read(path.clone().unwrap()).await.unwrap();
+                                        assert_eq!(
+                                            received.as_slice(),
+                                            include_bytes!("../../resources/TheBridge.pdf"),
+                                            "The file was corrupted across the federation"
+                                        );
+                                        file_received.store(true, Ordering::Relaxed);
+                                        let _ = file_received_tx.send(());
+                                        break;
+                                    }
+
+                                    _ => {}
+                                }
+                            }
+
+                            return remote.shutdown_kernel().await;
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                Err(NetworkError::msg("Client B signal stream ended"))
+            },
+        );
+
+        // client A lives on server A, and reaches client B through the federated link
+        let client_kernel_a = SingleClientServerConnectionKernel::new_passwordless_defaults(
+            uuid_a,
+            server_a_addr,
+            move |_connection, mut remote| async move {
+                let icid = icid_rx.await.unwrap();
+                client_b_ready_rx.await.unwrap();
+
+                let mut target = remote
+                    .propose_federated_target(uuid_a.to_string(), icid, uuid_b.to_string())
+                    .await?;
+                assert!(matches!(
+                    target.register_to_peer().await?,
+                    PeerRegisterStatus::Accepted
+                ));
+
+                let mut connection = target
+                    .connect_to_peer_custom(Default::default(), UdpMode::Disabled)
+                    .await?;
+                assert!(connection.remote.user().get_target_cid() != 0);
+                let (tx, mut rx) = connection.channel.split();
+                tx.send_message(MESSAGE.to_vec().into()).await?;
+                let echo = rx.next().await.unwrap();
+                assert_eq!(echo.as_ref(), MESSAGE);
+
+                // the file groups and their acks are relayed across the federated link as well
+                connection
+                    .remote
+                    .send_file_with_custom_chunking("../resources/TheBridge.pdf", 32 * 1024)
+                    .await?;
+                // keep the connection open until client B finishes receiving
+                file_received_rx.await.unwrap();
+                client_a_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client_a = NodeBuilder::default().build(client_kernel_a).unwrap();
+        let client_b = NodeBuilder::default().build(client_kernel_b).unwrap();
+        let clients = futures::future::try_join(client_a, client_b);
+
+        let task = async move {
+            tokio::select! {
+                server_res = server_a => Err(NetworkError::msg(format!("Server A ended prematurely: {:?}", server_res.map(|_| ())))),
+                server_res = server_b => Err(NetworkError::msg(format!("Server B ended prematurely: {:?}", server_res.map(|_| ())))),
+                client_res = clients => client_res.map(|_| ())
+            }
+        };
+
+        let _ = tokio::time::timeout(Duration::from_secs(120), task)
+            .await
+            .unwrap();
+
+        assert!(client_a_success.load(Ordering::Relaxed));
+        assert!(client_b_success.load(Ordering::Relaxed));
+        assert!(file_received.load(Ordering::Relaxed));
+    }
+}
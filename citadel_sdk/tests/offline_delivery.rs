@@ -0,0 +1,362 @@
+#[cfg(test)]
+mod tests {
+    use citadel_proto::prelude::{ForwardingPolicy, NetworkError, ServerMiscSettings};
+    use citadel_sdk::prefabs::client::single_connection::SingleClientServerConnectionKernel;
+    use citadel_sdk::prelude::*;
+    use citadel_sdk::test_common::{get_unused_tcp_port, server_test_node};
+    use futures::StreamExt;
+    use rstest::rstest;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use uuid::Uuid;
+
+    const FILE: &[u8] = include_bytes!("../../resources/TheBridge.pdf");
+
+    fn server_addr() -> SocketAddr {
+        SocketAddr::from_str(&format!("127.0.0.1:{}", get_unused_tcp_port())).unwrap()
+    }
+
+    fn recipient_backend() -> BackendType {
+        // the recipient's key must outlive its first node
+        let mut recipient_dir = std::env::temp_dir();
+        recipient_dir.push(format!("citadel_offline_{}", Uuid::new_v4().as_u128()));
+        BackendType::Filesystem(recipient_dir.to_str().unwrap().to_string())
+    }
+
+    /// Registers the recipient, then accepts the sender's registration and connection requests. The recipient goes offline
+    /// once the two peers share a key for sealing objects and messages
+    fn first_recipient_kernel(
+        username: String,
+        server_addr: SocketAddr,
+        ready_tx: oneshot::Sender<()>,
+    ) -> impl NetKernel {
+        SingleClientServerConnectionKernel::new_register_defaults(
+            "Recipient",
+            username,
+            "password",
+            server_addr,
+            move |_connection, mut remote| async move {
+                let mut signals = remote.get_unprocessed_signals_receiver().unwrap();
+                let _ = ready_tx.send(());
+
+                while let Some(signal) = signals.recv().await {
+                    match signal {
+                        NodeResult::PeerEvent(PeerEvent {
+                            event: signal @ PeerSignal::PostRegister(.., None),
+                            ..
+                        }) => {
+                            let _ = responses::peer_register(signal, true, &mut remote).await?;
+                        }
+
+                        NodeResult::PeerEvent(PeerEvent {
+                            event: signal @ PeerSignal::PostConnect(_, _, None, ..),
+                            ..
+                        }) => {
+                            let _ = responses::peer_connect(signal, true, &mut remote).await?;
+                        }
+
+                        NodeResult::PeerChannelCreated(PeerChannelCreated { .. }) => {
+                            // give both ends time to store the key, then go offline
+                            tokio::time::sleep(Duration::from_millis(500)).await;
+                            return remote.shutdown_kernel().await;
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                Err(NetworkError::msg("Recipient signal stream ended"))
+            },
+        )
+    }
+
+    /// Reconnects the recipient, returning the payloads of the messages delivered to it upon connecting
+    async fn receive_offline_messages(
+        username: String,
+        backend: BackendType,
+    ) -> Result<Vec<Vec<u8>>, NetworkError> {
+        let (messages_tx, messages_rx) = oneshot::channel();
+        let kernel = SingleClientServerConnectionKernel::new_connect_defaults(
+            username,
+            "password",
+            move |_connection, remote| async move {
+                let mut signals = remote.get_unprocessed_signals_receiver().unwrap();
+                let mut messages = Vec::new();
+                while let Ok(Some(signal)) =
+                    tokio::time::timeout(Duration::from_secs(3), signals.recv()).await
+                {
+                    if let NodeResult::MailboxDelivery(MailboxDelivery {
+                        items: MailboxTransfer::Messages(delivered),
+                        ..
+                    }) = signal
+                    {
+                        messages.extend(delivered.into_iter().map(|message| message.payload));
+                    }
+                }
+
+                let _ = messages_tx.send(messages);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let _ = NodeBuilder::default()
+            .with_backend(backend)
+            .build(kernel)
+            .unwrap()
+            .await?;
+        Ok(messages_rx.await.unwrap())
+    }
+
+    /// The recipient registers as a mutual of the sender and connects to the sender once, such that both share a key for
+    /// sealing objects. The recipient then goes offline, after which the sender uploads the file. Once the upload ends,
+    /// and after `reconnect_delay`, the recipient reconnects and counts the files delivered to it
+    #[rstest]
+    #[case(ForwardingPolicy::default(), true, Duration::ZERO, 1)]
+    #[case(
+        ForwardingPolicy { max_bytes_per_recipient: FILE.len() / 2, ..Default::default() },
+        false,
+        Duration::ZERO,
+        0
+    )]
+    #[case(
+        ForwardingPolicy { time_to_live: Duration::from_secs(1), ..Default::default() },
+        true,
+        Duration::from_secs(3),
+        0
+    )]
+    #[timeout(Duration::from_secs(120))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_store_and_forward_file(
+        #[case] forwarding_policy: ForwardingPolicy,
+        #[case] expect_stored: bool,
+        #[case] reconnect_delay: Duration,
+        #[case] expected_deliveries: usize,
+    ) {
+        let _ = citadel_logging::setup_log();
+        let sender_success = &AtomicBool::new(false);
+        let deliveries = &AtomicUsize::new(0);
+
+        let server_addr = server_addr();
+        let server = server_test_node(server_addr, EmptyKernel::default(), |builder| {
+            let _ = builder.with_server_misc_settings(ServerMiscSettings {
+                forwarding_policy,
+                ..Default::default()
+            });
+        });
+
+        let sender = format!("sender.{}", Uuid::new_v4().as_u128());
+        let recipient = format!("recipient.{}", Uuid::new_v4().as_u128());
+        let recipient_backend = recipient_backend();
+
+        let (recipient_ready_tx, recipient_ready_rx) = oneshot::channel::<()>();
+        let (recipient_offline_tx, recipient_offline_rx) = oneshot::channel::<()>();
+        let (uploaded_tx, uploaded_rx) = oneshot::channel::<()>();
+        let (delivered_tx, delivered_rx) = oneshot::channel::<()>();
+
+        let recipient_kernel =
+            first_recipient_kernel(recipient.clone(), server_addr, recipient_ready_tx);
+
+        let target = recipient.clone();
+        let sender_kernel = SingleClientServerConnectionKernel::new_register_defaults(
+            "Sender",
+            sender.clone(),
+            "password",
+            server_addr,
+            move |_connection, mut remote| async move {
+                recipient_ready_rx.await.unwrap();
+                let mut target = remote.propose_target(sender, target).await?;
+                assert!(matches!(
+                    target.register_to_peer().await?,
+                    PeerRegisterStatus::Accepted
+                ));
+                let _ = target.connect_to_peer().await?;
+
+                recipient_offline_rx.await.unwrap();
+                let stored = target
+                    .store_and_forward_file("../resources/TheBridge.pdf")
+                    .await;
+                assert_eq!(stored.is_ok(), expect_stored, "{:?}", stored);
+                let _ = uploaded_tx.send(());
+
+                delivered_rx.await.unwrap();
+                sender_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let reconnected_kernel = SingleClientServerConnectionKernel::new_connect_defaults(
+            recipient,
+            "password",
+            move |_connection, remote| async move {
+                let mut signals = remote.get_unprocessed_signals_receiver().unwrap();
+                // each delivery begins shortly after the previous one ends
+                while let Ok(Some(signal)) =
+                    tokio::time::timeout(Duration::from_secs(5), signals.recv()).await
+                {
+                    if let NodeResult::ObjectTransferHandle(ObjectTransferHandle {
+                        mut handle,
+                        ..
+                    }) = signal
+                    {
+                        handle
+                            .accept()
+                            .map_err(|err| NetworkError::msg(err.into_string()))?;
+                        let mut path = None;
+                        while let Some(status) = handle.next().await {
+                            match status {
+                                ObjectTransferStatus::ReceptionBeginning(file_path, _) => {
+                                    path = file_path;
+                                }
+
+                                ObjectTransferStatus::ReceptionComplete => {
+                                    let received =
+                                        tokio::fs::read(path.clone().unwrap()).await.unwrap();
+                                    assert_eq!(
+                                        received.as_slice(),
+                                        FILE,
+                                        "The forwarded file was corrupted"
+                                    );
+                                    let _ = deliveries.fetch_add(1, Ordering::Relaxed);
+                                    break;
+                                }
+
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+
+                let _ = delivered_tx.send(());
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let recipient_node = NodeBuilder::default()
+            .with_backend(recipient_backend.clone())
+            .build(recipient_kernel)
+            .unwrap();
+        let reconnected_node = NodeBuilder::default()
+            .with_backend(recipient_backend)
+            .build(reconnected_kernel)
+            .unwrap();
+        let sender_node = NodeBuilder::default().build(sender_kernel).unwrap();
+
+        let recipient_nodes = async move {
+            let _ = recipient_node.await?;
+            // the server must notice the recipient went offline before the sender uploads the file
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let _ = recipient_offline_tx.send(());
+            uploaded_rx.await.unwrap();
+            tokio::time::sleep(reconnect_delay).await;
+            reconnected_node.await
+        };
+
+        let clients = futures::future::try_join(sender_node, recipient_nodes);
+
+        let task = async move {
+            tokio::select! {
+                server_res = server => Err(NetworkError::msg(format!("Server ended prematurely: {:?}", server_res.map(|_| ())))),
+                client_res = clients => client_res.map(|_| ())
+            }
+        };
+
+        task.await.unwrap();
+
+        assert!(sender_success.load(Ordering::Relaxed));
+        assert_eq!(deliveries.load(Ordering::Relaxed), expected_deliveries);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_offline_messages_delivered_in_order() {
+        let _ = citadel_logging::setup_log();
+        let sender_success = &AtomicBool::new(false);
+        let messages = (0..3)
+            .map(|idx| format!("Offline message {}", idx).into_bytes())
+            .collect::<Vec<_>>();
+
+        let server_addr = server_addr();
+        let server = server_test_node(server_addr, EmptyKernel::default(), |_| {});
+
+        let sender = format!("sender.{}", Uuid::new_v4().as_u128());
+        let recipient = format!("recipient.{}", Uuid::new_v4().as_u128());
+        let recipient_backend = recipient_backend();
+
+        let (recipient_ready_tx, recipient_ready_rx) = oneshot::channel::<()>();
+        let (recipient_offline_tx, recipient_offline_rx) = oneshot::channel::<()>();
+        let (sent_tx, sent_rx) = oneshot::channel::<()>();
+        let (delivered_tx, delivered_rx) = oneshot::channel::<()>();
+
+        let recipient_kernel =
+            first_recipient_kernel(recipient.clone(), server_addr, recipient_ready_tx);
+
+        let target = recipient.clone();
+        let sent_messages = messages.clone();
+        let sender_kernel = SingleClientServerConnectionKernel::new_register_defaults(
+            "Sender",
+            sender.clone(),
+            "password",
+            server_addr,
+            move |_connection, mut remote| async move {
+                recipient_ready_rx.await.unwrap();
+                let mut target = remote.propose_target(sender, target).await?;
+                assert!(matches!(
+                    target.register_to_peer().await?,
+                    PeerRegisterStatus::Accepted
+                ));
+                let _ = target.connect_to_peer().await?;
+
+                recipient_offline_rx.await.unwrap();
+                for message in sent_messages {
+                    target.send_offline_message(message).await?;
+                }
+                let _ = sent_tx.send(());
+
+                delivered_rx.await.unwrap();
+                sender_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let recipient_node = NodeBuilder::default()
+            .with_backend(recipient_backend.clone())
+            .build(recipient_kernel)
+            .unwrap();
+        let sender_node = NodeBuilder::default().build(sender_kernel).unwrap();
+
+        let recipient_nodes = async move {
+            let _ = recipient_node.await?;
+            // the server must notice the recipient went offline before the sender sends the messages
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let _ = recipient_offline_tx.send(());
+            sent_rx.await.unwrap();
+            let delivered =
+                receive_offline_messages(recipient.clone(), recipient_backend.clone()).await?;
+            // the messages were acknowledged upon the first connection, and thus are not delivered again
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let redelivered = receive_offline_messages(recipient, recipient_backend).await?;
+            let _ = delivered_tx.send(());
+            Ok::<_, NetworkError>((delivered, redelivered))
+        };
+
+        let clients = futures::future::try_join(sender_node, recipient_nodes);
+
+        let task = async move {
+            tokio::select! {
+                server_res = server => Err(NetworkError::msg(format!("Server ended prematurely: {:?}", server_res.map(|_| ())))),
+                client_res = clients => client_res.map(|(_, delivered)| delivered)
+            }
+        };
+
+        let (delivered, redelivered) = tokio::time::timeout(Duration::from_secs(120), task)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(sender_success.load(Ordering::Relaxed));
+        assert_eq!(delivered, messages);
+        assert!(redelivered.is_empty());
+    }
+}
@@ -97,6 +97,49 @@ mod tests {
         Ok(())
     }
 
+    async fn handle_send_receive_e2e_acked(
+        barrier: Arc<Barrier>,
+        channel: PeerChannel,
+        count: usize,
+    ) -> Result<(), NetworkError> {
+        let (tx, rx) = channel.split();
+        let receipts = tx.delivery_receipts();
+        let mut tracked = Vec::new();
+
+        for idx in 0..count {
+            if idx % 2 == 0 {
+                tx.send_message_acked(MessageTransfer::create(idx as u64))
+                    .await?;
+            } else {
+                tracked.push(
+                    tx.send_message_tracked(MessageTransfer::create(idx as u64))
+                        .await?,
+                );
+            }
+        }
+
+        let mut cur_idx = 0usize;
+
+        let mut rx = rx.take(count);
+        while let Some(msg) = rx.next().await {
+            let msg = MessageTransfer::receive(msg);
+            assert_eq!(msg.idx, cur_idx as u64);
+            cur_idx += 1;
+        }
+
+        assert_eq!(cur_idx as usize, count);
+
+        // every message, acked or tracked, yields a receipt
+        let receipts = receipts.take(count).collect::<Vec<_>>().await;
+        assert_eq!(receipts.len(), count);
+        assert!(tracked
+            .iter()
+            .all(|ticket| receipts.iter().any(|receipt| receipt.ticket == *ticket)));
+        let _ = barrier.wait().await;
+
+        Ok(())
+    }
+
     async fn handle_send_receive_group(
         barrier: Arc<Barrier>,
         channel: GroupChannel,
@@ -115,7 +158,9 @@ mod tests {
 
         while let Some(msg) = rx.next().await {
             match msg {
-                GroupBroadcastPayload::Message { payload, sender } => {
+                GroupBroadcastPayload::Message {
+                    payload, sender, ..
+                } => {
                     let cur_idx = counter.entry(sender).or_insert(0usize);
                     log::trace!(target: "citadel", "**~ Received message {} for {}~**", cur_idx, sender);
                     let msg = MessageTransfer::receive(payload);
@@ -371,6 +416,86 @@ mod tests {
         assert!(client1_success.load(Ordering::Relaxed));
     }
 
+    #[rstest]
+    #[case(100, SecrecyMode::Perfect)]
+    #[case(100, SecrecyMode::BestEffort)]
+    #[timeout(std::time::Duration::from_secs(240))]
+    #[tokio::test]
+    async fn stress_test_p2p_messaging_acked(
+        #[case] message_count: usize,
+        #[case] secrecy_mode: SecrecyMode,
+    ) {
+        let _ = citadel_logging::setup_log();
+        citadel_sdk::test_common::TestBarrier::setup(2);
+        let client0_success = &AtomicBool::new(false);
+        let client1_success = &AtomicBool::new(false);
+
+        let (server, server_addr) = server_info();
+
+        let uuid0 = Uuid::new_v4();
+        let uuid1 = Uuid::new_v4();
+        let session_security = SessionSecuritySettingsBuilder::default()
+            .with_secrecy_mode(secrecy_mode)
+            .build()
+            .unwrap();
+
+        let client_kernel0 = PeerConnectionKernel::new_passwordless(
+            uuid0,
+            server_addr,
+            vec![uuid1.into()],
+            UdpMode::Disabled,
+            session_security,
+            move |mut connection, remote| async move {
+                handle_send_receive_e2e_acked(
+                    get_barrier(),
+                    connection.recv().await.unwrap()?.channel,
+                    message_count,
+                )
+                .await?;
+                log::trace!(target: "citadel", "***CLIENT0 TEST SUCCESS***");
+                client0_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client_kernel1 = PeerConnectionKernel::new_passwordless(
+            uuid1,
+            server_addr,
+            vec![uuid0.into()],
+            UdpMode::Disabled,
+            session_security,
+            move |mut connection, remote| async move {
+                handle_send_receive_e2e_acked(
+                    get_barrier(),
+                    connection.recv().await.unwrap()?.channel,
+                    message_count,
+                )
+                .await?;
+                log::trace!(target: "citadel", "***CLIENT1 TEST SUCCESS***");
+                client1_success.store(true, Ordering::Relaxed);
+                remote.shutdown_kernel().await
+            },
+        );
+
+        let client0 = NodeBuilder::default().build(client_kernel0).unwrap();
+        let client1 = NodeBuilder::default().build(client_kernel1).unwrap();
+        let clients = futures::future::try_join(client0, client1);
+
+        let task = async move {
+            tokio::select! {
+                server_res = server => Err(NetworkError::msg(format!("Server ended prematurely: {:?}", server_res.map(|_| ())))),
+                client_res = clients => client_res.map(|_| ())
+            }
+        };
+
+        let _ = tokio::time::timeout(Duration::from_secs(120), task)
+            .await
+            .unwrap();
+
+        assert!(client0_success.load(Ordering::Relaxed));
+        assert!(client1_success.load(Ordering::Relaxed));
+    }
+
     #[rstest]
     #[case(500, 3)]
     #[timeout(std::time::Duration::from_secs(240))]
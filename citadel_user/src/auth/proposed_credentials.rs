@@ -124,7 +124,11 @@ impl ProposedCredentials {
         };
         let full_name = full_name.trim();
 
-        (username.to_string(), full_name.to_string(), password.into())
+        (
+            username.to_string(),
+            full_name.to_string(),
+            SecBuffer::new_guarded(password),
+        )
     }
 
     /// Gets all the internal values
@@ -149,7 +153,7 @@ impl ProposedCredentials {
     pub fn password_transform<T: AsRef<[u8]>>(password_raw: T) -> SecBuffer {
         let mut digest = sha3::Sha3_256::default();
         digest.update(password_raw.as_ref());
-        digest.finalize().to_vec().into()
+        SecBuffer::new_guarded(digest.finalize())
     }
 
     pub(crate) fn into_auth_store(self) -> DeclaredAuthenticationMode {
@@ -1,20 +1,20 @@
 use super::utils::StreamableTargetInformation;
 use crate::account_loader::load_cnac_files;
 use crate::backend::memory::MemoryBackend;
-use crate::backend::utils::ObjectTransferStatus;
+use crate::backend::utils::{object_sink, ObjectTransferStatus};
 use crate::backend::BackendConnection;
 use crate::client_account::{ClientNetworkAccount, MutualPeer};
-use crate::directory_store::DirectoryStore;
+use crate::directory_store::{BasePath, DirectoryStore};
 use crate::misc::{AccountError, CNACMetadata};
 use crate::prelude::CNAC_SERIALIZED_EXTENSION;
 use async_trait::async_trait;
 use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_crypt::streaming_crypt_scrambler::{NamedSource, ObjectDigest, ObjectSource};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::io::AsyncWriteExt;
+use tokio::io::AsyncRead;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use tokio_stream::StreamExt;
 
 /// For handling I/O with the local filesystem
 pub struct FilesystemBackend<R: Ratchet, Fcm: Ratchet> {
@@ -337,40 +337,106 @@ impl<R: Ratchet, Fcm: Ratchet> BackendConnection<R, Fcm> for FilesystemBackend<R
         sink_metadata: Arc<dyn StreamableTargetInformation>,
         status_tx: UnboundedSender<ObjectTransferStatus>,
     ) -> Result<(), AccountError> {
-        let directory_store = self.directory_store.as_ref().unwrap();
-        let name = sink_metadata.get_target_name();
-        let save_path = directory_store.hyxe_virtual_dir.as_str();
-        let save_location = format!("{}{}", save_path, name);
-        let save_location = PathBuf::from(save_location);
+        let save_location = self.generate_object_save_path(sink_metadata.as_ref());
         log::info!(target: "citadel", "Will stream object to {:?}", save_location);
-        let file = tokio::fs::File::create(&save_location)
-            .await
-            .map_err(|err| AccountError::IoError(err.to_string()))?;
+        if let Some(manifest) = sink_metadata.get_manifest().cloned() {
+            let _ = status_tx.send(ObjectTransferStatus::ReceptionBeginning(
+                Some(save_location.clone()),
+                sink_metadata,
+            ));
+            return object_sink::stream_directory_to_path(source, &manifest, &save_location).await;
+        }
 
-        let mut writer = tokio::io::BufWriter::new(file);
-        let mut reader = tokio_util::io::StreamReader::new(
-            tokio_stream::wrappers::UnboundedReceiverStream::new(source).map(|r| {
-                Ok(std::io::Cursor::new(r)) as Result<std::io::Cursor<Vec<u8>>, std::io::Error>
-            }),
-        );
+        let file = object_sink::open_object_file(&save_location, sink_metadata.get_resume_offset())
+            .await?;
 
         let _ = status_tx.send(ObjectTransferStatus::ReceptionBeginning(
-            save_location,
+            Some(save_location),
             sink_metadata,
         ));
 
-        if let Err(err) = tokio::io::copy(&mut reader, &mut writer).await {
-            log::error!(target: "citadel", "Error while copying from reader to writer: {}", err);
+        object_sink::stream_object_to_writer(source, tokio::io::BufWriter::new(file)).await
+    }
+
+    async fn get_partial_object_len(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+    ) -> Result<Option<u64>, AccountError> {
+        object_sink::get_object_file_len(&self.generate_object_save_path(sink_metadata)).await
+    }
+
+    async fn get_stored_object_digests(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+        group_len: usize,
+    ) -> Result<Option<Vec<ObjectDigest>>, AccountError> {
+        if sink_metadata.get_manifest().is_some() {
+            return Ok(None);
         }
 
-        writer
-            .shutdown()
+        object_sink::digest_object_file(&self.generate_object_save_path(sink_metadata), group_len)
             .await
-            .map_err(|err| AccountError::IoError(err.to_string()))
+            .map(Some)
+    }
+
+    async fn remove_partial_object(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+    ) -> Result<(), AccountError> {
+        if sink_metadata.get_manifest().is_some() {
+            return Ok(());
+        }
+
+        object_sink::remove_object_file(&self.generate_object_save_path(sink_metadata)).await
+    }
+
+    async fn stream_object_to_store(
+        &self,
+        store_key: &str,
+        source: &mut (dyn AsyncRead + Send + Unpin),
+        max_len: u64,
+    ) -> Result<u64, AccountError> {
+        object_sink::stream_to_new_file(&self.generate_store_path(store_key), source, max_len).await
+    }
+
+    async fn get_object_from_store(
+        &self,
+        store_key: &str,
+        name: String,
+    ) -> Result<Box<dyn ObjectSource>, AccountError> {
+        let path = self.generate_store_path(store_key);
+        if !path.is_file() {
+            return Err(AccountError::msg("No object is stored under the given key"));
+        }
+
+        Ok(Box::new(NamedSource::new(name, path)))
+    }
+
+    async fn remove_object_from_store(&self, store_key: &str) -> Result<(), AccountError> {
+        object_sink::remove_object_file(&self.generate_store_path(store_key)).await
     }
 }
 
 impl<R: Ratchet, Fcm: Ratchet> FilesystemBackend<R, Fcm> {
+    fn generate_object_save_path(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+    ) -> PathBuf {
+        if let Some(target_path) = sink_metadata.get_target_path() {
+            return target_path.clone();
+        }
+
+        let directory_store = self.directory_store.as_ref().unwrap();
+        let save_path = directory_store.hyxe_virtual_dir.as_str();
+        PathBuf::from(format!("{}{}", save_path, sink_metadata.get_target_name()))
+    }
+
+    // objects in the object store are kept apart from both accounts and the objects received by the local node
+    fn generate_store_path(&self, store_key: &str) -> PathBuf {
+        let directory_store = self.directory_store.as_ref().unwrap();
+        directory_store.make_path(BasePath::ServerDir, format!("objects/{}", store_key))
+    }
+
     async fn save_cnac_by_cid(&self, cid: u64) -> Result<(), AccountError> {
         let cnac = self
             .memory_backend
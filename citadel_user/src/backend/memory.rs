@@ -4,20 +4,26 @@ use crate::backend::BackendConnection;
 use crate::client_account::{ClientNetworkAccount, MutualPeer};
 use crate::misc::{AccountError, CNACMetadata};
 use async_trait::async_trait;
+use bytes::Bytes;
 use citadel_crypt::stacked_ratchet::Ratchet;
+use citadel_crypt::streaming_crypt_scrambler::{NamedSource, ObjectSource};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
 pub(crate) struct MemoryBackend<R: Ratchet, Fcm: Ratchet> {
     pub(crate) clients: RwLock<HashMap<u64, ClientNetworkAccount<R, Fcm>>>,
+    // the object store, kept apart from the accounts
+    objects: RwLock<HashMap<String, Bytes>>,
 }
 
 impl<R: Ratchet, Fcm: Ratchet> Default for MemoryBackend<R, Fcm> {
     fn default() -> Self {
         Self {
             clients: RwLock::new(HashMap::new()),
+            objects: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -380,6 +386,50 @@ impl<R: Ratchet, Fcm: Ratchet> BackendConnection<R, Fcm> for MemoryBackend<R, Fc
     ) -> Result<(), AccountError> {
         no_backend_streaming(source, sink_metadata, status_tx).await
     }
+
+    #[allow(unused_results)]
+    async fn stream_object_to_store(
+        &self,
+        store_key: &str,
+        source: &mut (dyn AsyncRead + Send + Unpin),
+        max_len: u64,
+    ) -> Result<u64, AccountError> {
+        let mut object = Vec::new();
+        source
+            .take(max_len + 1)
+            .read_to_end(&mut object)
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))?;
+        if object.len() as u64 > max_len {
+            return Err(AccountError::msg("The object exceeds its reserved length"));
+        }
+
+        let len = object.len() as u64;
+        self.objects
+            .write()
+            .insert(store_key.to_string(), Bytes::from(object));
+        Ok(len)
+    }
+
+    async fn get_object_from_store(
+        &self,
+        store_key: &str,
+        name: String,
+    ) -> Result<Box<dyn ObjectSource>, AccountError> {
+        let object = self
+            .objects
+            .read()
+            .get(store_key)
+            .cloned()
+            .ok_or_else(|| AccountError::msg("No object is stored under the given key"))?;
+        Ok(Box::new(NamedSource::new(name, object)))
+    }
+
+    #[allow(unused_results)]
+    async fn remove_object_from_store(&self, store_key: &str) -> Result<(), AccountError> {
+        self.objects.write().remove(store_key);
+        Ok(())
+    }
 }
 
 pub(crate) async fn no_backend_streaming(
@@ -7,15 +7,21 @@ use async_trait::async_trait;
 
 use citadel_crypt::fcm::fcm_ratchet::ThinRatchet;
 use citadel_crypt::stacked_ratchet::{Ratchet, StackedRatchet};
+use citadel_crypt::streaming_crypt_scrambler::{ObjectDigest, ObjectSource};
 
 #[cfg(all(feature = "sql", not(coverage)))]
 use crate::backend::mysql_backend::SqlConnectionOptions;
 #[cfg(all(feature = "redis", not(coverage)))]
 use crate::backend::redis_backend::RedisConnectionOptions;
 use crate::backend::utils::misc::StreamableTargetInformation;
-use crate::backend::utils::ObjectTransferStatus;
+use crate::backend::utils::{
+    object_sink, ObjectTransferOrientation, ObjectTransferProgress, ObjectTransferSink,
+    ObjectTransferStatus, OBJECT_TRANSFER_PROGRESS_KEY,
+};
 use crate::client_account::{ClientNetworkAccount, MutualPeer};
 use crate::misc::{AccountError, CNACMetadata};
+use crate::serialization::SyncIO;
+use tokio::io::AsyncRead;
 use tokio::sync::mpsc::UnboundedSender;
 
 /// Implementation for the default filesystem backend
@@ -289,6 +295,179 @@ pub trait BackendConnection<R: Ratchet, Fcm: Ratchet>: Send + Sync {
         sink_metadata: Arc<dyn StreamableTargetInformation>,
         status_tx: UnboundedSender<ObjectTransferStatus>,
     ) -> Result<(), AccountError>;
+    /// Streams an object into the sink chosen by the receiver
+    async fn stream_object_to_sink(
+        &self,
+        source: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+        sink_metadata: Arc<dyn StreamableTargetInformation>,
+        sink: ObjectTransferSink,
+        status_tx: UnboundedSender<ObjectTransferStatus>,
+    ) -> Result<(), AccountError> {
+        match sink {
+            ObjectTransferSink::Backend => {
+                self.stream_object_to_backend(source, sink_metadata, status_tx)
+                    .await
+            }
+
+            #[cfg(feature = "filesystem")]
+            ObjectTransferSink::Path(path) => {
+                log::info!(target: "citadel", "Will stream object to {:?}", path);
+                if let Some(manifest) = sink_metadata.get_manifest().cloned() {
+                    let _ = status_tx.send(ObjectTransferStatus::ReceptionBeginning(
+                        Some(path.clone()),
+                        sink_metadata,
+                    ));
+                    return object_sink::stream_directory_to_path(source, &manifest, &path).await;
+                }
+
+                let file =
+                    object_sink::open_object_file(&path, sink_metadata.get_resume_offset()).await?;
+                let _ = status_tx.send(ObjectTransferStatus::ReceptionBeginning(
+                    Some(path),
+                    sink_metadata,
+                ));
+                object_sink::stream_object_to_writer(source, tokio::io::BufWriter::new(file)).await
+            }
+
+            ObjectTransferSink::Writer(writer) => {
+                let _ = status_tx.send(ObjectTransferStatus::ReceptionBeginning(
+                    None,
+                    sink_metadata,
+                ));
+                object_sink::stream_object_to_writer(source, writer).await
+            }
+        }
+    }
+    /// Returns the number of bytes of a partially-streamed object stored in the backend, if any. Backends that do not
+    /// store objects return None, and thus never resume transfers, unless the receiver chose the object's path
+    async fn get_partial_object_len(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+    ) -> Result<Option<u64>, AccountError> {
+        match sink_metadata.get_target_path() {
+            #[cfg(feature = "filesystem")]
+            Some(path) => object_sink::get_object_file_len(path).await,
+            _ => Ok(None),
+        }
+    }
+    /// Reads back a fully-streamed object, returning the digest of each of its groups as stored, such that the object is
+    /// verified as written rather than as received. Backends that do not store objects return None, unless the receiver
+    /// chose the object's path. Directories are verified file-by-file as they are unpacked, and thus also return None
+    #[cfg_attr(not(feature = "filesystem"), allow(unused_variables))]
+    async fn get_stored_object_digests(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+        group_len: usize,
+    ) -> Result<Option<Vec<ObjectDigest>>, AccountError> {
+        if sink_metadata.get_manifest().is_some() {
+            return Ok(None);
+        }
+
+        match sink_metadata.get_target_path() {
+            #[cfg(feature = "filesystem")]
+            Some(path) => object_sink::digest_object_file(path, group_len)
+                .await
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+    /// Removes a partially-streamed object from the backend, if any. Called when a transfer is cancelled. Partially-received
+    /// directories are left in place, since the directory may hold other files
+    async fn remove_partial_object(
+        &self,
+        sink_metadata: &dyn StreamableTargetInformation,
+    ) -> Result<(), AccountError> {
+        if sink_metadata.get_manifest().is_some() {
+            return Ok(());
+        }
+
+        match sink_metadata.get_target_path() {
+            #[cfg(feature = "filesystem")]
+            Some(path) => object_sink::remove_object_file(path).await,
+            _ => Ok(()),
+        }
+    }
+    /// Streams an object into the backend's object store under `store_key`, returning the number of bytes stored. The
+    /// object store is kept apart from the CNACs, such that stored objects are neither held in memory nor saved alongside
+    /// accounts. Fails if the source holds more than `max_len` bytes, or if the backend has no object store
+    async fn stream_object_to_store(
+        &self,
+        _store_key: &str,
+        _source: &mut (dyn AsyncRead + Send + Unpin),
+        _max_len: u64,
+    ) -> Result<u64, AccountError> {
+        Err(AccountError::msg("This backend has no object store"))
+    }
+    /// Returns a source, named `name`, that reads the object stored under `store_key`
+    async fn get_object_from_store(
+        &self,
+        _store_key: &str,
+        _name: String,
+    ) -> Result<Box<dyn ObjectSource>, AccountError> {
+        Err(AccountError::msg("This backend has no object store"))
+    }
+    /// Removes the object stored under `store_key`, if any
+    async fn remove_object_from_store(&self, _store_key: &str) -> Result<(), AccountError> {
+        Ok(())
+    }
+    /// Loads the progress of a previously interrupted object transfer
+    async fn get_object_transfer_progress(
+        &self,
+        implicated_cid: u64,
+        peer_cid: u64,
+        orientation: ObjectTransferOrientation,
+        transfer_id: u64,
+    ) -> Result<Option<ObjectTransferProgress>, AccountError> {
+        let sub_key = ObjectTransferProgress::sub_key(orientation, transfer_id);
+        self.get_byte_map_value(
+            implicated_cid,
+            peer_cid,
+            OBJECT_TRANSFER_PROGRESS_KEY,
+            &sub_key,
+        )
+        .await?
+        .map(ObjectTransferProgress::deserialize_from_owned_vector)
+        .transpose()
+    }
+    /// Stores the progress of an object transfer, overwriting any previous progress
+    async fn store_object_transfer_progress(
+        &self,
+        implicated_cid: u64,
+        peer_cid: u64,
+        progress: &ObjectTransferProgress,
+    ) -> Result<(), AccountError> {
+        let sub_key =
+            ObjectTransferProgress::sub_key(progress.orientation, progress.metadata.transfer_id);
+        let _ = self
+            .store_byte_map_value(
+                implicated_cid,
+                peer_cid,
+                OBJECT_TRANSFER_PROGRESS_KEY,
+                &sub_key,
+                progress.serialize_to_vector()?,
+            )
+            .await?;
+        Ok(())
+    }
+    /// Removes the progress of an object transfer. Should be called once the transfer completes
+    async fn remove_object_transfer_progress(
+        &self,
+        implicated_cid: u64,
+        peer_cid: u64,
+        orientation: ObjectTransferOrientation,
+        transfer_id: u64,
+    ) -> Result<(), AccountError> {
+        let sub_key = ObjectTransferProgress::sub_key(orientation, transfer_id);
+        let _ = self
+            .remove_byte_map_value(
+                implicated_cid,
+                peer_cid,
+                OBJECT_TRANSFER_PROGRESS_KEY,
+                &sub_key,
+            )
+            .await?;
+        Ok(())
+    }
 }
 
 /// This is what every C/NAC gets. This gets called before making I/O operations
@@ -1,8 +1,25 @@
+use citadel_crypt::directory_source::DirectoryManifest;
 use std::fmt::Debug;
+use std::path::PathBuf;
 
 /// Used for determining location
 pub trait StreamableTargetInformation: Debug + Send + Sync + 'static {
     /// Returns the target name. Should not include the full path,
     /// as this is determined by the backend
     fn get_target_name(&self) -> &String;
+    /// When resuming an interrupted transfer, returns the number of bytes of the
+    /// target that were already stored, and thus must be appended to
+    fn get_resume_offset(&self) -> Option<u64> {
+        None
+    }
+    /// Returns the path chosen by the receiver, overriding the location
+    /// determined by the backend
+    fn get_target_path(&self) -> Option<&PathBuf> {
+        None
+    }
+    /// Returns the manifest if the target is a directory, in which case the received data holds the contents
+    /// of each file in the manifest's order
+    fn get_manifest(&self) -> Option<&DirectoryManifest> {
+        None
+    }
 }
@@ -3,25 +3,53 @@ use futures::Stream;
 pub use misc::StreamableTargetInformation;
 use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
+use std::hash::Hasher;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use crate::misc::AccountError;
+use citadel_crypt::directory_source::DirectoryManifest;
+use citadel_crypt::streaming_crypt_scrambler::ObjectDigest;
 use std::sync::Arc;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
 /// Misc utils/traits
 pub mod misc;
+/// Destinations for received objects
+pub mod object_sink;
+
+pub use object_sink::ObjectTransferSink;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VirtualObjectMetadata {
     pub name: String,
     pub date_created: String,
     pub author: String,
+    /// Zero if the length of the source is unknown until the sender reaches EOF
     pub plaintext_length: usize,
+    /// Zero if the length of the source is unknown until the sender reaches EOF
     pub group_count: usize,
     pub object_id: u32,
+    /// Identifies the object across sessions, allowing an interrupted transfer to be resumed
+    pub transfer_id: u64,
+    /// When resuming an interrupted transfer, the point from which the transfer resumes
+    pub resume_point: Option<ResumePoint>,
+    /// Where the receiver saves the object, if not the backend's default location. Never sent to the adjacent node
+    #[serde(skip)]
+    pub target_path: Option<PathBuf>,
+    /// Present if the object is a directory, listing the files whose contents make up the object
+    pub manifest: Option<DirectoryManifest>,
+    /// Present if the object is stored by the server on behalf of an offline recipient
+    pub forwarding: Option<ObjectForwarding>,
+}
+
+/// Identifies an object that the server stores on behalf of an offline recipient, and forwards once the recipient
+/// next connects. The object is sealed for the recipient, and thus cannot be read by the server
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ObjectForwarding {
+    pub sender_cid: u64,
+    pub recipient_cid: u64,
 }
 
 impl VirtualObjectMetadata {
@@ -32,12 +60,126 @@ impl VirtualObjectMetadata {
     pub fn deserialize_from<'a, T: AsRef<[u8]> + 'a>(input: T) -> Option<Self> {
         Self::deserialize_from_vector(input.as_ref()).ok()
     }
+
+    /// Generates a transfer ID for an object. The object's contents are not hashed, and thus an object modified without
+    /// changing its name or length keeps its transfer ID. Instead, when resuming, the receiver sends the digest of the
+    /// groups it already holds, and the sender fails the transfer if its source no longer matches them
+    pub fn generate_transfer_id(name: &str, plaintext_length: usize, group_count: usize) -> u64 {
+        let mut hasher = twox_hash::XxHash64::default();
+        hasher.write(name.as_bytes());
+        hasher.write_u64(plaintext_length as u64);
+        hasher.write_u64(group_count as u64);
+        hasher.finish()
+    }
 }
 
 impl StreamableTargetInformation for VirtualObjectMetadata {
     fn get_target_name(&self) -> &String {
         &self.name
     }
+
+    fn get_resume_offset(&self) -> Option<u64> {
+        self.resume_point.map(|resume_point| resume_point.bytes)
+    }
+
+    fn get_target_path(&self) -> Option<&PathBuf> {
+        self.target_path.as_ref()
+    }
+
+    fn get_manifest(&self) -> Option<&DirectoryManifest> {
+        self.manifest.as_ref()
+    }
+}
+
+/// The point from which an interrupted object transfer resumes
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ResumePoint {
+    /// The number of groups already transferred
+    pub groups: usize,
+    /// The number of plaintext bytes in the groups already transferred
+    pub bytes: u64,
+}
+
+impl ResumePoint {
+    /// Returns the resume point for the first `groups` groups. Since every group except the last has the same size,
+    /// the number of bytes can be computed from this resume point
+    pub fn truncate(self, groups: usize) -> Self {
+        if groups >= self.groups {
+            self
+        } else {
+            Self {
+                groups,
+                bytes: (self.bytes / self.groups as u64) * groups as u64,
+            }
+        }
+    }
+
+    /// Returns the furthest resume point both ends agree upon, or None if the two are inconsistent
+    pub fn agree(self, other: Self) -> Option<Self> {
+        let (lower, higher) = if self.groups <= other.groups {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        (higher.truncate(lower.groups) == lower).then_some(lower)
+    }
+}
+
+/// The key in the byte map under which object transfer progress is stored
+pub const OBJECT_TRANSFER_PROGRESS_KEY: &str = "_object_transfer_progress";
+
+/// The persisted progress of an object transfer. This is stored on both ends of the transfer, allowing the transfer
+/// to resume from the first missing group if the session ends before the transfer completes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectTransferProgress {
+    pub orientation: ObjectTransferOrientation,
+    pub metadata: VirtualObjectMetadata,
+    /// The groups acknowledged by the receiver (sender orientation), or, stored locally (receiver orientation)
+    pub completed: ResumePoint,
+    /// The digest of each completed group, used to verify the entire object once a resumed transfer completes.
+    /// Only recorded by the receiver
+    #[serde(default)]
+    pub group_digests: Vec<ObjectDigest>,
+}
+
+impl ObjectTransferProgress {
+    pub fn new(
+        orientation: ObjectTransferOrientation,
+        metadata: VirtualObjectMetadata,
+        completed: ResumePoint,
+    ) -> Self {
+        Self {
+            orientation,
+            metadata,
+            completed,
+            group_digests: Vec::new(),
+        }
+    }
+
+    /// Returns the point from which the transfer may resume, if any groups were completed
+    pub fn resume_point(&self) -> Option<ResumePoint> {
+        (self.completed.groups != 0 && self.completed.groups < self.metadata.group_count)
+            .then_some(self.completed)
+    }
+
+    /// Marks the next group as complete, recording its digest if given
+    pub fn on_group_completed(&mut self, group_len: usize, digest: Option<ObjectDigest>) {
+        self.completed.groups += 1;
+        self.completed.bytes += group_len as u64;
+        self.group_digests.extend(digest);
+    }
+
+    /// Discards the progress past `resume_point`, such that the transfer resumes from the agreed point
+    pub fn truncate(&mut self, resume_point: ResumePoint) {
+        self.completed = resume_point;
+        self.group_digests.truncate(resume_point.groups);
+    }
+
+    /// The sub-key in the byte map under which the progress is stored
+    pub fn sub_key(orientation: ObjectTransferOrientation, transfer_id: u64) -> String {
+        format!("{:?}-{}", orientation, transfer_id)
+    }
 }
 
 /// Used to keep track of file transfer progress for either
@@ -48,7 +190,8 @@ pub struct ObjectTransferHandler {
     pub source: u64,
     pub receiver: u64,
     pub orientation: ObjectTransferOrientation,
-    start_recv_tx: Option<tokio::sync::oneshot::Sender<bool>>,
+    start_recv_tx: Option<tokio::sync::oneshot::Sender<Option<ObjectTransferSink>>>,
+    control_tx: Option<UnboundedSender<ObjectTransferControl>>,
 }
 
 impl Stream for ObjectTransferHandler {
@@ -64,7 +207,8 @@ impl ObjectTransferHandler {
         source: u64,
         receiver: u64,
         orientation: ObjectTransferOrientation,
-        start_recv_tx: Option<tokio::sync::oneshot::Sender<bool>>,
+        start_recv_tx: Option<tokio::sync::oneshot::Sender<Option<ObjectTransferSink>>>,
+        control_tx: Option<UnboundedSender<ObjectTransferControl>>,
     ) -> (Self, UnboundedSender<ObjectTransferStatus>) {
         let (tx, inner) = unbounded_channel();
 
@@ -74,6 +218,7 @@ impl ObjectTransferHandler {
             receiver,
             orientation,
             start_recv_tx,
+            control_tx,
         };
 
         (this, tx)
@@ -81,44 +226,99 @@ impl ObjectTransferHandler {
 
     /// When the local handle type is for a Receiver,
     /// the receiver must accept the transfer before
-    /// receiving the data
+    /// receiving the data. The data is saved to the
+    /// backend's default location
     pub fn accept(&mut self) -> Result<(), AccountError> {
-        self.respond(true)
+        self.accept_into(ObjectTransferSink::Backend)
+    }
+
+    /// Like [`Self::accept`], but writes the data into
+    /// the given sink
+    pub fn accept_into(&mut self, sink: ObjectTransferSink) -> Result<(), AccountError> {
+        self.respond(Some(sink))
     }
 
     /// When the local handle type is for a Receiver,
     /// the receiver can deny a request
     pub fn decline(&mut self) -> Result<(), AccountError> {
-        self.respond(false)
+        self.respond(None)
     }
 
-    fn respond(&mut self, accept: bool) -> Result<(), AccountError> {
+    /// Cancels the transfer on both ends. The sender stops transmitting, and the receiver deletes
+    /// the partially-received object
+    pub fn cancel(&self) -> Result<(), AccountError> {
+        self.control(ObjectTransferControl::Cancel)
+    }
+
+    /// Pauses the transfer on both ends. The group currently in transit is allowed to finish
+    pub fn pause(&self) -> Result<(), AccountError> {
+        self.control(ObjectTransferControl::Pause)
+    }
+
+    /// Resumes a paused transfer
+    pub fn resume(&self) -> Result<(), AccountError> {
+        self.control(ObjectTransferControl::Resume)
+    }
+
+    fn control(&self, control: ObjectTransferControl) -> Result<(), AccountError> {
+        self.control_tx
+            .as_ref()
+            .ok_or_else(|| AccountError::msg("This transfer cannot be controlled"))?
+            .send(control)
+            .map_err(|_| AccountError::msg("The transfer has already ended"))
+    }
+
+    fn respond(&mut self, sink: Option<ObjectTransferSink>) -> Result<(), AccountError> {
         if matches!(self.orientation, ObjectTransferOrientation::Receiver) {
             self.start_recv_tx
                 .take()
                 .ok_or_else(|| AccountError::msg("Start_recv_tx already called"))?
-                .send(accept)
-                .map_err(|err| AccountError::msg(err.to_string()))
+                .send(sink)
+                .map_err(|_| AccountError::msg("The transfer has already ended"))
         } else {
             Err(AccountError::msg("Local is not a receiver"))
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ObjectTransferOrientation {
     Receiver,
     Sender,
 }
 
+impl ObjectTransferOrientation {
+    /// Returns the orientation of the adjacent end of the transfer
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Receiver => Self::Sender,
+            Self::Sender => Self::Receiver,
+        }
+    }
+}
+
+/// A request, from either end of an object transfer, to change the state of the transfer
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ObjectTransferControl {
+    Cancel,
+    Pause,
+    Resume,
+}
+
 #[derive(Debug, Clone)]
 #[allow(variant_size_differences)]
 pub enum ObjectTransferStatus {
     TransferBeginning,
-    ReceptionBeginning(PathBuf, Arc<dyn StreamableTargetInformation>),
-    // relative group_id, total groups, Mb/s
+    // the path the object is saved to, or None if streamed into a writer
+    ReceptionBeginning(Option<PathBuf>, Arc<dyn StreamableTargetInformation>),
+    // relative group_id, total groups (zero if the object's length is unknown until the sender reaches EOF), Mb/s
     TransferTick(usize, usize, f32),
     ReceptionTick(usize, usize, f32),
+    // relative group id resumed from, total groups
+    Resumed(usize, usize),
+    Paused,
+    Unpaused,
+    Cancelled,
     TransferComplete,
     ReceptionComplete,
     Fail(String),
@@ -139,6 +339,7 @@ impl ObjectTransferStatus {
             self,
             ObjectTransferStatus::TransferComplete
                 | ObjectTransferStatus::ReceptionComplete
+                | ObjectTransferStatus::Cancelled
                 | ObjectTransferStatus::Fail(_)
         )
     }
@@ -163,6 +364,26 @@ impl std::fmt::Display for ObjectTransferStatus {
                 print_tick(f, *relative_group_id, *total_groups, *transfer_rate)
             }
 
+            ObjectTransferStatus::Resumed(relative_group_id, total_groups) => {
+                write!(
+                    f,
+                    "Resuming from group {} of {}",
+                    relative_group_id, total_groups
+                )
+            }
+
+            ObjectTransferStatus::Paused => {
+                write!(f, "Transfer paused")
+            }
+
+            ObjectTransferStatus::Unpaused => {
+                write!(f, "Transfer unpaused")
+            }
+
+            ObjectTransferStatus::Cancelled => {
+                write!(f, "Transfer cancelled")
+            }
+
             ObjectTransferStatus::TransferComplete => {
                 write!(f, "Transfer complete")
             }
@@ -197,9 +418,11 @@ fn print_tick(
 }
 
 /// There are two boundaries when this returns false: when the relative group ID == 0 (first) || == total_groups -1 (last)
-/// Then, there are intermediate points in a cycle when this returns false
+/// Then, there are intermediate points in a cycle when this returns false. Progress cannot be computed if the total is unknown
 fn can_print_progress(relative_group_id: usize, total_groups: usize) -> bool {
-    if relative_group_id != 0 && relative_group_id != total_groups - 1 {
+    if total_groups == 0 {
+        false
+    } else if relative_group_id != 0 && relative_group_id != total_groups - 1 {
         // suppose the total # of groups is n. We want to print out only every v% complete (where 0 < v < 1)
         // Let floor(n * v) = k. Thus every k relative_group_id's, a print out occurs.
         // Thus, if r = the current relative group id, then print-out when:
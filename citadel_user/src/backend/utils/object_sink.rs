@@ -0,0 +1,301 @@
+use crate::misc::AccountError;
+#[cfg(feature = "filesystem")]
+use citadel_crypt::directory_source::{DirectoryManifest, ManifestEntryKind};
+#[cfg(feature = "filesystem")]
+use citadel_crypt::streaming_crypt_scrambler::{group_digest, ObjectDigest};
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Where a received object is written, as chosen by the receiver when accepting the transfer
+#[derive(Default)]
+pub enum ObjectTransferSink {
+    /// The backend's default location. Backends that do not store objects discard the data
+    #[default]
+    Backend,
+    /// The file at the given path. Interrupted transfers resume by appending to this file
+    #[cfg(feature = "filesystem")]
+    Path(PathBuf),
+    /// An arbitrary writer, such as an in-memory buffer or custom storage. Transfers into a writer
+    /// cannot be resumed if interrupted. For directories, the writer receives the contents of each
+    /// file in the order listed by the manifest
+    Writer(Box<dyn AsyncWrite + Send + Unpin>),
+}
+
+impl ObjectTransferSink {
+    /// Returns the path the object is written to, if chosen by the receiver
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self {
+            #[cfg(feature = "filesystem")]
+            Self::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the object is written to an arbitrary writer
+    pub fn is_writer(&self) -> bool {
+        matches!(self, Self::Writer(..))
+    }
+}
+
+impl Debug for ObjectTransferSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Backend => write!(f, "Backend"),
+            #[cfg(feature = "filesystem")]
+            Self::Path(path) => write!(f, "Path({:?})", path),
+            Self::Writer(_) => write!(f, "Writer"),
+        }
+    }
+}
+
+/// Writes each chunk from `source` into `writer` until the source closes. Fails on the first write that fails, such
+/// that an object is never reported as received unless every chunk was written
+pub(crate) async fn stream_object_to_writer<W: AsyncWrite + Unpin>(
+    mut source: UnboundedReceiver<Vec<u8>>,
+    mut writer: W,
+) -> Result<(), AccountError> {
+    while let Some(chunk) = source.recv().await {
+        if let Err(err) = writer.write_all(&chunk).await {
+            log::error!(target: "citadel", "Error while writing object to sink: {}", err);
+            return Err(AccountError::IoError(err.to_string()));
+        }
+    }
+
+    writer
+        .shutdown()
+        .await
+        .map_err(|err| AccountError::IoError(err.to_string()))
+}
+
+/// Reads back the object file at `path`, returning the digest of each group of `group_len` bytes (the final group
+/// may be shorter)
+#[cfg(feature = "filesystem")]
+pub(crate) async fn digest_object_file(
+    path: &std::path::Path,
+    group_len: usize,
+) -> Result<Vec<ObjectDigest>, AccountError> {
+    use tokio::io::AsyncReadExt;
+
+    let io_err = |err: std::io::Error| AccountError::IoError(err.to_string());
+    let mut file = tokio::io::BufReader::new(tokio::fs::File::open(path).await.map_err(io_err)?);
+    let mut digests = Vec::new();
+    let mut group = vec![0u8; group_len];
+
+    while group_len != 0 {
+        let mut filled = 0;
+        while filled < group_len {
+            match file.read(&mut group[filled..]).await.map_err(io_err)? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+
+        if filled == 0 {
+            break;
+        }
+
+        digests.push(group_digest(&group[..filled]));
+        if filled < group_len {
+            break;
+        }
+    }
+
+    Ok(digests)
+}
+
+/// Unpacks a directory into `root` as its bytes arrive from `source`, creating each entry listed in the manifest. Each
+/// file is verified against the digest recorded in the manifest, and entries that would escape `root` are rejected
+#[cfg(feature = "filesystem")]
+pub(crate) async fn stream_directory_to_path(
+    mut source: UnboundedReceiver<Vec<u8>>,
+    manifest: &DirectoryManifest,
+    root: &std::path::Path,
+) -> Result<(), AccountError> {
+    use sha3::Digest;
+
+    let io_err = |err: std::io::Error| AccountError::IoError(err.to_string());
+    tokio::fs::create_dir_all(root).await.map_err(io_err)?;
+
+    let mut chunk = Vec::new();
+    let mut offset = 0;
+
+    for entry in &manifest.entries {
+        let path = root.join(entry.relative_path().ok_or_else(|| {
+            AccountError::msg(format!(
+                "The manifest entry {:?} lies outside of the directory",
+                entry.path
+            ))
+        })?);
+
+        match entry.kind {
+            ManifestEntryKind::Directory => {
+                tokio::fs::create_dir_all(&path).await.map_err(io_err)?;
+            }
+
+            ManifestEntryKind::File => {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(io_err)?;
+                }
+
+                let mut file = tokio::io::BufWriter::new(
+                    tokio::fs::File::create(&path).await.map_err(io_err)?,
+                );
+                let mut hasher = sha3::Sha3_256::default();
+                let mut remaining = entry.size as usize;
+
+                while remaining != 0 {
+                    if offset == chunk.len() {
+                        chunk = source.recv().await.ok_or_else(|| {
+                            AccountError::msg(format!(
+                                "The directory ended before {:?}",
+                                entry.path
+                            ))
+                        })?;
+                        offset = 0;
+                    }
+
+                    let len = remaining.min(chunk.len() - offset);
+                    let bytes = &chunk[offset..offset + len];
+                    hasher.update(bytes);
+                    file.write_all(bytes).await.map_err(io_err)?;
+                    offset += len;
+                    remaining -= len;
+                }
+
+                file.shutdown().await.map_err(io_err)?;
+
+                if hasher.finalize()[..] != entry.digest[..] {
+                    return Err(AccountError::msg(format!(
+                        "{:?} does not match the digest in the directory's manifest",
+                        entry.path
+                    )));
+                }
+
+                set_mode(&path, entry.mode).await?;
+            }
+        }
+    }
+
+    // directories are only restricted once their contents are written
+    for entry in manifest
+        .entries
+        .iter()
+        .filter(|entry| entry.kind == ManifestEntryKind::Directory)
+        .rev()
+    {
+        if let Some(relative_path) = entry.relative_path() {
+            set_mode(&root.join(relative_path), entry.mode).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(feature = "filesystem", unix))]
+async fn set_mode(path: &std::path::Path, mode: Option<u32>) -> Result<(), AccountError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(mode) = mode {
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(feature = "filesystem", not(unix)))]
+async fn set_mode(_path: &std::path::Path, _mode: Option<u32>) -> Result<(), AccountError> {
+    Ok(())
+}
+
+/// Opens the file at `path` for streaming. When resuming, anything past `resume_offset` is discarded, and the
+/// remaining groups are appended
+#[cfg(feature = "filesystem")]
+pub(crate) async fn open_object_file(
+    path: &std::path::Path,
+    resume_offset: Option<u64>,
+) -> Result<tokio::fs::File, AccountError> {
+    use tokio::io::AsyncSeekExt;
+
+    if let Some(resume_offset) = resume_offset {
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))?;
+        file.set_len(resume_offset)
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))?;
+        let _ = file
+            .seek(std::io::SeekFrom::Start(resume_offset))
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))?;
+        Ok(file)
+    } else {
+        tokio::fs::File::create(path)
+            .await
+            .map_err(|err| AccountError::IoError(err.to_string()))
+    }
+}
+
+/// Returns the length of a partially-streamed object file, if it exists
+#[cfg(feature = "filesystem")]
+pub(crate) async fn get_object_file_len(
+    path: &std::path::Path,
+) -> Result<Option<u64>, AccountError> {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => Ok(Some(metadata.len())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(AccountError::IoError(err.to_string())),
+    }
+}
+
+/// Streams the source into a new file at `path`, returning the number of bytes written. If the source holds more than
+/// `max_len` bytes, the file is removed and an error is returned
+#[cfg(feature = "filesystem")]
+pub(crate) async fn stream_to_new_file(
+    path: &std::path::Path,
+    source: &mut (dyn tokio::io::AsyncRead + Send + Unpin),
+    max_len: u64,
+) -> Result<u64, AccountError> {
+    use tokio::io::AsyncReadExt;
+
+    let io_err = |err: std::io::Error| AccountError::IoError(err.to_string());
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(io_err)?;
+    }
+
+    let res = async {
+        let mut file =
+            tokio::io::BufWriter::new(tokio::fs::File::create(path).await.map_err(io_err)?);
+        let written = tokio::io::copy(&mut source.take(max_len + 1), &mut file)
+            .await
+            .map_err(io_err)?;
+        file.flush().await.map_err(io_err)?;
+        if written > max_len {
+            return Err(AccountError::msg("The object exceeds its reserved length"));
+        }
+
+        Ok(written)
+    }
+    .await;
+
+    if res.is_err() {
+        remove_object_file(path).await?;
+    }
+
+    res
+}
+
+/// Removes a partially-streamed object file, if it exists
+#[cfg(feature = "filesystem")]
+pub(crate) async fn remove_object_file(path: &std::path::Path) -> Result<(), AccountError> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(AccountError::IoError(err.to_string())),
+    }
+}
@@ -1,14 +1,143 @@
+use crate::misc::AccountError;
+use citadel_crypt::entropy_bank::SecurityLevel;
+use citadel_pqcrypto::algorithm_dictionary::{
+    CryptoParameters, EncryptionAlgorithm, KemAlgorithm, KyberParameterSet, SigAlgorithm,
+};
+
 /// Miscellaneous settings for a node serving connections
 #[derive(Clone)]
 pub struct ServerMiscSettings {
     /// If enabled, allows inbound connections to use no credentials when logging-in
     pub allow_passwordless: bool,
+    /// The usernames of local accounts that belong to adjacent servers. When one of these accounts
+    /// connects, the session is treated as a federated (HyperWAN) link, and is trusted to relay
+    /// signals and packets on behalf of the clients of the adjacent server
+    pub federated_servers: Vec<String>,
+    /// The cryptographic requirements that inbound registrations and connections must satisfy
+    pub crypto_policy: CryptoPolicy,
+    /// Limits on the objects stored on behalf of offline recipients
+    pub forwarding_policy: ForwardingPolicy,
+    /// Limits on the messages queued on behalf of offline recipients
+    pub message_queue_policy: MessageQueuePolicy,
 }
 
 impl Default for ServerMiscSettings {
     fn default() -> Self {
         Self {
             allow_passwordless: true,
+            federated_servers: Vec::new(),
+            crypto_policy: CryptoPolicy::default(),
+            forwarding_policy: ForwardingPolicy::default(),
+            message_queue_policy: MessageQueuePolicy::default(),
+        }
+    }
+}
+
+/// Limits on the objects a server stores on behalf of offline recipients until they next connect
+#[derive(Clone, Debug)]
+pub struct ForwardingPolicy {
+    /// The maximum number of bytes stored for each recipient at any one time. Zero disables store-and-forward delivery
+    pub max_bytes_per_recipient: usize,
+    /// Objects not delivered within this duration are discarded
+    pub time_to_live: std::time::Duration,
+}
+
+impl Default for ForwardingPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_recipient: 64 * 1024 * 1024,
+            time_to_live: std::time::Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// Limits on the messages a server queues on behalf of offline recipients until they next connect
+#[derive(Clone, Debug)]
+pub struct MessageQueuePolicy {
+    /// The maximum number of messages queued for each recipient at any one time. Zero disables offline message queueing
+    pub max_messages_per_recipient: usize,
+    /// The maximum number of bytes of each queued message
+    pub max_message_len: usize,
+    /// Messages not delivered within this duration are discarded
+    pub time_to_live: std::time::Duration,
+}
+
+impl Default for MessageQueuePolicy {
+    fn default() -> Self {
+        Self {
+            max_messages_per_recipient: 1000,
+            max_message_len: 64 * 1024,
+            time_to_live: std::time::Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// A server-declared policy that is enforced during registration and pre-connect. For each list of
+/// algorithms, an empty list allows any algorithm. The default policy allows everything
+#[derive(Clone, Debug, Default)]
+pub struct CryptoPolicy {
+    /// The symmetric encryption algorithms clients may use
+    pub allowed_encryption_algorithms: Vec<EncryptionAlgorithm>,
+    /// The key encapsulation mechanisms clients may use
+    pub allowed_kem_algorithms: Vec<KemAlgorithm>,
+    /// The Kyber parameter sets clients may use
+    pub allowed_kyber_parameter_sets: Vec<KyberParameterSet>,
+    /// The signature algorithms clients may use
+    pub allowed_sig_algorithms: Vec<SigAlgorithm>,
+    /// Sessions requesting a lower security level are rejected
+    pub minimum_security_level: SecurityLevel,
+    /// If enabled, sessions that request best-effort secrecy are upgraded to perfect secrecy
+    pub require_perfect_secrecy: bool,
+}
+
+impl CryptoPolicy {
+    /// Ensures the given parameters are allowed by this policy, returning a description of the first violation otherwise
+    pub fn check_crypto_params(&self, params: &CryptoParameters) -> Result<(), AccountError> {
+        check_allowed(
+            &self.allowed_encryption_algorithms,
+            params.encryption_algorithm,
+            "encryption algorithm",
+        )?;
+        check_allowed(
+            &self.allowed_kem_algorithms,
+            params.kem_algorithm,
+            "KEM algorithm",
+        )?;
+        check_allowed(
+            &self.allowed_kyber_parameter_sets,
+            params.kyber_parameter_set,
+            "Kyber parameter set",
+        )?;
+        check_allowed(
+            &self.allowed_sig_algorithms,
+            params.sig_algorithm,
+            "signature algorithm",
+        )
+    }
+
+    /// Ensures the given security level is at least the minimum security level of this policy
+    pub fn check_security_level(&self, security_level: SecurityLevel) -> Result<(), AccountError> {
+        if security_level.value() < self.minimum_security_level.value() {
+            Err(AccountError::msg(format!(
+                "Server crypto policy requires a security level of at least {:?}, but {:?} was requested",
+                self.minimum_security_level, security_level
+            )))
+        } else {
+            Ok(())
         }
     }
 }
+
+fn check_allowed<T: PartialEq + std::fmt::Debug>(
+    allowed: &[T],
+    requested: T,
+    kind: &str,
+) -> Result<(), AccountError> {
+    if allowed.is_empty() || allowed.contains(&requested) {
+        Ok(())
+    } else {
+        Err(AccountError::msg(format!(
+            "Server crypto policy does not allow the {kind} {requested:?} (allowed: {allowed:?})"
+        )))
+    }
+}
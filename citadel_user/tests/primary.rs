@@ -1,15 +1,23 @@
 #[cfg(test)]
 mod tests {
 
+    use citadel_crypt::entropy_bank::SecurityLevel;
     use citadel_crypt::prelude::{ConstructorOpts, SecBuffer};
     use citadel_crypt::stacked_ratchet::constructor::{
         BobToAliceTransferType, StackedRatchetConstructor,
     };
     use citadel_crypt::stacked_ratchet::StackedRatchet;
-    use citadel_pqcrypto::algorithm_dictionary::KemAlgorithm;
+    use citadel_pqcrypto::algorithm_dictionary::{
+        CryptoParameters, KemAlgorithm, KyberParameterSet, SigAlgorithm,
+    };
     use citadel_user::account_manager::AccountManager;
     use citadel_user::auth::proposed_credentials::ProposedCredentials;
-    use citadel_user::backend::{BackendType, PersistenceHandler};
+    use citadel_user::backend::utils::{
+        ObjectTransferControl, ObjectTransferHandler, ObjectTransferOrientation,
+        ObjectTransferProgress, ObjectTransferSink, ObjectTransferStatus, ResumePoint,
+        StreamableTargetInformation, VirtualObjectMetadata,
+    };
+    use citadel_user::backend::{BackendConnection, BackendType, PersistenceHandler};
     use citadel_user::client_account::ClientNetworkAccount;
     use futures::Future;
     use std::str::FromStr;
@@ -17,8 +25,10 @@ mod tests {
     use citadel_pqcrypto::prelude::algorithm_dictionary::EncryptionAlgorithm;
     use citadel_user::misc::{AccountError, CNACMetadata};
     use citadel_user::prelude::{ConnectionInfo, MutualPeer};
+    use citadel_user::server_misc_settings::CryptoPolicy;
     use std::collections::HashMap;
     use std::net::SocketAddr;
+    use std::sync::Arc;
 
     #[derive(Clone)]
     struct TestContainer {
@@ -1559,4 +1569,460 @@ mod tests {
             good_name,
         );
     }
+
+    #[test]
+    fn test_crypto_policy() {
+        let permissive = CryptoPolicy::default();
+        assert!(permissive
+            .check_crypto_params(&CryptoParameters::default())
+            .is_ok());
+        assert!(permissive
+            .check_security_level(SecurityLevel::Standard)
+            .is_ok());
+
+        let policy = CryptoPolicy {
+            allowed_kem_algorithms: vec![KemAlgorithm::HybridX25519Kyber],
+            allowed_kyber_parameter_sets: vec![KyberParameterSet::Kyber1024],
+            allowed_sig_algorithms: vec![SigAlgorithm::HybridEd25519Dilithium3],
+            minimum_security_level: SecurityLevel::Reinforced,
+            ..Default::default()
+        };
+
+        let allowed = EncryptionAlgorithm::AES_GCM_256_SIV
+            + KemAlgorithm::HybridX25519Kyber
+            + SigAlgorithm::HybridEd25519Dilithium3;
+        assert!(policy.check_crypto_params(&allowed).is_ok());
+
+        let bad_kem = EncryptionAlgorithm::AES_GCM_256_SIV
+            + KemAlgorithm::Kyber
+            + SigAlgorithm::HybridEd25519Dilithium3;
+        assert!(policy.check_crypto_params(&bad_kem).is_err());
+
+        let bad_kyber_set = allowed + KyberParameterSet::Kyber512;
+        assert!(policy.check_crypto_params(&bad_kyber_set).is_err());
+
+        assert!(policy
+            .check_security_level(SecurityLevel::Standard)
+            .is_err());
+        assert!(policy.check_security_level(SecurityLevel::High).is_ok());
+    }
+
+    #[test]
+    fn test_resume_point() {
+        let sender = ResumePoint {
+            groups: 4,
+            bytes: 4000,
+        };
+        let receiver = ResumePoint {
+            groups: 3,
+            bytes: 3000,
+        };
+
+        assert_eq!(sender.truncate(3), receiver);
+        assert_eq!(sender.truncate(5), sender);
+        assert_eq!(sender.agree(receiver), Some(receiver));
+        assert_eq!(receiver.agree(sender), Some(receiver));
+
+        // the two ends disagree on the number of bytes in the first three groups
+        let inconsistent = ResumePoint {
+            groups: 3,
+            bytes: 2500,
+        };
+        assert_eq!(sender.agree(inconsistent), None);
+
+        let metadata = VirtualObjectMetadata {
+            name: "object.bin".to_string(),
+            date_created: "".to_string(),
+            author: "".to_string(),
+            plaintext_length: 4500,
+            group_count: 5,
+            object_id: 0,
+            transfer_id: 0,
+            resume_point: None,
+            target_path: None,
+            manifest: None,
+            forwarding: None,
+        };
+        let mut progress = ObjectTransferProgress::new(
+            ObjectTransferOrientation::Receiver,
+            metadata,
+            ResumePoint::default(),
+        );
+        for group in 0..4u8 {
+            progress.on_group_completed(1000, Some([group; 32]));
+        }
+        assert_eq!(progress.resume_point(), Some(sender));
+
+        // the digests of discarded groups are discarded too
+        progress.truncate(receiver);
+        assert_eq!(progress.resume_point(), Some(receiver));
+        assert_eq!(
+            progress.group_digests,
+            vec![[0u8; 32], [1u8; 32], [2u8; 32]]
+        );
+    }
+
+    #[test]
+    fn test_object_transfer_controls() {
+        let (control_tx, mut control_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (handle, _status_tx) = ObjectTransferHandler::new(
+            1,
+            2,
+            ObjectTransferOrientation::Sender,
+            None,
+            Some(control_tx),
+        );
+
+        handle.pause().unwrap();
+        handle.resume().unwrap();
+        handle.cancel().unwrap();
+        assert_eq!(control_rx.try_recv().unwrap(), ObjectTransferControl::Pause);
+        assert_eq!(
+            control_rx.try_recv().unwrap(),
+            ObjectTransferControl::Resume
+        );
+        assert_eq!(
+            control_rx.try_recv().unwrap(),
+            ObjectTransferControl::Cancel
+        );
+
+        // once the transfer ends, the controls are no longer accepted
+        drop(control_rx);
+        assert!(handle.cancel().is_err());
+
+        let (handle, _status_tx) =
+            ObjectTransferHandler::new(1, 2, ObjectTransferOrientation::Sender, None, None);
+        assert!(handle.pause().is_err());
+        assert_eq!(
+            ObjectTransferOrientation::Sender.flip(),
+            ObjectTransferOrientation::Receiver
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_object_to_sink() {
+        use tokio::io::AsyncReadExt;
+
+        let account_manager = acc_mgr(BackendType::InMemory).await;
+        let pers = account_manager.get_persistence_handler();
+        let metadata: Arc<dyn StreamableTargetInformation> = Arc::new(VirtualObjectMetadata {
+            name: "object.bin".to_string(),
+            date_created: "".to_string(),
+            author: "".to_string(),
+            plaintext_length: 6,
+            group_count: 2,
+            object_id: 0,
+            transfer_id: 0,
+            resume_point: None,
+            target_path: None,
+            manifest: None,
+            forwarding: None,
+        });
+
+        let (source_tx, source) = tokio::sync::mpsc::unbounded_channel();
+        let (status_tx, mut status_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (writer, mut reader) = tokio::io::duplex(64);
+        source_tx.send(vec![1, 2, 3]).unwrap();
+        source_tx.send(vec![4, 5, 6]).unwrap();
+        drop(source_tx);
+
+        pers.stream_object_to_sink(
+            source,
+            metadata,
+            ObjectTransferSink::Writer(Box::new(writer)),
+            status_tx,
+        )
+        .await
+        .unwrap();
+
+        let mut received = Vec::new();
+        let _ = reader.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, vec![1, 2, 3, 4, 5, 6]);
+        assert!(matches!(
+            status_rx.recv().await.unwrap(),
+            ObjectTransferStatus::ReceptionBeginning(None, _)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_object_to_failing_sink() {
+        let account_manager = acc_mgr(BackendType::InMemory).await;
+        let pers = account_manager.get_persistence_handler();
+        let metadata: Arc<dyn StreamableTargetInformation> = Arc::new(VirtualObjectMetadata {
+            name: "object.bin".to_string(),
+            date_created: "".to_string(),
+            author: "".to_string(),
+            plaintext_length: 6,
+            group_count: 2,
+            object_id: 0,
+            transfer_id: 0,
+            resume_point: None,
+            target_path: None,
+            manifest: None,
+            forwarding: None,
+        });
+
+        let (source_tx, source) = tokio::sync::mpsc::unbounded_channel();
+        let (status_tx, _status_rx) = tokio::sync::mpsc::unbounded_channel();
+        // writes fail once the reading end is dropped
+        let (writer, reader) = tokio::io::duplex(64);
+        drop(reader);
+        source_tx.send(vec![1, 2, 3]).unwrap();
+        source_tx.send(vec![4, 5, 6]).unwrap();
+        drop(source_tx);
+
+        assert!(pers
+            .stream_object_to_sink(
+                source,
+                metadata,
+                ObjectTransferSink::Writer(Box::new(writer)),
+                status_tx,
+            )
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[tokio::test]
+    async fn test_stored_object_digests() {
+        use citadel_crypt::streaming_crypt_scrambler::group_digest;
+
+        let nonce = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("citadel-object-{}.bin", nonce));
+        let account_manager = acc_mgr(BackendType::InMemory).await;
+        let pers = account_manager.get_persistence_handler();
+        let metadata: Arc<dyn StreamableTargetInformation> = Arc::new(VirtualObjectMetadata {
+            name: "object.bin".to_string(),
+            date_created: "".to_string(),
+            author: "".to_string(),
+            plaintext_length: 7,
+            group_count: 3,
+            object_id: 0,
+            transfer_id: 0,
+            resume_point: None,
+            target_path: Some(path.clone()),
+            manifest: None,
+            forwarding: None,
+        });
+
+        let (source_tx, source) = tokio::sync::mpsc::unbounded_channel();
+        let (status_tx, _status_rx) = tokio::sync::mpsc::unbounded_channel();
+        source_tx.send(vec![1, 2, 3]).unwrap();
+        source_tx.send(vec![4, 5, 6]).unwrap();
+        source_tx.send(vec![7]).unwrap();
+        drop(source_tx);
+
+        pers.stream_object_to_sink(
+            source,
+            metadata.clone(),
+            ObjectTransferSink::Path(path.clone()),
+            status_tx,
+        )
+        .await
+        .unwrap();
+
+        let digests = pers
+            .get_stored_object_digests(metadata.as_ref(), 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            digests,
+            vec![
+                group_digest(&[1, 2, 3]),
+                group_digest(&[4, 5, 6]),
+                group_digest(&[7])
+            ]
+        );
+
+        // the object as written, not as received, is verified
+        std::fs::write(&path, [1, 2, 3, 4, 0, 6, 7]).unwrap();
+        let digests = pers
+            .get_stored_object_digests(metadata.as_ref(), 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(digests[1], group_digest(&[4, 5, 6]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_object_store() {
+        use citadel_crypt::streaming_crypt_scrambler::ObjectStream;
+        use std::io::Read;
+
+        let nonce = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let mut backends = vec![BackendType::InMemory];
+        #[cfg(feature = "filesystem")]
+        backends.push(BackendType::filesystem(
+            std::env::temp_dir()
+                .join(format!("citadel-store-{}", nonce))
+                .to_str()
+                .unwrap(),
+        ));
+
+        for backend in backends {
+            let account_manager = acc_mgr(backend).await;
+            let pers = account_manager.get_persistence_handler();
+            let object = (0..100_000u32).map(|idx| idx as u8).collect::<Vec<u8>>();
+
+            let len = pers
+                .stream_object_to_store("object", &mut object.as_slice(), object.len() as u64)
+                .await
+                .unwrap();
+            assert_eq!(len, object.len() as u64);
+
+            let mut source = pers
+                .get_object_from_store("object", "object.bin".to_string())
+                .await
+                .unwrap();
+            assert_eq!(source.get_source_name().unwrap(), "object.bin");
+            let mut stored = Vec::new();
+            match source.try_get_stream().unwrap() {
+                ObjectStream::Fixed(mut stream) => {
+                    let _ = stream.read_to_end(&mut stored).unwrap();
+                }
+                ObjectStream::Async(_) => panic!("Stored objects have a known length"),
+            }
+            assert_eq!(stored, object);
+
+            // objects longer than their reserved length are not stored
+            assert!(pers
+                .stream_object_to_store("too_long", &mut object.as_slice(), 1000)
+                .await
+                .is_err());
+            assert!(pers
+                .get_object_from_store("too_long", "too_long".to_string())
+                .await
+                .is_err());
+
+            pers.remove_object_from_store("object").await.unwrap();
+            assert!(pers
+                .get_object_from_store("object", "object.bin".to_string())
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_directory_to_path() {
+        use citadel_crypt::directory_source::{DirectorySource, ManifestEntryKind};
+        use citadel_crypt::streaming_crypt_scrambler::{ObjectSource, ObjectStream};
+        use std::io::Read;
+
+        let nonce = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("citadel-dir-{}", nonce));
+        let source_dir = root.join("project");
+        std::fs::create_dir_all(source_dir.join("src/empty")).unwrap();
+        std::fs::write(source_dir.join("README.md"), b"hello").unwrap();
+        std::fs::write(source_dir.join("src/main.rs"), vec![7u8; 5000]).unwrap();
+        std::fs::write(source_dir.join("src/empty.txt"), b"").unwrap();
+
+        let mut source = DirectorySource::new(&source_dir).unwrap();
+        let manifest = source.get_manifest().cloned().unwrap();
+        assert_eq!(source.get_source_name().unwrap(), "project");
+        assert_eq!(manifest.file_count(), 3);
+        assert_eq!(manifest.total_len(), 5005);
+        let paths = manifest
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                ("README.md", ManifestEntryKind::File),
+                ("src", ManifestEntryKind::Directory),
+                ("src/empty", ManifestEntryKind::Directory),
+                ("src/empty.txt", ManifestEntryKind::File),
+                ("src/main.rs", ManifestEntryKind::File),
+            ]
+        );
+
+        let mut contents = Vec::new();
+        match source.try_get_stream().unwrap() {
+            ObjectStream::Fixed(mut stream) => {
+                assert_eq!(stream.length().unwrap(), 5005);
+                let _ = stream.read_to_end(&mut contents).unwrap();
+            }
+            ObjectStream::Async(_) => panic!("A directory has a known length"),
+        }
+
+        let account_manager = acc_mgr(BackendType::InMemory).await;
+        let pers = account_manager.get_persistence_handler();
+        let metadata = VirtualObjectMetadata {
+            name: "project".to_string(),
+            date_created: "".to_string(),
+            author: "".to_string(),
+            plaintext_length: contents.len(),
+            group_count: 1,
+            object_id: 0,
+            transfer_id: 0,
+            resume_point: None,
+            target_path: None,
+            manifest: Some(manifest),
+            forwarding: None,
+        };
+
+        let receive =
+            |contents: Vec<u8>, metadata: VirtualObjectMetadata, target: std::path::PathBuf| {
+                let pers = pers.clone();
+                async move {
+                    let (source_tx, source) = tokio::sync::mpsc::unbounded_channel();
+                    let (status_tx, _status_rx) = tokio::sync::mpsc::unbounded_channel();
+                    for chunk in contents.chunks(700) {
+                        source_tx.send(chunk.to_vec()).unwrap();
+                    }
+                    drop(source_tx);
+
+                    pers.stream_object_to_sink(
+                        source,
+                        Arc::new(metadata),
+                        ObjectTransferSink::Path(target),
+                        status_tx,
+                    )
+                    .await
+                }
+            };
+
+        let target = root.join("received");
+        receive(contents.clone(), metadata.clone(), target.clone())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(target.join("README.md")).unwrap(), b"hello");
+        assert_eq!(
+            std::fs::read(target.join("src/main.rs")).unwrap(),
+            vec![7u8; 5000]
+        );
+        assert!(std::fs::read(target.join("src/empty.txt"))
+            .unwrap()
+            .is_empty());
+        assert!(target.join("src/empty").is_dir());
+
+        // a file that does not match its digest is rejected
+        let mut corrupted = contents.clone();
+        corrupted[0] ^= 1;
+        assert!(receive(corrupted, metadata.clone(), root.join("corrupted"))
+            .await
+            .is_err());
+
+        // entries may not escape the directory
+        let mut escaping = metadata;
+        escaping.manifest.as_mut().unwrap().entries[0].path = "../README.md".to_string();
+        assert!(receive(contents, escaping, root.join("escaping"))
+            .await
+            .is_err());
+        assert!(!root.join("README.md").exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
 }
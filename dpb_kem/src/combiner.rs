@@ -0,0 +1,190 @@
+use crate::{Ciphertext, KemError, KeyEncapsulationMechanism, PublicKey, SecretKey, SharedSecret};
+use sha3::Digest;
+
+/// Joins two KEMs into a single hybrid KEM. The shared secret is
+///
+/// ss = SHA3-256(label || ss_first || ss_second || ct_first || ct_second || pk_second)
+///
+/// Binding both component secrets alongside the transcript ensures the output remains secret
+/// so long as at least one of the two KEMs remains unbroken
+///
+/// Public keys and ciphertexts are the concatenation of the first and second components. Since
+/// decapsulation requires the second public key, the secret key is the concatenation of the first
+/// secret key, the second secret key, and the second public key
+pub struct KemCombiner<A, B> {
+    first: A,
+    second: B,
+    label: &'static [u8],
+}
+
+impl<A: KeyEncapsulationMechanism, B: KeyEncapsulationMechanism> KemCombiner<A, B> {
+    /// The label is a domain separator, and should be unique per combination of KEMs
+    pub fn new(first: A, second: B, label: &'static [u8]) -> Self {
+        Self {
+            first,
+            second,
+            label,
+        }
+    }
+
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Returns the (first, second) components of a public key
+    pub fn split_public_key<'a>(
+        &self,
+        public_key: &'a [u8],
+    ) -> Result<(&'a [u8], &'a [u8]), KemError> {
+        split_exact(
+            public_key,
+            self.first.public_key_len(),
+            self.second.public_key_len(),
+        )
+    }
+
+    /// Returns the (first, second) components of a ciphertext
+    pub fn split_ciphertext<'a>(
+        &self,
+        ciphertext: &'a [u8],
+    ) -> Result<(&'a [u8], &'a [u8]), KemError> {
+        split_exact(
+            ciphertext,
+            self.first.ciphertext_len(),
+            self.second.ciphertext_len(),
+        )
+    }
+
+    fn combine(
+        &self,
+        first_shared_secret: &SharedSecret,
+        second_shared_secret: &SharedSecret,
+        ciphertext: &[u8],
+        second_public_key: &[u8],
+    ) -> SharedSecret {
+        let mut hasher = sha3::Sha3_256::new();
+        hasher.update(self.label);
+        hasher.update(first_shared_secret.as_slice());
+        hasher.update(second_shared_secret.as_slice());
+        hasher.update(ciphertext);
+        hasher.update(second_public_key);
+        hasher.finalize().to_vec().into()
+    }
+}
+
+impl<A: KeyEncapsulationMechanism, B: KeyEncapsulationMechanism> KeyEncapsulationMechanism
+    for KemCombiner<A, B>
+{
+    fn public_key_len(&self) -> usize {
+        self.first.public_key_len() + self.second.public_key_len()
+    }
+
+    fn secret_key_len(&self) -> usize {
+        self.first.secret_key_len() + self.second.secret_key_len() + self.second.public_key_len()
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        self.first.ciphertext_len() + self.second.ciphertext_len()
+    }
+
+    fn shared_secret_len(&self) -> usize {
+        32
+    }
+
+    fn keypair(&self) -> Result<(PublicKey, SecretKey), KemError> {
+        let (first_public_key, first_secret_key) = self.first.keypair()?;
+        let (second_public_key, second_secret_key) = self.second.keypair()?;
+        let public_key = [first_public_key.as_slice(), second_public_key.as_slice()].concat();
+        let secret_key = [
+            first_secret_key.as_slice(),
+            second_secret_key.as_slice(),
+            second_public_key.as_slice(),
+        ]
+        .concat();
+
+        Ok((public_key.into(), secret_key.into()))
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Ciphertext, SharedSecret), KemError> {
+        let (first_public_key, second_public_key) = self.split_public_key(public_key)?;
+        let (first_ciphertext, first_shared_secret) = self.first.encapsulate(first_public_key)?;
+        let (second_ciphertext, second_shared_secret) =
+            self.second.encapsulate(second_public_key)?;
+        let ciphertext = [first_ciphertext.as_slice(), second_ciphertext.as_slice()].concat();
+        let shared_secret = self.combine(
+            &first_shared_secret,
+            &second_shared_secret,
+            &ciphertext,
+            second_public_key,
+        );
+
+        Ok((ciphertext.into(), shared_secret))
+    }
+
+    fn decapsulate(&self, ciphertext: &[u8], secret_key: &[u8]) -> Result<SharedSecret, KemError> {
+        let (first_ciphertext, second_ciphertext) = self.split_ciphertext(ciphertext)?;
+        let (first_secret_key, rest) = split_exact(
+            secret_key,
+            self.first.secret_key_len(),
+            self.second.secret_key_len() + self.second.public_key_len(),
+        )?;
+        let (second_secret_key, second_public_key) = rest.split_at(self.second.secret_key_len());
+
+        let first_shared_secret = self.first.decapsulate(first_ciphertext, first_secret_key)?;
+        let second_shared_secret = self
+            .second
+            .decapsulate(second_ciphertext, second_secret_key)?;
+
+        Ok(self.combine(
+            &first_shared_secret,
+            &second_shared_secret,
+            ciphertext,
+            second_public_key,
+        ))
+    }
+}
+
+fn split_exact(
+    input: &[u8],
+    first_len: usize,
+    second_len: usize,
+) -> Result<(&[u8], &[u8]), KemError> {
+    if input.len() != first_len + second_len {
+        return Err(KemError::InvalidLength);
+    }
+
+    Ok(input.split_at(first_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combiner::KemCombiner;
+    use crate::kyber::{Kyber, KyberVariant};
+    use crate::x25519::X25519;
+    use crate::KeyEncapsulationMechanism;
+
+    #[test]
+    fn test_tampered_component_changes_shared_secret() {
+        let kem = KemCombiner::new(Kyber::new(KyberVariant::Kyber1024), X25519, b"test");
+        let (public_key, secret_key) = kem.keypair().unwrap();
+        let (ciphertext, shared_secret) = kem.encapsulate(public_key.as_slice()).unwrap();
+
+        // replace the classical component with a fresh, valid one
+        let (first_ciphertext, _) = kem.split_ciphertext(ciphertext.as_slice()).unwrap();
+        let (other_public_key, _) = X25519.keypair().unwrap();
+        let tampered = [first_ciphertext, other_public_key.as_slice()].concat();
+        let tampered_shared_secret = kem.decapsulate(&tampered, secret_key.as_slice()).unwrap();
+        assert_ne!(tampered_shared_secret, shared_secret);
+
+        // a different label yields a different shared secret
+        let other_kem = KemCombiner::new(Kyber::new(KyberVariant::Kyber1024), X25519, b"other");
+        let other_shared_secret = other_kem
+            .decapsulate(ciphertext.as_slice(), secret_key.as_slice())
+            .unwrap();
+        assert_ne!(other_shared_secret, shared_secret);
+    }
+}
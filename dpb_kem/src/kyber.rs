@@ -0,0 +1,193 @@
+use crate::{Ciphertext, KemError, KeyEncapsulationMechanism, PublicKey, SecretKey, SharedSecret};
+use rand::rngs::ThreadRng;
+use serde::{Deserialize, Serialize};
+
+/// The Kyber parameter sets, in increasing order of security
+///
+/// The parameter sets do not share a single variant of Kyber. Kyber1024 is the "90s" variant of round-3 Kyber (using
+/// AES-256-CTR and SHA-2 in place of SHAKE), provided by kyber-pke, since the Kyber encryption algorithm of
+/// citadel_pqcrypto encrypts under the same keys through kyber-pke. Kyber512 and Kyber768 are the standard variant of
+/// round-3 Kyber, provided by liboqs. Keys and ciphertexts are thus never interchangeable between parameter sets, nor
+/// with FIPS 203 ML-KEM
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KyberVariant {
+    /// The standard variant of round-3 Kyber512 (NIST security level 1)
+    Kyber512,
+    /// The standard variant of round-3 Kyber768 (NIST security level 3)
+    Kyber768,
+    /// The "90s" variant of round-3 Kyber1024 (NIST security level 5)
+    #[default]
+    Kyber1024,
+}
+
+impl KyberVariant {
+    pub fn list() -> &'static [KyberVariant] {
+        &[Self::Kyber512, Self::Kyber768, Self::Kyber1024]
+    }
+}
+
+/// Kyber, as specified in the NIST round-3 submission. See [`KyberVariant`] for the variant used by each parameter set
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Kyber {
+    variant: KyberVariant,
+}
+
+impl Kyber {
+    pub fn new(variant: KyberVariant) -> Self {
+        Self { variant }
+    }
+
+    pub fn variant(&self) -> KyberVariant {
+        self.variant
+    }
+
+    /// Returns (public key, secret key, ciphertext) lengths
+    const fn lengths(&self) -> (usize, usize, usize) {
+        match self.variant {
+            KyberVariant::Kyber512 => (800, 1632, 768),
+            KyberVariant::Kyber768 => (1184, 2400, 1088),
+            KyberVariant::Kyber1024 => (1568, 3168, 1568),
+        }
+    }
+}
+
+impl KeyEncapsulationMechanism for Kyber {
+    fn public_key_len(&self) -> usize {
+        self.lengths().0
+    }
+
+    fn secret_key_len(&self) -> usize {
+        self.lengths().1
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        self.lengths().2
+    }
+
+    fn shared_secret_len(&self) -> usize {
+        32
+    }
+
+    fn keypair(&self) -> Result<(PublicKey, SecretKey), KemError> {
+        let (public_key, secret_key) = match self.variant {
+            KyberVariant::Kyber1024 => {
+                let (public_key, secret_key) = kyber_pke::kem_keypair();
+                (public_key.to_vec(), secret_key.to_vec())
+            }
+            variant => runtime::kem_keypair(variant)?,
+        };
+
+        Ok((public_key.into(), secret_key.into()))
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Ciphertext, SharedSecret), KemError> {
+        if public_key.len() != self.public_key_len() {
+            return Err(KemError::InvalidLength);
+        }
+
+        let (ciphertext, shared_secret) = match self.variant {
+            KyberVariant::Kyber1024 => {
+                let (ciphertext, shared_secret) =
+                    kyber_pke::encapsulate(public_key, &mut ThreadRng::default())
+                        .map_err(|_err| KemError::Generic("Failed encapsulate step"))?;
+                (ciphertext.to_vec(), shared_secret.to_vec())
+            }
+            variant => runtime::encapsulate(variant, public_key)?,
+        };
+
+        Ok((ciphertext.into(), shared_secret.into()))
+    }
+
+    fn decapsulate(&self, ciphertext: &[u8], secret_key: &[u8]) -> Result<SharedSecret, KemError> {
+        if ciphertext.len() != self.ciphertext_len() || secret_key.len() != self.secret_key_len() {
+            return Err(KemError::InvalidLength);
+        }
+
+        let shared_secret = match self.variant {
+            KyberVariant::Kyber1024 => kyber_pke::decapsulate(ciphertext, secret_key)
+                .map(|shared_secret| shared_secret.to_vec())
+                .map_err(|err| KemError::Other(err.to_string()))?,
+            variant => runtime::decapsulate(variant, ciphertext, secret_key)?,
+        };
+
+        Ok(shared_secret.into())
+    }
+}
+
+/// The built-in kyber-pke backend is compiled against a single parameter set (the "90s" variant of Kyber1024), so
+/// the remaining parameter sets are selected at runtime through liboqs, which provides the standard variant
+#[cfg(not(target_family = "wasm"))]
+mod runtime {
+    use crate::kyber::KyberVariant;
+    use crate::KemError;
+    use oqs::kem::Kem;
+
+    pub fn kem_keypair(variant: KyberVariant) -> Result<(Vec<u8>, Vec<u8>), KemError> {
+        get_kem(variant)?
+            .keypair()
+            .map(|(public_key, secret_key)| (public_key.into_vec(), secret_key.into_vec()))
+            .map_err(|err| KemError::Other(err.to_string()))
+    }
+
+    pub fn encapsulate(
+        variant: KyberVariant,
+        public_key: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), KemError> {
+        let kem = get_kem(variant)?;
+        let public_key = kem
+            .public_key_from_bytes(public_key)
+            .ok_or(KemError::InvalidLength)?;
+        kem.encapsulate(public_key)
+            .map(|(ciphertext, shared_secret)| (ciphertext.into_vec(), shared_secret.into_vec()))
+            .map_err(|err| KemError::Other(err.to_string()))
+    }
+
+    pub fn decapsulate(
+        variant: KyberVariant,
+        ciphertext: &[u8],
+        secret_key: &[u8],
+    ) -> Result<Vec<u8>, KemError> {
+        let kem = get_kem(variant)?;
+        let ciphertext = kem
+            .ciphertext_from_bytes(ciphertext)
+            .ok_or(KemError::InvalidLength)?;
+        let secret_key = kem
+            .secret_key_from_bytes(secret_key)
+            .ok_or(KemError::InvalidLength)?;
+        kem.decapsulate(secret_key, ciphertext)
+            .map(|shared_secret| shared_secret.into_vec())
+            .map_err(|err| KemError::Other(err.to_string()))
+    }
+
+    fn get_kem(variant: KyberVariant) -> Result<Kem, KemError> {
+        let alg = match variant {
+            KyberVariant::Kyber512 => oqs::kem::Algorithm::Kyber512,
+            KyberVariant::Kyber768 => oqs::kem::Algorithm::Kyber768,
+            KyberVariant::Kyber1024 => oqs::kem::Algorithm::Kyber1024,
+        };
+
+        Kem::new(alg).map_err(|err| KemError::Other(err.to_string()))
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod runtime {
+    use crate::kyber::KyberVariant;
+    use crate::KemError;
+
+    const UNSUPPORTED: KemError = KemError::Unsupported(
+        "Only the Kyber1024 parameter set is currently supported on wasm targets",
+    );
+
+    pub fn kem_keypair(_: KyberVariant) -> Result<(Vec<u8>, Vec<u8>), KemError> {
+        Err(UNSUPPORTED)
+    }
+
+    pub fn encapsulate(_: KyberVariant, _: &[u8]) -> Result<(Vec<u8>, Vec<u8>), KemError> {
+        Err(UNSUPPORTED)
+    }
+
+    pub fn decapsulate(_: KyberVariant, _: &[u8], _: &[u8]) -> Result<Vec<u8>, KemError> {
+        Err(UNSUPPORTED)
+    }
+}
@@ -1,8 +1,274 @@
+//! A generic interface over key encapsulation mechanisms (KEMs)
+//!
+//! Each KEM implements [`KeyEncapsulationMechanism`], allowing higher-level containers to generate keys,
+//! encapsulate, and decapsulate without knowing which scheme is in use. Two KEMs may be joined into a single
+//! hybrid KEM via [`combiner::KemCombiner`], whose shared secret remains secure so long as either component does
+#![forbid(unsafe_code)]
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+use zeroize::Zeroize;
+
+/// For combining two KEMs into a single hybrid KEM
+pub mod combiner;
+/// The round-3 Kyber KEMs. These are not FIPS 203 ML-KEM, and the parameter sets differ in variant (see [`kyber`])
+pub mod kyber;
+/// A KEM built from X25519 Diffie-Hellman key agreement
+pub mod x25519;
+
+pub mod prelude {
+    pub use crate::combiner::KemCombiner;
+    pub use crate::kyber::{Kyber, KyberVariant};
+    pub use crate::x25519::X25519;
+    pub use crate::{
+        Ciphertext, KemError, KeyEncapsulationMechanism, PublicKey, SecretKey, SharedSecret,
+    };
+}
+
+/// A key encapsulation mechanism. Implementations are object-safe, allowing the KEM to be selected at runtime
+pub trait KeyEncapsulationMechanism: Send + Sync {
+    /// The length of the public key, in bytes
+    fn public_key_len(&self) -> usize;
+    /// The length of the secret key, in bytes
+    fn secret_key_len(&self) -> usize;
+    /// The length of the ciphertext, in bytes
+    fn ciphertext_len(&self) -> usize;
+    /// The length of the shared secret, in bytes
+    fn shared_secret_len(&self) -> usize;
+    /// Generates a new keypair. Returns (public key, secret key)
+    fn keypair(&self) -> Result<(PublicKey, SecretKey), KemError>;
+    /// Encapsulates a new shared secret against the given public key. Returns (ciphertext, shared secret)
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Ciphertext, SharedSecret), KemError>;
+    /// Recovers the shared secret from the ciphertext using the local secret key
+    fn decapsulate(&self, ciphertext: &[u8], secret_key: &[u8]) -> Result<SharedSecret, KemError>;
+}
+
+impl<T: KeyEncapsulationMechanism + ?Sized> KeyEncapsulationMechanism for Box<T> {
+    fn public_key_len(&self) -> usize {
+        (**self).public_key_len()
+    }
+
+    fn secret_key_len(&self) -> usize {
+        (**self).secret_key_len()
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        (**self).ciphertext_len()
+    }
+
+    fn shared_secret_len(&self) -> usize {
+        (**self).shared_secret_len()
+    }
+
+    fn keypair(&self) -> Result<(PublicKey, SecretKey), KemError> {
+        (**self).keypair()
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Ciphertext, SharedSecret), KemError> {
+        (**self).encapsulate(public_key)
+    }
+
+    fn decapsulate(&self, ciphertext: &[u8], secret_key: &[u8]) -> Result<SharedSecret, KemError> {
+        (**self).decapsulate(ciphertext, secret_key)
+    }
+}
+
+/// The error type for this crate
+#[derive(Debug)]
+pub enum KemError {
+    /// A key or ciphertext had an unexpected length
+    InvalidLength,
+    /// The KEM is not available on this target
+    Unsupported(&'static str),
+    /// For generic error types
+    Generic(&'static str),
+    /// For message types requiring heap
+    Other(String),
+}
+
+impl Display for KemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for KemError {}
+
+macro_rules! define_bytes_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+        pub struct $name(Vec<u8>);
+
+        impl $name {
+            pub fn as_slice(&self) -> &[u8] {
+                self.0.as_slice()
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            pub fn into_vec(mut self) -> Vec<u8> {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(bytes: Vec<u8>) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                self.as_slice()
+            }
+        }
+    };
+}
+
+/// Secret material is zeroed on drop, and never printed
+macro_rules! define_secret_bytes_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        define_bytes_type!($(#[$meta])* $name);
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                self.0.zeroize()
+            }
+        }
+
+        impl Debug for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}([REDACTED; {}])", stringify!($name), self.0.len())
+            }
+        }
+    };
+}
+
+define_bytes_type!(
+    /// An encoded public key
+    #[derive(Debug)]
+    PublicKey
+);
+define_secret_bytes_type!(
+    /// An encoded secret key
+    SecretKey
+);
+define_bytes_type!(
+    /// An encoded ciphertext
+    #[derive(Debug)]
+    Ciphertext
+);
+define_secret_bytes_type!(
+    /// A shared secret
+    SharedSecret
+);
+
 #[cfg(test)]
 mod tests {
+    use crate::prelude::*;
+
+    fn test_kem(kem: &dyn KeyEncapsulationMechanism) {
+        let (public_key, secret_key) = kem.keypair().unwrap();
+        assert_eq!(public_key.len(), kem.public_key_len());
+        assert_eq!(secret_key.len(), kem.secret_key_len());
+
+        let (ciphertext, shared_secret_bob) = kem.encapsulate(public_key.as_slice()).unwrap();
+        assert_eq!(ciphertext.len(), kem.ciphertext_len());
+        assert_eq!(shared_secret_bob.len(), kem.shared_secret_len());
+
+        let shared_secret_alice = kem
+            .decapsulate(ciphertext.as_slice(), secret_key.as_slice())
+            .unwrap();
+        assert_eq!(shared_secret_alice, shared_secret_bob);
+
+        // truncated inputs must be rejected rather than panic
+        assert!(kem.encapsulate(&public_key.as_slice()[1..]).is_err());
+    }
+
+    #[test]
+    fn test_all_kems() {
+        for variant in KyberVariant::list() {
+            test_kem(&Kyber::new(*variant));
+        }
+
+        test_kem(&X25519);
+        test_kem(&KemCombiner::new(
+            Kyber::new(KyberVariant::Kyber1024),
+            X25519,
+            b"test",
+        ));
+        test_kem(&KemCombiner::new(
+            Box::new(Kyber::new(KyberVariant::Kyber768)) as Box<dyn KeyEncapsulationMechanism>,
+            Box::new(X25519) as Box<dyn KeyEncapsulationMechanism>,
+            b"test",
+        ));
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn test_kyber_parameter_sets_do_not_interoperate() {
+        for local in KyberVariant::list() {
+            let local_kem = Kyber::new(*local);
+            let (public_key, secret_key) = local_kem.keypair().unwrap();
+            let (ciphertext, _) = local_kem.encapsulate(public_key.as_slice()).unwrap();
+
+            for remote in KyberVariant::list()
+                .iter()
+                .filter(|remote| *remote != local)
+            {
+                let remote_kem = Kyber::new(*remote);
+                assert!(remote_kem.encapsulate(public_key.as_slice()).is_err());
+                assert!(remote_kem
+                    .decapsulate(ciphertext.as_slice(), secret_key.as_slice())
+                    .is_err());
+            }
+        }
+    }
+
+    /// Kyber1024 is the "90s" variant, whereas the other parameter sets are the standard variant. Encapsulating against
+    /// a Kyber1024 public key under the standard variant thus yields a different shared secret
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn test_kyber1024_is_90s_variant() {
+        let kem = Kyber::new(KyberVariant::Kyber1024);
+        let (public_key, secret_key) = kem.keypair().unwrap();
+
+        let standard = oqs::kem::Kem::new(oqs::kem::Algorithm::Kyber1024).unwrap();
+        assert_eq!(standard.length_public_key(), kem.public_key_len());
+        assert_eq!(standard.length_ciphertext(), kem.ciphertext_len());
+        let (ciphertext, standard_shared_secret) = standard
+            .encapsulate(
+                standard
+                    .public_key_from_bytes(public_key.as_slice())
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let shared_secret = kem
+            .decapsulate(&ciphertext.into_vec(), secret_key.as_slice())
+            .unwrap();
+        assert_ne!(shared_secret.as_slice(), standard_shared_secret.into_vec());
+    }
+
+    #[test]
+    fn test_serde() {
+        let (public_key, secret_key) = X25519.keypair().unwrap();
+        assert_eq!(serde_roundtrip(&public_key), public_key);
+        assert_eq!(
+            serde_roundtrip(&KyberVariant::Kyber768),
+            KyberVariant::Kyber768
+        );
+        assert!(!format!("{secret_key:?}").contains(&format!("{:?}", secret_key.as_slice())));
+    }
+
+    fn serde_roundtrip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        let bytes = bincode2::serialize(value).unwrap();
+        bincode2::deserialize(&bytes).unwrap()
     }
 }
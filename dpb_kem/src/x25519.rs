@@ -0,0 +1,81 @@
+use crate::{Ciphertext, KemError, KeyEncapsulationMechanism, PublicKey, SecretKey, SharedSecret};
+use rand::rngs::ThreadRng;
+use x25519_dalek::StaticSecret;
+
+const X25519_KEY_LENGTH_BYTES: usize = 32;
+
+/// A Diffie-Hellman based KEM over X25519. Encapsulation generates an ephemeral keypair, whose public key
+/// is the ciphertext. Being classical, this should only be used as a component of a [`KemCombiner`](crate::combiner::KemCombiner)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct X25519;
+
+impl X25519 {
+    fn diffie_hellman(secret_key: &[u8], public_key: &[u8]) -> Result<SharedSecret, KemError> {
+        let secret_key: [u8; X25519_KEY_LENGTH_BYTES] =
+            secret_key.try_into().map_err(|_| KemError::InvalidLength)?;
+        let public_key: [u8; X25519_KEY_LENGTH_BYTES] =
+            public_key.try_into().map_err(|_| KemError::InvalidLength)?;
+
+        let shared_secret = StaticSecret::from(secret_key)
+            .diffie_hellman(&x25519_dalek::PublicKey::from(public_key));
+
+        // rejects low-order points that would force an all-zero shared secret
+        if !shared_secret.was_contributory() {
+            return Err(KemError::Generic("Non-contributory X25519 shared secret"));
+        }
+
+        Ok(shared_secret.as_bytes().to_vec().into())
+    }
+}
+
+impl KeyEncapsulationMechanism for X25519 {
+    fn public_key_len(&self) -> usize {
+        X25519_KEY_LENGTH_BYTES
+    }
+
+    fn secret_key_len(&self) -> usize {
+        X25519_KEY_LENGTH_BYTES
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        X25519_KEY_LENGTH_BYTES
+    }
+
+    fn shared_secret_len(&self) -> usize {
+        X25519_KEY_LENGTH_BYTES
+    }
+
+    fn keypair(&self) -> Result<(PublicKey, SecretKey), KemError> {
+        let secret = StaticSecret::random_from_rng(ThreadRng::default());
+        let public = x25519_dalek::PublicKey::from(&secret);
+        Ok((
+            public.as_bytes().to_vec().into(),
+            secret.to_bytes().to_vec().into(),
+        ))
+    }
+
+    fn encapsulate(&self, public_key: &[u8]) -> Result<(Ciphertext, SharedSecret), KemError> {
+        let (ephemeral_public_key, ephemeral_secret_key) = self.keypair()?;
+        let shared_secret = Self::diffie_hellman(ephemeral_secret_key.as_slice(), public_key)?;
+        Ok((ephemeral_public_key.into_vec().into(), shared_secret))
+    }
+
+    fn decapsulate(&self, ciphertext: &[u8], secret_key: &[u8]) -> Result<SharedSecret, KemError> {
+        Self::diffie_hellman(secret_key, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::x25519::X25519;
+    use crate::KeyEncapsulationMechanism;
+
+    #[test]
+    fn test_rejects_identity_point() {
+        let (_, secret_key) = X25519.keypair().unwrap();
+        assert!(X25519.encapsulate(&[0u8; 32]).is_err());
+        assert!(X25519
+            .decapsulate(&[0u8; 32], secret_key.as_slice())
+            .is_err());
+    }
+}